@@ -0,0 +1,355 @@
+use proc_macro2::Span;
+use quote::{format_ident, quote};
+use syn::visit_mut::VisitMut;
+use syn::{punctuated::Punctuated, FnArg, ItemFn, Token, UseTree};
+
+fn parse_version(v: &str) -> Option<(u64, u64, u64)> {
+    let core = v.trim().split(['-', '+']).next()?;
+    let mut parts = core.split('.');
+    let major = parts.next()?.parse().ok()?;
+    let minor = parts.next().unwrap_or("0").parse().ok()?;
+    let patch = parts.next().unwrap_or("0").parse().ok()?;
+    Some((major, minor, patch))
+}
+
+/// Returns whether `current` is strictly older than `until`, or `None` when
+/// either side is not a `major[.minor[.patch]]` version.
+pub fn version_before(current: &str, until: &str) -> Option<bool> {
+    Some(parse_version(current)? < parse_version(until)?)
+}
+
+/// Builds a forwarding wrapper with the same ABI and signature as `f`,
+/// calling straight through to `f` (as `Self::f` when `in_impl`). The caller
+/// adds the export attribute for `alias`.
+pub fn forwarding_wrapper(f: &ItemFn, alias: &str, in_impl: bool) -> Result<ItemFn, syn::Error> {
+    let suffix: String = alias
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    let ident = syn::Ident::new(&format!("__symbaker_alias_{suffix}"), Span::call_site());
+    let target = &f.sig.ident;
+    let target = if in_impl {
+        quote!(Self::#target)
+    } else {
+        quote!(#target)
+    };
+    let (sig, body) = forwarder(f, ident, target)?;
+
+    // Keep the wrapper under the same `#[cfg]` gates as the function it forwards to.
+    let cfgs = f.attrs.iter().filter(|a| a.path().is_ident("cfg"));
+    let vis = &f.vis;
+    Ok(syn::parse_quote! {
+        #(#cfgs)*
+        #[doc(hidden)]
+        #vis #sig #body
+    })
+}
+
+/// Branch used by the weak-alias trampoline, per `target_arch`. ELF targets
+/// outside this list (and Mach-O, PE/COFF) get a forwarding wrapper instead.
+const WEAK_ALIAS_BRANCHES: &[(&str, &str)] = &[
+    ("aarch64", "b"),
+    ("x86", "jmp"),
+    ("x86_64", "jmp"),
+    ("riscv64", "tail"),
+];
+
+/// Emits `alias` as a weak ELF function symbol that tail-jumps to `f`. The
+/// trampoline is written with `global_asm!` because stable Rust has no
+/// `#[linkage = "weak"]`; a plain `.set` alias would stay undefined whenever
+/// `f` lands in another codegen unit. On other targets `fallback` (a
+/// forwarding wrapper already carrying its export attribute) is emitted
+/// with a deprecation warning explaining the degradation.
+pub fn weak_alias(
+    f: &ItemFn,
+    alias: &str,
+    fallback: &ItemFn,
+) -> Result<proc_macro2::TokenStream, syn::Error> {
+    let plain = alias.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+        && alias.chars().next().is_some_and(|c| !c.is_ascii_digit());
+    if !plain {
+        return Err(syn::Error::new_spanned(
+            &f.sig.ident,
+            format!("symbaker: `alias` {alias:?} must be a plain symbol name (ASCII letters, digits and `_`)"),
+        ));
+    }
+    let elf = quote!(not(any(
+        target_vendor = "apple",
+        target_os = "windows",
+        target_os = "uefi"
+    )));
+    let cfgs: Vec<_> = f
+        .attrs
+        .iter()
+        .filter(|a| a.path().is_ident("cfg"))
+        .collect();
+    let target = &f.sig.ident;
+    let shims = WEAK_ALIAS_BRANCHES.iter().map(|(arch, branch)| {
+        let lines = [
+            format!(".pushsection .text.{alias},\"ax\",%progbits"),
+            format!(".weak {alias}"),
+            format!(".type {alias}, %function"),
+            format!("{alias}:"),
+            format!("{branch} {{0}}"),
+            format!(".size {alias}, . - {alias}"),
+            ".popsection".to_string(),
+        ];
+        quote! {
+            #(#cfgs)*
+            #[cfg(all(#elf, target_arch = #arch))]
+            ::core::arch::global_asm!(#(#lines),*, sym #target);
+        }
+    });
+    let arches = WEAK_ALIAS_BRANCHES
+        .iter()
+        .map(|(arch, _)| quote!(target_arch = #arch));
+    let unsupported = quote!(not(all(#elf, any(#(#arches),*))));
+    let note = format!(
+        "symbaker: weak aliases are unavailable on this target; `{alias}` is exported as a strong forwarding function instead"
+    );
+    let warn = format_ident!("__symbaker_no_weak_alias_{}", alias);
+    Ok(quote! {
+        #(#shims)*
+        #[cfg(#unsupported)]
+        #fallback
+        #(#cfgs)*
+        #[cfg(#unsupported)]
+        const _: () = {
+            #[deprecated(note = #note)]
+            #[allow(non_upper_case_globals)]
+            const #warn: () = ();
+            #warn
+        };
+    })
+}
+
+/// A signature like `f`'s, named `ident`, and a body calling `target` with
+/// every argument.
+fn forwarder(
+    f: &ItemFn,
+    ident: syn::Ident,
+    target: proc_macro2::TokenStream,
+) -> Result<(syn::Signature, proc_macro2::TokenStream), syn::Error> {
+    if let Some(v) = &f.sig.variadic {
+        return Err(syn::Error::new_spanned(
+            v,
+            "symbaker: alias exports cannot forward variadic functions",
+        ));
+    }
+    if let Some(a) = &f.sig.asyncness {
+        return Err(syn::Error::new_spanned(
+            a,
+            "symbaker: alias exports cannot forward async functions",
+        ));
+    }
+
+    let mut inputs = Punctuated::<FnArg, Token![,]>::new();
+    let mut forwarded = Vec::new();
+    for (i, arg) in f.sig.inputs.iter().enumerate() {
+        match arg {
+            FnArg::Typed(pt) => {
+                let ident = format_ident!("__symbaker_arg{}", i);
+                let ty = &pt.ty;
+                inputs.push(syn::parse_quote!(#ident: #ty));
+                forwarded.push(ident);
+            }
+            FnArg::Receiver(r) => {
+                return Err(syn::Error::new_spanned(
+                    r,
+                    "symbaker: alias exports cannot forward methods",
+                ));
+            }
+        }
+    }
+
+    let mut sig = f.sig.clone();
+    sig.ident = ident;
+    sig.inputs = inputs;
+    sig.constness = None;
+
+    let call = quote!(#target(#(#forwarded),*));
+    let body = if f.sig.unsafety.is_some() {
+        quote!({ unsafe { #call } })
+    } else {
+        quote!({ #call })
+    };
+    Ok((sig, body))
+}
+
+/// One name a `pub use` brings in: `path::ident as rename` (`rename` is
+/// `ident` when not renamed), or `path::*` when `ident` is `None`.
+pub struct UseLeaf {
+    pub leading_colon: bool,
+    pub path: Vec<syn::Ident>,
+    pub ident: Option<syn::Ident>,
+    pub rename: Option<syn::Ident>,
+}
+
+/// Flattens a use tree (groups included) into its leaves.
+pub fn use_leaves(tree: &UseTree, leading_colon: bool) -> Vec<UseLeaf> {
+    fn walk(tree: &UseTree, path: &mut Vec<syn::Ident>, colon: bool, out: &mut Vec<UseLeaf>) {
+        let leaf = |ident: Option<&syn::Ident>, rename: Option<&syn::Ident>| UseLeaf {
+            leading_colon: colon,
+            path: path.clone(),
+            ident: ident.cloned(),
+            rename: rename.cloned(),
+        };
+        match tree {
+            UseTree::Path(p) => {
+                path.push(p.ident.clone());
+                walk(&p.tree, path, colon, out);
+                path.pop();
+            }
+            UseTree::Name(n) => out.push(leaf(Some(&n.ident), Some(&n.ident))),
+            UseTree::Rename(r) => out.push(leaf(Some(&r.ident), Some(&r.rename))),
+            UseTree::Glob(_) => out.push(leaf(None, None)),
+            UseTree::Group(g) => {
+                for t in &g.items {
+                    walk(t, path, colon, out);
+                }
+            }
+        }
+    }
+    let mut out = Vec::new();
+    walk(tree, &mut Vec::new(), leading_colon, &mut out);
+    out
+}
+
+impl UseLeaf {
+    /// The function this leaf names and the items of the module defining it,
+    /// when its path runs through inline modules among `items` (a proc macro
+    /// cannot see any other file).
+    pub fn resolve_inline_fn<'a>(
+        &self,
+        mut items: &'a [syn::Item],
+    ) -> Option<(&'a [syn::Item], &'a ItemFn)> {
+        if self.leading_colon {
+            return None;
+        }
+        for seg in &self.path {
+            if seg == "self" {
+                continue;
+            }
+            items = items.iter().find_map(|it| match it {
+                syn::Item::Mod(m) if m.ident == *seg => m.content.as_ref().map(|(_, c)| &c[..]),
+                _ => None,
+            })?;
+        }
+        let ident = self.ident.as_ref()?;
+        items.iter().find_map(|it| match it {
+            syn::Item::Fn(f) if f.sig.ident == *ident => Some((items, f)),
+            _ => None,
+        })
+    }
+
+    /// This leaf on its own: `#vis use path::ident as rename;`.
+    pub fn to_use(&self, attrs: &[syn::Attribute], vis: &syn::Visibility) -> syn::Item {
+        let colon = self.leading_colon.then(|| quote!(::));
+        let path = &self.path;
+        let tail = match (&self.ident, &self.rename) {
+            (Some(i), Some(r)) if i != r => quote!(#i as #r),
+            (Some(i), _) => quote!(#i),
+            (None, _) => quote!(*),
+        };
+        syn::parse_quote!(#(#attrs)* #vis use #colon #(#path::)* #tail;)
+    }
+
+    /// Replaces the re-export of `f` with a function of the same visible
+    /// name and signature forwarding to it, so it can carry an export name.
+    /// Types the signature names from `source` (the module defining `f`) are
+    /// qualified through this leaf's path.
+    pub fn forwarding_fn(
+        &self,
+        f: &ItemFn,
+        source: &[syn::Item],
+        attrs: &[syn::Attribute],
+        vis: &syn::Visibility,
+    ) -> Result<ItemFn, syn::Error> {
+        let name = self.rename.clone().unwrap_or_else(|| f.sig.ident.clone());
+        let path = &self.path;
+        let target = &f.sig.ident;
+        let (mut sig, body) = forwarder(f, name, quote!(#(#path::)* #target))?;
+        let mut qualify = QualifyTypes {
+            path,
+            source,
+            error: None,
+        };
+        qualify.visit_signature_mut(&mut sig);
+        if let Some(e) = qualify.error {
+            return Err(e);
+        }
+        Ok(syn::parse_quote! {
+            #(#attrs)*
+            #vis #sig #body
+        })
+    }
+}
+
+/// Prefixes type and trait paths that start with an item of `source` with
+/// `path`, so a signature copied out of a submodule still resolves. A private
+/// item cannot be named outside its module, so it is an error instead.
+struct QualifyTypes<'a> {
+    path: &'a [syn::Ident],
+    source: &'a [syn::Item],
+    error: Option<syn::Error>,
+}
+
+impl QualifyTypes<'_> {
+    fn source_item_vis(&self, ident: &syn::Ident) -> Option<&syn::Visibility> {
+        self.source.iter().find_map(|it| match it {
+            syn::Item::Struct(i) if i.ident == *ident => Some(&i.vis),
+            syn::Item::Enum(i) if i.ident == *ident => Some(&i.vis),
+            syn::Item::Union(i) if i.ident == *ident => Some(&i.vis),
+            syn::Item::Type(i) if i.ident == *ident => Some(&i.vis),
+            syn::Item::Trait(i) if i.ident == *ident => Some(&i.vis),
+            _ => None,
+        })
+    }
+
+    fn qualify(&mut self, p: &mut syn::Path) {
+        if p.leading_colon.is_some() {
+            return;
+        }
+        let Some(first) = p.segments.first() else {
+            return;
+        };
+        let Some(vis) = self.source_item_vis(&first.ident) else {
+            return;
+        };
+        if matches!(vis, syn::Visibility::Inherited) {
+            if self.error.is_none() {
+                let module = self
+                    .path
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>()
+                    .join("::");
+                self.error = Some(syn::Error::new_spanned(
+                    &first.ident,
+                    format!(
+                        "symbaker_module: cannot forward this `pub use`: its signature names `{}`, which is private to `{module}`; make it `pub` or do not re-export the function",
+                        first.ident
+                    ),
+                ));
+            }
+            return;
+        }
+        let path = self.path;
+        let rest = &p.segments;
+        *p = syn::parse_quote!(#(#path::)* #rest);
+    }
+}
+
+impl VisitMut for QualifyTypes<'_> {
+    fn visit_type_path_mut(&mut self, t: &mut syn::TypePath) {
+        if t.qself.is_none() {
+            self.qualify(&mut t.path);
+        }
+        syn::visit_mut::visit_type_path_mut(self, t);
+    }
+
+    fn visit_trait_bound_mut(&mut self, b: &mut syn::TraitBound) {
+        self.qualify(&mut b.path);
+        syn::visit_mut::visit_trait_bound_mut(self, b);
+    }
+}
@@ -49,18 +49,16 @@ fn resolve_repo_arg(raw: &str) -> (String, Option<String>) {
     let is_hex = !raw.is_empty()
         && raw.len() >= 7
         && raw.len() <= 40
-        && raw
-            .bytes()
-            .all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f' | b'A'..=b'F'));
+        && raw.bytes().all(|b| b.is_ascii_hexdigit());
     if is_hex {
         return (DEFAULT_REPO.to_string(), Some(raw.to_string()));
     }
     (raw.to_string(), None)
 }
 
-fn parse_args(
-    args: &[OsString],
-) -> Result<(String, Option<String>, Option<PathBuf>, Option<u32>), String> {
+type ParsedArgs = (String, Option<String>, Option<PathBuf>, Option<u32>);
+
+fn parse_args(args: &[OsString]) -> Result<ParsedArgs, String> {
     let mut repo_arg = DEFAULT_REPO.to_string();
     let mut install_root = None::<PathBuf>;
     let mut wait_pid = None::<u32>;
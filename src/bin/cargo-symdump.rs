@@ -1,12 +1,21 @@
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use std::cell::RefCell;
 use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::env;
 use std::ffi::OsString;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Component, Path, PathBuf};
 use std::process::{Command, ExitCode};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
 
+#[path = "../filter/names.rs"]
+mod names;
+// `read_exports` and `demangle_legacy` are there for the integration tests.
+#[allow(dead_code)]
+#[path = "../objects.rs"]
+mod objects;
 #[path = "../out.rs"]
 mod out;
 
@@ -14,20 +23,121 @@ const DEFAULT_REPO: &str = "https://github.com/BlankMauser/symbaker";
 const INSTALLER_MARKER_FILE: &str = "cargo-symdump-installer.toml";
 const INSTALLER_VERSION: &str = "1";
 
-fn usage() {
+/// Why a command failed. [`Failure::exit_code`] is the CLI's exit-code
+/// contract, which CI scripts depend on: keep the numbers stable.
+#[derive(Debug, PartialEq)]
+enum Failure {
+    /// Any other error (I/O, `cargo metadata`, parsing): 1.
+    Other(String),
+    /// Bad command-line arguments: 2.
+    Usage(String),
+    /// The wrapped cargo command failed: 3.
+    Build(String),
+    /// `--fail-on-duplicates` found conflicting duplicate exports: 4.
+    Duplicates(String),
+    /// `verify`, `diff`, `--fail-unprefixed` or `--strict-foreign` found
+    /// problems: 5.
+    Regression(String),
+    /// No artifact to dump: 6.
+    NoArtifact(String),
+}
+
+impl Failure {
+    fn exit_code(&self) -> u8 {
+        match self {
+            Failure::Other(_) => 1,
+            Failure::Usage(_) => 2,
+            Failure::Build(_) => 3,
+            Failure::Duplicates(_) => 4,
+            Failure::Regression(_) => 5,
+            Failure::NoArtifact(_) => 6,
+        }
+    }
+}
+
+impl From<String> for Failure {
+    fn from(msg: String) -> Self {
+        Failure::Other(msg)
+    }
+}
+
+impl std::fmt::Display for Failure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Failure::Other(msg)
+            | Failure::Usage(msg)
+            | Failure::Build(msg)
+            | Failure::Duplicates(msg)
+            | Failure::Regression(msg)
+            | Failure::NoArtifact(msg) => f.write_str(msg),
+        }
+    }
+}
+
+/// Prints the usage text; `all` (`--help-all`) adds maintainer commands.
+fn usage(all: bool) {
     eprintln!("cargo-symdump: build then dump exported symbols from produced .nro files");
     eprintln!("usage:");
-    eprintln!("  cargo symdump init [--prefix <name>] [--force]");
-    eprintln!("  cargo symdump [--trace] --release");
+    eprintln!(
+        "  cargo symdump init [--prefix <name>] [--sep <str>] [--priority a,b,c] [--metadata] [--no-gitignore] [--with-build-checks] [--workspace-members-only] [--dry-run|--emit-cargo-config] [--force]"
+    );
+    eprintln!(
+        "  cargo symdump [--trace] [--force] [--fail-unprefixed] [--strict-foreign] [--fail-on-duplicates] [--summary-json <path>] [--top-package <name>] [--stable] [--copy-to <dir> [--rename <file>]] --release"
+    );
     eprintln!("  cargo symdump [--trace] build --profile release --target-dir target");
     eprintln!("  cargo symdump [--trace] skyline build --release");
-    eprintln!("  cargo symdump run [--trace] <cargo-subcommand...>");
-    eprintln!("  cargo symdump dump <path/to/file.nro|path/to/folder> [more paths...]");
+    eprintln!("  cargo symdump [--trace] --artifact <path/to/file.nro|path/to/folder>");
+    eprintln!(
+        "  cargo symdump run [--trace] [--force] [--strict-config] [--watch-config] [--no-symbaker|--no-symbaker-env] [--top-package <name>] <cargo-subcommand...>"
+    );
+    eprintln!(
+        "  cargo symdump test [--trace] [--force] [--no-dump] [--stable] [--top-package <name>] [cargo test args...]"
+    );
+    eprintln!(
+        "  cargo symdump dump [--recursive|--no-recursive] [--group-by crate] [--annotate] [--allow-missing-tools] [--verify-integrity] [--fail-on-duplicates] [--sidecar-name <pattern>] [--min-size <bytes>] [--stable] [-|--stdin] [--files-from <file|->] <path/to/file.nro|path/to/folder|glob> [more paths...]"
+    );
     eprintln!("  cargo symdump update [--repo <git-url|commit>] [--path <dir>]");
+    eprintln!("  cargo symdump doctor");
+    eprintln!("  cargo symdump stats");
+    eprintln!(
+        "  cargo symdump verify [--no-clashes] [--allow-extra] [--include-regex <re>] [--exclude-regex <re>] [--include-glob <glob>] [--exclude-glob <glob>] <path/to/file.nro|path/to/folder> [more paths...]"
+    );
+    eprintln!(
+        "    exits 5 on removed or hidden exports and on clashes, and on additions unless --allow-extra"
+    );
+    eprintln!(
+        "  cargo symdump diff [--strict|--additions-only] [--include-regex <re>] [--exclude-regex <re>] [--include-glob <glob>] [--exclude-glob <glob>] <old> <new> (artifacts, sidecars or sym.stable.log)"
+    );
+    eprintln!(
+        "    exits 5 when symbols were removed; --strict also on additions; --additions-only only on additions"
+    );
+    eprintln!(
+        "  cargo symdump gen-version-script [--output <path>] [--node <name>|--soname <name>]"
+    );
+    eprintln!(
+        "  any command: [--relative-paths] [--absolute-paths] [--redact-home] [--redact] (paths in .symbaker/ files are workspace-relative by default, stdout absolute)"
+    );
+    eprintln!(
+        "  before the command: [-q|-v|-vv] (quiet: errors only; -v: per-artifact strategy, counts, timing; -vv: also scans and cache decisions)"
+    );
     eprintln!("  outputs:");
     eprintln!("  - .symbaker/sym.log");
+    eprintln!("  - .symbaker/sym.stable.log (only with --stable)");
     eprintln!("  - .symbaker/resolution.toml (only with --trace)");
+    eprintln!("  - .symbaker/graph.dot (only with --trace)");
     eprintln!("  - .symbaker/trace.log (only with --trace)");
+    eprintln!("  exit codes:");
+    eprintln!("  - 0 success");
+    eprintln!("  - 1 other error (I/O, cargo metadata, unreadable files)");
+    eprintln!("  - 2 usage error");
+    eprintln!("  - 3 the cargo build failed");
+    eprintln!("  - 4 conflicting duplicate exports (only with --fail-on-duplicates)");
+    eprintln!("  - 5 verify, diff, --fail-unprefixed or --strict-foreign found problems");
+    eprintln!("  - 6 no artifact found to dump");
+    if all {
+        eprintln!("  maintainer commands:");
+        eprintln!("  cargo symdump bench <path/to/file.nro> [--iters N] [--json]");
+    }
 }
 
 fn find_flag_value(args: &[OsString], flag: &str) -> Option<PathBuf> {
@@ -46,6 +156,25 @@ fn find_flag_value(args: &[OsString], flag: &str) -> Option<PathBuf> {
     None
 }
 
+/// Removes `flag <value>` / `flag=<value>` from `args`, returning the value.
+fn take_flag_value(args: &mut Vec<OsString>, flag: &str) -> Option<PathBuf> {
+    let mut i = 0usize;
+    while i < args.len() {
+        let cur = args[i].to_string_lossy().to_string();
+        if cur == flag && i + 1 < args.len() {
+            let v = args.remove(i + 1);
+            args.remove(i);
+            return Some(PathBuf::from(v));
+        }
+        if let Some(v) = cur.strip_prefix(&format!("{flag}=")) {
+            args.remove(i);
+            return Some(PathBuf::from(v));
+        }
+        i += 1;
+    }
+    None
+}
+
 fn has_flag(args: &[OsString], flag: &str) -> bool {
     args.iter().any(|a| a == flag)
 }
@@ -77,9 +206,7 @@ fn resolve_repo_arg(raw: &str) -> (String, Option<String>) {
     let is_hex = !raw.is_empty()
         && raw.len() >= 7
         && raw.len() <= 40
-        && raw
-            .bytes()
-            .all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f' | b'A'..=b'F'));
+        && raw.bytes().all(|b| b.is_ascii_hexdigit());
     if is_hex {
         return (DEFAULT_REPO.to_string(), Some(raw.to_string()));
     }
@@ -132,20 +259,135 @@ fn installer_force_install_cmd(
     cmd
 }
 
-fn target_dir_from_args(args: &[OsString]) -> PathBuf {
-    if let Some(p) = find_flag_value(args, "--target-dir") {
-        return p;
+/// `build.target-dir` from the nearest `.cargo/config.toml` (or legacy
+/// `.cargo/config`) at or above `cwd`, falling back to `$CARGO_HOME`. Cargo
+/// resolves a relative value against the directory holding that `.cargo`.
+fn config_target_dir(cwd: &Path) -> Option<PathBuf> {
+    let mut cargo_dirs: Vec<PathBuf> = cwd.ancestors().map(|d| d.join(".cargo")).collect();
+    if let Ok(home) = env::var("CARGO_HOME") {
+        cargo_dirs.push(PathBuf::from(home));
     }
-    if let Ok(v) = env::var("CARGO_TARGET_DIR") {
-        if !v.trim().is_empty() {
-            return PathBuf::from(v);
+    for cargo_dir in cargo_dirs {
+        for name in ["config", "config.toml"] {
+            let Ok(text) = fs::read_to_string(cargo_dir.join(name)) else {
+                continue;
+            };
+            let Ok(doc) = toml::from_str::<toml::Value>(&text) else {
+                continue;
+            };
+            let Some(dir) = doc
+                .get("build")
+                .and_then(|b| b.get("target-dir"))
+                .and_then(|v| v.as_str())
+            else {
+                continue;
+            };
+            let base = cargo_dir.parent().unwrap_or(&cargo_dir);
+            return Some(base.join(dir));
         }
     }
-    PathBuf::from("target")
+    None
+}
+
+/// Where cargo writes artifacts for `args`, the way cargo picks it:
+/// `--target-dir`, then `CARGO_TARGET_DIR` (both relative to `cwd`), then
+/// `build.target-dir` from cargo config, then `target/` under the workspace
+/// root. Canonicalized when it exists.
+fn target_dir_for(
+    args: &[OsString],
+    cwd: &Path,
+    workspace_root: &Path,
+    env_target_dir: Option<String>,
+) -> PathBuf {
+    let dir = find_flag_value(args, "--target-dir")
+        .or_else(|| {
+            env_target_dir
+                .filter(|v| !v.trim().is_empty())
+                .map(PathBuf::from)
+        })
+        .map(|p| cwd.join(p))
+        .or_else(|| config_target_dir(cwd))
+        .unwrap_or_else(|| workspace_root.join("target"));
+    fs::canonicalize(&dir).unwrap_or(dir)
+}
+
+fn target_dir_from_args(args: &[OsString], workspace_root: &Path) -> PathBuf {
+    let cwd = env::current_dir().unwrap_or_else(|_| workspace_root.to_path_buf());
+    target_dir_for(
+        args,
+        &cwd,
+        workspace_root,
+        env::var("CARGO_TARGET_DIR").ok(),
+    )
+}
+
+/// Name of the directory cargo writes `profile` to (`debug` for the default
+/// `dev` profile).
+fn profile_dir_name(profile: Option<&str>) -> String {
+    match profile {
+        None | Some("dev") | Some("test") => "debug".to_string(),
+        Some("bench") => "release".to_string(),
+        Some(p) => p.to_string(),
+    }
+}
+
+/// `CARGO_CFG_TARGET_ARCH` of a target triple: its first component with the
+/// sub-architecture dropped (`thumbv7em` is `arm`, `i686` is `x86`). A path to
+/// a target spec `.json` uses its file stem.
+fn target_arch_of(triple: &str) -> &str {
+    let triple = Path::new(triple)
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .filter(|_| triple.ends_with(".json"))
+        .unwrap_or(triple);
+    let first = triple.split('-').next().unwrap_or(triple);
+    match first {
+        "i386" | "i486" | "i586" | "i686" | "i786" | "x86" => "x86",
+        "arm64" | "arm64e" | "arm64_32" | "arm64ec" => "aarch64",
+        "mipsisa32r6" | "mipsisa32r6el" => "mips32r6",
+        "mipsisa64r6" | "mipsisa64r6el" => "mips64r6",
+        "sparcv9" => "sparc64",
+        "bpfeb" | "bpfel" => "bpf",
+        a if a.starts_with("x86_64") => "x86_64",
+        a if a.starts_with("aarch64") => "aarch64",
+        a if a.starts_with("arm") || a.starts_with("thumb") => "arm",
+        a if a.starts_with("riscv64") => "riscv64",
+        a if a.starts_with("riscv32") => "riscv32",
+        a if a.starts_with("mips64") => "mips64",
+        a if a.starts_with("mips") => "mips",
+        a if a.starts_with("powerpc64") || a.starts_with("ppc64") => "powerpc64",
+        a if a.starts_with("powerpc") || a == "ppc" => "powerpc",
+        a => a,
+    }
+}
+
+/// Where to look for this build's `.nro` files: the directory to scan and
+/// the profile directory name (`debug` for `dev`/`test`) that
+/// [`out::all_nros`] filters on. With `--target <triple>` cargo writes to
+/// `{target_dir}/{triple}/{profile dir}`; without it the whole target dir is
+/// scanned.
+fn nro_search(
+    target_dir: &Path,
+    args: &[OsString],
+    profile: Option<&str>,
+) -> (PathBuf, Option<String>) {
+    let profile_dir = profile.map(|p| profile_dir_name(Some(p)));
+    let Some(triple) = find_flag_value(args, "--target") else {
+        return (target_dir.to_path_buf(), profile_dir);
+    };
+    let dir = target_dir.join(triple);
+    match &profile_dir {
+        Some(name) => (dir.join(name), profile_dir),
+        None => (dir, None),
+    }
 }
 
 fn discover_default_config_path() -> Option<PathBuf> {
-    let mut dir = env::current_dir().ok()?;
+    discover_config_path_from(env::current_dir().ok()?)
+}
+
+/// The nearest `symbaker.toml` at or above `dir`.
+fn discover_config_path_from(mut dir: PathBuf) -> Option<PathBuf> {
     loop {
         let candidate = dir.join("symbaker.toml");
         if candidate.exists() {
@@ -158,18 +400,68 @@ fn discover_default_config_path() -> Option<PathBuf> {
     None
 }
 
-fn discover_workspace_root() -> Result<PathBuf, String> {
-    let mut dir = env::current_dir().map_err(|e| format!("current_dir: {e}"))?;
+/// The config a build of `args` reads: `SYMBAKER_CONFIG`, else the nearest
+/// `symbaker.toml` from the `--manifest-path` directory (or the current one)
+/// up.
+fn config_path_for_args(args: &[OsString]) -> Option<PathBuf> {
+    if let Some(path) = env::var_os("SYMBAKER_CONFIG").filter(|v| !v.is_empty()) {
+        return Some(PathBuf::from(path));
+    }
+    let start = match out::manifest_path_from_args(args) {
+        Some(manifest) => {
+            let dir = manifest.parent().unwrap_or(Path::new("")).to_path_buf();
+            if dir.is_absolute() {
+                dir
+            } else {
+                env::current_dir().ok()?.join(dir)
+            }
+        }
+        None => env::current_dir().ok()?,
+    };
+    discover_config_path_from(start)
+}
+
+fn read_manifest(dir: &Path) -> Option<toml::Value> {
+    let text = fs::read_to_string(dir.join("Cargo.toml")).ok()?;
+    toml::from_str(&text).ok()
+}
+
+/// The nearest directory at or above `start` whose `Cargo.toml` has a
+/// `[workspace]` (unless that workspace excludes the nearer package), else
+/// the first directory with any `Cargo.toml`.
+fn workspace_root_from(start: &Path) -> Result<PathBuf, String> {
+    let mut first = None::<PathBuf>;
+    let mut dir = start.to_path_buf();
     loop {
         if dir.join("Cargo.toml").exists() {
-            return Ok(dir);
+            if let Some(ws) = read_manifest(&dir).and_then(|m| m.get("workspace").cloned()) {
+                let excluded = |pkg: &Path| {
+                    ws.get("exclude")
+                        .and_then(|v| v.as_array())
+                        .into_iter()
+                        .flatten()
+                        .filter_map(|e| e.as_str())
+                        .any(|e| pkg.starts_with(dir.join(e)))
+                };
+                return Ok(match first {
+                    Some(pkg) if excluded(&pkg) => pkg,
+                    _ => dir,
+                });
+            }
+            first.get_or_insert_with(|| dir.clone());
         }
         if !dir.pop() {
-            return Err("could not find Cargo.toml in current dir or parents".to_string());
+            return first
+                .ok_or_else(|| "could not find Cargo.toml in current dir or parents".to_string());
         }
     }
 }
 
+fn discover_workspace_root() -> Result<PathBuf, String> {
+    let dir = env::current_dir().map_err(|e| format!("current_dir: {e}"))?;
+    workspace_root_from(&dir)
+}
+
 fn discover_workspace_root_for_args(args: &[OsString]) -> Result<PathBuf, String> {
     if let Some(manifest) = out::manifest_path_from_args(args) {
         let p = if manifest.is_absolute() {
@@ -186,17 +478,182 @@ fn discover_workspace_root_for_args(args: &[OsString]) -> Result<PathBuf, String
     discover_workspace_root()
 }
 
-fn symbaker_output_dir(workspace_root: &PathBuf) -> Result<PathBuf, String> {
+/// `--relative-paths`, `--absolute-paths`, `--redact-home` and `--redact`,
+/// accepted anywhere on the command line.
+#[derive(Clone, Copy, Default)]
+struct PathFlags {
+    /// Print paths inside the workspace relative to it, as files already do.
+    relative_stdout: bool,
+    /// Keep absolute paths in the files written under `.symbaker/`.
+    absolute_files: bool,
+    /// Show the home directory as `~` in paths that stay absolute.
+    redact_home: bool,
+    /// Redact traced builds (`SYMBAKER_TRACE_REDACT`) and the paths in
+    /// `resolution.toml` for sharing.
+    redact: bool,
+}
+
+static PATH_FLAGS: OnceLock<PathFlags> = OnceLock::new();
+
+/// How much `cargo symdump` prints to stdout. Warnings and errors go to
+/// stderr at every level.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+enum Verbosity {
+    /// `-q`: nothing on success.
+    Quiet,
+    #[default]
+    Normal,
+    /// `-v`: per-artifact extraction strategy, symbol counts and timing.
+    Verbose,
+    /// `-vv`: also directory scans and cache decisions.
+    Debug,
+}
+
+/// Takes `-q`/`--quiet`, `-v`/`--verbose` (repeatable) and `-vv` from before
+/// the command; after it they belong to cargo or the subcommand.
+fn take_verbosity(args: &mut Vec<OsString>) -> Result<Verbosity, String> {
+    let quiet = take_leading_flag(args, "-q") | take_leading_flag(args, "--quiet");
+    let mut verbose = 0usize;
+    while take_leading_flag(args, "-v") || take_leading_flag(args, "--verbose") {
+        verbose += 1;
+    }
+    while take_leading_flag(args, "-vv") {
+        verbose += 2;
+    }
+    match (quiet, verbose) {
+        (true, 0) => Ok(Verbosity::Quiet),
+        (true, _) => Err("-q cannot be combined with -v/-vv".to_string()),
+        (false, 0) => Ok(Verbosity::Normal),
+        (false, 1) => Ok(Verbosity::Verbose),
+        (false, _) => Ok(Verbosity::Debug),
+    }
+}
+
+/// Stdout progress lines, filtered by [`Verbosity`]. Passed by reference so
+/// tests can capture what a command would print.
+struct Log {
+    level: Verbosity,
+    captured: Option<RefCell<Vec<String>>>,
+}
+
+impl Log {
+    fn new(level: Verbosity) -> Self {
+        Log {
+            level,
+            captured: None,
+        }
+    }
+
+    #[cfg(test)]
+    fn capturing(level: Verbosity) -> Self {
+        Log {
+            level,
+            captured: Some(RefCell::default()),
+        }
+    }
+
+    #[cfg(test)]
+    fn lines(&self) -> Vec<String> {
+        self.captured
+            .as_ref()
+            .map(|c| c.borrow().clone())
+            .unwrap_or_default()
+    }
+
+    fn at(&self, level: Verbosity, line: impl Into<String>) {
+        if level > self.level {
+            return;
+        }
+        match &self.captured {
+            Some(lines) => lines.borrow_mut().push(line.into()),
+            None => println!("{}", line.into()),
+        }
+    }
+
+    fn info(&self, line: impl Into<String>) {
+        self.at(Verbosity::Normal, line);
+    }
+
+    fn verbose(&self, line: impl Into<String>) {
+        self.at(Verbosity::Verbose, line);
+    }
+
+    fn debug(&self, line: impl Into<String>) {
+        self.at(Verbosity::Debug, line);
+    }
+}
+
+fn path_style(root: &Path, relative: bool) -> out::PathStyle {
+    let flags = PATH_FLAGS.get().copied().unwrap_or_default();
+    out::PathStyle {
+        root: relative.then(|| root.to_path_buf()),
+        home: if flags.redact_home {
+            out::home_dir()
+        } else {
+            None
+        },
+    }
+}
+
+/// How paths are written into files under `root`'s `.symbaker/`.
+fn file_paths(root: &Path) -> out::PathStyle {
+    let flags = PATH_FLAGS.get().copied().unwrap_or_default();
+    path_style(root, !flags.absolute_files)
+}
+
+/// `paths.show(path)`, redacted like the trace lines under `--redact`.
+fn report_path(paths: &out::PathStyle, workspace_root: &Path, path: &Path) -> String {
+    let shown = paths.show(path);
+    if !PATH_FLAGS.get().is_some_and(|f| f.redact) {
+        return shown;
+    }
+    symbaker_build::resolve::Redactor {
+        workspace: Some(workspace_root.to_string_lossy().to_string()),
+        home: out::home_dir().map(|h| h.to_string_lossy().to_string()),
+    }
+    .redact(&shown)
+}
+
+/// How paths are printed to stdout.
+fn stdout_paths(root: &Path) -> out::PathStyle {
+    let flags = PATH_FLAGS.get().copied().unwrap_or_default();
+    path_style(root, flags.relative_stdout)
+}
+
+fn symbaker_output_dir(workspace_root: &Path) -> Result<PathBuf, String> {
     let dir = workspace_root.join(".symbaker");
     fs::create_dir_all(&dir).map_err(|e| format!("mkdir {}: {e}", dir.display()))?;
     Ok(dir)
 }
 
+/// Extracts the `{:?}`-formatted string value that follows `key` (which ends
+/// with the opening quote), undoing Debug escapes such as `\"` and `\\`.
 fn extract_quoted(line: &str, key: &str) -> Option<String> {
     let start = line.find(key)? + key.len();
-    let tail = &line[start..];
-    let end = tail.find('"')?;
-    Some(tail[..end].to_string())
+    let mut out = String::new();
+    let mut chars = line[start..].chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => return Some(out),
+            '\\' => match chars.next()? {
+                'n' => out.push('\n'),
+                'r' => out.push('\r'),
+                't' => out.push('\t'),
+                '0' => out.push('\0'),
+                'u' => {
+                    let rest = chars.as_str();
+                    let body = rest.strip_prefix('{')?;
+                    let close = body.find('}')?;
+                    let code = u32::from_str_radix(&body[..close], 16).ok()?;
+                    out.push(char::from_u32(code)?);
+                    chars = body[close + 1..].chars();
+                }
+                other => out.push(other),
+            },
+            _ => out.push(c),
+        }
+    }
+    None
 }
 
 #[derive(Default, Clone)]
@@ -205,7 +662,19 @@ struct TraceCrate {
     manifest_dir: Option<String>,
     selected_source: Option<String>,
     resolved_prefix: Option<String>,
+    sep: Option<String>,
+    /// The top package the crate's expansion saw.
+    top_package: Option<String>,
+    prefix_hash_base: Option<String>,
+    prefix_hash_input: Option<String>,
+    prefix_hash: Option<String>,
     symbols: Vec<String>,
+    aliases: Vec<String>,
+    template_placeholders: BTreeSet<String>,
+    env_fingerprint: Option<String>,
+    config_hash: Option<String>,
+    /// Export (or alias) name to the Rust function it came from.
+    functions: BTreeMap<String, String>,
 }
 
 #[derive(Serialize)]
@@ -214,8 +683,153 @@ struct ResolutionCrate {
     manifest_dir: Option<String>,
     selected_source: Option<String>,
     resolved_prefix: Option<String>,
-    dependencies: Vec<String>,
-    symbols: Vec<String>,
+    /// Separator from the crate's config; an item's `sep` argument can
+    /// still differ, which its `export` shows.
+    sep: Option<String>,
+    /// `uniqueness = "hash"` derivation: `resolved_prefix` is
+    /// `{prefix_hash_base}_{fnv1a32(prefix_hash_input)}`.
+    prefix_hash_base: Option<String>,
+    prefix_hash_input: Option<String>,
+    prefix_hash: Option<String>,
+    /// Where an inherited prefix came from: the top package for
+    /// `top_package`, the workspace manifest for `workspace`, the config
+    /// file for `override`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    inherited_from: Option<String>,
+    dependencies: Vec<ReportDependency>,
+    /// Cargo features enabled for the crate in this build.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    features: Vec<String>,
+    /// Placeholders used by this crate's `symbaker_module` templates.
+    template_placeholders: Vec<String>,
+    /// Environment fingerprint the crate was expanded under.
+    env_fingerprint: Option<String>,
+    /// Hash of the `symbaker.toml` the crate was expanded with.
+    config_hash: Option<String>,
+    symbols: Vec<ReportSymbol>,
+    /// `compat_alias`/`prefix_aliases` forwarders and `alias` weak aliases;
+    /// intentional, not prefix leaks.
+    alias_symbols: Vec<ReportSymbol>,
+}
+
+/// A direct dependency of a reported crate.
+#[derive(Serialize, Debug, PartialEq)]
+struct ReportDependency {
+    name: String,
+    /// The dependency's own expansions appear in the trace.
+    uses_symbaker: bool,
+}
+
+/// A traced `selected source` without its detail: `override(crate="x")` is
+/// `override`, `prefer_package_prefix(package)` is `prefer_package_prefix`.
+fn source_kind(source: &str) -> &str {
+    source.split('(').next().unwrap_or(source)
+}
+
+/// Sources that name a crate after itself instead of inheriting a prefix;
+/// on a dependency they mean a leak (see `SYMBAKER_ENFORCE_INHERIT`).
+/// `prefer_package_prefix` is a deliberate opt-out, so it is not one.
+fn is_local_source(source: &str) -> bool {
+    matches!(
+        source_kind(source),
+        "package" | "crate" | "crate_fallback_after_priority"
+    )
+}
+
+/// `inherited_from` for a crate resolved from `source`.
+fn inherited_from(
+    source: Option<&str>,
+    top_package: Option<&str>,
+    workspace_manifest: &str,
+    config: Option<&str>,
+) -> Option<String> {
+    match source_kind(source?) {
+        "top_package" => top_package.map(str::to_string),
+        "workspace" => Some(workspace_manifest.to_string()),
+        "override" => config.map(str::to_string),
+        _ => None,
+    }
+}
+
+/// Graphviz view of the traced crates and their direct dependents: one edge
+/// per dependency on a traced crate, green when the dependency inherited its
+/// prefix and red when it fell back to a local source. Untraced crates are
+/// dashed.
+fn resolution_graph_dot(
+    traces: &BTreeMap<String, TraceCrate>,
+    deps: &HashMap<String, Vec<String>>,
+) -> String {
+    let mut nodes: BTreeSet<&str> = traces.keys().map(String::as_str).collect();
+    let mut edges = BTreeSet::<(&str, &str)>::new();
+    for (parent, children) in deps {
+        for child in children.iter().filter(|c| traces.contains_key(*c)) {
+            nodes.insert(parent);
+            edges.insert((parent, child));
+        }
+    }
+    let mut out = String::from("digraph symbaker {\n    rankdir=LR;\n    node [shape=box];\n");
+    for name in &nodes {
+        match traces.get(*name) {
+            Some(t) => out.push_str(&format!(
+                "    {:?} [label={:?}];\n",
+                name,
+                format!(
+                    "{name}\n{} ({})",
+                    t.resolved_prefix.as_deref().unwrap_or("?"),
+                    t.selected_source.as_deref().unwrap_or("unknown")
+                )
+            )),
+            None => out.push_str(&format!("    {name:?} [style=dashed];\n")),
+        }
+    }
+    for (parent, child) in edges {
+        let source = traces[child]
+            .selected_source
+            .as_deref()
+            .unwrap_or("unknown");
+        let color = if is_local_source(source) {
+            "red"
+        } else {
+            "forestgreen"
+        };
+        out.push_str(&format!(
+            "    {parent:?} -> {child:?} [color={color}, label={source:?}];\n"
+        ));
+    }
+    out.push_str("}\n");
+    out
+}
+
+/// One export of a crate and the Rust item it came from.
+#[derive(Serialize, Debug, PartialEq)]
+struct ReportSymbol {
+    /// Missing for traces written before export lines named the item.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    rust_name: Option<String>,
+    export: String,
+}
+
+/// `exports` sorted, each paired with its Rust item from `functions`.
+fn report_symbols(
+    mut exports: Vec<String>,
+    functions: &BTreeMap<String, String>,
+) -> Vec<ReportSymbol> {
+    exports.sort();
+    exports
+        .into_iter()
+        .map(|export| ReportSymbol {
+            rust_name: functions.get(&export).cloned(),
+            export,
+        })
+        .collect()
+}
+
+/// The export name of a report `symbols`/`alias_symbols` entry: a
+/// `{ rust_name, export }` table, or a plain string in older reports.
+fn report_symbol_export(entry: &toml::Value) -> Option<&str> {
+    entry
+        .as_str()
+        .or_else(|| entry.get("export").and_then(|v| v.as_str()))
 }
 
 #[derive(Serialize)]
@@ -223,17 +837,157 @@ struct ResolutionReport {
     generated_unix_utc: u64,
     top_package: Option<String>,
     symbaker_config: Option<String>,
+    /// Fingerprint of the environment this report was generated for.
+    env_fingerprint: Option<String>,
+    /// Hash of `symbaker_config` on disk when the report was written.
+    config_hash: Option<String>,
     trace_file: String,
     crates: Vec<ResolutionCrate>,
     overrides_template: BTreeMap<String, String>,
+    /// Exports of the dumped artifacts that no traced crate produced.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    foreign_exports: Vec<ForeignExport>,
+}
+
+/// An export that carries no resolved prefix and that no traced crate
+/// produced: usually a raw `#[no_mangle]` in a dependency without symbaker.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+struct ForeignExport {
+    symbol: String,
+    artifact: String,
+    /// Best guess at the crate that defines it: the one whose rlib has the
+    /// symbol, else the crate of a legacy-mangled Rust name.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    likely_crate: Option<String>,
+}
+
+/// Exports in `exports` that start with no resolved prefix plus its crate's
+/// separator (`__` when untraced) and are not listed under any crate of
+/// `traces`. `owner_of` guesses a symbol's crate.
+fn foreign_exports(
+    exports: &[(PathBuf, Vec<String>)],
+    traces: &BTreeMap<String, TraceCrate>,
+    owner_of: impl Fn(&str) -> Option<String>,
+    paths: &out::PathStyle,
+) -> Vec<ForeignExport> {
+    let prefixes: Vec<String> = traces
+        .values()
+        .filter_map(|t| {
+            let prefix = t.resolved_prefix.as_deref().filter(|p| !p.is_empty())?;
+            Some(format!("{prefix}{}", t.sep.as_deref().unwrap_or("__")))
+        })
+        .collect();
+    let attributed: HashSet<&str> = traces
+        .values()
+        .flat_map(|t| t.symbols.iter().chain(&t.aliases))
+        .map(String::as_str)
+        .collect();
+    let mut out = Vec::new();
+    for (artifact, symbols) in exports {
+        for symbol in symbols {
+            if attributed.contains(symbol.as_str())
+                || prefixes.iter().any(|p| symbol.starts_with(p.as_str()))
+            {
+                continue;
+            }
+            out.push(ForeignExport {
+                symbol: symbol.clone(),
+                artifact: paths.show(artifact),
+                likely_crate: owner_of(symbol).or_else(|| legacy_mangled_crate(symbol)),
+            });
+        }
+    }
+    out
+}
+
+/// Symbol to crate for every global symbol the `(crate, rlib)` pairs define.
+fn rlib_symbol_owners(rlibs: &[(String, PathBuf)]) -> HashMap<String, String> {
+    let mut owners = HashMap::new();
+    for (krate, rlib) in rlibs {
+        match out::defined_global_symbols(rlib) {
+            Ok(symbols) => {
+                for symbol in symbols {
+                    owners.entry(symbol).or_insert_with(|| krate.clone());
+                }
+            }
+            Err(e) => eprintln!("warning: cannot scan {}: {e}", rlib.display()),
+        }
+    }
+    owners
+}
+
+/// The crate named by a legacy-mangled Rust symbol (`_ZN7dep_lib3foo17h..E`).
+fn legacy_mangled_crate(symbol: &str) -> Option<String> {
+    let rest = symbol.trim_start_matches('_').strip_prefix("ZN")?;
+    let digits = rest.bytes().take_while(u8::is_ascii_digit).count();
+    let len: usize = rest[..digits].parse().ok()?;
+    rest.get(digits..digits + len).map(str::to_string)
+}
+
+/// Foreign exports of a traced build+dump, with owners guessed from `rlibs`
+/// (scanned only when there is something to attribute). Empty without a
+/// trace.
+fn find_foreign_exports(
+    workspace_root: &Path,
+    trace_file: &PathBuf,
+    exports: &[(PathBuf, Vec<String>)],
+    rlibs: &[(String, PathBuf)],
+) -> Result<Vec<ForeignExport>, String> {
+    if !trace_file.exists() {
+        return Ok(Vec::new());
+    }
+    let traces = parse_trace_file(trace_file)?;
+    let owners = std::cell::OnceCell::new();
+    let owner_of = |symbol: &str| {
+        owners
+            .get_or_init(|| rlib_symbol_owners(rlibs))
+            .get(symbol)
+            .cloned()
+    };
+    Ok(foreign_exports(
+        exports,
+        &traces,
+        owner_of,
+        &file_paths(workspace_root),
+    ))
+}
+
+/// Warns about each foreign export; under `--strict-foreign` any is an error.
+fn check_foreign(foreign: &[ForeignExport], strict: bool) -> Result<(), Failure> {
+    for f in foreign {
+        match &f.likely_crate {
+            Some(krate) => eprintln!(
+                "warning: foreign export {} in {} (likely from crate {krate})",
+                f.symbol, f.artifact
+            ),
+            None => eprintln!("warning: foreign export {} in {}", f.symbol, f.artifact),
+        }
+    }
+    if strict && !foreign.is_empty() {
+        return Err(Failure::Regression(format!(
+            "{} foreign export(s) not produced by symbaker; see foreign_exports in resolution.toml",
+            foreign.len()
+        )));
+    }
+    Ok(())
 }
 
+/// Groups trace lines by crate. Each line names its crate inline
+/// (`[symbaker] crate="name" ...`), so interleaved output from parallel
+/// builds is attributed correctly; logs from older versions without that
+/// field fall back to the most recent `env CARGO_PKG_NAME=` line.
 fn parse_trace_file(path: &PathBuf) -> Result<BTreeMap<String, TraceCrate>, String> {
     let body = fs::read_to_string(path).map_err(|e| format!("read {}: {e}", path.display()))?;
+    // Some Windows tools start the files they create with a UTF-8 BOM.
+    Ok(parse_trace(body.strip_prefix('\u{feff}').unwrap_or(&body)))
+}
+
+fn parse_trace(body: &str) -> BTreeMap<String, TraceCrate> {
     let mut map: BTreeMap<String, TraceCrate> = BTreeMap::new();
     let mut current_crate = None::<String>;
 
     for line in body.lines() {
+        let inline_crate = extract_quoted(line, "[symbaker] crate=\"").filter(|c| !c.is_empty());
         if line.contains("env CARGO_PKG_NAME=Some(\"") {
             let crate_name = extract_quoted(line, "CARGO_PKG_NAME=Some(\"");
             let manifest = extract_quoted(line, "CARGO_MANIFEST_DIR=Some(\"");
@@ -245,56 +999,103 @@ fn parse_trace_file(path: &PathBuf) -> Result<BTreeMap<String, TraceCrate>, Stri
             }
             continue;
         }
+        let Some(name) = inline_crate.or_else(|| current_crate.clone()) else {
+            continue;
+        };
+        let entry = map.entry(name.clone()).or_default();
+        if entry.name.is_empty() {
+            entry.name = name;
+        }
+        if let Some(fp) = extract_quoted(line, "env_fingerprint=\"") {
+            entry.env_fingerprint = Some(fp);
+            continue;
+        }
+        if let Some(hash) = extract_quoted(line, "config_hash=\"") {
+            entry.config_hash = Some(hash);
+            continue;
+        }
+        if line.contains("config field=sep ") {
+            entry.sep = extract_quoted(line, "value=\"");
+            continue;
+        }
+        if line.contains(" resolved candidates ") {
+            entry.top_package = extract_quoted(line, "top_package=Some(\"");
+            continue;
+        }
         if line.contains("selected source=") {
-            if let Some(name) = &current_crate {
-                let source = line
-                    .split("selected source=")
-                    .nth(1)
-                    .map(|s| s.split_whitespace().next().unwrap_or("").to_string())
-                    .filter(|s| !s.is_empty());
-                let prefix = extract_quoted(line, "sanitized=\"");
-                let entry = map.entry(name.clone()).or_default();
-                if entry.name.is_empty() {
-                    entry.name = name.clone();
-                }
-                if source.is_some() {
-                    entry.selected_source = source;
+            let source = line
+                .split("selected source=")
+                .nth(1)
+                .map(|s| s.split_whitespace().next().unwrap_or("").to_string())
+                .filter(|s| !s.is_empty());
+            let prefix = extract_quoted(line, "sanitized=\"");
+            if source.is_some() {
+                entry.selected_source = source;
+            }
+            if prefix.is_some() {
+                entry.resolved_prefix = prefix;
+            }
+            continue;
+        }
+        if line.contains("prefix_hash base=\"") {
+            entry.prefix_hash_base = extract_quoted(line, "base=\"");
+            entry.prefix_hash_input = extract_quoted(line, "input=\"");
+            entry.prefix_hash = extract_quoted(line, "hash=\"").map(|h| format!("fnv1a32:{h}"));
+            if let Some(result) = extract_quoted(line, "result=\"") {
+                entry.resolved_prefix = Some(result);
+            }
+            continue;
+        }
+        if let Some(list) = line.split("template_placeholders=[").nth(1) {
+            let list = list.split(']').next().unwrap_or_default();
+            entry.template_placeholders.extend(
+                list.split(',')
+                    .map(|p| p.trim().trim_matches('"'))
+                    .filter(|p| !p.is_empty())
+                    .map(str::to_string),
+            );
+            continue;
+        }
+        let alias_keys = ["compat_alias=\"", "prefix_alias=\"", "weak_alias=\""];
+        if alias_keys.iter().any(|k| line.contains(k)) {
+            let alias = alias_keys.iter().find_map(|k| extract_quoted(line, k));
+            if let Some(alias) = alias {
+                if let Some(function) = extract_quoted(line, "function=\"") {
+                    entry.functions.insert(alias.clone(), function);
                 }
-                if prefix.is_some() {
-                    entry.resolved_prefix = prefix;
+                if !entry.aliases.iter().any(|s| s == &alias) {
+                    entry.aliases.push(alias);
                 }
             }
             continue;
         }
-        if line.contains("export_name=\"") {
-            if let Some(name) = &current_crate {
-                if let Some(export) = extract_quoted(line, "export_name=\"") {
-                    let entry = map.entry(name.clone()).or_default();
-                    if !entry.symbols.iter().any(|s| s == &export) {
-                        entry.symbols.push(export);
-                    }
-                }
+        if let Some(export) = extract_quoted(line, "export_name=\"") {
+            if let Some(function) = extract_quoted(line, "function=\"") {
+                entry.functions.insert(export.clone(), function);
+            }
+            if !entry.symbols.iter().any(|s| s == &export) {
+                entry.symbols.push(export);
             }
         }
     }
+    map
+}
 
-    Ok(map)
+/// The resolved package graph from `cargo metadata`, keyed by package name.
+#[derive(Default)]
+struct MetadataTree {
+    /// Direct dependencies.
+    deps: HashMap<String, Vec<String>>,
+    /// Features cargo enabled for the package.
+    features: HashMap<String, Vec<String>>,
 }
 
-fn metadata_tree(args: &[OsString]) -> Result<HashMap<String, Vec<String>>, String> {
-    let mut cmd = Command::new("cargo");
-    cmd.args(["metadata", "--format-version", "1", "--no-deps"]);
-    if let Some(manifest) = out::manifest_path_from_args(args) {
-        cmd.arg("--manifest-path");
-        cmd.arg(manifest);
-    }
-    let out = cmd.output().map_err(|e| format!("cargo metadata: {e}"))?;
-    if !out.status.success() {
-        return Ok(HashMap::new());
-    }
-    let parsed: Value =
-        serde_json::from_slice(&out.stdout).map_err(|e| format!("parse metadata json: {e}"))?;
+fn metadata_tree(args: &[OsString]) -> Result<MetadataTree, String> {
+    let parsed = out::cargo_metadata(args, false).map_err(|e| e.to_string())?;
+    Ok(parse_metadata(&parsed))
+}
 
+fn parse_metadata(parsed: &Value) -> MetadataTree {
     let mut id_to_name = HashMap::<String, String>::new();
     if let Some(packages) = parsed.get("packages").and_then(|v| v.as_array()) {
         for p in packages {
@@ -306,7 +1107,7 @@ fn metadata_tree(args: &[OsString]) -> Result<HashMap<String, Vec<String>>, Stri
         }
     }
 
-    let mut deps_by_name = HashMap::<String, Vec<String>>::new();
+    let mut tree = MetadataTree::default();
     if let Some(nodes) = parsed
         .get("resolve")
         .and_then(|r| r.get("nodes"))
@@ -330,72 +1131,401 @@ fn metadata_tree(args: &[OsString]) -> Result<HashMap<String, Vec<String>>, Stri
                 }
             }
             deps.sort();
-            deps_by_name.insert(name, deps);
+            let mut features: Vec<String> = n
+                .get("features")
+                .and_then(|v| v.as_array())
+                .into_iter()
+                .flatten()
+                .filter_map(|f| f.as_str().map(str::to_string))
+                .collect();
+            features.sort();
+            features.dedup();
+            tree.features.insert(name.clone(), features);
+            tree.deps.insert(name, deps);
         }
     }
-    Ok(deps_by_name)
+    tree
 }
 
-fn write_resolution_report(
-    workspace_root: &PathBuf,
-    args: &[OsString],
-    trace_file: &PathBuf,
-) -> Result<PathBuf, String> {
-    if !trace_file.exists() {
-        return Err(format!("trace file missing: {}", trace_file.display()));
+/// `child -> parent` for every package that (transitively) depends on
+/// `symbaker`. A crate reachable from `top` gets the package that reaches it
+/// first in a breadth-first walk from `top` (dependencies in name order);
+/// any other crate, `top` included, gets its alphabetically first dependent.
+fn parent_map(deps: &HashMap<String, Vec<String>>, top: Option<&str>) -> BTreeMap<String, String> {
+    fn uses_symbaker<'a>(
+        name: &'a str,
+        deps: &'a HashMap<String, Vec<String>>,
+        memo: &mut HashMap<&'a str, bool>,
+    ) -> bool {
+        if let Some(&v) = memo.get(name) {
+            return v;
+        }
+        memo.insert(name, false);
+        let v = deps.get(name).is_some_and(|ds| {
+            ds.iter()
+                .any(|d| d == "symbaker" || uses_symbaker(d, deps, memo))
+        });
+        memo.insert(name, v);
+        v
     }
-    let traces = parse_trace_file(trace_file)?;
-    let deps = metadata_tree(args).unwrap_or_default();
+    let mut memo = HashMap::new();
+    let mut users: Vec<&str> = deps
+        .keys()
+        .map(String::as_str)
+        .filter(|n| uses_symbaker(n, deps, &mut memo))
+        .collect();
+    users.sort();
 
-    let mut crates = Vec::<ResolutionCrate>::new();
-    let mut overrides = BTreeMap::<String, String>::new();
+    let mut parents = BTreeMap::<String, String>::new();
+    if let Some(top) = top.filter(|t| deps.contains_key(*t)) {
+        let mut seen = BTreeSet::from([top.to_string()]);
+        let mut queue = std::collections::VecDeque::from([top.to_string()]);
+        while let Some(node) = queue.pop_front() {
+            for dep in deps.get(&node).into_iter().flatten() {
+                if seen.insert(dep.clone()) {
+                    parents.insert(dep.clone(), node.clone());
+                    queue.push_back(dep.clone());
+                }
+            }
+        }
+    }
+    parents.retain(|child, _| users.contains(&child.as_str()));
+    for user in users {
+        if parents.contains_key(user) {
+            continue;
+        }
+        let mut dependents: Vec<&String> = deps
+            .iter()
+            .filter(|(_, ds)| ds.iter().any(|d| d == user))
+            .map(|(name, _)| name)
+            .collect();
+        dependents.sort();
+        if let Some(first) = dependents.first() {
+            parents.insert(user.to_string(), first.to_string());
+        }
+    }
+    parents
+}
 
-    for (name, t) in traces {
-        let mut symbols = t.symbols;
-        symbols.sort();
-        let deps_for = deps.get(&name).cloned().unwrap_or_default();
-        if let Some(pref) = &t.resolved_prefix {
-            overrides.insert(name.clone(), pref.clone());
+/// Dependencies and enabled features for the resolution report. A `cargo
+/// metadata` failure is an error unless `ignore_errors`
+/// (`SYMBAKER_IGNORE_METADATA_ERRORS=1`), which warns and leaves every
+/// crate's `dependencies` and `features` empty.
+fn resolution_metadata(args: &[OsString], ignore_errors: bool) -> Result<MetadataTree, String> {
+    match metadata_tree(args) {
+        Ok(tree) => Ok(tree),
+        Err(e) if ignore_errors => {
+            eprintln!("warning: cargo metadata failed: {e}");
+            Ok(MetadataTree::default())
         }
-        crates.push(ResolutionCrate {
-            name,
-            manifest_dir: t.manifest_dir,
-            selected_source: t.selected_source,
-            resolved_prefix: t.resolved_prefix,
-            dependencies: deps_for,
-            symbols,
-        });
+        Err(e) => Err(format!(
+            "cargo metadata failed: {e} (set SYMBAKER_IGNORE_METADATA_ERRORS=1 to write resolution.toml without dependencies or features)"
+        )),
     }
-    crates.sort_by(|a, b| a.name.cmp(&b.name));
+}
 
+/// Reasons the traced crates cannot be reported together: crates traced
+/// under different environment fingerprints, or a fingerprint other than
+/// `expected` (the environment symdump runs the build with). Crates traced
+/// by versions without fingerprints are not checked.
+fn fingerprint_problems(
+    traces: &BTreeMap<String, TraceCrate>,
+    expected: Option<&str>,
+) -> Vec<String> {
+    let mut by_fp = BTreeMap::<&str, Vec<&str>>::new();
+    for (name, t) in traces {
+        if let Some(fp) = &t.env_fingerprint {
+            by_fp.entry(fp).or_default().push(name);
+        }
+    }
+    let mut problems = Vec::new();
+    if by_fp.len() > 1 {
+        let groups: Vec<String> = by_fp
+            .iter()
+            .map(|(fp, crates)| format!("{fp} ({})", crates.join(", ")))
+            .collect();
+        problems.push(format!(
+            "trace mixes builds with different environments: {}",
+            groups.join("; ")
+        ));
+    }
+    if let Some(expected) = expected {
+        for (fp, crates) in &by_fp {
+            if *fp != expected {
+                problems.push(format!(
+                    "crates {} were traced under env fingerprint {fp}, but the current environment is {expected}",
+                    crates.join(", ")
+                ));
+            }
+        }
+    }
+    problems
+}
+
+/// Crates expanded under a `symbaker.toml` other than the one on disk
+/// (`on_disk`), or, when there is none, under differing versions of it. Cargo
+/// does not re-expand a dependency whose inputs it thinks are unchanged, so
+/// this is almost always a stale build cache.
+fn config_hash_problems(
+    traces: &BTreeMap<String, TraceCrate>,
+    on_disk: Option<&str>,
+) -> Vec<String> {
+    let mut by_hash = BTreeMap::<&str, Vec<&str>>::new();
+    for (name, t) in traces {
+        if let Some(hash) = &t.config_hash {
+            by_hash.entry(hash).or_default().push(name);
+        }
+    }
+    let Some(on_disk) = on_disk else {
+        if by_hash.len() <= 1 {
+            return Vec::new();
+        }
+        let groups: Vec<String> = by_hash
+            .iter()
+            .map(|(hash, crates)| format!("{hash} ({})", crates.join(", ")))
+            .collect();
+        return vec![format!(
+            "trace mixes symbaker.toml versions (likely a stale build cache): {}",
+            groups.join("; ")
+        )];
+    };
+    by_hash
+        .iter()
+        .filter(|(hash, _)| **hash != on_disk)
+        .map(|(hash, crates)| {
+            let clean: Vec<String> = crates.iter().map(|c| format!("-p {c}")).collect();
+            format!(
+                "crates {} were expanded with symbaker.toml {hash}, but the file on disk is {on_disk} (likely a stale build cache; rebuild them with `cargo clean {}`)",
+                crates.join(", "),
+                clean.join(" ")
+            )
+        })
+        .collect()
+}
+
+/// [`symbaker_build::resolve::env_fingerprint`] of the env `cmd` will run
+/// with: its own overrides, then this process's env, then the workspace
+/// `.cargo/config.toml` `[env]` table (which cargo applies to unset keys).
+fn command_env_fingerprint(cmd: &Command, workspace_root: &Path) -> String {
+    let config_env = cargo_config_env(workspace_root);
+    let names: BTreeSet<String> = env::vars_os()
+        .map(|(k, _)| k)
+        .chain(cmd.get_envs().map(|(k, _)| k.to_os_string()))
+        .filter_map(|k| k.into_string().ok())
+        .chain(config_env.keys().cloned())
+        .collect();
+    symbaker_build::resolve::env_fingerprint(
+        |key| match cmd.get_envs().find(|(k, _)| *k == key) {
+            Some((_, v)) => v.map(|v| v.to_string_lossy().to_string()),
+            None => env::var(key).ok().or_else(|| config_env.get(key).cloned()),
+        },
+        names,
+    )
+}
+
+fn write_resolution_report(
+    workspace_root: &Path,
+    args: &[OsString],
+    trace_file: &PathBuf,
+    expected_fingerprint: Option<&str>,
+    force: bool,
+    foreign: Option<&[ForeignExport]>,
+) -> Result<PathBuf, String> {
+    if !trace_file.exists() {
+        return Err(format!("trace file missing: {}", trace_file.display()));
+    }
+    let traces = parse_trace_file(trace_file)?;
+    let config_hash = env::var("SYMBAKER_CONFIG")
+        .ok()
+        .and_then(|p| symbaker_build::resolve::config_hash(&p));
+    let mut problems = config_hash_problems(&traces, config_hash.as_deref());
+    problems.extend(fingerprint_problems(&traces, expected_fingerprint));
+    if !problems.is_empty() {
+        if !force {
+            return Err(format!(
+                "refusing to write resolution.toml from {}: {} (rebuild with --trace, or pass --force to write it anyway)",
+                trace_file.display(),
+                problems.join("; ")
+            ));
+        }
+        for p in &problems {
+            eprintln!("warning: resolution.toml may be misleading: {p}");
+        }
+    }
+    let ignore_errors = env::var("SYMBAKER_IGNORE_METADATA_ERRORS").as_deref() == Ok("1");
+    let metadata = resolution_metadata(args, ignore_errors)?;
+
+    let paths = file_paths(workspace_root);
+    let top_package = env::var("SYMBAKER_TOP_PACKAGE").ok();
+    let symbaker_config = env::var("SYMBAKER_CONFIG")
+        .ok()
+        .map(|c| report_path(&paths, workspace_root, Path::new(&c)));
+    let workspace_manifest =
+        report_path(&paths, workspace_root, &workspace_root.join("Cargo.toml"));
+    let graph = resolution_graph_dot(&traces, &metadata.deps);
+    let traced: BTreeSet<String> = traces.keys().cloned().collect();
+    let mut crates = Vec::<ResolutionCrate>::new();
+    let mut overrides = BTreeMap::<String, String>::new();
+
+    for (name, t) in traces {
+        let symbols = report_symbols(t.symbols, &t.functions);
+        let alias_symbols = report_symbols(t.aliases, &t.functions);
+        let deps_for = metadata
+            .deps
+            .get(&name)
+            .into_iter()
+            .flatten()
+            .map(|dep| ReportDependency {
+                name: dep.clone(),
+                uses_symbaker: traced.contains(dep),
+            })
+            .collect();
+        let inherited = inherited_from(
+            t.selected_source.as_deref(),
+            t.top_package.as_deref().or(top_package.as_deref()),
+            &workspace_manifest,
+            symbaker_config.as_deref(),
+        );
+        let features = metadata.features.get(&name).cloned().unwrap_or_default();
+        // Overrides are hashed again under `uniqueness = "hash"`, so suggest the base.
+        if let Some(pref) = t.prefix_hash_base.as_ref().or(t.resolved_prefix.as_ref()) {
+            overrides.insert(name.clone(), pref.clone());
+        }
+        crates.push(ResolutionCrate {
+            name,
+            manifest_dir: t
+                .manifest_dir
+                .map(|d| report_path(&paths, workspace_root, Path::new(&d))),
+            selected_source: t.selected_source,
+            resolved_prefix: t.resolved_prefix,
+            sep: t.sep,
+            prefix_hash_base: t.prefix_hash_base,
+            prefix_hash_input: t.prefix_hash_input,
+            prefix_hash: t.prefix_hash,
+            inherited_from: inherited,
+            dependencies: deps_for,
+            features,
+            template_placeholders: t.template_placeholders.into_iter().collect(),
+            env_fingerprint: t.env_fingerprint,
+            config_hash: t.config_hash,
+            symbols,
+            alias_symbols,
+        });
+    }
+    crates.sort_by(|a, b| a.name.cmp(&b.name));
+
+    let out_dir = symbaker_output_dir(workspace_root)?;
+    let out_path = out_dir.join("resolution.toml");
+    let foreign_exports = match foreign {
+        Some(foreign) => foreign.to_vec(),
+        None => previous_foreign_exports(&out_path),
+    };
     let report = ResolutionReport {
         generated_unix_utc: std::time::SystemTime::now()
             .duration_since(std::time::UNIX_EPOCH)
             .map(|d| d.as_secs())
             .unwrap_or(0),
-        top_package: env::var("SYMBAKER_TOP_PACKAGE").ok(),
-        symbaker_config: env::var("SYMBAKER_CONFIG").ok(),
-        trace_file: trace_file.display().to_string(),
+        top_package,
+        symbaker_config,
+        env_fingerprint: expected_fingerprint.map(str::to_string),
+        config_hash,
+        trace_file: report_path(&paths, workspace_root, trace_file),
         crates,
         overrides_template: overrides,
+        foreign_exports,
     };
-
-    let out_dir = symbaker_output_dir(workspace_root)?;
-    let out_path = out_dir.join("resolution.toml");
     let encoded =
         toml::to_string_pretty(&report).map_err(|e| format!("encode report toml: {e}"))?;
     fs::write(&out_path, encoded).map_err(|e| format!("write {}: {e}", out_path.display()))?;
+    let graph_path = out_dir.join("graph.dot");
+    fs::write(&graph_path, graph).map_err(|e| format!("write {}: {e}", graph_path.display()))?;
     Ok(out_path)
 }
 
-fn parse_init_args(args: &[OsString]) -> Result<(Option<String>, bool), String> {
-    let mut prefix = None::<String>;
-    let mut force = false;
+#[derive(Default)]
+struct InitArgs {
+    prefix: Option<String>,
+    sep: Option<String>,
+    priority: Option<Vec<String>>,
+    metadata: bool,
+    force: bool,
+    no_gitignore: bool,
+    with_build_checks: bool,
+    dry_run: bool,
+    emit_cargo_config: bool,
+    workspace_members_only: bool,
+}
+
+fn parse_priority(raw: &str) -> Result<Vec<String>, String> {
+    symbaker_build::resolve::parse_priority_list(raw).map_err(|e| format!("--priority: {e}"))
+}
+
+fn parse_init_args(args: &[OsString]) -> Result<InitArgs, String> {
+    let mut out = InitArgs::default();
     let mut i = 0usize;
     while i < args.len() {
         let cur = args[i].to_string_lossy();
         if cur == "--force" {
-            force = true;
+            out.force = true;
+            i += 1;
+            continue;
+        }
+        if cur == "--allow-sanitized" {
+            // Sanitized prefixes are always written now; still accepted so
+            // existing scripts keep working.
+            i += 1;
+            continue;
+        }
+        if cur == "--metadata" {
+            out.metadata = true;
+            i += 1;
+            continue;
+        }
+        if cur == "--no-gitignore" {
+            out.no_gitignore = true;
+            i += 1;
+            continue;
+        }
+        if cur == "--with-build-checks" {
+            out.with_build_checks = true;
+            i += 1;
+            continue;
+        }
+        if cur == "--emit-cargo-config" {
+            out.emit_cargo_config = true;
+            i += 1;
+            continue;
+        }
+        if cur == "--dry-run" {
+            out.dry_run = true;
+            i += 1;
+            continue;
+        }
+        if cur == "--workspace-members-only" {
+            out.workspace_members_only = true;
+            i += 1;
+            continue;
+        }
+        if cur == "--sep" || cur == "--priority" {
+            if i + 1 >= args.len() {
+                return Err(format!("missing value for {}", cur));
+            }
+            let v = args[i + 1].to_string_lossy().to_string();
+            if cur == "--sep" {
+                out.sep = Some(v);
+            } else {
+                out.priority = Some(parse_priority(&v)?);
+            }
+            i += 2;
+            continue;
+        }
+        if let Some(v) = cur.strip_prefix("--sep=") {
+            out.sep = Some(v.to_string());
+            i += 1;
+            continue;
+        }
+        if let Some(v) = cur.strip_prefix("--priority=") {
+            out.priority = Some(parse_priority(v)?);
             i += 1;
             continue;
         }
@@ -403,84 +1533,333 @@ fn parse_init_args(args: &[OsString]) -> Result<(Option<String>, bool), String>
             if i + 1 >= args.len() {
                 return Err("missing value for --prefix".to_string());
             }
-            prefix = Some(args[i + 1].to_string_lossy().to_string());
+            out.prefix = Some(sanitize_init_prefix(&args[i + 1].to_string_lossy()));
             i += 2;
             continue;
         }
         if let Some(v) = cur.strip_prefix("--prefix=") {
-            prefix = Some(v.to_string());
+            out.prefix = Some(sanitize_init_prefix(v));
             i += 1;
             continue;
         }
         return Err(format!("unknown init arg: {}", cur));
     }
-    Ok((prefix, force))
+    Ok(out)
 }
 
-fn run_init(args: Vec<OsString>) -> Result<(), String> {
-    let (prefix, force) = parse_init_args(&args)?;
-    let root = discover_workspace_root()?;
-    let cfg_path = root.join("symbaker.toml");
-    let out_dir = symbaker_output_dir(&root)?;
-    let cargo_cfg_dir = root.join(".cargo");
-    let cargo_cfg_path = cargo_cfg_dir.join("config.toml");
+/// Returns `raw` as the macros will bake it, noting when that differs so
+/// the written config matches the exported symbols.
+fn sanitize_init_prefix(raw: &str) -> String {
+    let baked = symbaker_build::resolve::sanitize(raw);
+    if baked != raw {
+        eprintln!(
+            "note: prefix '{}' sanitized to '{}'; writing sanitized form",
+            raw, baked
+        );
+    }
+    baked
+}
 
-    if !cfg_path.exists() || force {
-        let mut body = String::new();
-        if let Some(p) = prefix {
-            body.push_str(&format!("prefix = \"{}\"\n", p));
+/// Returns the prefix as it will be baked, or an error when that collides
+/// with `reserved` (the existing `symbaker.toml` `reserved_prefixes`).
+fn check_init_prefix(raw: &str, reserved: &[String]) -> Result<String, String> {
+    let baked = symbaker_build::resolve::sanitize(raw);
+    if let Some(r) = reserved
+        .iter()
+        .find(|r| **r == baked || symbaker_build::resolve::sanitize(r) == baked)
+    {
+        return Err(format!(
+            "prefix {:?} is reserved ({:?} in reserved_prefixes)",
+            baked, r
+        ));
+    }
+    Ok(baked)
+}
+
+/// `reserved_prefixes` of an existing config table (`symbaker.toml` or
+/// `[workspace.metadata.symbaker]`).
+fn reserved_prefixes(cfg: Option<&toml::Value>) -> Vec<String> {
+    cfg.and_then(|c| c.get("reserved_prefixes"))
+        .and_then(|v| v.as_array())
+        .into_iter()
+        .flatten()
+        .filter_map(|v| v.as_str().map(str::to_string))
+        .collect()
+}
+
+fn workspace_metadata_config(manifest: &toml::Value) -> Option<&toml::Value> {
+    manifest.get("workspace")?.get("metadata")?.get("symbaker")
+}
+
+/// The `symbaker.toml` template, also used as the body of
+/// `[workspace.metadata.symbaker]` under `--metadata`. Each of `members`
+/// gets an `[overrides]` entry naming itself, for the user to edit.
+fn init_config_body(
+    prefix: Option<&str>,
+    sep: &str,
+    priority: &[String],
+    members: &[String],
+) -> String {
+    let mut body = String::new();
+    if let Some(p) = prefix {
+        body.push_str(&format!("prefix = {}\n", toml::Value::from(p)));
+    } else {
+        body.push_str("# prefix = \"hdr\"\n");
+    }
+    body.push_str(&format!("sep = {}\n", toml::Value::from(sep)));
+    body.push_str(&format!(
+        "priority = {}\n",
+        toml::Value::from(priority.to_vec())
+    ));
+    body.push_str("\n[overrides]\n");
+    if members.is_empty() {
+        body.push_str("# ssbusync = \"hdr\"\n");
+    }
+    for name in members {
+        body.push_str(&format!("{name} = {}\n", toml::Value::from(name.as_str())));
+    }
+    body
+}
+
+/// Sets `[workspace.metadata.symbaker]` in a workspace manifest to `body`,
+/// keeping the rest of the file as written. Returns `None` when the table
+/// already exists and `force` is off.
+fn write_workspace_metadata(
+    manifest: &str,
+    body: &str,
+    force: bool,
+) -> Result<Option<String>, String> {
+    let mut doc = manifest
+        .parse::<toml_edit::DocumentMut>()
+        .map_err(|e| format!("parse manifest: {e}"))?;
+    let Some(workspace) = doc.get_mut("workspace").and_then(|w| w.as_table_mut()) else {
+        return Err(
+            "--metadata needs a [workspace] table in the root Cargo.toml; add one or drop --metadata to use symbaker.toml"
+                .to_string(),
+        );
+    };
+    let metadata = workspace
+        .entry("metadata")
+        .or_insert_with(|| {
+            let mut t = toml_edit::Table::new();
+            t.set_implicit(true);
+            toml_edit::Item::Table(t)
+        })
+        .as_table_mut()
+        .ok_or("[workspace.metadata] is not a table")?;
+    if metadata.contains_key("symbaker") && !force {
+        return Ok(None);
+    }
+    let table = body
+        .parse::<toml_edit::DocumentMut>()
+        .map_err(|e| format!("parse metadata body: {e}"))?
+        .as_table()
+        .clone();
+    metadata.insert("symbaker", toml_edit::Item::Table(table));
+    Ok(Some(doc.to_string()))
+}
+
+/// Ignores `.symbaker/` output except the `.gitkeep` that keeps it tracked;
+/// add `!/.symbaker/sym.log`-style lines to commit specific outputs.
+const GITIGNORE_ENTRIES: [&str; 3] = [
+    "/.symbaker/*",
+    "!/.symbaker/.gitkeep",
+    "target/**/*.exports.txt",
+];
+
+const GITKEEP: &str = "# This file ensures the .symbaker/ directory is tracked by git.\n";
+
+fn gitignore_lists(existing: &str, entry: &str) -> bool {
+    // `.symbaker/*` and `/.symbaker/*` (or `!` forms) are the same rule here.
+    let bare = |s: &str| {
+        let s = s.trim();
+        let (negated, rule) = match s.strip_prefix('!') {
+            Some(rule) => (true, rule),
+            None => (false, s),
+        };
+        (negated, rule.trim_matches('/').to_string())
+    };
+    existing.lines().any(|l| bare(l) == bare(entry))
+}
+
+/// Text to append to `.gitignore` (empty when the file is missing) so it
+/// covers symbaker's generated files, or `None` when it already does.
+fn gitignore_addition(existing: &str) -> Option<String> {
+    let missing: Vec<&str> = GITIGNORE_ENTRIES
+        .into_iter()
+        .filter(|entry| !gitignore_lists(existing, entry))
+        .collect();
+    if missing.is_empty() {
+        return None;
+    }
+    let mut out = String::new();
+    if !existing.is_empty() {
+        if !existing.ends_with('\n') {
+            out.push('\n');
+        }
+        out.push('\n');
+    }
+    out.push_str("# symbaker generated files\n");
+    for entry in missing {
+        out.push_str(entry);
+        out.push('\n');
+    }
+    Some(out)
+}
+
+/// A file change planned by `cargo symdump init`. Every edit is planned
+/// before any is applied, so `--dry-run` can print them instead.
+struct InitEdit {
+    path: PathBuf,
+    contents: String,
+    append: bool,
+    notes: Vec<String>,
+}
+
+fn apply_init_edits(edits: &[InitEdit], dry_run: bool, log: &Log) -> Result<(), String> {
+    use std::io::Write;
+    for edit in edits {
+        if dry_run {
+            let verb = if edit.append { "append to" } else { "write" };
+            for note in &edit.notes {
+                log.info(format!("would {note}"));
+            }
+            log.info(format!(
+                "would {verb} {}:\n{}",
+                edit.path.display(),
+                edit.contents
+            ));
+            continue;
+        }
+        if let Some(dir) = edit.path.parent() {
+            fs::create_dir_all(dir).map_err(|e| format!("mkdir {}: {e}", dir.display()))?;
+        }
+        let written = if edit.append {
+            fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&edit.path)
+                .and_then(|mut f| f.write_all(edit.contents.as_bytes()))
         } else {
-            body.push_str("# prefix = \"hdr\"\n");
+            fs::write(&edit.path, &edit.contents)
+        };
+        written.map_err(|e| format!("write {}: {e}", edit.path.display()))?;
+        for note in &edit.notes {
+            log.info(note.as_str());
         }
-        body.push_str("sep = \"__\"\n");
-        body.push_str("priority = [\"attr\", \"env_prefix\", \"config\", \"top_package\", \"workspace\", \"package\", \"crate\"]\n");
-        body.push_str("\n[overrides]\n");
-        body.push_str("# ssbusync = \"hdr\"\n");
-        fs::write(&cfg_path, body).map_err(|e| format!("write {}: {e}", cfg_path.display()))?;
-        println!("wrote {}", cfg_path.display());
-    } else {
-        println!("kept existing {}", cfg_path.display());
+        let verb = if edit.append { "updated" } else { "wrote" };
+        log.info(format!("{verb} {}", edit.path.display()));
+    }
+    Ok(())
+}
+
+/// Current contents of `path`, including edits already planned for it.
+fn planned_text(edits: &[InitEdit], path: &Path) -> Option<String> {
+    match edits.iter().rev().find(|e| e.path == path && !e.append) {
+        Some(edit) => Some(edit.contents.clone()),
+        None => fs::read_to_string(path).ok(),
+    }
+}
+
+fn plan_edit(edits: &mut Vec<InitEdit>, edit: InitEdit) {
+    if !edit.append {
+        edits.retain(|e| e.path != edit.path || e.append);
     }
+    edits.push(edit);
+}
+
+/// `SYMBAKER_*` keys set in a cargo config file, found line by line so a
+/// file that no longer parses is still checked.
+fn symbaker_env_keys(text: &str) -> Vec<String> {
+    text.lines()
+        .map(str::trim)
+        .filter(|l| l.starts_with("SYMBAKER_"))
+        .filter_map(|l| l.split(['=', ' ']).next())
+        .map(|k| k.to_string())
+        .collect()
+}
 
-    fs::create_dir_all(&cargo_cfg_dir)
-        .map_err(|e| format!("mkdir {}: {e}", cargo_cfg_dir.display()))?;
+/// Warns about a pre-1.39 `.cargo/config` next to `config.toml`. When both
+/// exist cargo reads the legacy file, so `SYMBAKER_*` entries in it would
+/// shadow what init writes; that needs `--force`.
+fn check_legacy_cargo_config(cargo_cfg_path: &Path, force: bool) -> Result<(), String> {
+    let legacy = cargo_cfg_path.with_file_name("config");
+    if !legacy.is_file() {
+        return Ok(());
+    }
+    eprintln!(
+        "warning: found {}; init writes {} as usual. Both files can coexist, but while both exist cargo reads {} and ignores {}",
+        legacy.display(),
+        cargo_cfg_path.display(),
+        legacy.display(),
+        cargo_cfg_path.display()
+    );
+    if !cargo_cfg_path.exists() {
+        return Ok(());
+    }
+    let text =
+        fs::read_to_string(&legacy).map_err(|e| format!("read {}: {e}", legacy.display()))?;
+    let keys = symbaker_env_keys(&text);
+    if keys.is_empty() || force {
+        return Ok(());
+    }
+    Err(format!(
+        "{} already sets {}; move them into {} and delete it, or pass --force to update {} anyway",
+        legacy.display(),
+        keys.join(", "),
+        cargo_cfg_path.display(),
+        cargo_cfg_path.display()
+    ))
+}
 
+/// Plans `.cargo/config.toml` with the symbaker `[env]` keys, keeping any
+/// that are already set. `dry_run` words the kept keys as nothing written.
+fn plan_cargo_config(
+    cargo_cfg_path: &Path,
+    cfg_path: &Path,
+    metadata: bool,
+    dry_run: bool,
+    log: &Log,
+) -> Result<InitEdit, String> {
     let cfg_value = cfg_path.to_string_lossy().to_string();
+    // With --metadata there is no config file; the macros read the manifest.
+    let mut keys = Vec::<(&str, String)>::new();
+    if !metadata {
+        keys.push(("SYMBAKER_CONFIG", cfg_value.clone()));
+        keys.push(("SYMBAKER_REQUIRE_CONFIG", "1".to_string()));
+    }
+    keys.push(("SYMBAKER_ENFORCE_INHERIT", "1".to_string()));
+    keys.push(("SYMBAKER_INITIALIZED", "1".to_string()));
+
     if !cargo_cfg_path.exists() {
         let mut body = String::new();
         body.push_str("# symbaker env config\n");
         body.push_str("# SYMBAKER_CONFIG: path to symbaker.toml\n");
         body.push_str("# SYMBAKER_REQUIRE_CONFIG: 1 => error if SYMBAKER_CONFIG is missing\n");
-        body.push_str(
-            "# SYMBAKER_ENFORCE_INHERIT: 1 => error if dependancy takes over symbaker\n",
-        );
+        body.push_str("# SYMBAKER_ENFORCE_INHERIT: 1 => error if dependancy takes over symbaker\n");
         body.push_str(
             "# SYMBAKER_INITIALIZED: 1 => marks setup complete (removes uninitialized warning)\n",
         );
         body.push_str("\n[env]\n");
-        let cfg_literal = cfg_value.replace('\'', "''");
-        body.push_str(&format!("SYMBAKER_CONFIG = '{}'\n", cfg_literal));
-        body.push_str("SYMBAKER_REQUIRE_CONFIG = \"1\"\n");
-        body.push_str("SYMBAKER_ENFORCE_INHERIT = \"1\"\n");
-        body.push_str("SYMBAKER_INITIALIZED = \"1\"\n");
-        fs::write(&cargo_cfg_path, body)
-            .map_err(|e| format!("write {}: {e}", cargo_cfg_path.display()))?;
-        println!("wrote {}", cargo_cfg_path.display());
-        println!("updated {}", cargo_cfg_path.display());
-        println!("output dir: {}", out_dir.display());
-        println!("symbaker init complete");
-        return Ok(());
+        for (key, value) in &keys {
+            if *key == "SYMBAKER_CONFIG" {
+                body.push_str(&format!("{key} = '{}'\n", value.replace('\'', "''")));
+            } else {
+                body.push_str(&format!("{key} = \"{value}\"\n"));
+            }
+        }
+        return Ok(InitEdit {
+            path: cargo_cfg_path.to_path_buf(),
+            contents: body,
+            append: false,
+            notes: Vec::new(),
+        });
     }
 
-    let mut doc = if cargo_cfg_path.exists() {
-        let text = fs::read_to_string(&cargo_cfg_path)
-            .map_err(|e| format!("read {}: {e}", cargo_cfg_path.display()))?;
-        toml::from_str::<toml::Value>(&text)
-            .unwrap_or_else(|_| toml::Value::Table(Default::default()))
-    } else {
-        toml::Value::Table(Default::default())
-    };
-
+    let text = fs::read_to_string(cargo_cfg_path)
+        .map_err(|e| format!("read {}: {e}", cargo_cfg_path.display()))?;
+    let mut doc = toml::from_str::<toml::Value>(&text)
+        .unwrap_or_else(|_| toml::Value::Table(Default::default()));
     let table = match doc.as_table_mut() {
         Some(t) => t,
         None => return Err(format!("{} is not a TOML table", cargo_cfg_path.display())),
@@ -492,410 +1871,2813 @@ fn run_init(args: Vec<OsString>) -> Result<(), String> {
         Some(t) => t,
         None => return Err(format!("{} has non-table [env]", cargo_cfg_path.display())),
     };
-    match env_tbl.get("SYMBAKER_CONFIG") {
-        Some(existing) => {
-            println!(
-                "kept existing [env].SYMBAKER_CONFIG in {}: {}",
-                cargo_cfg_path.display(),
-                existing
-            );
-        }
-        None => {
-            env_tbl.insert(
-                "SYMBAKER_CONFIG".to_string(),
-                toml::Value::String(cfg_value),
-            );
-            println!(
-                "added [env].SYMBAKER_CONFIG to {}",
-                cargo_cfg_path.display()
-            );
-        }
-    }
-    match env_tbl.get("SYMBAKER_REQUIRE_CONFIG") {
-        Some(existing) => {
-            println!(
-                "kept existing [env].SYMBAKER_REQUIRE_CONFIG in {}: {}",
-                cargo_cfg_path.display(),
-                existing
-            );
-        }
-        None => {
-            env_tbl.insert(
-                "SYMBAKER_REQUIRE_CONFIG".to_string(),
-                toml::Value::String("1".to_string()),
-            );
-            println!(
-                "added [env].SYMBAKER_REQUIRE_CONFIG to {}",
-                cargo_cfg_path.display()
-            );
-        }
-    }
-    match env_tbl.get("SYMBAKER_ENFORCE_INHERIT") {
-        Some(existing) => {
-            println!(
-                "kept existing [env].SYMBAKER_ENFORCE_INHERIT in {}: {}",
-                cargo_cfg_path.display(),
-                existing
-            );
-        }
-        None => {
-            env_tbl.insert(
-                "SYMBAKER_ENFORCE_INHERIT".to_string(),
-                toml::Value::String("1".to_string()),
-            );
-            println!(
-                "added [env].SYMBAKER_ENFORCE_INHERIT to {}",
-                cargo_cfg_path.display()
-            );
-        }
-    }
-    match env_tbl.get("SYMBAKER_INITIALIZED") {
-        Some(existing) => {
-            println!(
-                "kept existing [env].SYMBAKER_INITIALIZED in {}: {}",
-                cargo_cfg_path.display(),
-                existing
-            );
-        }
-        None => {
-            env_tbl.insert(
-                "SYMBAKER_INITIALIZED".to_string(),
-                toml::Value::String("1".to_string()),
-            );
-            println!(
-                "added [env].SYMBAKER_INITIALIZED to {}",
-                cargo_cfg_path.display()
-            );
+    let mut notes = Vec::new();
+    for (key, value) in keys {
+        match env_tbl.get(key) {
+            Some(existing) => {
+                log.info(format!(
+                    "{} existing [env].{key} in {}: {}",
+                    if dry_run { "would keep" } else { "kept" },
+                    cargo_cfg_path.display(),
+                    existing
+                ));
+            }
+            None => {
+                env_tbl.insert(key.to_string(), toml::Value::String(value));
+                notes.push(format!("add [env].{key} to {}", cargo_cfg_path.display()));
+            }
         }
     }
-
     let encoded = toml::to_string_pretty(&doc)
         .map_err(|e| format!("encode {}: {e}", cargo_cfg_path.display()))?;
-    fs::write(&cargo_cfg_path, encoded)
-        .map_err(|e| format!("write {}: {e}", cargo_cfg_path.display()))?;
-    println!("updated {}", cargo_cfg_path.display());
-    println!("output dir: {}", out_dir.display());
-    println!("symbaker init complete");
-    Ok(())
+    Ok(InitEdit {
+        path: cargo_cfg_path.to_path_buf(),
+        contents: encoded,
+        append: false,
+        notes,
+    })
 }
 
-fn apply_symbaker_env(
-    cmd: &mut Command,
-    cargo_args: &[OsString],
-    workspace_root: &PathBuf,
-    trace_enabled: bool,
-) {
-    if env::var_os("SYMBAKER_TOP_PACKAGE").is_none() {
-        if let Some(pkg) = out::discover_top_package_name(cargo_args) {
-            cmd.env("SYMBAKER_TOP_PACKAGE", pkg);
-        }
+/// The `[env]` table of a planned `.cargo/config.toml`.
+fn cargo_env_preview(contents: &str) -> Result<String, String> {
+    let doc = toml::from_str::<toml::Value>(contents)
+        .map_err(|e| format!("parse planned cargo config: {e}"))?;
+    let mut preview = toml::Table::new();
+    if let Some(env) = doc.get("env") {
+        preview.insert("env".to_string(), env.clone());
     }
-    if env::var_os("SYMBAKER_CONFIG").is_none() {
-        if let Some(path) = discover_default_config_path() {
-            cmd.env("SYMBAKER_CONFIG", path);
-        }
+    toml::to_string_pretty(&preview).map_err(|e| format!("encode [env]: {e}"))
+}
+
+const BUILD_CHECK_RS: &str = "fn main() {\n    symbaker_build::require_initialized();\n}\n";
+
+/// Derives a `symbaker-build` dependency from how a manifest depends on
+/// `symbaker`: the same version, git source, or `<path>/symbaker-build`.
+fn symbaker_build_dependency(symbaker: &toml_edit::Item) -> Option<toml_edit::Item> {
+    if let Some(version) = symbaker.as_str() {
+        return Some(toml_edit::value(version));
     }
-    if env::var_os("SYMBAKER_ENFORCE_INHERIT").is_none() {
-        cmd.env("SYMBAKER_ENFORCE_INHERIT", "1");
+    let dep = symbaker.as_table_like()?;
+    if dep.contains_key("workspace") {
+        return None;
     }
-    if env::var_os("SYMBAKER_INITIALIZED").is_none() {
-        cmd.env("SYMBAKER_INITIALIZED", "1");
+    let mut out = toml_edit::InlineTable::new();
+    if let Some(path) = dep.get("path").and_then(|p| p.as_str()) {
+        out.insert(
+            "path",
+            format!("{}/symbaker-build", path.trim_end_matches('/')).into(),
+        );
     }
-    if trace_enabled {
-        if env::var_os("SYMBAKER_TRACE").is_none() {
-            cmd.env("SYMBAKER_TRACE", "1");
-        }
-        if env::var_os("SYMBAKER_TRACE_FILE").is_none() {
-            let trace_path = workspace_root.join(".symbaker").join("trace.log");
-            cmd.env("SYMBAKER_TRACE_FILE", trace_path);
+    for key in ["git", "branch", "tag", "rev", "version"] {
+        if let Some(v) = dep.get(key).and_then(|v| v.as_str()) {
+            out.insert(key, v.into());
         }
     }
+    (!out.is_empty()).then(|| toml_edit::value(out))
 }
 
-fn run_build_then_dump(mut args: Vec<OsString>) -> Result<(), String> {
-    // When invoked as `cargo symdump ...`, some environments may still include
-    // a leading `symdump` token in argv. Drop it to avoid recursion.
-    while args
-        .first()
-        .map(|s| s.to_string_lossy() == "symdump")
-        .unwrap_or(false)
+/// Adds `symbaker-build` to `[build-dependencies]`. `Ok(None)` when it is
+/// already there.
+fn add_symbaker_build_dependency(manifest: &str) -> Result<Option<String>, String> {
+    let mut doc = manifest
+        .parse::<toml_edit::DocumentMut>()
+        .map_err(|e| format!("parse manifest: {e}"))?;
+    if doc
+        .get("build-dependencies")
+        .and_then(|d| d.get("symbaker-build"))
+        .is_some()
     {
-        args.remove(0);
+        return Ok(None);
     }
+    let dep = doc
+        .get("dependencies")
+        .and_then(|d| d.get("symbaker"))
+        .and_then(symbaker_build_dependency)
+        .ok_or("cannot derive a symbaker-build dependency from its symbaker dependency")?;
+    let build_deps = doc
+        .entry("build-dependencies")
+        .or_insert_with(|| toml_edit::Item::Table(toml_edit::Table::new()))
+        .as_table_like_mut()
+        .ok_or("[build-dependencies] is not a table")?;
+    build_deps.insert("symbaker-build", dep);
+    Ok(Some(doc.to_string()))
+}
 
-    let trace_enabled = has_flag(&args, "--trace");
-    args.retain(|a| a != "--trace");
-    if args.is_empty() || args[0].to_string_lossy().starts_with('-') {
-        args.insert(0, OsString::from("build"));
-    }
-    let workspace_root = discover_workspace_root_for_args(&args)?;
-    let out_dir = symbaker_output_dir(&workspace_root)?;
-    let trace_file = out_dir.join("trace.log");
-    if trace_enabled {
-        let _ = fs::remove_file(&trace_file);
+/// The `cargo metadata --no-deps` package entries of the workspace members.
+fn workspace_member_packages(root: &Path) -> Result<Vec<Value>, String> {
+    let out = Command::new("cargo")
+        .args([
+            "metadata",
+            "--format-version",
+            "1",
+            "--no-deps",
+            "--manifest-path",
+        ])
+        .arg(root.join("Cargo.toml"))
+        .output()
+        .map_err(|e| format!("cargo metadata: {e}"))?;
+    if !out.status.success() {
+        return Err(format!(
+            "cargo metadata failed: {}",
+            String::from_utf8_lossy(&out.stderr).trim()
+        ));
     }
-
-    let mut build = Command::new("cargo");
-    build.args(&args);
-    apply_symbaker_env(&mut build, &args, &workspace_root, trace_enabled);
-    let status = build
-        .status()
+    let parsed: Value =
+        serde_json::from_slice(&out.stdout).map_err(|e| format!("parse metadata json: {e}"))?;
+    let members: Vec<&str> = parsed
+        .get("workspace_members")
+        .and_then(|v| v.as_array())
+        .into_iter()
+        .flatten()
+        .filter_map(|v| v.as_str())
+        .collect();
+    Ok(parsed
+        .get("packages")
+        .and_then(|v| v.as_array())
+        .into_iter()
+        .flatten()
+        .filter(|p| {
+            p.get("id")
+                .and_then(|v| v.as_str())
+                .is_some_and(|id| members.contains(&id))
+        })
+        .cloned()
+        .collect())
+}
+
+/// Plans a `require_initialized()` build script plus `symbaker-build`
+/// build-dependency for each workspace member that uses `symbaker`.
+fn plan_build_checks(root: &Path, edits: &mut Vec<InitEdit>, log: &Log) -> Result<(), String> {
+    for pkg in workspace_member_packages(root)? {
+        let name = pkg.get("name").and_then(|v| v.as_str()).unwrap_or_default();
+        let uses_symbaker = pkg
+            .get("dependencies")
+            .and_then(|v| v.as_array())
+            .into_iter()
+            .flatten()
+            .any(|d| {
+                d.get("name").and_then(|v| v.as_str()) == Some("symbaker")
+                    && d.get("kind").is_none_or(|k| k.is_null())
+            });
+        if !uses_symbaker {
+            continue;
+        }
+        let Some(manifest_path) = pkg
+            .get("manifest_path")
+            .and_then(|v| v.as_str())
+            .map(PathBuf::from)
+        else {
+            continue;
+        };
+        let build_script = pkg
+            .get("targets")
+            .and_then(|v| v.as_array())
+            .into_iter()
+            .flatten()
+            .find(|t| {
+                t.get("kind")
+                    .and_then(|k| k.as_array())
+                    .is_some_and(|k| k.iter().any(|k| k.as_str() == Some("custom-build")))
+            })
+            .and_then(|t| t.get("src_path").and_then(|v| v.as_str()));
+        if let Some(script) = build_script {
+            log.info(format!(
+                "skipping {name}: it already has a build script ({script}); call symbaker_build::require_initialized() from it"
+            ));
+            continue;
+        }
+        let text = planned_text(edits, &manifest_path)
+            .ok_or_else(|| format!("read {}", manifest_path.display()))?;
+        let updated = match add_symbaker_build_dependency(&text) {
+            Ok(updated) => updated,
+            Err(e) => {
+                log.info(format!(
+                    "skipping {name}: {e}; add symbaker-build as a build-dependency by hand"
+                ));
+                continue;
+            }
+        };
+        if let Some(updated) = updated {
+            plan_edit(
+                edits,
+                InitEdit {
+                    path: manifest_path.clone(),
+                    contents: updated,
+                    append: false,
+                    notes: vec![format!("add symbaker-build build-dependency to {name}")],
+                },
+            );
+        }
+        let dir = manifest_path.parent().unwrap_or(root);
+        plan_edit(
+            edits,
+            InitEdit {
+                path: dir.join("build.rs"),
+                contents: BUILD_CHECK_RS.to_string(),
+                append: false,
+                notes: vec![format!("add require_initialized() build script to {name}")],
+            },
+        );
+    }
+    Ok(())
+}
+
+fn run_init(args: Vec<OsString>, log: &Log) -> Result<(), Failure> {
+    let InitArgs {
+        prefix,
+        sep,
+        priority,
+        metadata,
+        force,
+        no_gitignore,
+        with_build_checks,
+        dry_run,
+        emit_cargo_config,
+        workspace_members_only,
+    } = parse_init_args(&args).map_err(Failure::Usage)?;
+    let root = discover_workspace_root()?;
+    let cfg_path = root.join("symbaker.toml");
+    let manifest_path = root.join("Cargo.toml");
+    if read_manifest(&root)
+        .is_some_and(|m| m.get("workspace").is_some() && m.get("package").is_none())
+    {
+        log.info(format!(
+            "note: {} is a virtual workspace (no [package]); symbaker config is written there for every member",
+            root.display()
+        ));
+    }
+    let existing = if metadata {
+        let text = fs::read_to_string(&manifest_path)
+            .map_err(|e| format!("read {}: {e}", manifest_path.display()))?;
+        let manifest = toml::from_str::<toml::Value>(&text)
+            .map_err(|e| format!("parse {}: {e}", manifest_path.display()))?;
+        workspace_metadata_config(&manifest).cloned()
+    } else {
+        fs::read_to_string(&cfg_path)
+            .ok()
+            .and_then(|text| toml::from_str::<toml::Value>(&text).ok())
+    };
+    let exists = if metadata {
+        existing.is_some()
+    } else {
+        cfg_path.exists()
+    };
+    let prefix = match prefix {
+        Some(p) if !exists || force => Some(check_init_prefix(
+            &p,
+            &reserved_prefixes(existing.as_ref()),
+        )?),
+        other => other,
+    };
+    let cargo_cfg_path = root.join(".cargo").join("config.toml");
+
+    let priority = priority.unwrap_or_else(|| {
+        let mut p = symbaker_build::resolve::default_priority();
+        if metadata {
+            // The metadata prefix is the "workspace" source; rank it where
+            // "config" would be so it still beats the top package name.
+            p.retain(|k| k != "workspace");
+            let at = p.iter().position(|k| k == "config").unwrap_or(0);
+            p.insert(at, "workspace".to_string());
+        }
+        p
+    });
+    let members = if workspace_members_only {
+        let mut names: Vec<String> = workspace_member_packages(&root)?
+            .iter()
+            .filter_map(|p| p.get("name").and_then(|v| v.as_str()).map(str::to_string))
+            .collect();
+        names.sort();
+        names
+    } else {
+        Vec::new()
+    };
+    let body = init_config_body(
+        prefix.as_deref(),
+        sep.as_deref().unwrap_or("__"),
+        &priority,
+        &members,
+    );
+
+    // `--emit-cargo-config` only previews, so it writes nothing either.
+    let writes = !dry_run && !emit_cargo_config;
+    let kept = if writes { "kept" } else { "would keep" };
+    let mut edits = Vec::<InitEdit>::new();
+    if metadata {
+        let text = fs::read_to_string(&manifest_path)
+            .map_err(|e| format!("read {}: {e}", manifest_path.display()))?;
+        match write_workspace_metadata(&text, &body, force)? {
+            Some(updated) => plan_edit(
+                &mut edits,
+                InitEdit {
+                    path: manifest_path.clone(),
+                    contents: updated,
+                    append: false,
+                    notes: vec!["set [workspace.metadata.symbaker]".to_string()],
+                },
+            ),
+            None => log.info(format!(
+                "{kept} existing [workspace.metadata.symbaker] in {}",
+                manifest_path.display()
+            )),
+        }
+    } else if !exists || force {
+        plan_edit(
+            &mut edits,
+            InitEdit {
+                path: cfg_path.clone(),
+                contents: body.clone(),
+                append: false,
+                notes: Vec::new(),
+            },
+        );
+    } else {
+        log.info(format!("{kept} existing {}", cfg_path.display()));
+    }
+
+    if !no_gitignore {
+        let gitignore_path = root.join(".gitignore");
+        let text = fs::read_to_string(&gitignore_path).unwrap_or_default();
+        if let Some(addition) = gitignore_addition(&text) {
+            plan_edit(
+                &mut edits,
+                InitEdit {
+                    path: gitignore_path,
+                    contents: addition,
+                    append: true,
+                    notes: Vec::new(),
+                },
+            );
+        }
+    }
+
+    let gitkeep_path = root.join(".symbaker").join(".gitkeep");
+    if !gitkeep_path.exists() || force {
+        plan_edit(
+            &mut edits,
+            InitEdit {
+                path: gitkeep_path,
+                contents: GITKEEP.to_string(),
+                append: false,
+                notes: Vec::new(),
+            },
+        );
+    }
+
+    check_legacy_cargo_config(&cargo_cfg_path, force)?;
+    let cargo_cfg = plan_cargo_config(&cargo_cfg_path, &cfg_path, metadata, !writes, log)?;
+    if emit_cargo_config {
+        // Preview of the two files init owns; nothing is written.
+        let target = if metadata {
+            "[workspace.metadata.symbaker]"
+        } else {
+            "symbaker.toml"
+        };
+        match existing.as_ref().filter(|_| exists && !force) {
+            Some(kept) => log.info(format!(
+                "# {target} (kept existing)\n{}",
+                toml::to_string_pretty(kept).map_err(|e| format!("encode {target}: {e}"))?
+            )),
+            None => log.info(format!("# {target}\n{body}")),
+        }
+        log.info(format!(
+            "# {} (resulting [env])\n{}",
+            cargo_cfg_path.display(),
+            cargo_env_preview(&cargo_cfg.contents)?
+        ));
+        return Ok(());
+    }
+    plan_edit(&mut edits, cargo_cfg);
+
+    if with_build_checks {
+        plan_build_checks(&root, &mut edits, log)?;
+    }
+
+    apply_init_edits(&edits, dry_run, log)?;
+    if dry_run {
+        log.info("dry run: nothing written");
+        return Ok(());
+    }
+    let out_dir = symbaker_output_dir(&root)?;
+    log.info(format!(
+        "output dir: {}",
+        stdout_paths(&root).show(&out_dir)
+    ));
+    log.info("symbaker init complete");
+    Ok(())
+}
+
+/// Takes `--top-package <name>` out of `args`, checking the name against the
+/// workspace members `cargo metadata` reports for the remaining args.
+fn take_top_package(args: &mut Vec<OsString>) -> Result<Option<String>, String> {
+    let Some(name) = take_flag_value(args, "--top-package") else {
+        return Ok(None);
+    };
+    let name = name.to_string_lossy().to_string();
+    let members = out::workspace_package_names(args)?;
+    if !members.contains(&name) {
+        return Err(format!(
+            "--top-package {:?} is not a workspace member (expected one of {})",
+            name,
+            members.join(", ")
+        ));
+    }
+    Ok(Some(name))
+}
+
+/// Takes every `--override <crate>=<prefix>` out of `args`.
+fn take_overrides(args: &mut Vec<OsString>) -> Result<Vec<(String, String)>, String> {
+    let mut out = Vec::new();
+    while let Some(v) = take_flag_value(args, "--override") {
+        let v = v.to_string_lossy().to_string();
+        match v.split_once('=') {
+            Some((krate, prefix)) if !krate.trim().is_empty() && !prefix.trim().is_empty() => {
+                out.push((krate.trim().to_string(), prefix.trim().to_string()));
+            }
+            _ => return Err(format!("--override {v:?}: expected <crate>=<prefix>")),
+        }
+    }
+    Ok(out)
+}
+
+/// Whether a `priority` the build can see (`SYMBAKER_PRIORITY`, the config
+/// file or `[workspace.metadata.symbaker]`) names `parent_package` or
+/// `root_package`.
+fn priority_names_parents(workspace_root: &Path, cargo_args: &[OsString]) -> bool {
+    let names = |text: &str| text.contains("parent_package") || text.contains("root_package");
+    let env_priority = env::var("SYMBAKER_PRIORITY")
+        .ok()
+        .or_else(|| cargo_config_env(workspace_root).remove("SYMBAKER_PRIORITY"));
+    let file_priority = config_path_for_args(cargo_args)
+        .and_then(|p| fs::read_to_string(p).ok())
+        .and_then(|text| toml::from_str::<toml::Value>(&text).ok())
+        .and_then(|v| v.get("priority").map(|p| p.to_string()));
+    let workspace_priority = read_manifest(workspace_root).and_then(|m| {
+        workspace_metadata_config(&m)
+            .and_then(|c| c.get("priority"))
+            .map(|p| p.to_string())
+    });
+    [env_priority, file_priority, workspace_priority]
+        .iter()
+        .flatten()
+        .any(|p| names(p))
+}
+
+/// Sets the `SYMBAKER_*` env for a wrapped cargo command and returns the top
+/// package the build will see, if any. Each `--override` becomes the
+/// crate's `SYMBAKER_OVERRIDE_<CRATE>`, which wins over its `[overrides]`
+/// entry in the config file.
+fn apply_symbaker_env(
+    cmd: &mut Command,
+    cargo_args: &[OsString],
+    workspace_root: &Path,
+    top_package: Option<&str>,
+    overrides: &[(String, String)],
+    trace_enabled: bool,
+) -> Option<String> {
+    let top_package = match top_package {
+        Some(pkg) => Some(pkg.to_string()),
+        None => env::var("SYMBAKER_TOP_PACKAGE")
+            .ok()
+            .or_else(|| out::discover_top_package_name(cargo_args)),
+    };
+    if let Some(pkg) = &top_package {
+        cmd.env("SYMBAKER_TOP_PACKAGE", pkg);
+    }
+    for (krate, prefix) in overrides {
+        cmd.env(
+            symbaker_build::resolve::crate_override_env_key(krate),
+            prefix,
+        );
+    }
+    // For the `parent_package`/`root_package` priority keys; other builds
+    // skip the full `cargo metadata` this needs.
+    if env::var_os("SYMBAKER_PARENT_PACKAGES").is_none()
+        && priority_names_parents(workspace_root, cargo_args)
+    {
+        match metadata_tree(cargo_args) {
+            Ok(tree) => {
+                let pairs: Vec<String> = parent_map(&tree.deps, top_package.as_deref())
+                    .into_iter()
+                    .map(|(child, parent)| format!("{child}={parent}"))
+                    .collect();
+                cmd.env("SYMBAKER_PARENT_PACKAGES", pairs.join(","));
+            }
+            Err(e) => eprintln!(
+                "warning: cargo metadata failed; parent_package/root_package are unavailable: {e}"
+            ),
+        }
+    }
+    if env::var_os("SYMBAKER_CONFIG").is_none() {
+        if let Some(path) = config_path_for_args(cargo_args) {
+            cmd.env("SYMBAKER_CONFIG", path);
+        }
+    }
+    if env::var_os("SYMBAKER_ENFORCE_INHERIT").is_none() {
+        cmd.env("SYMBAKER_ENFORCE_INHERIT", "1");
+    }
+    if env::var_os("SYMBAKER_INITIALIZED").is_none() {
+        cmd.env("SYMBAKER_INITIALIZED", "1");
+    }
+    if env::var_os("SYMBAKER_MACRO_VERSION").is_none() {
+        cmd.env("SYMBAKER_MACRO_VERSION", env!("CARGO_PKG_VERSION"));
+    }
+    // For `{profile}`/`{target_arch}` in module templates: proc macros do not
+    // see cargo's build-script `PROFILE`/`CARGO_CFG_TARGET_ARCH`.
+    if env::var_os("SYMBAKER_PROFILE").is_none() {
+        let profile = profile_from_args(cargo_args);
+        cmd.env("SYMBAKER_PROFILE", profile_dir_name(profile.as_deref()));
+    }
+    if env::var_os("SYMBAKER_TARGET_ARCH").is_none() {
+        let arch = find_flag_value(cargo_args, "--target")
+            .map(|t| target_arch_of(&t.to_string_lossy()).to_string())
+            .unwrap_or_else(|| env::consts::ARCH.to_string());
+        cmd.env("SYMBAKER_TARGET_ARCH", arch);
+    }
+    if trace_enabled {
+        if env::var_os("SYMBAKER_TRACE").is_none() {
+            cmd.env("SYMBAKER_TRACE", "1");
+        }
+        if env::var_os("SYMBAKER_TRACE_FILE").is_none() {
+            let trace_path = workspace_root.join(".symbaker").join("trace.log");
+            cmd.env("SYMBAKER_TRACE_FILE", trace_path);
+        }
+        if PATH_FLAGS.get().is_some_and(|f| f.redact) {
+            cmd.env("SYMBAKER_TRACE_REDACT", "1");
+        }
+    }
+    top_package
+}
+
+/// `foreign_exports` of the report at `path`, for runs that dump no artifact
+/// and so cannot recompute them. Empty when there is no readable report.
+fn previous_foreign_exports(path: &Path) -> Vec<ForeignExport> {
+    #[derive(Deserialize)]
+    struct Previous {
+        #[serde(default)]
+        foreign_exports: Vec<ForeignExport>,
+    }
+    fs::read_to_string(path)
+        .ok()
+        .and_then(|text| toml::from_str::<Previous>(&text).ok())
+        .map(|p| p.foreign_exports)
+        .unwrap_or_default()
+}
+
+/// Rewrites `resolution.toml` after a traced run. No trace file means nothing
+/// was expanded (e.g. a fully cached build) and is skipped; any other failure,
+/// such as `cargo metadata` failing or a trace from another environment
+/// (unless `force`), is returned. `foreign` is `None` for runs that dump no
+/// artifact (`run`, `test`), which keep the report's previous list.
+fn refresh_resolution_report(
+    workspace_root: &Path,
+    args: &[OsString],
+    trace_file: &PathBuf,
+    expected_fingerprint: Option<&str>,
+    force: bool,
+    foreign: Option<&[ForeignExport]>,
+    log: &Log,
+) -> Result<(), String> {
+    if !trace_file.exists() {
+        log.debug(format!(
+            "no trace at {}; resolution.toml left as is",
+            trace_file.display()
+        ));
+        return Ok(());
+    }
+    let report = write_resolution_report(
+        workspace_root,
+        args,
+        trace_file,
+        expected_fingerprint,
+        force,
+        foreign,
+    )?;
+    log.info(format!(
+        "resolution: {}",
+        stdout_paths(workspace_root).show(&report)
+    ));
+    Ok(())
+}
+
+fn run_build_then_dump(mut args: Vec<OsString>, log: &Log) -> Result<(), Failure> {
+    // When invoked as `cargo symdump ...`, some environments may still include
+    // a leading `symdump` token in argv. Drop it to avoid recursion.
+    while args
+        .first()
+        .map(|s| s.to_string_lossy() == "symdump")
+        .unwrap_or(false)
+    {
+        args.remove(0);
+    }
+
+    // --fail-unprefixed, --strict-foreign and --summary-json read the
+    // resolution trace, so they imply --trace.
+    let fail_unprefixed = has_flag(&args, "--fail-unprefixed");
+    let strict_foreign = take_flag(&mut args, "--strict-foreign");
+    let trace_enabled = has_flag(&args, "--trace")
+        || fail_unprefixed
+        || strict_foreign
+        || find_flag_value(&args, "--summary-json").is_some();
+    let stable = has_flag(&args, "--stable");
+    let force = has_flag(&args, "--force");
+    let fail_on_duplicates = take_flag(&mut args, "--fail-on-duplicates");
+    args.retain(|a| {
+        a != "--trace" && a != "--fail-unprefixed" && a != "--stable" && a != "--force"
+    });
+    let artifact = take_flag_value(&mut args, "--artifact");
+    let top_package = take_top_package(&mut args).map_err(Failure::Usage)?;
+    let overrides = take_overrides(&mut args).map_err(Failure::Usage)?;
+    let summary_json = take_flag_value(&mut args, "--summary-json");
+    let copy_to = take_flag_value(&mut args, "--copy-to");
+    let rename = take_flag_value(&mut args, "--rename").map(|v| v.to_string_lossy().to_string());
+    if rename.is_some() && copy_to.is_none() {
+        return Err(Failure::Usage("--rename needs --copy-to <dir>".to_string()));
+    }
+    if args.is_empty() || args[0].to_string_lossy().starts_with('-') {
+        args.insert(0, OsString::from("build"));
+    }
+    let workspace_root = discover_workspace_root_for_args(&args)?;
+    let out_dir = symbaker_output_dir(&workspace_root)?;
+    let trace_file = out_dir.join("trace.log");
+
+    // Externally built artifact: skip cargo and reuse any trace already on disk.
+    if let Some(path) = artifact {
+        let files = resolve_dump_inputs(vec![path], true, log)?;
+        log.debug("--artifact: skipping cargo, reusing any trace on disk");
+        let options = DumpOptions {
+            stable,
+            fail_on_duplicates,
+            ..DumpOptions::default()
+        };
+        let exports = dump_artifacts(&files, &out_dir, &options, log)?;
+        if trace_enabled {
+            // What a build from here would run with; a trace from elsewhere is stale.
+            let mut probe = Command::new("cargo");
+            apply_symbaker_env(
+                &mut probe,
+                &args,
+                &workspace_root,
+                top_package.as_deref(),
+                &overrides,
+                trace_enabled,
+            );
+            let expected = command_env_fingerprint(&probe, &workspace_root);
+            let foreign = find_foreign_exports(&workspace_root, &trace_file, &exports, &[])?;
+            refresh_resolution_report(
+                &workspace_root,
+                &args,
+                &trace_file,
+                Some(&expected),
+                force,
+                Some(&foreign),
+                log,
+            )?;
+            check_foreign(&foreign, strict_foreign)?;
+        }
+        if let Some(path) = &summary_json {
+            let top_package = top_package.or_else(|| env::var("SYMBAKER_TOP_PACKAGE").ok());
+            write_build_summary(path, &trace_file, &exports, top_package, log)?;
+        }
+        if fail_unprefixed {
+            check_unprefixed(&trace_file)?;
+        }
+        if let Some(dest) = &copy_to {
+            copy_artifacts(&files, dest, rename.as_deref(), &workspace_root, log)?;
+        }
+        return Ok(());
+    }
+
+    if trace_enabled {
+        log.debug(format!(
+            "removing {} so the build writes a fresh trace",
+            trace_file.display()
+        ));
+        let _ = fs::remove_file(&trace_file);
+    }
+
+    // Plain `cargo build` reports its artifacts as JSON; wrappers such as
+    // `cargo skyline` post-process them, so those still scan target/.
+    let capture = args[0] == "build"
+        && !args
+            .iter()
+            .any(|a| a.to_string_lossy().starts_with("--message-format"));
+    let mut build = Command::new("cargo");
+    build.args(&args);
+    if capture {
+        build.arg("--message-format=json-render-diagnostics");
+    }
+    let top_package = apply_symbaker_env(
+        &mut build,
+        &args,
+        &workspace_root,
+        top_package.as_deref(),
+        &overrides,
+        trace_enabled,
+    );
+    let expected = command_env_fingerprint(&build, &workspace_root);
+    let (status, captured, rlibs) = if capture {
+        build_capturing_artifacts(&mut build)?
+    } else {
+        let status = build
+            .status()
+            .map_err(|e| format!("failed to run cargo build: {e}"))?;
+        (status, Vec::new(), Vec::new())
+    };
+    if !status.success() {
+        return Err(Failure::Build(format!("cargo {:?} failed", args)));
+    }
+
+    let profile = profile_from_args(&args);
+    let target_dir = target_dir_from_args(&args, &workspace_root);
+    let (search_dir, profile_dir) = nro_search(&target_dir, &args, profile.as_deref());
+    let nros = if !captured.is_empty() {
+        log.debug(format!(
+            "{} artifact(s) from cargo's JSON messages",
+            captured.len()
+        ));
+        captured
+    } else if builds_multiple_targets(&args) {
+        log.debug(format!("scanning {} for every .nro", search_dir.display()));
+        out::all_nros(&search_dir, profile_dir.as_deref()).map_err(Failure::NoArtifact)?
+    } else {
+        log.debug(format!(
+            "scanning {} for the newest .nro",
+            search_dir.display()
+        ));
+        vec![out::newest_nro(&search_dir, profile_dir.as_deref()).map_err(Failure::NoArtifact)?]
+    };
+    let options = DumpOptions {
+        stable,
+        fail_on_duplicates,
+        ..DumpOptions::default()
+    };
+    let exports = dump_artifacts(&nros, &out_dir, &options, log)?;
+
+    if trace_enabled {
+        let foreign = find_foreign_exports(&workspace_root, &trace_file, &exports, &rlibs)?;
+        refresh_resolution_report(
+            &workspace_root,
+            &args,
+            &trace_file,
+            Some(&expected),
+            force,
+            Some(&foreign),
+            log,
+        )?;
+        check_foreign(&foreign, strict_foreign)?;
+    }
+    if let Some(path) = &summary_json {
+        write_build_summary(path, &trace_file, &exports, top_package, log)?;
+    }
+    if fail_unprefixed {
+        check_unprefixed(&trace_file)?;
+    }
+    if let Some(dest) = &copy_to {
+        copy_artifacts(&nros, dest, rename.as_deref(), &workspace_root, log)?;
+    }
+    Ok(())
+}
+
+/// `--copy-to <dir>`: copies each dumped artifact and its sidecar into
+/// `dest` (created if missing), as `rename` when given. Renaming needs a
+/// single artifact; the sidecar follows the new name.
+fn copy_artifacts(
+    files: &[PathBuf],
+    dest: &Path,
+    rename: Option<&str>,
+    workspace_root: &Path,
+    log: &Log,
+) -> Result<Vec<PathBuf>, String> {
+    if let Some(name) = rename {
+        if files.len() > 1 {
+            return Err(format!(
+                "--rename {name} needs exactly one artifact, but {} were dumped",
+                files.len()
+            ));
+        }
+        if Path::new(name).file_name() != Some(std::ffi::OsStr::new(name)) {
+            return Err(format!("--rename {name:?} must be a file name, not a path"));
+        }
+    }
+    fs::create_dir_all(dest).map_err(|e| format!("mkdir {}: {e}", dest.display()))?;
+    let shown = stdout_paths(workspace_root);
+    let mut copied = Vec::new();
+    for artifact in files {
+        let name = match rename {
+            Some(name) => OsString::from(name),
+            None => artifact
+                .file_name()
+                .ok_or_else(|| format!("invalid artifact file name: {}", artifact.display()))?
+                .to_os_string(),
+        };
+        let target = dest.join(name);
+        let sidecar = out::sidecar_path(artifact, out::DEFAULT_SIDECAR_NAME)?;
+        let sidecar_target = out::sidecar_path(&target, out::DEFAULT_SIDECAR_NAME)?;
+        for (from, to) in [(artifact, &target), (&sidecar, &sidecar_target)] {
+            fs::copy(from, to)
+                .map_err(|e| format!("copy {} -> {}: {e}", from.display(), to.display()))?;
+            log.info(format!(
+                "copied: {} -> {}",
+                shown.show(from),
+                shown.show(to)
+            ));
+        }
+        copied.push(target);
+    }
+    Ok(copied)
+}
+
+/// `--summary-json`: aggregate numbers for CI dashboards, much smaller than
+/// `resolution.toml`.
+#[derive(Serialize, Debug, PartialEq)]
+struct BuildSummary {
+    top_package: Option<String>,
+    /// The top package's resolved prefix, when it was traced.
+    top_prefix: Option<String>,
+    /// Exports summed over every dumped artifact.
+    total_symbols: usize,
+    /// Symbols several artifacts export with conflicting implementations.
+    duplicate_symbols: usize,
+    /// Duplicates whose implementations look identical; not counted above.
+    identical_duplicates: usize,
+    /// Traced crates per `selected_source`.
+    sources: BTreeMap<String, usize>,
+    /// Traced exports per crate.
+    crate_symbols: BTreeMap<String, usize>,
+}
+
+fn build_summary(
+    traces: &BTreeMap<String, TraceCrate>,
+    exports: &[(PathBuf, Vec<String>)],
+    top_package: Option<String>,
+) -> BuildSummary {
+    let mut sources = BTreeMap::<String, usize>::new();
+    for t in traces.values() {
+        let source = t.selected_source.as_deref().unwrap_or("unknown");
+        *sources.entry(source.to_string()).or_default() += 1;
+    }
+    let (conflicting, identical) = classify_duplicates(find_duplicate_symbols(exports));
+    BuildSummary {
+        top_prefix: top_package
+            .as_ref()
+            .and_then(|p| traces.get(p))
+            .and_then(|t| t.resolved_prefix.clone()),
+        top_package,
+        total_symbols: exports.iter().map(|(_, symbols)| symbols.len()).sum(),
+        duplicate_symbols: conflicting.len(),
+        identical_duplicates: identical.len(),
+        sources,
+        crate_symbols: traces
+            .iter()
+            .map(|(name, t)| (name.clone(), t.symbols.len()))
+            .collect(),
+    }
+}
+
+fn write_build_summary(
+    path: &Path,
+    trace_file: &PathBuf,
+    exports: &[(PathBuf, Vec<String>)],
+    top_package: Option<String>,
+    log: &Log,
+) -> Result<(), String> {
+    let traces = parse_trace_file(trace_file)
+        .map_err(|e| format!("--summary-json needs a resolution trace: {e}"))?;
+    let summary = build_summary(&traces, exports, top_package);
+    let encoded =
+        serde_json::to_string_pretty(&summary).map_err(|e| format!("encode summary json: {e}"))?;
+    fs::write(path, encoded + "\n").map_err(|e| format!("write {}: {e}", path.display()))?;
+    log.info(format!("summary: {}", path.display()));
+    Ok(())
+}
+
+/// Crates whose prefix fell back to their own name (`crate` or
+/// `crate_fallback_after_priority`), as `(name, source)`.
+fn unprefixed_crates(traces: &BTreeMap<String, TraceCrate>) -> Vec<(String, String)> {
+    traces
+        .values()
+        .filter_map(|t| {
+            let source = t.selected_source.as_deref()?;
+            matches!(source, "crate" | "crate_fallback_after_priority")
+                .then(|| (t.name.clone(), source.to_string()))
+        })
+        .collect()
+}
+
+/// Workspace-wide `SYMBAKER_ENFORCE_INHERIT`: fails when any traced crate
+/// fell back to its own name, which usually means config is missing.
+fn check_unprefixed(trace_file: &PathBuf) -> Result<(), Failure> {
+    let traces = parse_trace_file(trace_file)
+        .map_err(|e| format!("--fail-unprefixed needs a resolution trace: {e}"))?;
+    let unprefixed = unprefixed_crates(&traces);
+    if unprefixed.is_empty() {
+        return Ok(());
+    }
+    for (name, source) in &unprefixed {
+        eprintln!("unprefixed: {name} (source={source})");
+    }
+    Err(Failure::Regression(format!(
+        "{} crate(s) fell back to their own name as prefix; set SYMBAKER_CONFIG/SYMBAKER_TOP_PACKAGE or add [overrides] entries",
+        unprefixed.len()
+    )))
+}
+
+/// Filenames of `cdylib` artifacts in cargo `--message-format=json` output,
+/// swapped for a same-stem `.nro` next to them when one exists.
+fn cdylib_artifacts_from_messages(stdout: &str) -> Vec<PathBuf> {
+    let mut out = Vec::<PathBuf>::new();
+    for line in stdout.lines() {
+        let Ok(msg) = serde_json::from_str::<Value>(line) else {
+            continue;
+        };
+        if msg.get("reason").and_then(|v| v.as_str()) != Some("compiler-artifact") {
+            continue;
+        }
+        let is_cdylib = msg
+            .get("target")
+            .and_then(|t| t.get("crate_types"))
+            .and_then(|v| v.as_array())
+            .map(|a| a.iter().any(|t| t.as_str() == Some("cdylib")))
+            .unwrap_or(false);
+        if !is_cdylib {
+            continue;
+        }
+        for f in msg
+            .get("filenames")
+            .and_then(|v| v.as_array())
+            .into_iter()
+            .flatten()
+            .filter_map(|v| v.as_str())
+        {
+            let path = PathBuf::from(f);
+            let nro = path.with_extension("nro");
+            let path = if nro.is_file() { nro } else { path };
+            if !out.contains(&path) {
+                out.push(path);
+            }
+        }
+    }
+    out
+}
+
+/// `(lib target name, .rlib)` pairs.
+type Rlibs = Vec<(String, PathBuf)>;
+
+/// The rlib of every library in cargo `--message-format=json` output, fresh
+/// or rebuilt.
+fn rlib_artifacts_from_messages(stdout: &str) -> Rlibs {
+    let mut out = Rlibs::new();
+    for line in stdout.lines() {
+        let Ok(msg) = serde_json::from_str::<Value>(line) else {
+            continue;
+        };
+        if msg.get("reason").and_then(|v| v.as_str()) != Some("compiler-artifact") {
+            continue;
+        }
+        let Some(name) = msg
+            .get("target")
+            .and_then(|t| t.get("name"))
+            .and_then(|v| v.as_str())
+        else {
+            continue;
+        };
+        for f in msg
+            .get("filenames")
+            .and_then(|v| v.as_array())
+            .into_iter()
+            .flatten()
+            .filter_map(|v| v.as_str())
+            .filter(|f| f.ends_with(".rlib"))
+        {
+            out.push((name.to_string(), PathBuf::from(f)));
+        }
+    }
+    out
+}
+
+/// Runs cargo with JSON messages on stdout (diagnostics still render on
+/// stderr) and returns the cdylib artifacts and rlibs it reported.
+fn build_capturing_artifacts(
+    build: &mut Command,
+) -> Result<(std::process::ExitStatus, Vec<PathBuf>, Rlibs), String> {
+    use std::io::{BufRead, BufReader};
+
+    let mut child = build
+        .stdout(std::process::Stdio::piped())
+        .spawn()
         .map_err(|e| format!("failed to run cargo build: {e}"))?;
+    let mut artifacts = Vec::<PathBuf>::new();
+    let mut rlibs = Rlibs::new();
+    if let Some(stdout) = child.stdout.take() {
+        for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+            if !line.starts_with('{') {
+                println!("{line}");
+                continue;
+            }
+            for path in cdylib_artifacts_from_messages(&line) {
+                if !artifacts.contains(&path) {
+                    artifacts.push(path);
+                }
+            }
+            rlibs.extend(rlib_artifacts_from_messages(&line));
+        }
+    }
+    let status = child
+        .wait()
+        .map_err(|e| format!("failed to wait for cargo build: {e}"))?;
+    Ok((status, artifacts, rlibs))
+}
+
+/// Whether the forwarded cargo args can produce more than one `.nro`.
+fn builds_multiple_targets(args: &[OsString]) -> bool {
+    ["--all-targets", "--tests", "--examples", "--bins"]
+        .iter()
+        .any(|f| has_flag(args, f))
+}
+
+#[cfg(unix)]
+mod signals {
+    use std::sync::atomic::{AtomicI32, Ordering};
+
+    /// Signals aimed at this process alone (`kill`, a closed session) that
+    /// the child must see too.
+    const FORWARDED: [libc::c_int; 2] = [libc::SIGTERM, libc::SIGHUP];
+
+    static CHILD_PID: AtomicI32 = AtomicI32::new(0);
+
+    extern "C" fn forward(sig: libc::c_int) {
+        let pid = CHILD_PID.load(Ordering::SeqCst);
+        if pid > 0 {
+            unsafe {
+                libc::kill(pid, sig);
+            }
+        }
+    }
+
+    fn set_handler(sig: libc::c_int, handler: libc::sighandler_t) {
+        unsafe {
+            let mut action: libc::sigaction = std::mem::zeroed();
+            action.sa_sigaction = handler;
+            action.sa_flags = libc::SA_RESTART;
+            libc::sigemptyset(&mut action.sa_mask);
+            libc::sigaction(sig, &action, std::ptr::null_mut());
+        }
+    }
+
+    /// While `pid` runs: SIGTERM and SIGHUP are relayed to it, and SIGINT is
+    /// ignored here, since a terminal Ctrl-C already reaches the child in
+    /// this process group and the child decides whether to stop.
+    pub fn forward_to(pid: u32) {
+        CHILD_PID.store(pid as i32, Ordering::SeqCst);
+        set_handler(libc::SIGINT, libc::SIG_IGN);
+        for sig in FORWARDED {
+            set_handler(
+                sig,
+                forward as extern "C" fn(libc::c_int) as libc::sighandler_t,
+            );
+        }
+    }
+
+    /// Restores the default handlers; if the child was stopped by SIGINT,
+    /// this process stops the same way, so e.g. `--watch-config` ends too.
+    pub fn reset(status: &std::io::Result<std::process::ExitStatus>) {
+        use std::os::unix::process::ExitStatusExt;
+        CHILD_PID.store(0, Ordering::SeqCst);
+        for sig in [libc::SIGINT].into_iter().chain(FORWARDED) {
+            set_handler(sig, libc::SIG_DFL);
+        }
+        if status
+            .as_ref()
+            .is_ok_and(|s| s.signal() == Some(libc::SIGINT))
+        {
+            unsafe {
+                libc::raise(libc::SIGINT);
+            }
+        }
+    }
+}
+
+/// Runs `cmd` to completion. On Unix the child stays in this process group,
+/// so it keeps the terminal (interactive `cargo run` can read stdin) and gets
+/// Ctrl-C directly; SIGTERM and SIGHUP sent to the wrapper are relayed, so it
+/// is never orphaned when the wrapper exits.
+fn run_child(cmd: &mut Command) -> std::io::Result<std::process::ExitStatus> {
+    #[cfg(unix)]
+    {
+        let mut child = cmd.spawn()?;
+        signals::forward_to(child.id());
+        let status = child.wait();
+        signals::reset(&status);
+        status
+    }
+    #[cfg(not(unix))]
+    {
+        cmd.status()
+    }
+}
+
+/// Removes every `flag` from `args`, returning whether there was one.
+fn take_flag(args: &mut Vec<OsString>, flag: &str) -> bool {
+    let before = args.len();
+    args.retain(|a| a != flag);
+    args.len() != before
+}
+
+/// Removes `flag` if it appears before the cargo subcommand, where it is
+/// ours; after it, `--force` means something else to e.g. `cargo install`.
+fn take_leading_flag(args: &mut Vec<OsString>, flag: &str) -> bool {
+    let sub = args
+        .iter()
+        .position(|a| !a.to_string_lossy().starts_with('-'))
+        .unwrap_or(args.len());
+    match args[..sub].iter().position(|a| a == flag) {
+        Some(at) => {
+            args.remove(at);
+            true
+        }
+        None => false,
+    }
+}
+
+fn run_wrapped_cargo(mut args: Vec<OsString>, log: &Log) -> Result<(), Failure> {
+    while args
+        .first()
+        .map(|s| s.to_string_lossy() == "symdump")
+        .unwrap_or(false)
+    {
+        args.remove(0);
+    }
+    let trace_enabled = has_flag(&args, "--trace");
+    args.retain(|a| a != "--trace");
+    let top_package = take_top_package(&mut args).map_err(Failure::Usage)?;
+    let overrides = take_overrides(&mut args).map_err(Failure::Usage)?;
+    let force = take_leading_flag(&mut args, "--force");
+    let disable = take_leading_flag(&mut args, "--no-symbaker");
+    let strict_config = take_leading_flag(&mut args, "--strict-config");
+    let watch_config = take_leading_flag(&mut args, "--watch-config");
+    // Leaves the child with exactly the env of this process.
+    let no_env = take_leading_flag(&mut args, "--no-symbaker-env");
+    if args.is_empty() {
+        return Err(Failure::Usage(
+            "usage: cargo symdump run <cargo-subcommand...>".to_string(),
+        ));
+    }
+    if no_env
+        && (trace_enabled
+            || disable
+            || strict_config
+            || watch_config
+            || top_package.is_some()
+            || !overrides.is_empty())
+    {
+        return Err(Failure::Usage(
+            "--no-symbaker-env sets no SYMBAKER_* env; it cannot be combined with --trace, --no-symbaker, --strict-config, --watch-config, --top-package or --override"
+                .to_string(),
+        ));
+    }
+    if no_env {
+        let status = run_child(Command::new("cargo").args(&args))
+            .map_err(|e| format!("failed to run cargo: {e}"))?;
+        if !status.success() {
+            return Err(Failure::Build(format!("cargo {:?} failed", args)));
+        }
+        return Ok(());
+    }
+    let workspace_root = discover_workspace_root_for_args(&args)?;
+    let out_dir = symbaker_output_dir(&workspace_root)?;
+    let trace_file = out_dir.join("trace.log");
+
+    let run_once = || -> Result<(), Failure> {
+        if trace_enabled {
+            let _ = fs::remove_file(&trace_file);
+        }
+        let mut cmd = Command::new("cargo");
+        cmd.args(&args);
+        apply_symbaker_env(
+            &mut cmd,
+            &args,
+            &workspace_root,
+            top_package.as_deref(),
+            &overrides,
+            trace_enabled,
+        );
+        if strict_config {
+            cmd.env("SYMBAKER_STRICT_CONFIG", "1");
+        }
+        if disable {
+            cmd.env("SYMBAKER_DISABLE", "1");
+        }
+        let expected = command_env_fingerprint(&cmd, &workspace_root);
+        let status = run_child(&mut cmd).map_err(|e| format!("failed to run cargo: {e}"))?;
+        if !status.success() {
+            return Err(Failure::Build(format!("cargo {:?} failed", args)));
+        }
+        // A disabled build resolves nothing, so there is no report to refresh.
+        if trace_enabled && !disable {
+            refresh_resolution_report(
+                &workspace_root,
+                &args,
+                &trace_file,
+                Some(&expected),
+                force,
+                None,
+                log,
+            )?;
+        }
+        Ok(())
+    };
+    if !watch_config {
+        return run_once();
+    }
+
+    let config = config_path_for_args(&args).ok_or_else(|| {
+        Failure::Usage(
+            "--watch-config found no symbaker.toml (set SYMBAKER_CONFIG or run `cargo symdump init`)"
+                .to_string(),
+        )
+    })?;
+    let mut seen = fs::read_to_string(&config).unwrap_or_default();
+    loop {
+        // A failed run is reported and waits for the next edit.
+        if let Err(e) = run_once() {
+            eprintln!("error: {e}");
+        }
+        log.info(format!(
+            "watching {} for changes (Ctrl-C to stop)",
+            config.display()
+        ));
+        let changed = wait_for_change(&config, &seen, CONFIG_POLL, CONFIG_DEBOUNCE);
+        log.info(format!("{} changed:", config.display()));
+        for line in config_diff(&seen, &changed) {
+            log.info(line);
+        }
+        seen = changed;
+    }
+}
+
+/// How often `run --watch-config` checks the config.
+const CONFIG_POLL: Duration = Duration::from_millis(250);
+/// How long the config must stay unchanged before `--watch-config` reruns,
+/// so an editor's save in several writes triggers one run.
+const CONFIG_DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// Polls `path` until its contents differ from `seen` and then hold still
+/// for `debounce`; returns them. A missing file reads as empty.
+fn wait_for_change(path: &Path, seen: &str, poll: Duration, debounce: Duration) -> String {
+    let read = || fs::read_to_string(path).unwrap_or_default();
+    let mut current = read();
+    while current == seen {
+        std::thread::sleep(poll);
+        current = read();
+    }
+    loop {
+        std::thread::sleep(debounce);
+        let settled = read();
+        if settled == current {
+            return settled;
+        }
+        current = settled;
+    }
+}
+
+/// Line diff of `old` against `new`, blank lines skipped: `- line` for each
+/// removed and `+ line` for each added line, in file order. Lines only match
+/// under the same `[table]` header, and a change is preceded by `  [table]`
+/// whenever that differs from the header shown last, so a key moved between
+/// tables, a reordered line or a duplicate shows up.
+fn config_diff(old: &str, new: &str) -> Vec<String> {
+    // Each line with the header it sits under (a header with itself).
+    let lines = |text: &'_ str| -> Vec<(Option<String>, String)> {
+        let mut table = None;
+        text.lines()
+            .map(str::trim_end)
+            .filter(|l| !l.trim().is_empty())
+            .map(|l| {
+                if l.trim_start().starts_with('[') {
+                    table = Some(l.to_string());
+                }
+                (table.clone(), l.to_string())
+            })
+            .collect()
+    };
+    let (old, new) = (lines(old), lines(new));
+    let (n, m) = (old.len(), new.len());
+    // common[i][j]: longest common subsequence of old[i..] and new[j..].
+    let mut common = vec![vec![0usize; m + 1]; n + 1];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            common[i][j] = if old[i] == new[j] {
+                common[i + 1][j + 1] + 1
+            } else {
+                common[i + 1][j].max(common[i][j + 1])
+            };
+        }
+    }
+
+    let mut shown = None::<&str>;
+    let mut out = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < n || j < m {
+        if i < n && j < m && old[i] == new[j] {
+            i += 1;
+            j += 1;
+            continue;
+        }
+        let (sign, (table, line)) = if i < n && (j == m || common[i + 1][j] >= common[i][j + 1]) {
+            i += 1;
+            ('-', &old[i - 1])
+        } else {
+            j += 1;
+            ('+', &new[j - 1])
+        };
+        let table = table.as_deref();
+        if table != shown && table != Some(line.as_str()) {
+            if let Some(header) = table {
+                out.push(format!("  {header}"));
+            }
+        }
+        shown = table;
+        out.push(format!("{sign} {line}"));
+    }
+    out
+}
+
+/// `cargo symdump test`: `cargo test` with the symbaker env, then dumps the
+/// cdylibs that run (re)built. Unchanged libraries are not fresh and are
+/// skipped.
+fn run_test_then_dump(mut args: Vec<OsString>, log: &Log) -> Result<(), Failure> {
+    let trace_enabled = has_flag(&args, "--trace");
+    let no_dump = has_flag(&args, "--no-dump");
+    let stable = has_flag(&args, "--stable");
+    let force = has_flag(&args, "--force");
+    args.retain(|a| a != "--trace" && a != "--no-dump" && a != "--stable" && a != "--force");
+    let top_package = take_top_package(&mut args).map_err(Failure::Usage)?;
+    let overrides = take_overrides(&mut args).map_err(Failure::Usage)?;
+    args.insert(0, OsString::from("test"));
+    let workspace_root = discover_workspace_root_for_args(&args)?;
+    let out_dir = symbaker_output_dir(&workspace_root)?;
+    let trace_file = out_dir.join("trace.log");
+    if trace_enabled {
+        log.debug(format!(
+            "removing {} so the tests write a fresh trace",
+            trace_file.display()
+        ));
+        let _ = fs::remove_file(&trace_file);
+    }
+
+    let started = std::time::SystemTime::now();
+    let mut cmd = Command::new("cargo");
+    cmd.args(&args);
+    apply_symbaker_env(
+        &mut cmd,
+        &args,
+        &workspace_root,
+        top_package.as_deref(),
+        &overrides,
+        trace_enabled,
+    );
+    let expected = command_env_fingerprint(&cmd, &workspace_root);
+    let status = run_child(&mut cmd).map_err(|e| format!("failed to run cargo: {e}"))?;
     if !status.success() {
-        return Err(format!("cargo {:?} failed", args));
+        return Err(Failure::Build(format!("cargo {:?} failed", args)));
+    }
+    if trace_enabled {
+        refresh_resolution_report(
+            &workspace_root,
+            &args,
+            &trace_file,
+            Some(&expected),
+            force,
+            None,
+            log,
+        )?;
+    }
+    if no_dump {
+        return Ok(());
+    }
+
+    let target_dir = target_dir_from_args(&args, &workspace_root);
+    log.debug(format!(
+        "scanning {} for cdylibs built since the tests started",
+        target_dir.display()
+    ));
+    let fresh = out::fresh_dylibs(&target_dir, &out::workspace_dylib_names(&args), started);
+    if fresh.is_empty() {
+        log.info(format!(
+            "no freshly built cdylib under {}; nothing dumped",
+            target_dir.display()
+        ));
+        return Ok(());
+    }
+    let options = DumpOptions {
+        stable,
+        ..DumpOptions::default()
+    };
+    dump_artifacts(&fresh, &out_dir, &options, log)?;
+    Ok(())
+}
+
+/// Collects `.nro` files under `dir`; without `recursive` only the top level is scanned.
+fn collect_nro_files(dir: &Path, recursive: bool) -> Result<Vec<PathBuf>, String> {
+    let mut stack = vec![dir.to_path_buf()];
+    let mut found = Vec::<PathBuf>::new();
+    while let Some(cur) = stack.pop() {
+        let entries = fs::read_dir(&cur).map_err(|e| format!("read_dir {}: {e}", cur.display()))?;
+        for entry in entries {
+            let entry = entry.map_err(|e| format!("read_dir entry error: {e}"))?;
+            let path = entry.path();
+            let meta = entry
+                .metadata()
+                .map_err(|e| format!("metadata {}: {e}", path.display()))?;
+            if meta.is_dir() {
+                if recursive {
+                    stack.push(path);
+                }
+                continue;
+            }
+            if path
+                .extension()
+                .and_then(|s| s.to_str())
+                .map(|s| s.eq_ignore_ascii_case("nro"))
+                .unwrap_or(false)
+            {
+                found.push(path);
+            }
+        }
+    }
+    found.sort();
+    Ok(found)
+}
+
+/// Whether a dump input contains `*` or `?` and should be expanded by
+/// [`expand_glob`] rather than taken literally. The `?` of a Windows
+/// verbatim prefix (`\\?\C:\...`) is not a wildcard.
+fn is_glob_pattern(path: &Path) -> bool {
+    let text = path.to_string_lossy();
+    let text = text.strip_prefix(r"\\?\").unwrap_or(&text);
+    text.contains(['*', '?'])
+}
+
+/// Paths matching `pattern`, expanded here rather than by the shell so
+/// quoting behaves the same everywhere. Each component is matched with
+/// [`names::wildcard_match`]; a `**` component matches any number of
+/// directories.
+fn expand_glob(pattern: &Path) -> Result<Vec<PathBuf>, String> {
+    let mut base = PathBuf::new();
+    let mut parts = Vec::<String>::new();
+    for component in pattern.components() {
+        let text = component.as_os_str().to_string_lossy();
+        let literal = matches!(component, Component::Prefix(_) | Component::RootDir)
+            || !text.contains(['*', '?']);
+        if parts.is_empty() && literal {
+            base.push(component);
+        } else {
+            parts.push(text.into_owned());
+        }
+    }
+    let mut found = Vec::<PathBuf>::new();
+    glob_walk(&base, &parts, &mut found)?;
+    found.sort();
+    found.dedup();
+    Ok(found)
+}
+
+fn glob_walk(dir: &Path, parts: &[String], found: &mut Vec<PathBuf>) -> Result<(), String> {
+    let Some((first, rest)) = parts.split_first() else {
+        found.push(dir.to_path_buf());
+        return Ok(());
+    };
+    let listed = if dir.as_os_str().is_empty() {
+        Path::new(".")
+    } else {
+        dir
+    };
+    if !listed.is_dir() {
+        return Ok(());
+    }
+    if first == "**" {
+        glob_walk(dir, rest, found)?;
+    }
+    let entries =
+        fs::read_dir(listed).map_err(|e| format!("read_dir {}: {e}", listed.display()))?;
+    for entry in entries {
+        let entry = entry.map_err(|e| format!("read_dir entry error: {e}"))?;
+        let path = dir.join(entry.file_name());
+        if first == "**" {
+            // Not `path.is_dir()`: following symlinks could loop forever.
+            if entry.file_type().is_ok_and(|t| t.is_dir()) {
+                glob_walk(&path, parts, found)?;
+            }
+        } else if names::wildcard_match(first, &entry.file_name().to_string_lossy()) {
+            glob_walk(&path, rest, found)?;
+        }
+    }
+    Ok(())
+}
+
+fn resolve_dump_inputs(
+    paths: Vec<PathBuf>,
+    recursive: bool,
+    log: &Log,
+) -> Result<Vec<PathBuf>, Failure> {
+    if paths.is_empty() {
+        return Err(Failure::Usage(
+            "usage: cargo symdump dump <path/to/file.nro|path/to/folder> [more paths...]"
+                .to_string(),
+        ));
+    }
+
+    let mut expanded = Vec::<PathBuf>::new();
+    for path in paths {
+        if is_glob_pattern(&path) {
+            let matches = expand_glob(&path)?;
+            if matches.is_empty() {
+                return Err(Failure::NoArtifact(format!(
+                    "glob '{}' matched no files",
+                    path.display()
+                )));
+            }
+            log.debug(format!(
+                "glob {} matched {} path(s)",
+                path.display(),
+                matches.len()
+            ));
+            expanded.extend(matches);
+        } else {
+            expanded.push(path);
+        }
+    }
+
+    let mut files = Vec::<PathBuf>::new();
+    for path in expanded {
+        let canon = path
+            .canonicalize()
+            .map_err(|e| Failure::NoArtifact(format!("{}: {e}", path.display())))?;
+        let meta =
+            fs::metadata(&canon).map_err(|e| format!("metadata {}: {e}", canon.display()))?;
+        if meta.is_dir() {
+            let found = collect_nro_files(&canon, recursive)?;
+            log.debug(format!(
+                "scanned {}{}: {} .nro file(s)",
+                canon.display(),
+                if recursive { " recursively" } else { "" },
+                found.len()
+            ));
+            files.extend(found);
+        } else if meta.is_file() {
+            files.push(canon);
+        } else {
+            return Err(Failure::Other(format!(
+                "unsupported path type: {}",
+                canon.display()
+            )));
+        }
+    }
+
+    let mut uniq = BTreeSet::<PathBuf>::new();
+    for file in files {
+        uniq.insert(file);
+    }
+    let out: Vec<PathBuf> = uniq.into_iter().collect();
+    if out.is_empty() {
+        return Err(Failure::NoArtifact(
+            "no files to dump (no .nro files found in provided folders)".to_string(),
+        ));
+    }
+    Ok(out)
+}
+
+fn find_duplicate_symbols(rows: &[(PathBuf, Vec<String>)]) -> Vec<(String, Vec<PathBuf>)> {
+    let mut by_symbol = BTreeMap::<String, BTreeSet<PathBuf>>::new();
+    for (artifact, symbols) in rows {
+        let mut seen = HashSet::<String>::new();
+        for symbol in symbols {
+            if !seen.insert(symbol.clone()) {
+                continue;
+            }
+            by_symbol
+                .entry(symbol.clone())
+                .or_default()
+                .insert(artifact.clone());
+        }
+    }
+
+    by_symbol
+        .into_iter()
+        .filter_map(|(symbol, files)| {
+            if files.len() <= 1 {
+                None
+            } else {
+                Some((symbol, files.into_iter().collect()))
+            }
+        })
+        .collect()
+}
+
+fn write_batch_sym_log(
+    rows: &[(PathBuf, Vec<String>)],
+    out_path: &PathBuf,
+    paths: &out::PathStyle,
+) -> Result<(), String> {
+    let mut body = String::new();
+    body.push_str("# symbaker sym.log\n");
+    body.push_str("# format: source=<path> then one symbol per line\n");
+    for (artifact, symbols) in rows {
+        body.push_str(&format!("\n# source={}\n", paths.show(artifact)));
+        for symbol in symbols {
+            body.push_str(symbol);
+            body.push('\n');
+        }
+    }
+    fs::write(out_path, body).map_err(|e| format!("write {}: {e}", out_path.display()))
+}
+
+fn run_dump_many(mut args: Vec<OsString>, log: &Log) -> Result<(), Failure> {
+    let group_by = take_flag_value(&mut args, "--group-by");
+    if let Some(g) = &group_by {
+        if g.as_os_str() != "crate" {
+            return Err(Failure::Usage(format!(
+                "unknown --group-by value {:?} (expected `crate`)",
+                g.to_string_lossy()
+            )));
+        }
+    }
+    let min_size = take_flag_value(&mut args, "--min-size")
+        .map(|v| parse_size(&v.to_string_lossy()))
+        .transpose()
+        .map_err(Failure::Usage)?;
+    let sidecar_name =
+        take_flag_value(&mut args, "--sidecar-name").map(|v| v.to_string_lossy().to_string());
+    let files_from = take_flag_value(&mut args, "--files-from");
+    let mut recursive = true;
+    let mut stable = false;
+    let mut annotate = false;
+    let mut allow_missing_tools = false;
+    let mut verify_integrity = false;
+    let mut fail_on_duplicates = false;
+    let mut from_stdin = false;
+    let mut paths = Vec::<PathBuf>::new();
+    for arg in args {
+        if arg == "-" || arg == "--stdin" {
+            from_stdin = true;
+        } else if arg == "--stable" {
+            stable = true;
+        } else if arg == "--annotate" {
+            annotate = true;
+        } else if arg == "--allow-missing-tools" {
+            allow_missing_tools = true;
+        } else if arg == "--verify-integrity" {
+            verify_integrity = true;
+        } else if arg == "--fail-on-duplicates" {
+            fail_on_duplicates = true;
+        } else if arg == "--recursive" {
+            recursive = true;
+        } else if arg == "--no-recursive" {
+            recursive = false;
+        } else {
+            paths.push(PathBuf::from(arg));
+        }
+    }
+    match files_from {
+        Some(list) if list.as_os_str() == "-" => from_stdin = true,
+        Some(list) => {
+            let body = fs::read_to_string(&list)
+                .map_err(|e| format!("--files-from {}: {e}", list.display()))?;
+            paths.extend(parse_path_list(&body));
+        }
+        None => {}
+    }
+    if from_stdin {
+        let mut body = String::new();
+        std::io::Read::read_to_string(&mut std::io::stdin(), &mut body)
+            .map_err(|e| format!("read paths from stdin: {e}"))?;
+        paths.extend(parse_path_list(&body));
+    }
+    open_dump_events()?;
+    let files = readable_dump_inputs(
+        resolve_dump_inputs(paths, recursive, log)?,
+        allow_missing_tools,
+    )?;
+    let (files, corrupt) = if verify_integrity {
+        drop_corrupt_nros(files)
+    } else {
+        (files, 0)
+    };
+    let corrupt_error = format!("{corrupt} file(s) failed --verify-integrity");
+    if corrupt > 0 && files.is_empty() {
+        return Err(corrupt_error.into());
+    }
+    let root = discover_workspace_root()?;
+    let out_dir = symbaker_output_dir(&root)?;
+    let crates = if group_by.is_some() || annotate {
+        match load_resolution_crates(&root) {
+            Ok(crates) => Some(crates),
+            Err(e) => {
+                if group_by.is_some() {
+                    eprintln!("warning: --group-by crate needs a resolution report, listing per file instead: {e}");
+                }
+                if annotate {
+                    eprintln!("warning: --annotate needs a resolution report, writing plain sidecars: {e}");
+                }
+                None
+            }
+        }
+    } else {
+        None
+    };
+    let owners = crates
+        .as_ref()
+        .filter(|_| group_by.is_some())
+        .map(|c| symbol_owners(c));
+    let annotations = crates
+        .as_ref()
+        .filter(|_| annotate)
+        .map(|c| symbol_annotations(c));
+    let options = DumpOptions {
+        owners: owners.as_ref(),
+        annotations: annotations.as_ref(),
+        min_size,
+        stable,
+        sidecar_name: sidecar_name.as_deref().unwrap_or(out::DEFAULT_SIDECAR_NAME),
+        fail_on_duplicates,
+    };
+    dump_artifacts(&files, &out_dir, &options, log)?;
+    if corrupt > 0 {
+        return Err(corrupt_error.into());
+    }
+    Ok(())
+}
+
+/// `--verify-integrity`: prints an error for, and drops, each `.nro` that
+/// fails [`out::verify_nro_integrity`]; other inputs are not checked. Returns
+/// the remaining files and how many were dropped.
+fn drop_corrupt_nros(files: Vec<PathBuf>) -> (Vec<PathBuf>, usize) {
+    let mut corrupt = 0usize;
+    let kept = files
+        .into_iter()
+        .filter(|f| {
+            if f.extension().and_then(|e| e.to_str()) != Some("nro") {
+                return true;
+            }
+            match out::verify_nro_integrity(f) {
+                Ok(()) => true,
+                Err(e) => {
+                    eprintln!("error: {}: {e}; skipping it", f.display());
+                    corrupt += 1;
+                    false
+                }
+            }
+        })
+        .collect();
+    (kept, corrupt)
+}
+
+/// Without nm/objdump only `.nro` inputs and the formats the native readers
+/// recognize can be read: the others are an error naming the missing tools,
+/// or with `--allow-missing-tools` a warning and dropped.
+fn readable_dump_inputs(
+    files: Vec<PathBuf>,
+    allow_missing_tools: bool,
+) -> Result<Vec<PathBuf>, Failure> {
+    let Some(missing) = out::missing_symbol_tools() else {
+        return Ok(files);
+    };
+    let (readable, others): (Vec<PathBuf>, Vec<PathBuf>) = files.into_iter().partition(|f| {
+        f.extension().and_then(|e| e.to_str()) == Some("nro") || objects::is_native_readable(f)
+    });
+    if others.is_empty() {
+        return Ok(readable);
+    }
+    if !allow_missing_tools {
+        return Err(Failure::Other(format!(
+            "{missing}. {} input(s) are in no format read natively, e.g. {} (pass --allow-missing-tools to dump only the readable files)",
+            others.len(),
+            others[0].display()
+        )));
+    }
+    eprintln!(
+        "warning: {missing}. Skipping {} unrecognized input(s)",
+        others.len()
+    );
+    if readable.is_empty() {
+        return Err(Failure::NoArtifact(
+            "no readable inputs left to dump".to_string(),
+        ));
+    }
+    Ok(readable)
+}
+
+/// One path per line (as from `find`); blank lines and `\r` line endings are
+/// ignored.
+fn parse_path_list(body: &str) -> Vec<PathBuf> {
+    body.lines()
+        .map(|l| l.trim_end_matches('\r'))
+        .filter(|l| !l.trim().is_empty())
+        .map(PathBuf::from)
+        .collect()
+}
+
+/// Parses a byte count given in decimal or `0x`-prefixed hex.
+fn parse_size(raw: &str) -> Result<u64, String> {
+    let raw = raw.trim();
+    let parsed = match raw.strip_prefix("0x").or_else(|| raw.strip_prefix("0X")) {
+        Some(hex) => u64::from_str_radix(hex, 16),
+        None => raw.parse(),
+    };
+    parsed.map_err(|_| {
+        format!(
+            "invalid size {:?} (expected bytes, e.g. 4096 or 0x1000)",
+            raw
+        )
+    })
+}
+
+/// Exported symbol (including aliases) -> crates that export it, per `resolution.toml`.
+type SymbolOwners = BTreeMap<String, BTreeSet<String>>;
+
+fn symbol_owners(crates: &[toml::Value]) -> SymbolOwners {
+    let mut owners = SymbolOwners::new();
+    for c in crates {
+        let Some(name) = c.get("name").and_then(|v| v.as_str()) else {
+            continue;
+        };
+        for key in ["symbols", "alias_symbols"] {
+            for sym in c.get(key).and_then(|v| v.as_array()).into_iter().flatten() {
+                if let Some(sym) = report_symbol_export(sym) {
+                    owners
+                        .entry(sym.to_string())
+                        .or_default()
+                        .insert(name.to_string());
+                }
+            }
+        }
     }
+    owners
+}
 
-    let target_dir = target_dir_from_args(&args);
-    let profile = profile_from_args(&args);
-    let nros = out::all_nros(&target_dir, profile.as_deref())?;
+/// Sidecar comments for `--annotate`: `crate=<name> fn=<function>` per
+/// export, joining crates with `+` when several claim a symbol. The function
+/// comes from each entry's `rust_name`, or the `functions` table of older
+/// reports; reports written before either only yield `crate=`.
+fn symbol_annotations(crates: &[toml::Value]) -> BTreeMap<String, String> {
+    let mut functions = BTreeMap::<String, BTreeSet<String>>::new();
+    for c in crates {
+        let legacy = c
+            .get("functions")
+            .and_then(|v| v.as_table())
+            .into_iter()
+            .flatten()
+            .filter_map(|(sym, function)| Some((sym.as_str(), function.as_str()?)));
+        let named = ["symbols", "alias_symbols"]
+            .into_iter()
+            .flat_map(|key| c.get(key).and_then(|v| v.as_array()).into_iter().flatten())
+            .filter_map(|entry| {
+                Some((
+                    entry.get("export")?.as_str()?,
+                    entry.get("rust_name")?.as_str()?,
+                ))
+            });
+        for (sym, function) in legacy.chain(named) {
+            functions
+                .entry(sym.to_string())
+                .or_default()
+                .insert(function.to_string());
+        }
+    }
+    symbol_owners(crates)
+        .into_iter()
+        .map(|(sym, owners)| {
+            let mut note = format!("crate={}", owners.into_iter().collect::<Vec<_>>().join("+"));
+            if let Some(fns) = functions.get(&sym) {
+                note.push_str(" fn=");
+                note.push_str(&fns.iter().cloned().collect::<Vec<_>>().join("+"));
+            }
+            (sym, note)
+        })
+        .collect()
+}
+
+type Duplicates = Vec<(String, Vec<PathBuf>)>;
+
+/// Splits [`find_duplicate_symbols`] into (conflicting, identical)
+/// implementations by comparing each file's [`out::SymbolFingerprint`].
+fn classify_duplicates(duplicates: Duplicates) -> (Duplicates, Duplicates) {
+    let files: BTreeSet<&PathBuf> = duplicates.iter().flat_map(|(_, files)| files).collect();
+    let fingerprints: HashMap<PathBuf, _> = files
+        .into_iter()
+        .filter_map(|f| Some((f.clone(), out::nro_symbol_fingerprints(f)?)))
+        .collect();
+    let (identical, conflicting): (Duplicates, Duplicates) =
+        duplicates.into_iter().partition(|(symbol, files)| {
+            same_fingerprint(files.iter().map(|f| fingerprints.get(f)?.get(symbol)))
+        });
+    (conflicting, identical)
+}
+
+/// Whether every definition has a fingerprint and they are all equal.
+fn same_fingerprint<'a>(
+    mut fingerprints: impl Iterator<Item = Option<&'a out::SymbolFingerprint>>,
+) -> bool {
+    let Some(Some(first)) = fingerprints.next() else {
+        return false;
+    };
+    fingerprints.all(|fp| fp == Some(first))
+}
+
+/// Rolls duplicated symbols up by the set of crates that own them. Symbols no
+/// crate claims are grouped under `(unknown)`.
+fn group_duplicates_by_crate(
+    duplicates: &[(String, Vec<PathBuf>)],
+    owners: &SymbolOwners,
+) -> BTreeMap<Vec<String>, Vec<String>> {
+    let mut groups = BTreeMap::<Vec<String>, Vec<String>>::new();
+    for (symbol, _) in duplicates {
+        let key = match owners.get(symbol) {
+            Some(crates) => crates.iter().cloned().collect(),
+            None => vec!["(unknown)".to_string()],
+        };
+        groups.entry(key).or_default().push(symbol.clone());
+    }
+    groups
+}
+
+/// What [`dump_artifacts`] writes besides the sidecars and `sym.log`.
+#[derive(Clone, Copy)]
+struct DumpOptions<'a> {
+    /// `--group-by crate`: roll duplicates up by owning crate.
+    owners: Option<&'a SymbolOwners>,
+    /// `--annotate`: provenance comments for the sidecars.
+    annotations: Option<&'a BTreeMap<String, String>>,
+    min_size: Option<u64>,
+    stable: bool,
+    sidecar_name: &'a str,
+    /// `--fail-on-duplicates`: conflicting duplicates are a [`Failure::Duplicates`].
+    fail_on_duplicates: bool,
+}
+
+impl Default for DumpOptions<'_> {
+    fn default() -> Self {
+        DumpOptions {
+            owners: None,
+            annotations: None,
+            min_size: None,
+            stable: false,
+            sidecar_name: out::DEFAULT_SIDECAR_NAME,
+            fail_on_duplicates: false,
+        }
+    }
+}
+
+/// Writes sidecars for every artifact, then `sym.log` and (when symbols
+/// collide across files) `duplicates.log` into `out_dir`. `min_size` trims
+/// the sidecars and `sym.log`; duplicates are still checked on every export.
+/// `annotations` adds provenance comments to the sidecars. `stable` also
+/// writes `sym.stable.log`. `sidecar_name` is the `--sidecar-name` pattern;
+/// two artifacts mapping to one sidecar is an error. Returns each artifact's
+/// exports, or with `fail_on_duplicates` an error once `duplicates.log` is
+/// written if any duplicate is conflicting.
+fn dump_artifacts(
+    files: &[PathBuf],
+    out_dir: &Path,
+    options: &DumpOptions,
+    log: &Log,
+) -> Result<Vec<(PathBuf, Vec<String>)>, Failure> {
+    let DumpOptions {
+        owners,
+        annotations,
+        min_size,
+        stable,
+        sidecar_name,
+        fail_on_duplicates,
+    } = *options;
+    let mut sidecars = HashMap::<PathBuf, &PathBuf>::new();
+    for artifact in files {
+        let sidecar = out::sidecar_path(artifact, sidecar_name)?;
+        if let Some(other) = sidecars.insert(sidecar.clone(), artifact) {
+            return Err(Failure::Usage(format!(
+                "{} and {} would both write sidecar {}; add {{parent}} or {{hash}} to --sidecar-name",
+                other.display(),
+                artifact.display(),
+                sidecar.display()
+            )));
+        }
+    }
+    let root = out_dir.parent().unwrap_or(out_dir);
+    let (written, shown) = (file_paths(root), stdout_paths(root));
     let mut exports_by_file = Vec::<(PathBuf, Vec<String>)>::new();
-    for artifact in &nros {
-        let sidecar = out::write_exports_sidecar(artifact)?;
-        let symbols = out::exported_symbols(artifact)?;
-        println!("nro: {}", artifact.display());
-        println!("exports: {}", sidecar.display());
+    let mut rows = Vec::<(PathBuf, Vec<String>)>::new();
+    for artifact in files {
+        dump_event(serde_json::json!({"event": "start", "file": shown.show(artifact)}));
+        if min_size.is_some() && out::nro_symbol_sizes(artifact).is_none() {
+            eprintln!(
+                "warning: --min-size ignored for {}: symbol sizes are only read from .nro files",
+                artifact.display()
+            );
+        }
+        let started = Instant::now();
+        let out::Extraction { symbols, strategy } = out::extract_dump_exports(artifact)?;
+        let kept = out::filter_min_size(artifact, symbols.clone(), min_size);
+        let sidecar = out::write_exports_sidecar(artifact, &kept, annotations, sidecar_name)?;
+        log.info(format!("nro: {}", shown.show(artifact)));
+        log.info(format!("exports: {}", shown.show(&sidecar)));
+        log.verbose(format!(
+            "  strategy: {strategy}; {} symbol(s), {} kept; {:.1}ms",
+            symbols.len(),
+            kept.len(),
+            started.elapsed().as_secs_f64() * 1000.0
+        ));
+        dump_event(serde_json::json!({
+            "event": "done",
+            "file": shown.show(artifact),
+            "symbols": kept.len(),
+        }));
+        rows.push((artifact.clone(), kept));
         exports_by_file.push((artifact.clone(), symbols));
     }
-
     let sym_log_path = out_dir.join("sym.log");
     if exports_by_file.len() == 1 {
-        let sym_log = out::write_symbol_log(&exports_by_file[0].0, &sym_log_path)?;
-        println!("sym.log: {}", sym_log.display());
+        let sym_log =
+            out::write_symbol_log(&exports_by_file[0].0, &sym_log_path, min_size, &written)?;
+        log.info(format!("sym.log: {}", shown.show(&sym_log)));
     } else {
-        write_batch_sym_log(&exports_by_file, &sym_log_path)?;
-        println!("sym.log: {}", sym_log_path.display());
+        write_batch_sym_log(&rows, &sym_log_path, &written)?;
+        log.info(format!("sym.log: {}", shown.show(&sym_log_path)));
     }
-    let resolution = if trace_enabled {
-        write_resolution_report(&workspace_root, &args, &trace_file).ok()
-    } else {
-        None
-    };
-    let duplicates = find_duplicate_symbols(&exports_by_file);
-    if duplicates.is_empty() {
-        println!(
+    if stable {
+        let stable_log = out::write_stable_symbol_log(&rows, &out_dir.join("sym.stable.log"))?;
+        log.info(format!("sym.stable.log: {}", shown.show(&stable_log)));
+    }
+
+    let (conflicting, identical) = classify_duplicates(find_duplicate_symbols(&exports_by_file));
+    for (kind, duplicates) in [("conflicting", &conflicting), ("identical", &identical)] {
+        for (symbol, files) in duplicates {
+            let files: Vec<String> = files.iter().map(|f| shown.show(f)).collect();
+            dump_event(serde_json::json!({
+                "event": "duplicate",
+                "symbol": symbol,
+                "files": files,
+                "kind": kind,
+            }));
+        }
+    }
+    dump_event(serde_json::json!({
+        "event": "summary",
+        "artifacts": rows.len(),
+        "total_symbols": rows.iter().map(|(_, symbols)| symbols.len()).sum::<usize>(),
+    }));
+    if conflicting.is_empty() && identical.is_empty() {
+        log.info(format!(
             "duplicate symbols: none (checked {} artifact(s))",
             exports_by_file.len()
-        );
-    } else {
-        let dup_log = out_dir.join("duplicates.log");
-        let mut dup_body = String::new();
-        dup_body.push_str("# symbaker duplicates.log\n");
-        dup_body.push_str("# format: symbol followed by files exporting it\n");
-        for (symbol, files) in &duplicates {
+        ));
+        return Ok(exports_by_file);
+    }
+
+    let dup_log = out_dir.join("duplicates.log");
+    let mut dup_body = String::new();
+    dup_body.push_str("# symbaker duplicates.log\n");
+    if let Some(owners) = owners {
+        dup_body.push_str("# by crate: owning crates, conflicting symbol count, symbols\n");
+        for (crates, symbols) in group_duplicates_by_crate(&conflicting, owners) {
+            let line = format!("{}: {} symbol(s)", crates.join(" + "), symbols.len());
+            log.info(format!("  {line}"));
+            dup_body.push_str(&format!("\n## {line}\n"));
+            for symbol in symbols {
+                dup_body.push_str(&format!("  {symbol}\n"));
+            }
+        }
+        dup_body.push('\n');
+    }
+    dup_body.push_str("# format: symbol followed by files exporting it\n");
+    dup_body.push_str(&format!(
+        "# identical implementations (heuristic): same size and first {} bytes, PC-relative\n\
+         # immediates masked; everything else, including non-.nro files, is conflicting\n",
+        out::FINGERPRINT_BYTES
+    ));
+    for (heading, duplicates) in [
+        ("conflicting implementations", &conflicting),
+        ("identical implementations (heuristic)", &identical),
+    ] {
+        if duplicates.is_empty() {
+            continue;
+        }
+        dup_body.push_str(&format!("\n## {heading}: {}\n", duplicates.len()));
+        for (symbol, files) in duplicates {
             dup_body.push_str(&format!("\n{symbol}\n"));
             for file in files {
-                dup_body.push_str(&format!("  {}\n", file.display()));
+                dup_body.push_str(&format!("  {}\n", written.show(file)));
             }
         }
-        fs::write(&dup_log, dup_body).map_err(|e| format!("write {}: {e}", dup_log.display()))?;
-        println!("duplicates: {}", dup_log.display());
-        println!(
-            "found {} duplicated symbol(s) across {} artifact(s)",
-            duplicates.len(),
-            exports_by_file.len()
-        );
     }
-    if let Some(report) = resolution {
-        println!("resolution: {}", report.display());
+    fs::write(&dup_log, dup_body).map_err(|e| format!("write {}: {e}", dup_log.display()))?;
+    log.info(format!("duplicates: {}", shown.show(&dup_log)));
+    log.info(format!(
+        "found {} duplicated symbol(s) across {} artifact(s): {} conflicting, {} identical (heuristic)",
+        conflicting.len() + identical.len(),
+        exports_by_file.len(),
+        conflicting.len(),
+        identical.len()
+    ));
+    if fail_on_duplicates && !conflicting.is_empty() {
+        return Err(Failure::Duplicates(format!(
+            "--fail-on-duplicates: {} conflicting duplicate symbol(s), see {}",
+            conflicting.len(),
+            shown.show(&dup_log)
+        )));
     }
-    Ok(())
+    Ok(exports_by_file)
 }
 
-fn run_wrapped_cargo(mut args: Vec<OsString>) -> Result<(), String> {
-    while args
-        .first()
-        .map(|s| s.to_string_lossy() == "symdump")
-        .unwrap_or(false)
+/// Where `cargo symdump dump` writes its `SYMBAKER_EVENT_FD` progress events.
+static DUMP_EVENTS: OnceLock<Mutex<fs::File>> = OnceLock::new();
+
+/// Opens the descriptor named by `SYMBAKER_EVENT_FD` for [`dump_event`];
+/// unset means no events.
+fn open_dump_events() -> Result<(), String> {
+    let Ok(raw) = env::var("SYMBAKER_EVENT_FD") else {
+        return Ok(());
+    };
+    let fd = raw
+        .trim()
+        .parse::<i32>()
+        .ok()
+        .filter(|fd| *fd >= 0)
+        .ok_or_else(|| {
+            format!("SYMBAKER_EVENT_FD must be a file descriptor number, got {raw:?}")
+        })?;
+    #[cfg(unix)]
     {
-        args.remove(0);
-    }
-    let trace_enabled = has_flag(&args, "--trace");
-    args.retain(|a| a != "--trace");
-    if args.is_empty() {
-        return Err("usage: cargo symdump run <cargo-subcommand...>".to_string());
+        use std::os::unix::io::FromRawFd;
+        if fs::metadata(format!("/dev/fd/{fd}")).is_err() {
+            return Err(format!(
+                "SYMBAKER_EVENT_FD={fd} is not an open file descriptor"
+            ));
+        }
+        // SAFETY: `fd` is open (checked above) and handed to us by whoever
+        // set SYMBAKER_EVENT_FD; the static keeps it open until exit.
+        let file = unsafe { fs::File::from_raw_fd(fd) };
+        let _ = DUMP_EVENTS.set(Mutex::new(file));
     }
-    let workspace_root = discover_workspace_root_for_args(&args)?;
-    let out_dir = symbaker_output_dir(&workspace_root)?;
-    let trace_file = out_dir.join("trace.log");
-    if trace_enabled {
-        let _ = fs::remove_file(&trace_file);
+    #[cfg(not(unix))]
+    eprintln!("warning: SYMBAKER_EVENT_FD={fd} ignored: events need a Unix file descriptor");
+    Ok(())
+}
+
+/// Writes `event` as one JSON line to the `SYMBAKER_EVENT_FD` stream, if open.
+fn dump_event(event: Value) {
+    let Some(sink) = DUMP_EVENTS.get() else {
+        return;
+    };
+    let mut file = sink.lock().unwrap_or_else(|e| e.into_inner());
+    // A reader that went away must not fail the dump.
+    let _ = std::io::Write::write_all(&mut *file, format!("{event}\n").as_bytes());
+}
+
+fn cargo_config_env(workspace_root: &Path) -> BTreeMap<String, String> {
+    let mut out = BTreeMap::<String, String>::new();
+    let path = workspace_root.join(".cargo").join("config.toml");
+    let Ok(text) = fs::read_to_string(&path) else {
+        return out;
+    };
+    let Ok(doc) = toml::from_str::<toml::Value>(&text) else {
+        return out;
+    };
+    let Some(env_tbl) = doc.get("env").and_then(|v| v.as_table()) else {
+        return out;
+    };
+    for (key, value) in env_tbl {
+        let value = match value {
+            toml::Value::String(s) => Some(s.clone()),
+            toml::Value::Table(t) => t
+                .get("value")
+                .and_then(|v| v.as_str())
+                .map(|s| s.to_string()),
+            _ => None,
+        };
+        if let Some(v) = value {
+            out.insert(key.clone(), v);
+        }
     }
+    out
+}
 
-    let mut cmd = Command::new("cargo");
-    cmd.args(&args);
-    apply_symbaker_env(&mut cmd, &args, &workspace_root, trace_enabled);
-    let status = cmd
-        .status()
-        .map_err(|e| format!("failed to run cargo: {e}"))?;
-    if !status.success() {
-        return Err(format!("cargo {:?} failed", args));
+fn run_doctor(args: Vec<OsString>, log: &Log) -> Result<(), Failure> {
+    if let Some(extra) = args.first() {
+        return Err(Failure::Usage(format!(
+            "unknown doctor arg: {}",
+            extra.to_string_lossy()
+        )));
     }
-    if trace_enabled {
-        if let Ok(report) = write_resolution_report(&workspace_root, &args, &trace_file) {
-            println!("resolution: {}", report.display());
+    let mut failures = 0usize;
+    let mut report = |ok: bool, label: &str, detail: String| {
+        if ok {
+            log.info(format!("[ok]   {label}: {detail}"));
+        } else {
+            failures += 1;
+            // Still shown under `-q`: the error alone does not say which check failed.
+            log.at(Verbosity::Quiet, format!("[fail] {label}: {detail}"));
+        }
+    };
+
+    // Cargo only injects `[env]` into the processes it spawns, so fold the
+    // workspace config in for keys the shell did not set.
+    let root = discover_workspace_root().ok();
+    if let Some(root) = &root {
+        for (key, value) in cargo_config_env(root) {
+            if key.starts_with("SYMBAKER_") && env::var_os(&key).is_none() {
+                env::set_var(key, value);
+            }
         }
     }
+
+    match symbaker_build::check_initialized() {
+        Ok(()) => report(
+            true,
+            "env",
+            "SYMBAKER_* init markers are consistent".to_string(),
+        ),
+        Err(e) => report(false, "env", e),
+    }
+
+    let cfg_path = env::var("SYMBAKER_CONFIG")
+        .ok()
+        .filter(|v| !v.trim().is_empty())
+        .map(PathBuf::from)
+        .or_else(discover_default_config_path);
+    match cfg_path {
+        Some(path) => match fs::read_to_string(&path) {
+            Ok(text) => match toml::from_str::<toml::Value>(&text) {
+                Ok(_) => report(true, "config", format!("{} parses", path.display())),
+                Err(e) => report(false, "config", format!("{}: {e}", path.display())),
+            },
+            Err(e) => report(false, "config", format!("read {}: {e}", path.display())),
+        },
+        None => report(
+            false,
+            "config",
+            "no symbaker.toml found (run `cargo symdump init`)".to_string(),
+        ),
+    }
+
+    match out::missing_symbol_tools() {
+        Some(_) => report(
+            true,
+            "tools",
+            "nm/objdump missing; .nro, ELF, PE, COFF, Mach-O and ar/lib inputs are read natively"
+                .to_string(),
+        ),
+        None => report(
+            true,
+            "tools",
+            format!(
+                "nm={} objdump={}",
+                out::pick_nm().as_deref().unwrap_or("missing"),
+                out::pick_objdump().as_deref().unwrap_or("missing")
+            ),
+        ),
+    }
+
+    match out::nro_parser_selftest() {
+        Ok(()) => report(
+            true,
+            "nro parser",
+            "embedded test image round-trips".to_string(),
+        ),
+        Err(e) => report(false, "nro parser", e),
+    }
+
+    if failures > 0 {
+        return Err(Failure::Other(format!(
+            "doctor found {failures} problem(s)"
+        )));
+    }
+    log.info("doctor: all checks passed");
     Ok(())
 }
 
-fn collect_nro_files(dir: &PathBuf) -> Result<Vec<PathBuf>, String> {
-    let mut stack = vec![dir.clone()];
-    let mut found = Vec::<PathBuf>::new();
-    while let Some(cur) = stack.pop() {
-        let entries = fs::read_dir(&cur).map_err(|e| format!("read_dir {}: {e}", cur.display()))?;
-        for entry in entries {
-            let entry = entry.map_err(|e| format!("read_dir entry error: {e}"))?;
-            let path = entry.path();
-            let meta = entry
-                .metadata()
-                .map_err(|e| format!("metadata {}: {e}", path.display()))?;
-            if meta.is_dir() {
-                stack.push(path);
-                continue;
-            }
-            if path
-                .extension()
-                .and_then(|s| s.to_str())
-                .map(|s| s.eq_ignore_ascii_case("nro"))
-                .unwrap_or(false)
-            {
-                found.push(path);
-            }
+/// Reads the `[[crates]]` entries of `.symbaker/resolution.toml`.
+fn load_resolution_crates(root: &Path) -> Result<Vec<toml::Value>, String> {
+    let path = root.join(".symbaker").join("resolution.toml");
+    let text = fs::read_to_string(&path).map_err(|e| {
+        format!(
+            "read {}: {e} (run `cargo symdump --trace ...` to generate it)",
+            path.display()
+        )
+    })?;
+    let doc: toml::Value =
+        toml::from_str(&text).map_err(|e| format!("parse {}: {e}", path.display()))?;
+    Ok(doc
+        .get("crates")
+        .and_then(|v| v.as_array())
+        .cloned()
+        .unwrap_or_default())
+}
+
+/// Every export (including aliases) recorded for `crates`.
+fn resolution_exports(crates: &[toml::Value]) -> BTreeSet<String> {
+    let mut exports = BTreeSet::<String>::new();
+    for c in crates {
+        for key in ["symbols", "alias_symbols"] {
+            let list = c.get(key).and_then(|v| v.as_array());
+            exports.extend(
+                list.into_iter()
+                    .flatten()
+                    .filter_map(report_symbol_export)
+                    .map(str::to_string),
+            );
         }
     }
-    found.sort();
-    Ok(found)
+    exports
 }
 
-fn resolve_dump_inputs(paths: Vec<PathBuf>) -> Result<Vec<PathBuf>, String> {
-    if paths.is_empty() {
-        return Err(
-            "usage: cargo symdump dump <path/to/file.nro|path/to/folder> [more paths...]"
+/// Version node derived from a soname, e.g. `libhdr.so.1` -> `LIBHDR_SO_1`.
+fn version_node_from_soname(soname: &str) -> String {
+    soname
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_uppercase()
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+/// GNU ld version script exporting `exports` and making everything else local.
+/// Without `node` the script is anonymous (`{ ... };`).
+fn version_script(node: Option<&str>, exports: &BTreeSet<String>) -> String {
+    let mut body = String::new();
+    body.push_str("/* symbaker symbols.map: generated from .symbaker/resolution.toml */\n");
+    match node {
+        Some(n) => body.push_str(&format!("{n} {{\n")),
+        None => body.push_str("{\n"),
+    }
+    body.push_str("  global:\n");
+    for sym in exports {
+        body.push_str(&format!("    {sym};\n"));
+    }
+    body.push_str("  local:\n    *;\n};\n");
+    body
+}
+
+fn run_gen_version_script(mut args: Vec<OsString>, log: &Log) -> Result<(), Failure> {
+    let output = take_flag_value(&mut args, "--output");
+    let soname = take_flag_value(&mut args, "--soname");
+    let node = take_flag_value(&mut args, "--node")
+        .map(|n| n.to_string_lossy().to_string())
+        .or_else(|| soname.map(|s| version_node_from_soname(&s.to_string_lossy())));
+    if let Some(extra) = args.first() {
+        return Err(Failure::Usage(format!(
+            "unknown gen-version-script arg: {}",
+            extra.to_string_lossy()
+        )));
+    }
+    let root = discover_workspace_root()?;
+    let exports = resolution_exports(&load_resolution_crates(&root)?);
+    if exports.is_empty() {
+        return Err(Failure::Other(
+            "resolution.toml records no exports (run `cargo symdump --trace ...` after building)"
                 .to_string(),
-        );
+        ));
     }
+    let path = match output {
+        Some(p) => p,
+        None => symbaker_output_dir(&root)?.join("symbols.map"),
+    };
+    fs::write(&path, version_script(node.as_deref(), &exports))
+        .map_err(|e| format!("write {}: {e}", path.display()))?;
+    log.info(format!(
+        "symbols.map: {} ({} export(s))",
+        path.display(),
+        exports.len()
+    ));
+    log.info(format!(
+        "link with: -C link-arg=-Wl,--version-script={}",
+        path.display()
+    ));
+    Ok(())
+}
 
-    let mut files = Vec::<PathBuf>::new();
-    for path in paths {
-        let canon = path
-            .canonicalize()
-            .map_err(|e| format!("{}: {e}", path.display()))?;
-        let meta =
-            fs::metadata(&canon).map_err(|e| format!("metadata {}: {e}", canon.display()))?;
-        if meta.is_dir() {
-            files.extend(collect_nro_files(&canon)?);
-        } else if meta.is_file() {
-            files.push(canon);
-        } else {
-            return Err(format!("unsupported path type: {}", canon.display()));
+/// `cargo symdump bench` results; durations in microseconds.
+#[derive(Serialize, Debug, PartialEq)]
+struct BenchReport {
+    artifact: String,
+    iters: usize,
+    min_us: u64,
+    median_us: u64,
+    max_us: u64,
+    peak_symbols: usize,
+}
+
+impl BenchReport {
+    /// Summarizes one non-empty `durations` entry per iteration.
+    fn new(artifact: String, mut durations: Vec<Duration>, peak_symbols: usize) -> Self {
+        durations.sort();
+        let us = |d: Duration| u64::try_from(d.as_micros()).unwrap_or(u64::MAX);
+        BenchReport {
+            artifact,
+            iters: durations.len(),
+            min_us: us(durations[0]),
+            median_us: us(durations[durations.len() / 2]),
+            max_us: us(durations[durations.len() - 1]),
+            peak_symbols,
         }
     }
+}
 
-    let mut uniq = BTreeSet::<PathBuf>::new();
-    for file in files {
-        uniq.insert(file);
+/// Hidden `bench`: runs the `.nro` symbol parser `--iters` times (default
+/// 10) on one artifact and reports min/median/max and the peak row count.
+fn run_bench(mut args: Vec<OsString>, log: &Log) -> Result<(), Failure> {
+    let json = take_flag(&mut args, "--json");
+    let iters = match take_flag_value(&mut args, "--iters") {
+        Some(raw) => raw
+            .to_string_lossy()
+            .parse::<usize>()
+            .ok()
+            .filter(|n| *n > 0)
+            .ok_or_else(|| {
+                Failure::Usage(format!(
+                    "--iters must be a positive integer, got {:?}",
+                    raw.to_string_lossy()
+                ))
+            })?,
+        None => 10,
+    };
+    let [artifact] = args.as_slice() else {
+        return Err(Failure::Usage(
+            "usage: cargo symdump bench <path/to/file.nro> [--iters N] [--json]".to_string(),
+        ));
+    };
+    let artifact = PathBuf::from(artifact);
+
+    let mut durations = Vec::<Duration>::with_capacity(iters);
+    let mut peak_symbols = 0usize;
+    for _ in 0..iters {
+        let start = Instant::now();
+        let count = out::nro_symbol_count(&artifact)?;
+        durations.push(start.elapsed());
+        peak_symbols = peak_symbols.max(count);
     }
-    let out: Vec<PathBuf> = uniq.into_iter().collect();
-    if out.is_empty() {
-        return Err("no files to dump (no .nro files found in provided folders)".to_string());
+    let report = BenchReport::new(artifact.display().to_string(), durations, peak_symbols);
+    if json {
+        let encoded =
+            serde_json::to_string(&report).map_err(|e| format!("encode bench json: {e}"))?;
+        log.info(encoded);
+    } else {
+        let ms = |us: u64| us as f64 / 1000.0;
+        log.info(format!(
+            "bench: {} ({} iter(s))",
+            report.artifact, report.iters
+        ));
+        log.info(format!(
+            "parse: min {:.3}ms  median {:.3}ms  max {:.3}ms",
+            ms(report.min_us),
+            ms(report.median_us),
+            ms(report.max_us)
+        ));
+        log.info(format!("peak symbols: {}", report.peak_symbols));
     }
-    Ok(out)
+    Ok(())
 }
 
-fn find_duplicate_symbols(rows: &[(PathBuf, Vec<String>)]) -> Vec<(String, Vec<PathBuf>)> {
-    let mut by_symbol = BTreeMap::<String, BTreeSet<PathBuf>>::new();
-    for (artifact, symbols) in rows {
-        let mut seen = HashSet::<String>::new();
-        for symbol in symbols {
-            if !seen.insert(symbol.clone()) {
-                continue;
-            }
-            by_symbol
-                .entry(symbol.clone())
-                .or_default()
-                .insert(artifact.clone());
-        }
+fn run_stats(args: Vec<OsString>, log: &Log) -> Result<(), Failure> {
+    if let Some(extra) = args.first() {
+        return Err(Failure::Usage(format!(
+            "unknown stats arg: {}",
+            extra.to_string_lossy()
+        )));
     }
+    let root = discover_workspace_root()?;
+    let crates = load_resolution_crates(&root)?;
 
-    by_symbol
-        .into_iter()
-        .filter_map(|(symbol, files)| {
-            if files.len() <= 1 {
-                None
+    let count = |c: &toml::Value, key: &str| {
+        c.get(key)
+            .and_then(|v| v.as_array())
+            .map(|a| a.len())
+            .unwrap_or(0)
+    };
+    let (mut total_symbols, mut total_aliases) = (0usize, 0usize);
+    log.info(format!("{:<32} {:>8} {:>8}", "crate", "exports", "aliases"));
+    for c in &crates {
+        let name = c.get("name").and_then(|v| v.as_str()).unwrap_or("?");
+        let symbols = count(c, "symbols");
+        let aliases = count(c, "alias_symbols");
+        total_symbols += symbols;
+        total_aliases += aliases;
+        log.info(format!("{:<32} {:>8} {:>8}", name, symbols, aliases));
+    }
+    log.info(format!(
+        "{:<32} {:>8} {:>8}",
+        "total", total_symbols, total_aliases
+    ));
+    Ok(())
+}
+
+/// `verify --no-clashes`: prints every name defined GLOBAL more than once
+/// within a single artifact (not duplicates across artifacts, which `dump`
+/// reports) and returns how many were found.
+fn report_clashes(
+    files: &[PathBuf],
+    expected: &BTreeSet<String>,
+    log: &Log,
+) -> Result<usize, String> {
+    let mut clashes = 0usize;
+    for artifact in files {
+        if !artifact
+            .extension()
+            .is_some_and(|e| e.eq_ignore_ascii_case("nro"))
+        {
+            eprintln!(
+                "warning: --no-clashes reads .nro dynsym only; skipped {}",
+                artifact.display()
+            );
+            continue;
+        }
+        for (sym, addrs) in out::duplicate_global_definitions(artifact)? {
+            let addrs: Vec<String> = addrs.iter().map(|a| format!("0x{a:X}")).collect();
+            let origin = if expected.contains(&sym) {
+                "symbaker export"
             } else {
-                Some((symbol, files.into_iter().collect()))
-            }
+                "not a symbaker export"
+            };
+            log.at(
+                Verbosity::Quiet,
+                format!(
+                    "clash: {sym} is defined GLOBAL {} times in {} at {} ({origin})",
+                    addrs.len(),
+                    artifact.display(),
+                    addrs.join(", ")
+                ),
+            );
+            clashes += 1;
+        }
+    }
+    Ok(clashes)
+}
+
+/// Takes every `--include-regex`/`--exclude-regex`/`--include-glob`/
+/// `--exclude-glob` flag; each is repeatable and takes a comma list, like the
+/// `symbaker_module` options of the same names.
+fn take_name_matcher(args: &mut Vec<OsString>) -> Result<names::NameMatcher, String> {
+    let mut take = |flag: &str| {
+        let mut specs = Vec::new();
+        while let Some(v) = take_flag_value(args, flag) {
+            specs.extend(
+                v.to_string_lossy()
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|s| !s.is_empty())
+                    .map(str::to_string),
+            );
+        }
+        specs
+    };
+    let (include_regex, exclude_regex) = (take("--include-regex"), take("--exclude-regex"));
+    let (include_glob, exclude_glob) = (take("--include-glob"), take("--exclude-glob"));
+    let mut matcher = names::NameMatcher::default();
+    for (specs, kind, out) in [
+        (include_regex, "include", &mut matcher.include_regex),
+        (exclude_regex, "exclude", &mut matcher.exclude_regex),
+    ] {
+        for r in specs {
+            out.push(names::compile_regex(&r, kind)?);
+        }
+    }
+    for (specs, kind, out) in [
+        (include_glob, "include", &mut matcher.include_glob),
+        (exclude_glob, "exclude", &mut matcher.exclude_glob),
+    ] {
+        for g in specs {
+            names::check_glob(&g, kind)?;
+            out.push(g);
+        }
+    }
+    Ok(matcher)
+}
+
+/// Exports of the verified artifacts that carry one of the report's prefixes
+/// followed by that crate's `sep` (`__` when unrecorded) but are not recorded
+/// in it: ABI added since the report was written. A crate's prefixes are its
+/// `resolved_prefix` and whatever precedes `sep` in its recorded exports.
+fn unexpected_exports(
+    crates: &[toml::Value],
+    recorded: &BTreeSet<String>,
+    exported: &HashSet<String>,
+) -> BTreeSet<String> {
+    let mut heads = BTreeSet::<String>::new();
+    for c in crates {
+        let sep = c
+            .get("sep")
+            .and_then(|v| v.as_str())
+            .filter(|s| !s.is_empty())
+            .unwrap_or("__");
+        let own = resolution_exports(std::slice::from_ref(c));
+        let prefixes = c
+            .get("resolved_prefix")
+            .and_then(|v| v.as_str())
+            .into_iter()
+            .chain(
+                own.iter()
+                    .filter_map(|sym| sym.split_once(sep).map(|(p, _)| p)),
+            )
+            .filter(|p| !p.is_empty());
+        heads.extend(prefixes.map(|p| format!("{p}{sep}")));
+    }
+    exported
+        .iter()
+        .filter(|sym| !recorded.contains(*sym))
+        .filter(|sym| {
+            heads.iter().any(|h| {
+                sym.strip_prefix(h.as_str())
+                    .is_some_and(|rest| !rest.is_empty())
+            })
         })
+        .cloned()
         .collect()
 }
 
-fn write_batch_sym_log(rows: &[(PathBuf, Vec<String>)], out_path: &PathBuf) -> Result<(), String> {
-    let mut body = String::new();
-    body.push_str("# symbaker sym.log\n");
-    body.push_str("# format: source=<path> then one symbol per line\n");
-    for (artifact, symbols) in rows {
-        body.push_str(&format!("\n# source={}\n", artifact.display()));
-        for symbol in symbols {
-            body.push_str(symbol);
-            body.push('\n');
+/// Checks that every export recorded in `resolution.toml` is present in the
+/// given artifacts and not marked HIDDEN (GLOBAL but not dynamically resolvable).
+fn run_verify(mut args: Vec<OsString>, log: &Log) -> Result<(), Failure> {
+    let no_clashes = take_flag(&mut args, "--no-clashes");
+    let allow_extra = take_flag(&mut args, "--allow-extra");
+    let matcher = take_name_matcher(&mut args)?;
+    let files = resolve_dump_inputs(args.into_iter().map(PathBuf::from).collect(), true, log)?;
+    let root = discover_workspace_root()?;
+    let crates = load_resolution_crates(&root)?;
+    let recorded = resolution_exports(&crates);
+    let matched = |sym: &String| matcher.included(sym) && !matcher.excluded(sym);
+    let expected: BTreeSet<String> = recorded.iter().filter(|s| matched(s)).cloned().collect();
+
+    let mut exported = HashSet::<String>::new();
+    let mut hidden = BTreeMap::<String, PathBuf>::new();
+    for artifact in &files {
+        exported.extend(out::exported_symbols(artifact)?);
+        for sym in out::hidden_global_functions(artifact)? {
+            hidden.entry(sym).or_insert_with(|| artifact.clone());
         }
     }
-    fs::write(out_path, body).map_err(|e| format!("write {}: {e}", out_path.display()))
+
+    let clashes = if no_clashes {
+        report_clashes(&files, &expected, log)?
+    } else {
+        0
+    };
+    let mut problems = 0usize;
+    for sym in &expected {
+        if let Some(artifact) = hidden.get(sym) {
+            log.at(
+                Verbosity::Quiet,
+                format!(
+                    "[ERROR] hidden: {sym} is GLOBAL but STV_HIDDEN in {} (not dynamically resolvable)",
+                    artifact.display()
+                ),
+            );
+            problems += 1;
+        } else if !exported.contains(sym) {
+            log.at(Verbosity::Quiet, format!("[ERROR] removed: {sym}"));
+            problems += 1;
+        }
+    }
+    let added: Vec<String> = unexpected_exports(&crates, &recorded, &exported)
+        .into_iter()
+        .filter(matched)
+        .collect();
+    // Like `doctor`, what fails the run still prints under `-q`.
+    let (label, level) = if allow_extra {
+        ("[allowed]", Verbosity::Normal)
+    } else {
+        ("[ERROR]", Verbosity::Quiet)
+    };
+    for sym in &added {
+        log.at(level, format!("{label} added: {sym}"));
+    }
+    if problems > 0 {
+        return Err(Failure::Regression(format!(
+            "verify: {problems} of {} expected export(s) are not resolvable",
+            expected.len()
+        )));
+    }
+    if !added.is_empty() && !allow_extra {
+        return Err(Failure::Regression(format!(
+            "verify: {} export(s) not in resolution.toml (pass --allow-extra to accept additions)",
+            added.len()
+        )));
+    }
+    if clashes > 0 {
+        return Err(Failure::Regression(format!(
+            "verify: {clashes} symbol(s) defined GLOBAL more than once in one artifact"
+        )));
+    }
+    log.info(format!(
+        "verify: ok ({} expected export(s), {} added, {} artifact(s))",
+        expected.len(),
+        added.len(),
+        files.len()
+    ));
+    Ok(())
 }
 
-fn run_dump_many(paths: Vec<PathBuf>) -> Result<(), String> {
-    let files = resolve_dump_inputs(paths)?;
-    let root = discover_workspace_root()?;
-    let out_dir = symbaker_output_dir(&root)?;
+/// Which symbol changes make `diff` fail.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum DiffMode {
+    /// Default: removed symbols break downstream consumers.
+    Removals,
+    /// `--strict`: any change.
+    Strict,
+    /// `--additions-only`: new symbols (ABI growth); removals pass.
+    AdditionsOnly,
+}
 
-    let mut exports_by_file = Vec::<(PathBuf, Vec<String>)>::new();
-    for artifact in &files {
-        let sidecar = out::write_exports_sidecar(artifact)?;
-        let symbols = out::exported_symbols(artifact)?;
-        println!("nro: {}", artifact.display());
-        println!("exports: {}", sidecar.display());
-        exports_by_file.push((artifact.clone(), symbols));
+impl DiffMode {
+    fn fails(self, removed: usize, added: usize) -> bool {
+        match self {
+            DiffMode::Removals => removed > 0,
+            DiffMode::Strict => removed + added > 0,
+            DiffMode::AdditionsOnly => added > 0,
+        }
     }
+}
 
-    let sym_log_path = out_dir.join("sym.log");
-    if exports_by_file.len() == 1 {
-        let sym_log = out::write_symbol_log(&exports_by_file[0].0, &sym_log_path)?;
-        println!("sym.log: {}", sym_log.display());
-    } else {
-        write_batch_sym_log(&exports_by_file, &sym_log_path)?;
-        println!("sym.log: {}", sym_log_path.display());
-    }
+/// Symbol names of a text listing: the first word of each line, skipping
+/// blank lines and `#` comments. Reads exports sidecars (annotated or not)
+/// and `sym.stable.log`.
+fn listing_symbols(body: &str) -> BTreeSet<String> {
+    body.lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty() && !l.starts_with('#'))
+        .filter_map(|l| l.split_whitespace().next())
+        .map(str::to_string)
+        .collect()
+}
 
-    let duplicates = find_duplicate_symbols(&exports_by_file);
-    if duplicates.is_empty() {
-        println!(
-            "duplicate symbols: none (checked {} artifact(s))",
-            exports_by_file.len()
-        );
-        return Ok(());
+/// One side of `diff`: a `.txt`/`.log` listing, else an artifact whose
+/// exports are read like `dump` reads them.
+fn diff_side(path: &Path) -> Result<BTreeSet<String>, Failure> {
+    if !path.is_file() {
+        return Err(Failure::NoArtifact(format!(
+            "{}: no such file",
+            path.display()
+        )));
+    }
+    if path
+        .extension()
+        .is_some_and(|e| e.eq_ignore_ascii_case("txt") || e.eq_ignore_ascii_case("log"))
+    {
+        let body = fs::read_to_string(path).map_err(|e| format!("read {}: {e}", path.display()))?;
+        return Ok(listing_symbols(&body));
     }
+    Ok(out::extract_exports(path)?.symbols.into_iter().collect())
+}
 
-    let dup_log = out_dir.join("duplicates.log");
-    let mut dup_body = String::new();
-    dup_body.push_str("# symbaker duplicates.log\n");
-    dup_body.push_str("# format: symbol followed by files exporting it\n");
-    for (symbol, files) in &duplicates {
-        dup_body.push_str(&format!("\n{symbol}\n"));
-        for file in files {
-            dup_body.push_str(&format!("  {}\n", file.display()));
+fn run_diff(mut args: Vec<OsString>, log: &Log) -> Result<(), Failure> {
+    let mode = match (
+        take_flag(&mut args, "--strict"),
+        take_flag(&mut args, "--additions-only"),
+    ) {
+        (true, true) => {
+            return Err(Failure::Usage(
+                "--strict and --additions-only cannot be combined".to_string(),
+            ))
         }
-    }
-    fs::write(&dup_log, dup_body).map_err(|e| format!("write {}: {e}", dup_log.display()))?;
-    println!("duplicates: {}", dup_log.display());
-    println!(
-        "found {} duplicated symbol(s) across {} artifact(s)",
-        duplicates.len(),
-        exports_by_file.len()
+        (true, false) => DiffMode::Strict,
+        (false, true) => DiffMode::AdditionsOnly,
+        (false, false) => DiffMode::Removals,
+    };
+    let matcher = take_name_matcher(&mut args)?;
+    let [old, new] = args.as_slice() else {
+        return Err(Failure::Usage(
+            "usage: cargo symdump diff [--strict|--additions-only] [--include-regex <re>] [--exclude-regex <re>] [--include-glob <glob>] [--exclude-glob <glob>] <old> <new>".to_string(),
+        ));
+    };
+    let matched = |side: BTreeSet<String>| -> BTreeSet<String> {
+        side.into_iter()
+            .filter(|s| matcher.included(s) && !matcher.excluded(s))
+            .collect()
+    };
+    let (old, new) = (
+        matched(diff_side(Path::new(old))?),
+        matched(diff_side(Path::new(new))?),
     );
+    let removed: Vec<&String> = old.difference(&new).collect();
+    let added: Vec<&String> = new.difference(&old).collect();
+    for sym in &removed {
+        log.info(format!("- {sym}"));
+    }
+    for sym in &added {
+        log.info(format!("+ {sym}"));
+    }
+    let summary = format!("diff: {} removed, {} added", removed.len(), added.len());
+    if mode.fails(removed.len(), added.len()) {
+        return Err(Failure::Regression(summary));
+    }
+    log.info(summary);
     Ok(())
 }
 
-fn run_update(mut args: Vec<OsString>) -> Result<(), String> {
+fn run_update(mut args: Vec<OsString>, log: &Log) -> Result<(), Failure> {
     let mut repo_arg = DEFAULT_REPO.to_string();
     let mut install_root = None::<PathBuf>;
     let mut i = 0usize;
@@ -958,7 +4740,9 @@ fn run_update(mut args: Vec<OsString>) -> Result<(), String> {
         .status()
         .map_err(|e| format!("failed to run cargo install: {e}"))?;
     if !status.success() {
-        return Err(format!("cargo install failed for repo: {repo}"));
+        return Err(Failure::Build(format!(
+            "cargo install failed for repo: {repo}"
+        )));
     }
 
     if let Err(e) = write_installer_marker(&marker_path) {
@@ -969,7 +4753,7 @@ fn run_update(mut args: Vec<OsString>) -> Result<(), String> {
         );
     }
 
-    println!("updated cargo-symdump from: {repo}");
+    log.info(format!("updated cargo-symdump from: {repo}"));
     Ok(())
 }
 
@@ -982,35 +4766,1332 @@ fn main() -> ExitCode {
     {
         args.remove(0);
     }
-    if args.is_empty() || args[0] == "-h" || args[0] == "--help" {
-        usage();
+    let _ = PATH_FLAGS.set(PathFlags {
+        relative_stdout: take_flag(&mut args, "--relative-paths"),
+        absolute_files: take_flag(&mut args, "--absolute-paths"),
+        redact_home: take_flag(&mut args, "--redact-home"),
+        redact: take_flag(&mut args, "--redact"),
+    });
+    let log = match take_verbosity(&mut args) {
+        Ok(level) => Log::new(level),
+        Err(e) => {
+            eprintln!("error: {e}");
+            return ExitCode::from(Failure::Usage(e).exit_code());
+        }
+    };
+    if args.is_empty() || args[0] == "-h" || args[0] == "--help" || args[0] == "--help-all" {
+        usage(args.first().is_some_and(|a| a == "--help-all"));
         return ExitCode::SUCCESS;
     }
 
     let result = if args[0] == "dump" {
         if args.len() < 2 {
-            Err(
+            Err(Failure::Usage(
                 "usage: cargo symdump dump <path/to/file.nro|path/to/folder> [more paths...]"
                     .to_string(),
-            )
+            ))
         } else {
-            run_dump_many(args.into_iter().skip(1).map(PathBuf::from).collect())
+            run_dump_many(args.into_iter().skip(1).collect(), &log)
         }
     } else if args[0] == "init" {
-        run_init(args.into_iter().skip(1).collect())
+        run_init(args.into_iter().skip(1).collect(), &log)
     } else if args[0] == "run" {
-        run_wrapped_cargo(args.into_iter().skip(1).collect())
+        run_wrapped_cargo(args.into_iter().skip(1).collect(), &log)
+    } else if args[0] == "test" {
+        run_test_then_dump(args.into_iter().skip(1).collect(), &log)
     } else if args[0] == "update" {
-        run_update(args.into_iter().skip(1).collect())
+        run_update(args.into_iter().skip(1).collect(), &log)
+    } else if args[0] == "doctor" {
+        run_doctor(args.into_iter().skip(1).collect(), &log)
+    } else if args[0] == "stats" {
+        run_stats(args.into_iter().skip(1).collect(), &log)
+    } else if args[0] == "verify" {
+        run_verify(args.into_iter().skip(1).collect(), &log)
+    } else if args[0] == "diff" {
+        run_diff(args.into_iter().skip(1).collect(), &log)
+    } else if args[0] == "gen-version-script" {
+        run_gen_version_script(args.into_iter().skip(1).collect(), &log)
+    } else if args[0] == "bench" {
+        run_bench(args.into_iter().skip(1).collect(), &log)
     } else {
-        run_build_then_dump(args)
+        run_build_then_dump(args, &log)
     };
 
     match result {
         Ok(()) => ExitCode::SUCCESS,
         Err(e) => {
             eprintln!("error: {e}");
-            ExitCode::FAILURE
+            ExitCode::from(e.exit_code())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn watch_config_diffs_and_waits_for_settled_edits() {
+        let old = "prefix = \"hdr\"\n\n[overrides]\nsmashline = \"hdr\"\n";
+        let new = "prefix = \"hdr\"\n\n[overrides]\nsmashline = \"sml\"\nssbusync = \"hdr\"\n";
+        assert_eq!(
+            config_diff(old, new),
+            [
+                "  [overrides]",
+                "- smashline = \"hdr\"",
+                "+ smashline = \"sml\"",
+                "+ ssbusync = \"hdr\""
+            ]
+        );
+        assert!(config_diff(old, old).is_empty());
+        // Moved between tables, reordered and duplicated lines all show.
+        let moved = "prefix = \"hdr\"\n\n[overrides]\n\n[prefix_aliases]\nsmashline = \"hdr\"\n";
+        assert_eq!(
+            config_diff(old, moved),
+            [
+                "  [overrides]",
+                "- smashline = \"hdr\"",
+                "+ [prefix_aliases]",
+                "+ smashline = \"hdr\""
+            ]
+        );
+        assert_eq!(
+            config_diff("a = 1\nb = 2\n", "b = 2\na = 1\n"),
+            ["- a = 1", "+ a = 1"]
+        );
+        assert_eq!(
+            config_diff("[t]\na = 1\n", "[t]\na = 1\na = 1\n"),
+            ["  [t]", "+ a = 1"]
+        );
+
+        let dir = env::temp_dir().join(format!("symbaker_watch_{}", std::process::id()));
+        fs::create_dir_all(dir.join("plugin")).unwrap();
+        let config = dir.join("symbaker.toml");
+        fs::write(&config, old).unwrap();
+        let writer = {
+            let config = config.clone();
+            std::thread::spawn(move || {
+                std::thread::sleep(Duration::from_millis(30));
+                fs::write(&config, "prefix = \"h").unwrap();
+                std::thread::sleep(Duration::from_millis(5));
+                fs::write(&config, new).unwrap();
+            })
+        };
+        let ms = Duration::from_millis;
+        assert_eq!(wait_for_change(&config, old, ms(5), ms(250)), new);
+        writer.join().unwrap();
+
+        if env::var_os("SYMBAKER_CONFIG").is_none() {
+            let manifest = dir.join("plugin").join("Cargo.toml");
+            let args = vec![
+                OsString::from("build"),
+                OsString::from(format!("--manifest-path={}", manifest.display())),
+            ];
+            assert_eq!(config_path_for_args(&args), Some(config));
+        }
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn diff_modes_pick_which_changes_fail() {
+        let listing = "# symbaker sym.stable.log\n\n# source=a.nro\nhdr__init  # crate=hdr fn=init\nhdr__tick\n";
+        assert_eq!(
+            listing_symbols(listing).into_iter().collect::<Vec<_>>(),
+            ["hdr__init", "hdr__tick"]
+        );
+        let cases = [(1, 0), (0, 1), (0, 0)];
+        let outcome = |mode: DiffMode| cases.map(|(removed, added)| mode.fails(removed, added));
+        assert_eq!(outcome(DiffMode::Removals), [true, false, false]);
+        assert_eq!(outcome(DiffMode::Strict), [true, true, false]);
+        assert_eq!(outcome(DiffMode::AdditionsOnly), [false, true, false]);
+    }
+
+    #[test]
+    fn failure_exit_codes_are_the_documented_contract() {
+        let msg = || "x".to_string();
+        let codes = [
+            Failure::Other(msg()),
+            Failure::Usage(msg()),
+            Failure::Build(msg()),
+            Failure::Duplicates(msg()),
+            Failure::Regression(msg()),
+            Failure::NoArtifact(msg()),
+        ]
+        .map(|f| f.exit_code());
+        assert_eq!(codes, [1, 2, 3, 4, 5, 6]);
+        assert_eq!(Failure::from(msg()), Failure::Other(msg()));
+    }
+
+    #[test]
+    fn extract_quoted_undoes_debug_escapes() {
+        for raw in [
+            "plain",
+            r#"C:\a\"weird"\b"#,
+            "tab\there",
+            "line\nbreak",
+            "snow\u{2603}man",
+        ] {
+            let line = format!("env CARGO_MANIFEST_DIR=Some({:?}) next=\"x\"", raw);
+            assert_eq!(
+                extract_quoted(&line, "CARGO_MANIFEST_DIR=Some(\"").as_deref(),
+                Some(raw)
+            );
+        }
+        assert_eq!(extract_quoted("key=\"unterminated", "key=\""), None);
+    }
+
+    #[test]
+    fn parent_map_walks_from_the_top_package() {
+        let deps: HashMap<String, Vec<String>> = [
+            ("app", vec!["mid", "serde"]),
+            ("tool", vec!["leaf"]),
+            ("mid", vec!["leaf", "symbaker"]),
+            ("leaf", vec!["symbaker"]),
+            ("serde", vec![]),
+            ("symbaker", vec![]),
+        ]
+        .into_iter()
+        .map(|(k, v)| (k.to_string(), v.into_iter().map(str::to_string).collect()))
+        .collect();
+
+        let from_app = parent_map(&deps, Some("app"));
+        assert_eq!(from_app.get("mid").map(String::as_str), Some("app"));
+        // Reached through mid even though tool sorts after it too.
+        assert_eq!(from_app.get("leaf").map(String::as_str), Some("mid"));
+        assert!(
+            !from_app.contains_key("serde"),
+            "serde does not use symbaker"
+        );
+
+        // Building `-p mid`: mid's own parent falls back to its dependent.
+        let from_mid = parent_map(&deps, Some("mid"));
+        assert_eq!(from_mid.get("mid").map(String::as_str), Some("app"));
+        assert_eq!(from_mid.get("leaf").map(String::as_str), Some("mid"));
+
+        let unknown_top = parent_map(&deps, None);
+        assert_eq!(unknown_top.get("leaf").map(String::as_str), Some("mid"));
+    }
+
+    #[test]
+    fn metadata_records_enabled_features_per_package() {
+        let parsed: Value = serde_json::from_str(
+            r#"{
+                "packages": [
+                    {"id": "app 0.1.0", "name": "app"},
+                    {"id": "hooks 0.1.0", "name": "hooks"}
+                ],
+                "resolve": {"nodes": [
+                    {"id": "app 0.1.0", "deps": [{"pkg": "hooks 0.1.0"}], "features": []},
+                    {"id": "hooks 0.1.0", "deps": [], "features": ["std", "default", "std"]}
+                ]}
+            }"#,
+        )
+        .unwrap();
+        let tree = parse_metadata(&parsed);
+        assert_eq!(tree.deps["app"], ["hooks"]);
+        assert_eq!(tree.features["hooks"], ["default", "std"]);
+        assert!(tree.features["app"].is_empty());
+
+        let args: Vec<OsString> = ["build", "-F", "extra", "--release", "--no-default-features"]
+            .into_iter()
+            .map(OsString::from)
+            .collect();
+        assert_eq!(
+            out::feature_args(&args),
+            ["--features", "extra", "--no-default-features"]
+        );
+    }
+
+    #[test]
+    fn leading_flags_stop_at_the_subcommand() {
+        let mut args: Vec<OsString> = ["--no-symbaker", "install", "--force", "x"]
+            .into_iter()
+            .map(OsString::from)
+            .collect();
+        assert!(take_leading_flag(&mut args, "--no-symbaker"));
+        assert!(!take_leading_flag(&mut args, "--force"));
+        assert_eq!(args, ["install", "--force", "x"]);
+    }
+
+    #[test]
+    fn no_symbaker_env_rejects_env_setting_flags() {
+        for flag in [
+            "--trace",
+            "--no-symbaker",
+            "--strict-config",
+            "--override=a=b",
+        ] {
+            let args = ["--no-symbaker-env", flag, "build"]
+                .map(OsString::from)
+                .to_vec();
+            let err = run_wrapped_cargo(args, &Log::new(Verbosity::Normal)).unwrap_err();
+            assert!(
+                matches!(&err, Failure::Usage(msg) if msg.contains("cannot be combined")),
+                "{flag}: {err}"
+            );
+        }
+    }
+
+    #[test]
+    fn name_filter_flags_use_the_module_vocabulary() {
+        let mut args: Vec<OsString> = [
+            "--include-glob",
+            "*init_*, *deinit",
+            "--exclude-regex=_test$",
+            "a.nro",
+        ]
+        .into_iter()
+        .map(OsString::from)
+        .collect();
+        let m = take_name_matcher(&mut args).unwrap();
+        assert_eq!(args, ["a.nro"]);
+        let kept = |s: &str| m.included(s) && !m.excluded(s);
+        assert!(kept("hdr__init_fighter"));
+        assert!(kept("hdr__deinit"));
+        assert!(!kept("hdr__init_fighter_test"));
+        assert!(!kept("hdr__on_frame"));
+
+        let mut bad: Vec<OsString> = vec!["--include-glob".into(), "[ab]*".into()];
+        assert!(take_name_matcher(&mut bad)
+            .unwrap_err()
+            .contains("unsupported include glob"));
+    }
+
+    #[test]
+    fn stdin_path_list_skips_blank_lines() {
+        let body = "a/one.nro\r\n\n  \nb dir/two.nro\n";
+        assert_eq!(
+            parse_path_list(body),
+            vec![PathBuf::from("a/one.nro"), PathBuf::from("b dir/two.nro")]
+        );
+    }
+
+    #[test]
+    fn mixed_env_fingerprints_are_reported() {
+        let traces = parse_trace(concat!(
+            "[symbaker] crate=\"a\" env_fingerprint=\"11111111\"\n",
+            "[symbaker] crate=\"a\" macro=symbaker function=\"f\" resolved_prefix=\"p\" export_name=\"p__f\"\n",
+            "[symbaker] crate=\"b\" env_fingerprint=\"22222222\"\n",
+            "[symbaker] crate=\"c\" env_fingerprint=\"11111111\"\n",
+            "[symbaker] crate=\"old\" macro=symbaker function=\"g\" export_name=\"p__g\"\n",
+        ));
+        assert_eq!(traces["a"].env_fingerprint.as_deref(), Some("11111111"));
+        assert_eq!(traces["a"].symbols, vec!["p__f".to_string()]);
+        assert_eq!(traces["old"].env_fingerprint, None);
+
+        let problems = fingerprint_problems(&traces, None);
+        assert_eq!(problems.len(), 1, "{problems:?}");
+        assert!(
+            problems[0].contains("11111111 (a, c); 22222222 (b)"),
+            "{problems:?}"
+        );
+
+        let problems = fingerprint_problems(&traces, Some("11111111"));
+        assert_eq!(problems.len(), 2, "{problems:?}");
+        assert!(problems[1].contains("crates b were traced under env fingerprint 22222222"));
+
+        let same = parse_trace("[symbaker] crate=\"a\" env_fingerprint=\"11111111\"\n");
+        assert!(fingerprint_problems(&same, Some("11111111")).is_empty());
+        assert_eq!(fingerprint_problems(&same, Some("33333333")).len(), 1);
+    }
+
+    #[test]
+    fn config_edited_between_crates_is_reported_as_stale_cache() {
+        // `dep` was expanded (and cached) before symbaker.toml was edited.
+        let traces = parse_trace(concat!(
+            "[symbaker] crate=\"dep\" config_hash=\"aaaaaaaa\"\n",
+            "[symbaker] crate=\"dep\" macro=symbaker function=\"f\" resolved_prefix=\"old\" export_name=\"old__f\"\n",
+            "[symbaker] crate=\"app\" config_hash=\"bbbbbbbb\"\n",
+            "[symbaker] crate=\"app\" macro=symbaker function=\"g\" resolved_prefix=\"new\" export_name=\"new__g\"\n",
+            "[symbaker] crate=\"legacy\" macro=symbaker function=\"h\" export_name=\"new__h\"\n",
+        ));
+        assert_eq!(traces["dep"].config_hash.as_deref(), Some("aaaaaaaa"));
+        assert_eq!(traces["legacy"].config_hash, None);
+
+        let problems = config_hash_problems(&traces, Some("bbbbbbbb"));
+        assert_eq!(problems.len(), 1, "{problems:?}");
+        assert!(problems[0].contains("crates dep were expanded with symbaker.toml aaaaaaaa"));
+        assert!(problems[0].contains("stale build cache"));
+        assert!(problems[0].contains("`cargo clean -p dep`"));
+
+        let problems = config_hash_problems(&traces, None);
+        assert_eq!(problems.len(), 1, "{problems:?}");
+        assert!(problems[0].contains("aaaaaaaa (dep); bbbbbbbb (app)"));
+
+        let problems = config_hash_problems(&traces, Some("cccccccc"));
+        assert_eq!(problems.len(), 2, "{problems:?}");
+
+        let same = parse_trace("[symbaker] crate=\"a\" config_hash=\"aaaaaaaa\"\n");
+        assert!(config_hash_problems(&same, Some("aaaaaaaa")).is_empty());
+        assert!(config_hash_problems(&same, None).is_empty());
+    }
+
+    #[test]
+    fn interleaved_trace_lines_follow_their_inline_crate() {
+        let log = [
+            r#"[symbaker] crate="a" env CARGO_PKG_NAME=Some("a") CARGO_MANIFEST_DIR=Some("/w/a")"#,
+            r#"[symbaker] crate="b" env CARGO_PKG_NAME=Some("b") CARGO_MANIFEST_DIR=Some("/w/b")"#,
+            r#"[symbaker] crate="a" selected source=config raw="hdr" sanitized="hdr""#,
+            r#"[symbaker] crate="b" selected source=crate raw="b" sanitized="b""#,
+            r#"[symbaker] crate="a" macro=symbaker function="f" resolved_prefix="hdr" export_name="hdr__f""#,
+            r#"[symbaker] crate="b" macro=symbaker function="g" resolved_prefix="b" export_name="b__g""#,
+            r#"[symbaker] crate="a" macro=symbaker function="f" compat_alias="f""#,
+            r#"[symbaker] crate="b" macro=symbaker_module module="m" template="{prefix}_{crate}_{name}" template_placeholders=["prefix", "name", "crate"]"#,
+        ]
+        .join("\n");
+        let map = parse_trace(&log);
+        assert_eq!(map["a"].manifest_dir.as_deref(), Some("/w/a"));
+        assert_eq!(map["a"].selected_source.as_deref(), Some("config"));
+        assert_eq!(map["a"].symbols, ["hdr__f"]);
+        assert_eq!(map["a"].aliases, ["f"]);
+        assert_eq!(map["b"].selected_source.as_deref(), Some("crate"));
+        assert_eq!(map["b"].symbols, ["b__g"]);
+        assert_eq!(
+            map["b"].template_placeholders.iter().collect::<Vec<_>>(),
+            ["crate", "name", "prefix"]
+        );
+        assert!(map["a"].template_placeholders.is_empty());
+
+        // Older logs without the inline field still follow the env lines.
+        let legacy = "[symbaker] env CARGO_PKG_NAME=Some(\"c\")\n[symbaker] macro=symbaker export_name=\"c__h\"";
+        assert_eq!(parse_trace(legacy)["c"].symbols, ["c__h"]);
+
+        let dir = std::env::temp_dir().join(format!("symdump_bom_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("trace.log");
+        fs::write(&path, format!("\u{feff}{legacy}\n")).unwrap();
+        let map = parse_trace_file(&path).unwrap();
+        assert_eq!(map.keys().collect::<Vec<_>>(), ["c"]);
+        assert_eq!(map["c"].symbols, ["c__h"]);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn copy_to_renames_the_artifact_and_its_sidecar() {
+        let dir = std::env::temp_dir().join(format!("symdump_copy_to_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let nro = dir.join("plugin.nro");
+        fs::write(&nro, b"nro").unwrap();
+        fs::write(dir.join("plugin.nro.exports.txt"), "hdr__init\n").unwrap();
+        let dest = dir.join("sd").join("atmosphere").join("plugins");
+
+        let log = Log::capturing(Verbosity::Normal);
+        let copied = copy_artifacts(
+            std::slice::from_ref(&nro),
+            &dest,
+            Some("libhdr.nro"),
+            &dir,
+            &log,
+        )
+        .unwrap();
+        assert_eq!(copied, [dest.join("libhdr.nro")]);
+        assert_eq!(log.lines().len(), 2, "{:?}", log.lines());
+        assert!(log.lines()[0].starts_with("copied: "));
+        assert_eq!(fs::read(dest.join("libhdr.nro")).unwrap(), b"nro");
+        assert_eq!(
+            fs::read_to_string(dest.join("libhdr.nro.exports.txt")).unwrap(),
+            "hdr__init\n"
+        );
+
+        let two = [nro.clone(), nro.clone()];
+        assert!(copy_artifacts(&two, &dest, Some("x.nro"), &dir, &log)
+            .unwrap_err()
+            .contains("exactly one artifact"));
+        assert!(copy_artifacts(&[nro], &dest, Some("a/x.nro"), &dir, &log)
+            .unwrap_err()
+            .contains("must be a file name"));
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn init_prefix_is_checked_against_sanitize_and_reserved() {
+        assert_eq!(check_init_prefix("hdr", &[]).as_deref(), Ok("hdr"));
+        assert_eq!(check_init_prefix("my-mod!", &[]).as_deref(), Ok("my_mod_"));
+        let reserved = vec!["nn".to_string(), "my-mod!".to_string()];
+        assert!(check_init_prefix("nn", &reserved).is_err());
+        assert!(check_init_prefix("my_mod_", &reserved).is_err());
+    }
+
+    #[test]
+    fn init_prefix_arg_is_sanitized() {
+        let args = |v: &[&str]| v.iter().map(OsString::from).collect::<Vec<_>>();
+        let parsed = parse_init_args(&args(&["--prefix", "my-prefix"])).unwrap();
+        assert_eq!(parsed.prefix.as_deref(), Some("my_prefix"));
+        let parsed = parse_init_args(&args(&["--prefix=hdr"])).unwrap();
+        assert_eq!(parsed.prefix.as_deref(), Some("hdr"));
+    }
+
+    #[test]
+    fn cdylib_artifacts_come_from_compiler_artifact_messages() {
+        let stdout = [
+            r#"{"reason":"compiler-artifact","target":{"crate_types":["lib"]},"filenames":["/t/libdep.rlib"]}"#,
+            r#"{"reason":"compiler-artifact","target":{"crate_types":["cdylib"]},"filenames":["/t/libplugin.so"]}"#,
+            r#"{"reason":"build-finished","success":true}"#,
+            "not json",
+        ]
+        .join("\n");
+        assert_eq!(
+            cdylib_artifacts_from_messages(&stdout),
+            [PathBuf::from("/t/libplugin.so")]
+        );
+    }
+
+    #[test]
+    fn rlibs_come_from_lib_artifact_messages() {
+        let stdout = [
+            r#"{"reason":"compiler-artifact","target":{"name":"dep_lib","crate_types":["lib"]},"filenames":["/t/libdep_lib-1.rlib","/t/libdep_lib-1.rmeta"],"fresh":true}"#,
+            r#"{"reason":"compiler-artifact","target":{"name":"plugin","crate_types":["cdylib"]},"filenames":["/t/libplugin.so"]}"#,
+        ]
+        .join("\n");
+        assert_eq!(
+            rlib_artifacts_from_messages(&stdout),
+            [("dep_lib".to_string(), PathBuf::from("/t/libdep_lib-1.rlib"))]
+        );
+    }
+
+    #[test]
+    fn foreign_exports_skip_traced_and_prefixed_symbols() {
+        let traces = BTreeMap::from([(
+            "a".to_string(),
+            TraceCrate {
+                name: "a".to_string(),
+                resolved_prefix: Some("hdr".to_string()),
+                symbols: vec!["custom_name".to_string()],
+                aliases: vec!["old__tick".to_string()],
+                ..Default::default()
+            },
+        )]);
+        let exports = vec![(
+            PathBuf::from("/w/target/plugin.nro"),
+            [
+                "hdr__init",
+                "hdrx_hook",
+                "custom_name",
+                "old__tick",
+                "raw_hook",
+                "_ZN7dep_lib4hook17h0123456789abcdefE",
+                "unknown",
+            ]
+            .map(str::to_string)
+            .to_vec(),
+        )];
+        let paths = out::PathStyle {
+            root: Some(PathBuf::from("/w")),
+            home: None,
+        };
+        let owner_of = |s: &str| (s == "raw_hook").then(|| "raw_dep".to_string());
+        let foreign = foreign_exports(&exports, &traces, owner_of, &paths);
+        let found: Vec<(&str, Option<&str>)> = foreign
+            .iter()
+            .map(|f| (f.symbol.as_str(), f.likely_crate.as_deref()))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                ("hdrx_hook", None),
+                ("raw_hook", Some("raw_dep")),
+                ("_ZN7dep_lib4hook17h0123456789abcdefE", Some("dep_lib")),
+                ("unknown", None)
+            ]
+        );
+        assert_eq!(foreign[0].artifact, "target/plugin.nro");
+        assert!(check_foreign(&foreign, false).is_ok());
+        assert!(matches!(
+            check_foreign(&foreign, true),
+            Err(Failure::Regression(msg)) if msg.starts_with("4 foreign export(s)")
+        ));
+        assert_eq!(
+            legacy_mangled_crate("__ZN3std2io5stdio17h00E").as_deref(),
+            Some("std")
+        );
+        assert_eq!(legacy_mangled_crate("_ZN99short"), None);
+    }
+
+    #[test]
+    fn unprefixed_crates_are_own_name_fallbacks() {
+        let traced = |name: &str, source: &str| TraceCrate {
+            name: name.to_string(),
+            selected_source: Some(source.to_string()),
+            ..Default::default()
+        };
+        let traces = BTreeMap::from([
+            ("a".to_string(), traced("a", "top_package")),
+            ("b".to_string(), traced("b", "crate")),
+            (
+                "c".to_string(),
+                traced("c", "crate_fallback_after_priority"),
+            ),
+            (
+                "d".to_string(),
+                traced("d", "prefer_package_prefix(crate_fallback)"),
+            ),
+        ]);
+        assert_eq!(
+            unprefixed_crates(&traces),
+            [
+                ("b".to_string(), "crate".to_string()),
+                ("c".to_string(), "crate_fallback_after_priority".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn graph_dot_colors_edges_by_inheritance() {
+        // The workspace_host fixture: host_ws -> ssbusync -> symbaker.
+        let deps = HashMap::from([
+            ("host_ws".to_string(), vec!["ssbusync".to_string()]),
+            ("ssbusync".to_string(), vec!["symbaker".to_string()]),
+            ("symbaker".to_string(), vec!["syn".to_string()]),
+        ]);
+        let traced = |source: &str, prefix: &str| TraceCrate {
+            selected_source: Some(source.to_string()),
+            resolved_prefix: Some(prefix.to_string()),
+            ..Default::default()
+        };
+        let mut traces =
+            BTreeMap::from([("ssbusync".to_string(), traced("top_package", "host_ws"))]);
+        assert_eq!(
+            resolution_graph_dot(&traces, &deps),
+            "digraph symbaker {\n    rankdir=LR;\n    node [shape=box];\n    \
+             \"host_ws\" [style=dashed];\n    \
+             \"ssbusync\" [label=\"ssbusync\\nhost_ws (top_package)\"];\n    \
+             \"host_ws\" -> \"ssbusync\" [color=forestgreen, label=\"top_package\"];\n}\n"
+        );
+
+        traces.insert("ssbusync".to_string(), traced("package", "ssbusync"));
+        assert!(resolution_graph_dot(&traces, &deps)
+            .contains("\"host_ws\" -> \"ssbusync\" [color=red, label=\"package\"];"));
+
+        // Sources as the resolver traces them, details included.
+        let log = [
+            r#"[symbaker] crate="a" selected source=top_package raw="host_ws" sanitized="host_ws""#,
+            r#"[symbaker] crate="b" selected source=workspace raw="ws" sanitized="ws""#,
+            r#"[symbaker] crate="c" selected source=override(crate="c") raw="x" sanitized="x""#,
+            r#"[symbaker] crate="d" selected source=crate raw="d" sanitized="d""#,
+            r#"[symbaker] crate="e" selected source=prefer_package_prefix(package) raw="e" sanitized="e""#,
+            r#"[symbaker] crate="f" selected source=prefer_package_prefix(crate_fallback) raw="f" sanitized="f""#,
+        ]
+        .join("\n");
+        let traces = parse_trace(&log);
+        let source = |name: &str| traces[name].selected_source.as_deref();
+        let from = |name| {
+            inherited_from(
+                source(name),
+                Some("host_ws"),
+                "Cargo.toml",
+                Some("symbaker.toml"),
+            )
+        };
+        assert_eq!(from("a").as_deref(), Some("host_ws"));
+        assert_eq!(from("b").as_deref(), Some("Cargo.toml"));
+        assert_eq!(from("c").as_deref(), Some("symbaker.toml"));
+        assert_eq!(from("d"), None);
+        let local: Vec<_> = ["a", "b", "c", "d", "e", "f"]
+            .into_iter()
+            .filter(|name| is_local_source(source(name).unwrap()))
+            .collect();
+        assert_eq!(local, ["d"]);
+    }
+
+    #[test]
+    fn workspace_root_prefers_workspace_manifest_unless_excluded() {
+        let tests = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests");
+        assert_eq!(
+            workspace_root_from(&tests.join("plugin_workspace").join("plugin_a").join("src")),
+            Ok(tests.join("plugin_workspace"))
+        );
+        // The repo root workspace excludes its fixtures.
+        assert_eq!(
+            workspace_root_from(&tests.join("fixture_app").join("src")),
+            Ok(tests.join("fixture_app"))
+        );
+    }
+
+    #[test]
+    fn build_summary_counts_sources_symbols_and_duplicates() {
+        let traced = |source: &str, prefix: &str, symbols: &[&str]| TraceCrate {
+            selected_source: Some(source.to_string()),
+            resolved_prefix: Some(prefix.to_string()),
+            symbols: symbols.iter().map(|s| s.to_string()).collect(),
+            ..Default::default()
+        };
+        let traces = BTreeMap::from([
+            ("app".to_string(), traced("top_package", "app", &["app__a"])),
+            (
+                "dep".to_string(),
+                traced("top_package", "app", &["app__b", "app__c"]),
+            ),
+            ("solo".to_string(), traced("crate", "solo", &[])),
+        ]);
+        let exports = [
+            (
+                PathBuf::from("1.nro"),
+                vec!["app__a".to_string(), "app__b".to_string()],
+            ),
+            (PathBuf::from("2.nro"), vec!["app__b".to_string()]),
+        ];
+
+        let summary = build_summary(&traces, &exports, Some("app".to_string()));
+        assert_eq!(summary.top_prefix.as_deref(), Some("app"));
+        assert_eq!(summary.total_symbols, 3);
+        assert_eq!(summary.duplicate_symbols, 1);
+        assert_eq!(
+            summary.sources,
+            BTreeMap::from([("crate".to_string(), 1), ("top_package".to_string(), 2)])
+        );
+        assert_eq!(summary.crate_symbols["dep"], 2);
+        assert_eq!(build_summary(&traces, &exports, None).top_prefix, None);
+    }
+
+    #[test]
+    fn gitignore_addition_skips_listed_entries() {
+        assert_eq!(
+            gitignore_addition("target/\n.symbaker/*\n!.symbaker/.gitkeep\n").as_deref(),
+            Some("\n# symbaker generated files\ntarget/**/*.exports.txt\n")
+        );
+        assert_eq!(
+            gitignore_addition("target/").as_deref(),
+            Some("\n\n# symbaker generated files\n/.symbaker/*\n!/.symbaker/.gitkeep\ntarget/**/*.exports.txt\n")
+        );
+        assert_eq!(
+            gitignore_addition("").as_deref(),
+            Some("# symbaker generated files\n/.symbaker/*\n!/.symbaker/.gitkeep\ntarget/**/*.exports.txt\n")
+        );
+        let full = GITIGNORE_ENTRIES.join("\n");
+        assert_eq!(gitignore_addition(&full), None);
+    }
+
+    #[test]
+    fn symbaker_build_dependency_follows_symbaker_source() {
+        let derive = |deps: &str| {
+            add_symbaker_build_dependency(&format!(
+                "[package]\nname = \"m\"\n\n[dependencies]\n{deps}"
+            ))
+            .map(|m| m.and_then(|m| m.split("[build-dependencies]").nth(1).map(str::to_string)))
+        };
+        assert_eq!(
+            derive("symbaker = \"0.1\"\n").unwrap().as_deref(),
+            Some("\nsymbaker-build = \"0.1\"\n")
+        );
+        assert_eq!(
+            derive("symbaker = { path = \"../symbaker/\" }\n")
+                .unwrap()
+                .as_deref(),
+            Some("\nsymbaker-build = { path = \"../symbaker/symbaker-build\" }\n")
+        );
+        assert_eq!(
+            derive("symbaker = { git = \"https://example.com/s\", rev = \"abc\" }\n")
+                .unwrap()
+                .as_deref(),
+            Some("\nsymbaker-build = { git = \"https://example.com/s\", rev = \"abc\" }\n")
+        );
+        assert!(derive("symbaker = { workspace = true }\n").is_err());
+        assert_eq!(
+            add_symbaker_build_dependency(
+                "[dependencies]\nsymbaker = \"0.1\"\n[build-dependencies]\nsymbaker-build = \"0.1\"\n"
+            ),
+            Ok(None)
+        );
+    }
+
+    #[test]
+    fn target_triple_narrows_the_nro_search_dir() {
+        let args = |v: &[&str]| v.iter().map(OsString::from).collect::<Vec<_>>();
+        let out = Path::new("out");
+        assert_eq!(
+            nro_search(out, &args(&["build"]), Some("release")),
+            (out.to_path_buf(), Some("release".to_string()))
+        );
+        assert_eq!(
+            nro_search(
+                out,
+                &args(&["build", "--target=aarch64-unknown-none"]),
+                Some("release")
+            ),
+            (
+                out.join("aarch64-unknown-none").join("release"),
+                Some("release".to_string())
+            )
+        );
+    }
+
+    #[test]
+    fn dev_profile_with_a_target_finds_the_nro_under_debug() {
+        let tmp = std::env::temp_dir().join(format!("symdump_nro_dev_{}", std::process::id()));
+        let debug = tmp.join("aarch64-unknown-none").join("debug");
+        fs::create_dir_all(&debug).unwrap();
+        fs::write(debug.join("plugin.nro"), b"nro").unwrap();
+        let args: Vec<OsString> = ["build", "--target", "aarch64-unknown-none"]
+            .iter()
+            .map(OsString::from)
+            .collect();
+
+        for profile in [Some("dev"), Some("test"), None] {
+            let (dir, profile_dir) = nro_search(&tmp, &args, profile);
+            let found = out::newest_nro(&dir, profile_dir.as_deref());
+            assert_eq!(found, Ok(debug.join("plugin.nro")), "{profile:?}");
+        }
+        let _ = fs::remove_dir_all(&tmp);
+    }
+
+    #[test]
+    fn target_arch_follows_cargo_cfg_semantics() {
+        for (triple, arch) in [
+            ("thumbv7em-none-eabihf", "arm"),
+            ("armv7-unknown-linux-gnueabihf", "arm"),
+            ("i686-pc-windows-msvc", "x86"),
+            ("x86_64-unknown-linux-gnu", "x86_64"),
+            ("aarch64-nintendo-switch-freestanding", "aarch64"),
+            ("arm64e-apple-darwin", "aarch64"),
+            ("riscv32imac-unknown-none-elf", "riscv32"),
+            ("mipsel-unknown-linux-gnu", "mips"),
+            ("powerpc64le-unknown-linux-gnu", "powerpc64"),
+            ("wasm32-unknown-unknown", "wasm32"),
+            ("specs/aarch64-skyline-switch.json", "aarch64"),
+        ] {
+            assert_eq!(target_arch_of(triple), arch, "{triple}");
+        }
+    }
+
+    #[test]
+    fn target_dir_follows_cargo_precedence_and_relative_bases() {
+        let tmp = std::env::temp_dir().join(format!("symdump_target_dir_{}", std::process::id()));
+        let ws = tmp.join("ws");
+        let sub = ws.join("crates").join("plugin");
+        fs::create_dir_all(&sub).unwrap();
+        fs::create_dir_all(tmp.join("shared-target")).unwrap();
+        let canon = |p: PathBuf| fs::canonicalize(&p).unwrap_or(p);
+        let none: &[OsString] = &[];
+
+        assert_eq!(target_dir_for(none, &sub, &ws, None), ws.join("target"));
+
+        // Relative CARGO_TARGET_DIR / --target-dir are taken from the cwd.
+        fs::create_dir_all(sub.join("rel-target")).unwrap();
+        assert_eq!(
+            target_dir_for(none, &sub, &ws, Some("rel-target".to_string())),
+            canon(sub.join("rel-target"))
+        );
+        let flag = [OsString::from("--target-dir=flag-target")];
+        assert_eq!(
+            target_dir_for(&flag, &sub, &ws, Some("rel-target".to_string())),
+            sub.join("flag-target")
+        );
+
+        // build.target-dir is relative to the directory holding `.cargo`.
+        fs::create_dir_all(ws.join(".cargo")).unwrap();
+        fs::write(
+            ws.join(".cargo").join("config.toml"),
+            "[build]\ntarget-dir = \"../shared-target\"\n",
+        )
+        .unwrap();
+        assert_eq!(
+            target_dir_for(none, &sub, &ws, None),
+            canon(tmp.join("shared-target"))
+        );
+        assert_eq!(
+            target_dir_for(none, &sub, &ws, Some(String::new())),
+            canon(tmp.join("shared-target"))
+        );
+        let _ = fs::remove_dir_all(&tmp);
+    }
+
+    #[test]
+    fn verify_extras_are_unrecorded_exports_with_a_known_prefix() {
+        let crates: Vec<toml::Value> = toml::from_str::<toml::Value>(
+            r#"
+            [[crates]]
+            name = "hdr"
+            resolved_prefix = "hdr"
+            symbols = ["hdr__init", "custom__named"]
+
+            [[crates]]
+            name = "dotted"
+            resolved_prefix = "dot"
+            sep = "."
+            symbols = ["dot.init"]
+            "#,
+        )
+        .unwrap()["crates"]
+            .as_array()
+            .unwrap()
+            .clone();
+        let recorded = resolution_exports(&crates);
+        let exported: HashSet<String> = [
+            "hdr__init",
+            "hdr__new_hook",
+            "custom__added",
+            "hdrx__other",
+            "hdr_foo",
+            "dot.init",
+            "dot.added",
+            "dot_other",
+            "memcpy",
+        ]
+        .into_iter()
+        .map(str::to_string)
+        .collect();
+        assert_eq!(
+            unexpected_exports(&crates, &recorded, &exported),
+            BTreeSet::from([
+                "custom__added".to_string(),
+                "dot.added".to_string(),
+                "hdr__new_hook".to_string()
+            ])
+        );
+    }
+
+    #[test]
+    fn version_script_lists_exports_as_global_and_hides_the_rest() {
+        let crates: Vec<toml::Value> = toml::from_str::<toml::Value>(
+            r#"
+            [[crates]]
+            name = "hdr"
+            symbols = ["hdr__b", "hdr__a"]
+            alias_symbols = ["old__a"]
+            "#,
+        )
+        .unwrap()["crates"]
+            .as_array()
+            .unwrap()
+            .clone();
+        let exports = resolution_exports(&crates);
+        assert_eq!(
+            version_script(None, &exports),
+            "/* symbaker symbols.map: generated from .symbaker/resolution.toml */\n\
+             {\n  global:\n    hdr__a;\n    hdr__b;\n    old__a;\n  local:\n    *;\n};\n"
+        );
+        let node = version_node_from_soname("libhdr.so.1");
+        assert_eq!(node, "LIBHDR_SO_1");
+        assert!(version_script(Some(&node), &exports).contains("\nLIBHDR_SO_1 {\n"));
+    }
+
+    #[test]
+    fn metadata_failures_fail_the_report_unless_ignored() {
+        let args = [
+            OsString::from("--manifest-path"),
+            OsString::from("/nonexistent/symbaker/Cargo.toml"),
+        ];
+        let Err(err) = resolution_metadata(&args, false) else {
+            panic!("a missing manifest must fail the report");
+        };
+        assert!(err.starts_with("cargo metadata failed: "), "{err}");
+        assert!(err.contains("SYMBAKER_IGNORE_METADATA_ERRORS=1"), "{err}");
+        let tree = resolution_metadata(&args, true).unwrap();
+        assert!(tree.deps.is_empty() && tree.features.is_empty());
+    }
+
+    #[test]
+    fn sizes_parse_as_decimal_or_hex() {
+        assert_eq!(parse_size("4096"), Ok(4096));
+        assert_eq!(parse_size("0x1000"), Ok(4096));
+        assert_eq!(parse_size("0X1f"), Ok(31));
+        assert!(parse_size("0x").is_err());
+        assert!(parse_size("4k").is_err());
+    }
+
+    #[test]
+    fn priority_flag_accepts_only_known_keys() {
+        assert_eq!(
+            parse_priority("config, attr").unwrap(),
+            ["config".to_string(), "attr".to_string()]
+        );
+        assert!(parse_priority("config,cfg").is_err());
+        assert!(parse_priority("attr,attr").is_err());
+    }
+
+    #[test]
+    fn workspace_metadata_table_respects_force() {
+        let body = init_config_body(Some("hdr"), "_", &["workspace".to_string()], &[]);
+        let clean = "# root\n[workspace]\nmembers = [\"a\"]\n";
+        let written = write_workspace_metadata(clean, &body, false)
+            .unwrap()
+            .unwrap();
+        assert!(written.starts_with(clean), "{written}");
+        let doc = toml::from_str::<toml::Value>(&written).unwrap();
+        let cfg = workspace_metadata_config(&doc).unwrap();
+        assert_eq!(cfg["prefix"].as_str(), Some("hdr"));
+        assert_eq!(cfg["sep"].as_str(), Some("_"));
+
+        let populated = "[workspace]\n\n[workspace.metadata.symbaker]\nprefix = \"old\"\n";
+        assert_eq!(
+            write_workspace_metadata(populated, &body, false).unwrap(),
+            None
+        );
+        let forced = write_workspace_metadata(populated, &body, true)
+            .unwrap()
+            .unwrap();
+        let doc = toml::from_str::<toml::Value>(&forced).unwrap();
+        assert_eq!(
+            workspace_metadata_config(&doc).unwrap()["prefix"].as_str(),
+            Some("hdr")
+        );
+
+        assert!(write_workspace_metadata("[package]\nname = \"a\"\n", &body, false).is_err());
+    }
+
+    #[test]
+    fn annotations_name_crate_and_function() {
+        let traces = parse_trace(concat!(
+            "[symbaker] crate=\"a\" macro=symbaker function=\"init\" resolved_prefix=\"hdr\" export_name=\"hdr__init\"\n",
+            "[symbaker] crate=\"a\" macro=symbaker function=\"init\" compat_alias=\"init\"\n",
+        ));
+        assert_eq!(
+            traces["a"].functions,
+            BTreeMap::from([
+                ("hdr__init".to_string(), "init".to_string()),
+                ("init".to_string(), "init".to_string()),
+            ])
+        );
+
+        let crates: Vec<toml::Value> = toml::from_str::<toml::Value>(
+            r#"
+            [[crates]]
+            name = "a"
+            symbols = ["hdr__init"]
+            functions = { hdr__init = "init" }
+            [[crates]]
+            name = "old"
+            symbols = ["old__f"]
+            "#,
+        )
+        .unwrap()["crates"]
+            .as_array()
+            .unwrap()
+            .clone();
+        let notes = symbol_annotations(&crates);
+        assert_eq!(notes["hdr__init"], "crate=a fn=init");
+        assert_eq!(notes["old__f"], "crate=old");
+        assert!(!notes.contains_key("memcpy"));
+    }
+
+    #[test]
+    fn report_pairs_exports_with_rust_names_and_sep() {
+        let traces = parse_trace(concat!(
+            "[symbaker] crate=\"a\" config field=sep source=Some(File) value=\"_\"\n",
+            "[symbaker] crate=\"a\" macro=symbaker function=\"init\" resolved_prefix=\"hdr\" export_name=\"hdr_init\"\n",
+            "[symbaker] crate=\"a\" macro=symbaker function=\"init\" compat_alias=\"init\"\n",
+        ));
+        let a = &traces["a"];
+        assert_eq!(a.sep.as_deref(), Some("_"));
+
+        #[derive(Serialize)]
+        struct Crate {
+            name: &'static str,
+            symbols: Vec<ReportSymbol>,
+            alias_symbols: Vec<ReportSymbol>,
+        }
+        let report = toml::to_string(&BTreeMap::from([(
+            "crates",
+            [Crate {
+                name: "a",
+                symbols: report_symbols(a.symbols.clone(), &a.functions),
+                alias_symbols: report_symbols(vec!["unknown".to_string()], &a.functions),
+            }],
+        )]))
+        .unwrap();
+        let crates = toml::from_str::<toml::Value>(&report).unwrap()["crates"]
+            .as_array()
+            .unwrap()
+            .clone();
+        assert_eq!(
+            crates[0]["symbols"][0],
+            toml::from_str::<toml::Value>("rust_name = \"init\"\nexport = \"hdr_init\"").unwrap()
+        );
+        assert!(crates[0]["alias_symbols"][0].get("rust_name").is_none());
+        assert_eq!(symbol_annotations(&crates)["hdr_init"], "crate=a fn=init");
+        assert_eq!(
+            resolution_exports(&crates),
+            BTreeSet::from(["hdr_init".to_string(), "unknown".to_string()])
+        );
+    }
+
+    #[test]
+    fn dump_inputs_expand_globs_across_nested_dirs() {
+        let dir = std::env::temp_dir().join(format!("symdump_glob_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        for rel in [
+            "mods/a/plugin_one.nro",
+            "mods/a/deep/plugin_two.nro",
+            "mods/b/other.nro",
+            "plugin_top.nro",
+        ] {
+            let path = dir.join(rel);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(&path, b"").unwrap();
+        }
+
+        let log = Log::capturing(Verbosity::Debug);
+        let found =
+            resolve_dump_inputs(vec![dir.join("mods/**/plugin_*.nro")], true, &log).unwrap();
+        let names: Vec<_> = found
+            .iter()
+            .map(|p| p.file_name().unwrap().to_string_lossy().to_string())
+            .collect();
+        assert_eq!(names, ["plugin_two.nro", "plugin_one.nro"]);
+        assert!(
+            log.lines()[0].ends_with("matched 2 path(s)"),
+            "{:?}",
+            log.lines()
+        );
+
+        let overlapping = vec![dir.join("mods/?/*.nro"), dir.join("mods/b/other.nro")];
+        assert_eq!(
+            resolve_dump_inputs(overlapping, true, &log).unwrap().len(),
+            2
+        );
+
+        let err = resolve_dump_inputs(vec![dir.join("mods/**/*.elf")], true, &log).unwrap_err();
+        assert!(
+            matches!(&err, Failure::NoArtifact(msg) if msg.contains("glob") && msg.contains("matched no files")),
+            "{err}"
+        );
+        assert!(matches!(
+            resolve_dump_inputs(vec![dir.join("mods/missing.nro")], true, &log),
+            Err(Failure::NoArtifact(_))
+        ));
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn glob_detection_ignores_the_verbatim_prefix() {
+        assert!(!is_glob_pattern(Path::new(r"\\?\C:\mods\plugin.nro")));
+        assert!(is_glob_pattern(Path::new(r"\\?\C:\mods\*.nro")));
+        assert!(is_glob_pattern(Path::new("mods/?/plugin.nro")));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn recursive_globs_do_not_follow_symlink_cycles() {
+        let dir = std::env::temp_dir().join(format!("symdump_glob_loop_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("mods/a")).unwrap();
+        fs::write(dir.join("mods/a/plugin.nro"), b"").unwrap();
+        std::os::unix::fs::symlink(dir.join("mods"), dir.join("mods/a/loop")).unwrap();
+
+        let found = expand_glob(&dir.join("mods/**/*.nro")).unwrap();
+        assert_eq!(found, [dir.join("mods/a/plugin.nro")]);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn verbosity_flags_filter_log_lines() {
+        let parse = |flags: &[&str]| {
+            let mut args: Vec<OsString> = flags.iter().map(OsString::from).collect();
+            args.extend(["dump", "-v", "x.nro"].map(OsString::from));
+            let level = take_verbosity(&mut args);
+            assert_eq!(args[args.len() - 3..], ["dump", "-v", "x.nro"]);
+            level
+        };
+        assert_eq!(parse(&[]), Ok(Verbosity::Normal));
+        assert_eq!(parse(&["-q"]), Ok(Verbosity::Quiet));
+        assert_eq!(parse(&["--verbose"]), Ok(Verbosity::Verbose));
+        assert_eq!(parse(&["-v", "-v"]), Ok(Verbosity::Debug));
+        assert_eq!(parse(&["-vv"]), Ok(Verbosity::Debug));
+        assert!(parse(&["-q", "-v"]).is_err());
+
+        for (level, expected) in [
+            (Verbosity::Quiet, 0),
+            (Verbosity::Normal, 1),
+            (Verbosity::Verbose, 2),
+            (Verbosity::Debug, 3),
+        ] {
+            let log = Log::capturing(level);
+            log.info("info");
+            log.verbose("verbose");
+            log.debug("debug");
+            assert_eq!(log.lines().len(), expected, "{level:?}");
         }
     }
+
+    #[test]
+    fn quiet_init_dry_run_prints_nothing() {
+        let edits = [InitEdit {
+            path: PathBuf::from("symbaker.toml"),
+            contents: "prefix = \"hdr\"\n".to_string(),
+            append: false,
+            notes: vec!["set prefix".to_string()],
+        }];
+        let log = Log::capturing(Verbosity::Normal);
+        apply_init_edits(&edits, true, &log).unwrap();
+        assert_eq!(
+            log.lines(),
+            [
+                "would set prefix",
+                "would write symbaker.toml:\nprefix = \"hdr\"\n"
+            ]
+        );
+        let quiet = Log::capturing(Verbosity::Quiet);
+        apply_init_edits(&edits, true, &quiet).unwrap();
+        assert!(quiet.lines().is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn run_child_relays_sigterm_to_the_child() {
+        // The child signals this process once the relay is set up; the
+        // relayed SIGTERM hits its trap.
+        let status = run_child(Command::new("sh").args([
+            "-c",
+            "trap 'exit 7' TERM; sleep 1; kill -TERM $PPID; sleep 5 & wait",
+        ]))
+        .unwrap();
+        assert_eq!(status.code(), Some(7));
+    }
+
+    #[test]
+    fn bench_report_sorts_durations() {
+        let ms = Duration::from_millis;
+        let report = BenchReport::new("a.nro".to_string(), vec![ms(9), ms(2), ms(5), ms(4)], 312);
+        assert_eq!(
+            report,
+            BenchReport {
+                artifact: "a.nro".to_string(),
+                iters: 4,
+                min_us: 2000,
+                median_us: 5000,
+                max_us: 9000,
+                peak_symbols: 312,
+            }
+        );
+
+        let log = Log::capturing(Verbosity::Normal);
+        assert!(matches!(
+            run_bench(vec!["a.nro".into(), "--iters".into(), "0".into()], &log),
+            Err(Failure::Usage(msg)) if msg.contains("--iters must be a positive integer")
+        ));
+        assert!(run_bench(vec!["a.elf".into(), "--iters=1".into()], &log)
+            .unwrap_err()
+            .to_string()
+            .contains("is not an .nro"));
+    }
+
+    #[test]
+    fn duplicates_split_into_conflicting_and_identical() {
+        let fp = |hash: &str| out::SymbolFingerprint {
+            size: 16,
+            hash: hash.to_string(),
+        };
+        let (a, also_a, b) = (fp("aa"), fp("aa"), fp("bb"));
+        assert!(same_fingerprint([Some(&a), Some(&also_a)].into_iter()));
+        assert!(!same_fingerprint([Some(&a), Some(&b)].into_iter()));
+        assert!(!same_fingerprint([Some(&a), None].into_iter()));
+        assert!(!same_fingerprint([None, None].into_iter()));
+
+        // Without .nro symbol data nothing can be shown identical.
+        let dup = (
+            "hdr__init".to_string(),
+            vec![PathBuf::from("a.so"), PathBuf::from("b.so")],
+        );
+        let (conflicting, identical) = classify_duplicates(vec![dup.clone()]);
+        assert_eq!(conflicting, [dup]);
+        assert!(identical.is_empty());
+    }
+
+    #[test]
+    fn duplicates_group_by_owning_crates() {
+        let crates: Vec<toml::Value> = toml::from_str::<toml::Value>(
+            r#"
+            [[crates]]
+            name = "a"
+            symbols = ["hdr__x", "hdr__y"]
+            [[crates]]
+            name = "b"
+            symbols = ["hdr__x", "hdr__y"]
+            alias_symbols = ["old__z"]
+            "#,
+        )
+        .unwrap()["crates"]
+            .as_array()
+            .unwrap()
+            .clone();
+        let owners = symbol_owners(&crates);
+        let dup = |s: &str| {
+            (
+                s.to_string(),
+                vec![PathBuf::from("1.nro"), PathBuf::from("2.nro")],
+            )
+        };
+        let groups = group_duplicates_by_crate(
+            &[dup("hdr__x"), dup("hdr__y"), dup("old__z"), dup("mystery")],
+            &owners,
+        );
+        let flat: Vec<(String, usize)> = groups
+            .iter()
+            .map(|(k, v)| (k.join(" + "), v.len()))
+            .collect();
+        assert_eq!(
+            flat,
+            [
+                ("(unknown)".to_string(), 1),
+                ("a + b".to_string(), 2),
+                ("b".to_string(), 1)
+            ]
+        );
+    }
+
+    #[test]
+    fn top_package_flag_must_name_a_workspace_member() {
+        let manifest = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests")
+            .join("plugin_workspace")
+            .join("Cargo.toml");
+        let args = |top: &str| {
+            vec![
+                OsString::from("build"),
+                OsString::from("--top-package"),
+                OsString::from(top),
+                OsString::from("--manifest-path"),
+                manifest.clone().into_os_string(),
+            ]
+        };
+
+        let mut ok = args("plugin_a");
+        assert_eq!(
+            take_top_package(&mut ok).unwrap().as_deref(),
+            Some("plugin_a")
+        );
+        assert!(!ok.iter().any(|a| a == "--top-package" || a == "plugin_a"));
+
+        let err = take_top_package(&mut args("nope")).unwrap_err();
+        assert!(err.contains("plugin_a, plugin_b"), "{err}");
+    }
+
+    #[test]
+    fn override_flags_accumulate() {
+        let mut args: Vec<OsString> = [
+            "build",
+            "--override",
+            "ssbusync=hdr",
+            "--release",
+            "--override=smashline=hdr_sl",
+        ]
+        .map(OsString::from)
+        .to_vec();
+        assert_eq!(
+            take_overrides(&mut args).unwrap(),
+            [
+                ("ssbusync".to_string(), "hdr".to_string()),
+                ("smashline".to_string(), "hdr_sl".to_string())
+            ]
+        );
+        assert_eq!(args, ["build", "--release"]);
+
+        let mut bad = vec![OsString::from("--override=ssbusync")];
+        assert!(take_overrides(&mut bad)
+            .unwrap_err()
+            .contains("<crate>=<prefix>"));
+        let mut bad = vec![OsString::from("--override=ssbusync=")];
+        assert!(take_overrides(&mut bad).is_err());
+    }
+
+    #[test]
+    fn symbaker_env_keys_are_found_per_line() {
+        let text = "[env]\nSYMBAKER_CONFIG = { value = \"symbaker.toml\", relative = true }\nSYMBAKER_INITIALIZED=\"1\"\nOTHER = \"SYMBAKER_X\"\n";
+        assert_eq!(
+            symbaker_env_keys(text),
+            ["SYMBAKER_CONFIG", "SYMBAKER_INITIALIZED"]
+        );
+        assert!(symbaker_env_keys("[build]\njobs = 4\n").is_empty());
+    }
 }
@@ -1,14 +1,117 @@
 use regex::Regex;
-use syn::{punctuated::Punctuated, Expr, ExprLit, Lit, Meta, Token};
+use symbaker_build::resolve::{env_var, sanitize};
+use syn::{punctuated::Punctuated, spanned::Spanned, Expr, ExprLit, Lit, Meta, Token};
+
+mod names;
+
+pub use names::{wildcard_match, NameMatcher};
 
 #[derive(Debug, Default)]
 pub struct ModuleRules {
-    pub include_regex: Vec<Regex>,
-    pub exclude_regex: Vec<Regex>,
-    pub include_glob: Vec<String>,
-    pub exclude_glob: Vec<String>,
+    pub names: NameMatcher,
+    pub include_attr: Vec<String>,
+    pub requires_attr: Vec<String>,
+    pub abi: Vec<String>,
+    pub exclude_async: bool,
+    pub only: Vec<String>,
+    only_span: Option<proc_macro2::Span>,
     pub template: Option<String>,
     pub suffix: Option<String>,
+    pub vars: TemplateVars,
+}
+
+/// Placeholders a `template` may use; `{module}` only inside `symbaker_module`.
+pub const TEMPLATE_PLACEHOLDERS: [&str; 8] = [
+    "prefix",
+    "sep",
+    "module",
+    "name",
+    "suffix",
+    "crate",
+    "target_arch",
+    "profile",
+];
+
+/// Environment-derived template components, each sanitized and resolved
+/// once per expansion. Only the ones the template uses are looked up.
+#[derive(Debug, Default)]
+pub struct TemplateVars {
+    pub crate_name: String,
+    pub target_arch: String,
+    pub profile: String,
+}
+
+/// Known placeholders that appear in `template`, in [`TEMPLATE_PLACEHOLDERS`] order.
+pub fn used_placeholders(template: &str) -> Vec<&'static str> {
+    TEMPLATE_PLACEHOLDERS
+        .into_iter()
+        .filter(|p| template.contains(&format!("{{{p}}}")))
+        .collect()
+}
+
+/// First non-empty value among `keys`. Proc macros do not get the
+/// build-script `CARGO_CFG_*`/`PROFILE` vars from cargo, so the `SYMBAKER_*`
+/// fallbacks are what normally applies: `symbaker_build::emit_rerun_directives()`
+/// forwards them from the crate's build script, and `cargo symdump` sets them.
+fn first_env(keys: &[&str]) -> Option<String> {
+    keys.iter()
+        .filter_map(|k| env_var(k).ok())
+        .find(|v| !v.trim().is_empty())
+}
+
+/// Resolves the environment-derived placeholders `template` uses. `what` names
+/// the attribute in errors (`symbaker_module` or `symbaker`).
+pub fn resolve_template_vars(
+    template: &str,
+    value_span: &Expr,
+    what: &str,
+) -> Result<TemplateVars, syn::Error> {
+    let used = used_placeholders(template);
+    let lookup = |name: &str, keys: &[&str]| -> Result<String, syn::Error> {
+        if !used.contains(&name) {
+            return Ok(String::new());
+        }
+        first_env(keys).map(|v| sanitize(v.trim())).ok_or_else(|| {
+            syn::Error::new_spanned(
+                value_span,
+                format!(
+                    "{what}: template uses {{{name}}} but none of {} is set (a build script calling `symbaker_build::emit_rerun_directives()` or `cargo symdump` sets the last one)",
+                    keys.join(", ")
+                ),
+            )
+        })
+    };
+    Ok(TemplateVars {
+        crate_name: lookup("crate", &["CARGO_PKG_NAME"])?,
+        target_arch: lookup(
+            "target_arch",
+            &["CARGO_CFG_TARGET_ARCH", "SYMBAKER_TARGET_ARCH"],
+        )?,
+        profile: lookup("profile", &["PROFILE", "SYMBAKER_PROFILE"])?,
+    })
+}
+
+impl TemplateVars {
+    /// Fills every placeholder of `template`.
+    pub fn render(
+        &self,
+        template: &str,
+        prefix: &str,
+        sep: &str,
+        module: &str,
+        name: &str,
+        suffix: &str,
+    ) -> String {
+        template
+            .replace("{prefix}", prefix)
+            .replace("{sep}", sep)
+            .replace("{module}", module)
+            .replace("{name}", name)
+            .replace("{suffix}", suffix)
+            .replace("{crate}", &self.crate_name)
+            .replace("{target_arch}", &self.target_arch)
+            .replace("{profile}", &self.profile)
+    }
 }
 
 fn parse_csv(value: &str) -> Vec<String> {
@@ -26,12 +129,8 @@ fn validate_globs(
     kind: &str,
 ) -> Result<Vec<String>, syn::Error> {
     for g in specs {
-        if g.contains('[') || g.contains(']') || g.contains('{') || g.contains('}') {
-            return Err(syn::Error::new_spanned(
-                value_span,
-                format!("symbaker_module: unsupported {kind} glob '{g}' (use only '*' and '?')"),
-            ));
-        }
+        names::check_glob(g, kind)
+            .map_err(|e| syn::Error::new_spanned(value_span, format!("symbaker_module: {e}")))?;
     }
     Ok(specs.to_vec())
 }
@@ -41,16 +140,13 @@ fn compile_regexes(
     value_span: &Expr,
     kind: &str,
 ) -> Result<Vec<Regex>, syn::Error> {
-    let mut out = Vec::new();
-    for r in specs {
-        out.push(Regex::new(r).map_err(|e| {
-            syn::Error::new_spanned(
-                value_span,
-                format!("symbaker_module: invalid {kind} regex '{r}': {e}"),
-            )
-        })?);
-    }
-    Ok(out)
+    specs
+        .iter()
+        .map(|r| {
+            names::compile_regex(r, kind)
+                .map_err(|e| syn::Error::new_spanned(value_span, format!("symbaker_module: {e}")))
+        })
+        .collect()
 }
 
 pub fn parse_module_rules(args: &Punctuated<Meta, Token![,]>) -> Result<ModuleRules, syn::Error> {
@@ -61,6 +157,9 @@ pub fn parse_module_rules(args: &Punctuated<Meta, Token![,]>) -> Result<ModuleRu
     let mut exclude_glob_src: Vec<String> = Vec::new();
 
     for a in args {
+        if a.path().is_ident("exclude_async") && matches!(a, Meta::Path(_)) {
+            out.exclude_async = true;
+        }
         if let Meta::NameValue(nv) = a {
             let Some(key) = nv.path.get_ident().map(|i| i.to_string()) else {
                 continue;
@@ -75,11 +174,30 @@ pub fn parse_module_rules(args: &Punctuated<Meta, Token![,]>) -> Result<ModuleRu
                     "exclude_regex" => exclude_regex_src.extend(parse_csv(&v)),
                     "include_glob" => include_glob_src.extend(parse_csv(&v)),
                     "exclude_glob" => exclude_glob_src.extend(parse_csv(&v)),
+                    "include_attr" => out.include_attr.extend(parse_csv(&v)),
+                    "requires_attr" => out.requires_attr.extend(parse_csv(&v)),
+                    "abi" => out.abi.extend(parse_csv(&v)),
+                    "only" => {
+                        out.only.extend(parse_csv(&v));
+                        out.only_span = Some(nv.value.span());
+                    }
                     "template" => out.template = Some(v),
                     "suffix" => out.suffix = Some(v),
                     _ => {}
                 }
             }
+            if key == "exclude_async" {
+                let Expr::Lit(ExprLit {
+                    lit: Lit::Bool(b), ..
+                }) = &nv.value
+                else {
+                    return Err(syn::Error::new_spanned(
+                        &nv.value,
+                        "symbaker_module: `exclude_async` takes `true` or `false`",
+                    ));
+                };
+                out.exclude_async = b.value;
+            }
         }
     }
 
@@ -92,16 +210,31 @@ pub fn parse_module_rules(args: &Punctuated<Meta, Token![,]>) -> Result<ModuleRu
                 .unwrap_or_default();
             match key.as_str() {
                 "include_regex" => {
-                    out.include_regex = compile_regexes(&include_regex_src, &nv.value, "include")?
+                    out.names.include_regex =
+                        compile_regexes(&include_regex_src, &nv.value, "include")?
                 }
                 "exclude_regex" => {
-                    out.exclude_regex = compile_regexes(&exclude_regex_src, &nv.value, "exclude")?
+                    out.names.exclude_regex =
+                        compile_regexes(&exclude_regex_src, &nv.value, "exclude")?
                 }
                 "include_glob" => {
-                    out.include_glob = validate_globs(&include_glob_src, &nv.value, "include")?
+                    out.names.include_glob =
+                        validate_globs(&include_glob_src, &nv.value, "include")?
                 }
                 "exclude_glob" => {
-                    out.exclude_glob = validate_globs(&exclude_glob_src, &nv.value, "exclude")?
+                    out.names.exclude_glob =
+                        validate_globs(&exclude_glob_src, &nv.value, "exclude")?
+                }
+                "only" if !include_regex_src.is_empty() || !include_glob_src.is_empty() => {
+                    return Err(syn::Error::new_spanned(
+                        &nv.value,
+                        "symbaker_module: `only` cannot be combined with `include_regex`/`include_glob`",
+                    ));
+                }
+                "template" => {
+                    if let Some(tpl) = &out.template {
+                        out.vars = resolve_template_vars(tpl, &nv.value, "symbaker_module")?;
+                    }
                 }
                 _ => {}
             }
@@ -111,76 +244,273 @@ pub fn parse_module_rules(args: &Punctuated<Meta, Token![,]>) -> Result<ModuleRu
     Ok(out)
 }
 
-fn wildcard_match(pattern: &str, text: &str) -> bool {
-    let p = pattern.as_bytes();
-    let t = text.as_bytes();
-    let (mut pi, mut ti) = (0usize, 0usize);
-    let (mut star, mut match_i) = (None::<usize>, 0usize);
-
-    while ti < t.len() {
-        if pi < p.len() && (p[pi] == b'?' || p[pi] == t[ti]) {
-            pi += 1;
-            ti += 1;
-        } else if pi < p.len() && p[pi] == b'*' {
-            star = Some(pi);
-            pi += 1;
-            match_i = ti;
-        } else if let Some(star_pos) = star {
-            pi = star_pos + 1;
-            match_i += 1;
-            ti = match_i;
-        } else {
-            return false;
+/// The ABI string of `sig`; a bare `extern fn` is `"C"`.
+fn fn_abi(sig: &syn::Signature) -> Option<String> {
+    let abi = sig.abi.as_ref()?;
+    Some(
+        abi.name
+            .as_ref()
+            .map_or_else(|| "C".to_string(), |n| n.value()),
+    )
+}
+
+impl ModuleRules {
+    fn included(&self, name: &str) -> bool {
+        if !self.only.is_empty() {
+            return self.only.iter().any(|n| n == name);
         }
+        self.names.included(name)
     }
 
-    while pi < p.len() && p[pi] == b'*' {
-        pi += 1;
+    fn excluded(&self, name: &str) -> bool {
+        self.names.excluded(name)
     }
-    pi == p.len()
-}
 
-impl ModuleRules {
-    fn included(&self, name: &str) -> bool {
-        let regex_ok = if self.include_regex.is_empty() {
-            true
-        } else {
-            self.include_regex.iter().any(|r| r.is_match(name))
+    /// Strips the `include_attr`/`requires_attr` marker attributes, which
+    /// are not real attributes. Call after [`Self::skip_reason`].
+    pub fn strip_markers(&self, item: &mut syn::Item) {
+        let attrs = match item {
+            syn::Item::Const(c) => &mut c.attrs,
+            syn::Item::Static(st) => &mut st.attrs,
+            syn::Item::Fn(f) => &mut f.attrs,
+            _ => return,
         };
-        let glob_ok = if self.include_glob.is_empty() {
-            true
-        } else {
-            self.include_glob.iter().any(|g| wildcard_match(g, name))
-        };
-        regex_ok && glob_ok
+        attrs.retain(|a| {
+            !self
+                .include_attr
+                .iter()
+                .chain(&self.requires_attr)
+                .any(|n| a.path().is_ident(n))
+        });
     }
 
-    fn excluded(&self, name: &str) -> bool {
-        if self.exclude_regex.iter().any(|r| r.is_match(name)) {
-            return true;
+    /// Why `item` is not prefixed, as a trace reason code, or `None` when it
+    /// is. Items carrying an `include_attr` marker are included in addition
+    /// to name matches; with `include_attr` and no name includes, only marked
+    /// items are. Excludes and the structural filters (`requires_attr`, and
+    /// for functions `abi`/`exclude_async`) always apply.
+    pub fn skip_reason(&self, module: &str, item: &syn::Item) -> Option<&'static str> {
+        let (ident, attrs, sig) = match item {
+            syn::Item::Const(c) => (&c.ident, &c.attrs, None),
+            syn::Item::Static(st) => (&st.ident, &st.attrs, None),
+            syn::Item::Fn(f) => (&f.sig.ident, &f.attrs, Some(&f.sig)),
+            _ => return Some("unsupported_item"),
+        };
+        let has_attr = |names: &[String]| {
+            attrs
+                .iter()
+                .any(|a| names.iter().any(|n| a.path().is_ident(n)))
+        };
+        let name = ident.to_string();
+        let subject = format!("{module}::{name}");
+        if self.excluded(&name) || self.excluded(&subject) {
+            return Some("excluded");
+        }
+        let by_name = if self.include_attr.is_empty() || self.has_name_includes() {
+            self.included(&name) || self.included(&subject)
+        } else {
+            false
+        };
+        if !by_name && !has_attr(&self.include_attr) {
+            return Some(if self.not_in_only(&name) {
+                "not_in_only"
+            } else {
+                "not_included"
+            });
         }
-        if self.exclude_glob.iter().any(|g| wildcard_match(g, name)) {
-            return true;
+        if !self.requires_attr.is_empty() && !has_attr(&self.requires_attr) {
+            return Some("missing_attr");
         }
-        false
+        if let Some(sig) = sig {
+            if !self.abi.is_empty() && !self.abi.iter().any(|a| Some(a) == fn_abi(sig).as_ref()) {
+                return Some("abi");
+            }
+            if self.exclude_async && sig.asyncness.is_some() {
+                return Some("async");
+            }
+        }
+        None
     }
 
-    pub fn should_prefix(&self, module: &str, name: &str) -> bool {
-        let subject = format!("{module}::{name}");
-        let include = self.included(name) || self.included(&subject);
-        include && !self.excluded(name) && !self.excluded(&subject)
+    fn has_name_includes(&self) -> bool {
+        !self.names.include_regex.is_empty()
+            || !self.names.include_glob.is_empty()
+            || !self.only.is_empty()
+    }
+
+    /// Whether `only` is set and does not list `name`.
+    pub fn not_in_only(&self, name: &str) -> bool {
+        !self.only.is_empty() && !self.only.iter().any(|n| n == name)
+    }
+
+    /// Rejects `only` entries that name no item in the module, so typos do
+    /// not silently drop an export.
+    pub fn check_only_names<'a>(
+        &self,
+        items: impl IntoIterator<Item = &'a syn::Ident>,
+    ) -> Result<(), syn::Error> {
+        let present: Vec<String> = items.into_iter().map(|i| i.to_string()).collect();
+        let missing: Vec<&str> = self
+            .only
+            .iter()
+            .filter(|n| !present.contains(n))
+            .map(String::as_str)
+            .collect();
+        if missing.is_empty() {
+            return Ok(());
+        }
+        Err(syn::Error::new(
+            self.only_span.unwrap_or_else(proc_macro2::Span::call_site),
+            format!(
+                "symbaker_module: `only` names no fn/static/const in this module: {}",
+                missing.join(", ")
+            ),
+        ))
     }
 
     pub fn render_export_name(&self, prefix: &str, sep: &str, module: &str, name: &str) -> String {
         let suffix = self.suffix.as_deref().unwrap_or("");
         if let Some(tpl) = &self.template {
-            return tpl
-                .replace("{prefix}", prefix)
-                .replace("{sep}", sep)
-                .replace("{module}", module)
-                .replace("{name}", name)
-                .replace("{suffix}", suffix);
+            return self.vars.render(tpl, prefix, sep, module, name, suffix);
         }
         format!("{prefix}{sep}{name}{suffix}")
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules(args: proc_macro2::TokenStream) -> ModuleRules {
+        use syn::parse::Parser;
+        let args = Punctuated::<Meta, Token![,]>::parse_terminated
+            .parse2(args)
+            .unwrap();
+        parse_module_rules(&args).unwrap()
+    }
+
+    #[test]
+    fn structural_filters_report_reason_codes() {
+        let r = rules(quote::quote!(
+            abi = "C",
+            requires_attr = "candidate",
+            exclude_async = true
+        ));
+        let c: syn::Item = syn::parse_quote!(
+            #[candidate]
+            pub extern "C" fn c_fn() {}
+        );
+        // Parsed from a string: rustfmt would add `"C"` inside `parse_quote!`.
+        let bare: syn::Item = syn::parse_str("#[candidate] pub extern fn bare_fn() {}").unwrap();
+        let rust: syn::Item = syn::parse_quote!(
+            #[candidate]
+            pub fn rust_fn() {}
+        );
+        let unmarked: syn::Item = syn::parse_quote!(
+            pub extern "C" fn unmarked() {}
+        );
+        let asynced: syn::Item = syn::parse_quote!(
+            #[candidate]
+            pub async extern "C" fn asynced() {}
+        );
+        let st: syn::Item = syn::parse_quote!(
+            #[candidate]
+            pub static S: u32 = 0;
+        );
+
+        assert_eq!(r.skip_reason("m", &c), None);
+        let syn::Item::Fn(bare_fn) = &bare else {
+            unreachable!()
+        };
+        assert!(bare_fn.sig.abi.as_ref().unwrap().name.is_none());
+        assert_eq!(fn_abi(&bare_fn.sig).as_deref(), Some("C"));
+        assert_eq!(r.skip_reason("m", &bare), None);
+        assert_eq!(r.skip_reason("m", &rust), Some("abi"));
+        assert_eq!(r.skip_reason("m", &unmarked), Some("missing_attr"));
+        assert_eq!(r.skip_reason("m", &asynced), Some("async"));
+        // Statics have no ABI; only `requires_attr` applies to them.
+        assert_eq!(r.skip_reason("m", &st), None);
+    }
+
+    #[test]
+    fn bare_exclude_async_is_true() {
+        let asynced: syn::Item = syn::parse_quote!(
+            pub async extern "C" fn asynced() {}
+        );
+        let r = rules(quote::quote!(exclude_async));
+        assert!(r.exclude_async);
+        assert_eq!(r.skip_reason("m", &asynced), Some("async"));
+        assert!(!rules(quote::quote!(exclude_async = false)).exclude_async);
+    }
+
+    #[test]
+    fn exclude_async_must_be_a_bool() {
+        use syn::parse::Parser;
+        let args = Punctuated::<Meta, Token![,]>::parse_terminated
+            .parse2(quote::quote!(exclude_async = "true"))
+            .unwrap();
+        let err = parse_module_rules(&args).unwrap_err().to_string();
+        assert_eq!(
+            err,
+            "symbaker_module: `exclude_async` takes `true` or `false`"
+        );
+    }
+
+    #[test]
+    fn oversized_regex_is_rejected() {
+        use syn::parse::Parser;
+        let args = Punctuated::<Meta, Token![,]>::parse_terminated
+            .parse2(quote::quote!(include_regex = "^(\\w{100}){100}$"))
+            .unwrap();
+        let err = parse_module_rules(&args).unwrap_err().to_string();
+        assert!(
+            err.contains("include regex '^(\\w{100}){100}$' is too large"),
+            "{err}"
+        );
+
+        // Small patterns are unaffected by the limit.
+        let r = rules(quote::quote!(include_regex = "^(\\w{2}){3}$"));
+        assert!(r.names.included("abcdef"));
+    }
+
+    #[test]
+    fn name_rules_are_checked_before_structural_ones() {
+        let r = rules(quote::quote!(
+            only = "kept",
+            exclude_glob = "*hidden*",
+            abi = "C"
+        ));
+        let hidden: syn::Item = syn::parse_quote!(
+            pub fn hidden() {}
+        );
+        let other: syn::Item = syn::parse_quote!(
+            pub fn other() {}
+        );
+        let kept: syn::Item = syn::parse_quote!(
+            pub fn kept() {}
+        );
+
+        assert_eq!(r.skip_reason("m", &hidden), Some("excluded"));
+        assert_eq!(r.skip_reason("m", &other), Some("not_in_only"));
+        assert_eq!(r.skip_reason("m", &kept), Some("abi"));
+    }
+
+    #[test]
+    fn markers_are_stripped() {
+        let r = rules(quote::quote!(
+            include_attr = "api",
+            requires_attr = "candidate"
+        ));
+        let mut f: syn::Item = syn::parse_quote!(
+            #[api]
+            #[candidate]
+            #[inline]
+            pub extern "C" fn f() {}
+        );
+        assert_eq!(r.skip_reason("m", &f), None);
+        r.strip_markers(&mut f);
+        let syn::Item::Fn(f) = f else { unreachable!() };
+        assert_eq!(f.attrs.len(), 1);
+        assert!(f.attrs[0].path().is_ident("inline"));
+    }
+}
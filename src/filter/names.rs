@@ -0,0 +1,112 @@
+//! Include/exclude name matching, shared by the `symbaker_module` rules and
+//! `cargo symdump` (which pulls this file in with `#[path]`).
+
+use regex::{Regex, RegexBuilder};
+
+/// Regex and glob include/exclude lists over plain names.
+#[derive(Debug, Default)]
+pub struct NameMatcher {
+    pub include_regex: Vec<Regex>,
+    pub exclude_regex: Vec<Regex>,
+    pub include_glob: Vec<String>,
+    pub exclude_glob: Vec<String>,
+}
+
+impl NameMatcher {
+    /// Whether `name` passes the includes: each kind that is set (regex,
+    /// glob) must have a match. No includes means everything is included.
+    pub fn included(&self, name: &str) -> bool {
+        let regex_ok = if self.include_regex.is_empty() {
+            true
+        } else {
+            self.include_regex.iter().any(|r| r.is_match(name))
+        };
+        let glob_ok = if self.include_glob.is_empty() {
+            true
+        } else {
+            self.include_glob.iter().any(|g| wildcard_match(g, name))
+        };
+        regex_ok && glob_ok
+    }
+
+    pub fn excluded(&self, name: &str) -> bool {
+        self.exclude_regex.iter().any(|r| r.is_match(name))
+            || self.exclude_glob.iter().any(|g| wildcard_match(g, name))
+    }
+}
+
+/// Compiled-program limit for a name regex. Function names are short, so
+/// anything near it is a mistake or a generated pattern gone wrong; the
+/// regex crate's own 10 MiB default lets such a pattern use far more memory
+/// inside the compiler than is reasonable for a macro.
+pub const REGEX_SIZE_LIMIT: usize = 1 << 20;
+
+/// Lazy DFA cache limit for a name regex.
+pub const REGEX_DFA_SIZE_LIMIT: usize = 1 << 20;
+
+/// Compiles one `kind` (`include`/`exclude`) regex within
+/// [`REGEX_SIZE_LIMIT`].
+pub fn compile_regex(pattern: &str, kind: &str) -> Result<Regex, String> {
+    RegexBuilder::new(pattern)
+        .size_limit(REGEX_SIZE_LIMIT)
+        .dfa_size_limit(REGEX_DFA_SIZE_LIMIT)
+        .build()
+        .map_err(|e| {
+            let shown = abbreviate(pattern);
+            match e {
+                regex::Error::CompiledTooBig(limit) => format!(
+                    "{kind} regex '{shown}' is too large to compile (over {limit} bytes); \
+                     use a simpler pattern, e.g. a glob or a shorter repetition"
+                ),
+                e => format!("invalid {kind} regex '{shown}': {e}"),
+            }
+        })
+}
+
+/// `pattern`, cut to its first 60 characters for error messages.
+fn abbreviate(pattern: &str) -> String {
+    match pattern.char_indices().nth(60) {
+        Some((at, _)) => format!("{}...", &pattern[..at]),
+        None => pattern.to_string(),
+    }
+}
+
+/// Rejects glob syntax beyond `*` and `?`, which [`wildcard_match`] does not
+/// understand.
+pub fn check_glob(pattern: &str, kind: &str) -> Result<(), String> {
+    if pattern.contains(['[', ']', '{', '}']) {
+        return Err(format!(
+            "unsupported {kind} glob '{pattern}' (use only '*' and '?')"
+        ));
+    }
+    Ok(())
+}
+
+pub fn wildcard_match(pattern: &str, text: &str) -> bool {
+    let p = pattern.as_bytes();
+    let t = text.as_bytes();
+    let (mut pi, mut ti) = (0usize, 0usize);
+    let (mut star, mut match_i) = (None::<usize>, 0usize);
+
+    while ti < t.len() {
+        if pi < p.len() && (p[pi] == b'?' || p[pi] == t[ti]) {
+            pi += 1;
+            ti += 1;
+        } else if pi < p.len() && p[pi] == b'*' {
+            star = Some(pi);
+            pi += 1;
+            match_i = ti;
+        } else if let Some(star_pos) = star {
+            pi = star_pos + 1;
+            match_i += 1;
+            ti = match_i;
+        } else {
+            return false;
+        }
+    }
+
+    while pi < p.len() && p[pi] == b'*' {
+        pi += 1;
+    }
+    pi == p.len()
+}
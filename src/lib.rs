@@ -1,125 +1,97 @@
+#![cfg_attr(
+    feature = "nightly-tracked",
+    feature(proc_macro_tracked_env, proc_macro_tracked_path)
+)]
+
 use proc_macro::TokenStream;
 use quote::quote;
-use std::{collections::HashMap, fs::OpenOptions, io::Write, sync::OnceLock};
+use std::sync::OnceLock;
 use syn::{
     parse_macro_input, punctuated::Punctuated, Expr, ExprLit, ItemFn, ItemMod, Lit, LitInt, Meta,
     Token,
 };
 
-use figment::{
-    providers::{Env, Format, Toml},
-    Figment,
+use symbaker_build::resolve::{
+    builds_dynamic_lib, check_strict_config, consumed_inputs, detect_top_level_package_name,
+    env_var, explain_prefix, load_config, parse_priority_list, read_package_edition,
+    read_package_metadata_bool, read_prefix_from_package_metadata,
+    read_prefix_from_workspace_metadata, resolve_prefix, sanitize, top_level_package_name,
+    trace_emit, trace_record, truthy_env, try_load_config, warn_export_rewritten, Config,
+    ConfigOrigin, PrefixSource,
 };
-use serde::Deserialize;
 
+mod alias;
 mod filter;
 
-#[derive(Debug, Deserialize, Default)]
-struct Config {
-    prefix: Option<String>,
-    sep: Option<String>,
-    priority: Option<Vec<String>>,
-    overrides: Option<HashMap<String, String>>,
-}
-
-#[derive(Clone, Copy, Debug)]
-enum PrefixSource {
-    Override,
-    PreferPackagePrefixPackage,
-    PreferPackagePrefixCrateFallback,
-    Attr,
-    EnvPrefix,
-    Config,
-    TopPackage,
-    Workspace,
-    Package,
-    Crate,
-    CrateFallbackAfterPriority,
-}
-fn sanitize(s: &str) -> String {
-    let mut out: String = s
-        .chars()
-        .map(|c| {
-            if c.is_ascii_alphanumeric() || c == '_' {
-                c
-            } else {
-                '_'
-            }
-        })
-        .collect();
-    if out.is_empty() {
-        out.push('_');
+/// Trims surplus trailing `_` from `prefix` so the prefix/sep junction never
+/// carries a longer underscore run than the separator (minimum two).
+fn normalize_prefix_boundary(prefix: &str, sep: &str) -> String {
+    if !sep.chars().all(|c| c == '_') {
+        return prefix.to_string();
     }
-    if out.chars().next().unwrap().is_ascii_digit() {
-        out.insert(0, '_');
-    }
-    out
-}
-
-fn trace_enabled() -> bool {
-    match std::env::var("SYMBAKER_TRACE") {
-        Ok(v) => {
-            let n = v.trim().to_ascii_lowercase();
-            matches!(n.as_str(), "1" | "true" | "yes" | "on")
-        }
-        Err(_) => false,
+    let allowed = sep.len().max(2) - sep.len();
+    let trimmed = prefix.trim_end_matches('_');
+    let trailing = prefix.len() - trimmed.len();
+    if trailing <= allowed || trimmed.is_empty() {
+        return prefix.to_string();
     }
+    format!("{trimmed}{}", "_".repeat(allowed))
 }
 
-fn trace_emit(line: impl AsRef<str>) {
-    if !trace_enabled() {
-        return;
+/// Renders an export name, applying boundary normalization when
+/// `normalize_exports` is enabled and reporting any change it made.
+fn render_export(cfg: &Config, prefix: &str, sep: &str, render: impl Fn(&str) -> String) -> String {
+    let naive = render(prefix);
+    if !cfg.normalize_exports.unwrap_or(false) {
+        return naive;
     }
-    let msg = format!("[symbaker] {}", line.as_ref());
-    eprintln!("{msg}");
-
-    let path = match std::env::var("SYMBAKER_TRACE_FILE") {
-        Ok(v) if !v.trim().is_empty() => v,
-        _ => return,
-    };
-
-    if let Ok(mut file) = OpenOptions::new().create(true).append(true).open(path) {
-        let _ = writeln!(file, "{msg}");
+    let export = render(&normalize_prefix_boundary(prefix, sep));
+    if export != naive {
+        trace_emit(format!(
+            "export_normalize naive={:?} result={:?}",
+            naive, export
+        ));
+        warn_export_rewritten(format!("{:?} was normalized to {:?}", naive, export));
     }
+    export
 }
 
-fn trace_bootstrap() {
-    static DID_TRACE: OnceLock<()> = OnceLock::new();
-    if DID_TRACE.get().is_some() || !trace_enabled() {
-        return;
+/// Declares every env var and file resolution consumed. On nightly with
+/// `nightly-tracked` they become real dependencies of the expansion; on stable
+/// they are only traced (build scripts get them via
+/// `symbaker_build::emit_rerun_directives()`).
+fn track_inputs() {
+    let (envs, files) = consumed_inputs();
+    trace_emit(format!("inputs env={:?} files={:?}", envs, files));
+    #[cfg(feature = "nightly-tracked")]
+    {
+        for key in &envs {
+            let _ = proc_macro::tracked::env_var(key);
+        }
+        for file in &files {
+            proc_macro::tracked::path(file);
+        }
     }
-    let _ = DID_TRACE.set(());
-    trace_emit(format!(
-        "env CARGO_PKG_NAME={:?} CARGO_MANIFEST_DIR={:?} CARGO_PRIMARY_PACKAGE={:?} SYMBAKER_TOP_PACKAGE={:?} SYMBAKER_PREFIX={:?} SYMBAKER_CONFIG={:?} SYMBAKER_PRIORITY={:?}",
-        std::env::var("CARGO_PKG_NAME").ok(),
-        std::env::var("CARGO_MANIFEST_DIR").ok(),
-        std::env::var("CARGO_PRIMARY_PACKAGE").ok(),
-        std::env::var("SYMBAKER_TOP_PACKAGE").ok(),
-        std::env::var("SYMBAKER_PREFIX").ok(),
-        std::env::var("SYMBAKER_CONFIG").ok(),
-        std::env::var("SYMBAKER_PRIORITY").ok(),
-    ));
 }
 
-fn trace_hard_fail() -> bool {
-    matches!(std::env::var("SYMBAKER_TRACE_HARD").as_deref(), Ok("1"))
-}
-
-fn truthy_env(key: &str) -> bool {
-    match std::env::var(key) {
-        Ok(v) => {
-            let n = v.trim().to_ascii_lowercase();
-            matches!(n.as_str(), "1" | "true" | "yes" | "on")
-        }
-        Err(_) => false,
-    }
+/// `SYMBAKER_TRACE_HARD`: `1` records the resolution (stderr and
+/// `SYMBAKER_TRACE_FILE`) and fails that item only, so every other item still
+/// expands and rustc reports the whole crate; `log` records without failing.
+fn trace_hard(item: &impl quote::ToTokens, line: String) -> Option<proc_macro2::TokenStream> {
+    let fail = match env_var("SYMBAKER_TRACE_HARD").as_deref() {
+        Ok("1") => true,
+        Ok("log") => false,
+        _ => return None,
+    };
+    trace_record(format!("trace_hard {line}"));
+    fail.then(|| trace_compile_error(item, format!("symbaker trace: {line}")))
 }
 
 fn validate_required_config() -> Result<(), syn::Error> {
     if !truthy_env("SYMBAKER_REQUIRE_CONFIG") {
         return Ok(());
     }
-    let path = match std::env::var("SYMBAKER_CONFIG") {
+    let path = match env_var("SYMBAKER_CONFIG") {
         Ok(v) if !v.trim().is_empty() => v,
         _ => {
             return Err(syn::Error::new(
@@ -141,6 +113,69 @@ fn validate_required_config() -> Result<(), syn::Error> {
     Ok(())
 }
 
+/// [`load_config`], except that with `SYMBAKER_REQUIRE_CONFIG=1` a missing or
+/// unreadable config is an error instead of silently falling back to defaults.
+fn load_config_checked() -> Result<Config, syn::Error> {
+    validate_required_config()?;
+    check_strict_config().map_err(|e| {
+        syn::Error::new(
+            proc_macro2::Span::call_site(),
+            format!("symbaker: SYMBAKER_STRICT_CONFIG=1 but {e}"),
+        )
+    })?;
+    if !truthy_env("SYMBAKER_REQUIRE_CONFIG") {
+        return Ok(load_config());
+    }
+    try_load_config().map_err(|e| {
+        syn::Error::new(
+            proc_macro2::Span::call_site(),
+            format!("symbaker: SYMBAKER_REQUIRE_CONFIG=1 but the config could not be loaded: {e}"),
+        )
+    })
+}
+
+/// [`resolve_prefix`] plus the checks every entry point runs on the result:
+/// input tracking, the dependency-fallback warning, `SYMBAKER_ENFORCE_INHERIT`,
+/// and, with `SYMBAKER_REQUIRE_CONFIG=1`, rejecting a prefix that only came
+/// from the crate-name fallback after every `priority` key missed.
+fn resolve_prefix_checked(
+    cfg: &Config,
+    attr_prefix: Option<String>,
+) -> Result<(String, String, String, PrefixSource), syn::Error> {
+    let resolved = resolve_prefix(cfg, attr_prefix);
+    let source = resolved.3;
+    track_inputs();
+    warn_on_dependency_fallback(source);
+    enforce_inherited_prefix(source)?;
+    if matches!(source, PrefixSource::CrateFallbackAfterPriority)
+        && truthy_env("SYMBAKER_REQUIRE_CONFIG")
+    {
+        return Err(syn::Error::new(
+            proc_macro2::Span::call_site(),
+            format!(
+                "symbaker: no `priority` source produced a prefix (priority = {:?}) while SYMBAKER_REQUIRE_CONFIG=1; add \"crate\" to `priority` or set `prefix` in symbaker.toml.",
+                cfg.priority
+            ),
+        ));
+    }
+    Ok(resolved)
+}
+
+/// `SYMBAKER_DISABLE=1`: `#[symbaker]` and `#[symbaker_module]` hand their
+/// item back unchanged and `resolved_prefix!()` is `""`, without loading
+/// config or running any check, so a build can be compared against the exact
+/// same source unprefixed. Traced once per crate.
+fn symbaker_disabled() -> bool {
+    if !truthy_env("SYMBAKER_DISABLE") {
+        return false;
+    }
+    static DID_TRACE: OnceLock<()> = OnceLock::new();
+    if DID_TRACE.set(()).is_ok() {
+        trace_emit("disabled SYMBAKER_DISABLE=1: items are passed through unchanged");
+    }
+    true
+}
+
 fn warn_if_not_initialized() {
     if truthy_env("SYMBAKER_INITIALIZED") {
         return;
@@ -155,10 +190,24 @@ fn warn_if_not_initialized() {
     );
 }
 
-fn trace_compile_error(msg: String) -> TokenStream {
-    syn::Error::new(proc_macro2::Span::call_site(), msg)
-        .to_compile_error()
-        .into()
+fn trace_compile_error(item: &impl quote::ToTokens, msg: String) -> proc_macro2::TokenStream {
+    syn::Error::new_spanned(item, msg).to_compile_error()
+}
+
+/// `explain`: reports how this one invocation was named as an error on
+/// `item`. Stable proc macros cannot emit notes; unlike `SYMBAKER_TRACE_HARD`
+/// no other item in the crate is affected.
+fn explain_error(
+    item: &impl quote::ToTokens,
+    what: String,
+    lines: &[String],
+) -> proc_macro2::TokenStream {
+    let mut msg = format!("symbaker explain: {what}");
+    for line in lines {
+        msg.push_str("\n  ");
+        msg.push_str(line);
+    }
+    syn::Error::new_spanned(item, msg).to_compile_error()
 }
 
 fn enforce_inherited_prefix(source: PrefixSource) -> Result<(), syn::Error> {
@@ -178,7 +227,7 @@ fn enforce_inherited_prefix(source: PrefixSource) -> Result<(), syn::Error> {
             static DID_WARN: OnceLock<()> = OnceLock::new();
             if DID_WARN.get().is_none() {
                 let _ = DID_WARN.set(());
-                let crate_name = std::env::var("CARGO_PKG_NAME").ok();
+                let crate_name = env_var("CARGO_PKG_NAME").ok();
                 eprintln!(
                     "warning: symbaker dependency {:?} fell back to a local prefix, but SYMBAKER_TOP_PACKAGE is unset. Skipping strict inheritance. Set SYMBAKER_TOP_PACKAGE or run `cargo symdump init` to re-enable this check.",
                     crate_name
@@ -189,7 +238,7 @@ fn enforce_inherited_prefix(source: PrefixSource) -> Result<(), syn::Error> {
     }
 
     // Primary package is allowed to resolve with its own crate/package fallback.
-    if std::env::var("CARGO_PRIMARY_PACKAGE").is_ok() {
+    if env_var("CARGO_PRIMARY_PACKAGE").is_ok() {
         return Ok(());
     }
     // Explicit per-crate opt-outs or overrides remain valid in strict mode.
@@ -201,9 +250,11 @@ fn enforce_inherited_prefix(source: PrefixSource) -> Result<(), syn::Error> {
         | PrefixSource::EnvPrefix
         | PrefixSource::Config
         | PrefixSource::TopPackage
+        | PrefixSource::ParentPackage
+        | PrefixSource::RootPackage
         | PrefixSource::Workspace => Ok(()),
         PrefixSource::Package | PrefixSource::Crate | PrefixSource::CrateFallbackAfterPriority => {
-            let crate_name = std::env::var("CARGO_PKG_NAME").ok();
+            let crate_name = env_var("CARGO_PKG_NAME").ok();
             Err(syn::Error::new(
                 proc_macro2::Span::call_site(),
                 format!(
@@ -219,7 +270,7 @@ fn warn_on_dependency_fallback(source: PrefixSource) {
     if truthy_env("SYMBAKER_ENFORCE_INHERIT") {
         return;
     }
-    if std::env::var("CARGO_PRIMARY_PACKAGE").is_ok() {
+    if env_var("CARGO_PRIMARY_PACKAGE").is_ok() {
         return;
     }
     match source {
@@ -229,7 +280,7 @@ fn warn_on_dependency_fallback(source: PrefixSource) {
                 return;
             }
             let _ = DID_WARN.set(());
-            let crate_name = std::env::var("CARGO_PKG_NAME").unwrap_or_else(|_| "unknown".into());
+            let crate_name = env_var("CARGO_PKG_NAME").unwrap_or_else(|_| "unknown".into());
             eprintln!(
                 "warning: symbaker fallback detected in dependency crate {:?}: resolved local {:?} source. This can leak dependency prefixes into final exports. run `cargo symdump init` in workspace root (enables SYMBAKER_REQUIRE_CONFIG=1 and SYMBAKER_ENFORCE_INHERIT=1), or set SYMBAKER_CONFIG/SYMBAKER_TOP_PACKAGE explicitly.",
                 crate_name, source
@@ -239,310 +290,291 @@ fn warn_on_dependency_fallback(source: PrefixSource) {
     }
 }
 
-fn load_config() -> Config {
-    // Highest-level “shared” config file path
-    let cfg_path = std::env::var("SYMBAKER_CONFIG").ok();
-    trace_emit(format!("load_config SYMBAKER_CONFIG={:?}", cfg_path));
-
-    let mut fig = Figment::new();
-
-    // Optional file config
-    if let Some(p) = cfg_path.clone() {
-        let exists = std::path::Path::new(&p).exists();
-        trace_emit(format!(
-            "load_config merging file path={:?} exists={}",
-            p, exists
-        ));
-        fig = fig.merge(Toml::file(p));
-    }
-
-    // Optional env overrides:
-    // SYMBAKER_PREFIX, SYMBAKER_SEP, SYMBAKER_PRIORITY
-    fig = fig.merge(Env::prefixed("SYMBAKER_"));
+fn parse_attr_prefix(args: &Punctuated<Meta, Token![,]>) -> Option<String> {
+    parse_attr_str(args, "prefix")
+}
 
-    match fig.extract::<Config>() {
-        Ok(cfg) => {
-            trace_emit(format!(
-                "load_config extracted prefix={:?} sep={:?} priority={:?}",
-                cfg.prefix, cfg.sep, cfg.priority
-            ));
-            cfg
-        }
-        Err(e) => {
-            trace_emit(format!("load_config extract error: {}", e));
-            Config::default()
+fn parse_attr_str(args: &Punctuated<Meta, Token![,]>, key: &str) -> Option<String> {
+    for a in args {
+        if let Meta::NameValue(nv) = a {
+            if nv.path.is_ident(key) {
+                if let Expr::Lit(ExprLit {
+                    lit: Lit::Str(s), ..
+                }) = &nv.value
+                {
+                    return Some(s.value());
+                }
+            }
         }
     }
+    None
 }
 
-fn default_priority() -> Vec<String> {
-    vec![
-        "attr".into(),
-        "env_prefix".into(),  // SYMBAKER_PREFIX
-        "config".into(),      // SYMBAKER_CONFIG file
-        "top_package".into(), // top-level package being built
-        "workspace".into(),
-        "package".into(),
-        "crate".into(),
-    ]
-}
-
-fn top_level_package_name() -> Option<String> {
-    detect_top_level_package_name()
+/// The `template` attribute of `#[symbaker]` with its environment-derived
+/// placeholders resolved. `{module}` is rejected: a lone fn has no module to
+/// fill it with.
+fn parse_attr_template(
+    args: &Punctuated<Meta, Token![,]>,
+) -> Result<Option<(String, filter::TemplateVars)>, syn::Error> {
+    let Some(nv) = args.iter().find_map(|a| match a {
+        Meta::NameValue(nv) if nv.path.is_ident("template") => Some(nv),
+        _ => None,
+    }) else {
+        return Ok(None);
+    };
+    let Expr::Lit(ExprLit {
+        lit: Lit::Str(s), ..
+    }) = &nv.value
+    else {
+        return Err(syn::Error::new_spanned(
+            &nv.value,
+            "symbaker: `template` must be a string literal",
+        ));
+    };
+    let template = s.value();
+    if template.contains("{module}") {
+        return Err(syn::Error::new_spanned(
+            &nv.value,
+            "symbaker: `{module}` is only available in `symbaker_module` templates",
+        ));
+    }
+    let vars = filter::resolve_template_vars(&template, &nv.value, "symbaker")?;
+    Ok(Some((template, vars)))
 }
 
-fn detect_top_level_package_name() -> Option<String> {
-    if let Ok(v) = std::env::var("SYMBAKER_TOP_PACKAGE") {
-        if !v.trim().is_empty() {
-            return Some(v);
-        }
+/// Applies the attribute's `sep` and `priority` to `cfg`; both win over the
+/// config file and env for this expansion only.
+fn apply_attr_config(
+    args: &Punctuated<Meta, Token![,]>,
+    cfg: &mut Config,
+) -> Result<(), syn::Error> {
+    if let Some(sep) = parse_attr_str(args, "sep") {
+        cfg.sep = Some(sep);
+        cfg.origins.insert("sep", ConfigOrigin::Attr);
     }
+    let Some(value) = args.iter().find_map(|a| match a {
+        Meta::NameValue(nv) if nv.path.is_ident("priority") => Some(&nv.value),
+        _ => None,
+    }) else {
+        return Ok(());
+    };
+    let raw = parse_attr_str(args, "priority").ok_or_else(|| {
+        syn::Error::new_spanned(
+            value,
+            "symbaker: `priority` must be a string like \"attr,crate\"",
+        )
+    })?;
+    let priority = parse_priority_list(&raw)
+        .map_err(|e| syn::Error::new_spanned(value, format!("symbaker: {e}")))?;
+    cfg.priority = Some(priority);
+    Ok(())
+}
 
-    if std::env::var("CARGO_PRIMARY_PACKAGE").is_ok() {
-        if let Ok(v) = std::env::var("CARGO_PKG_NAME") {
-            if !v.trim().is_empty() {
-                return Some(v);
+fn parse_attr_bool(args: &Punctuated<Meta, Token![,]>, key: &str) -> Option<bool> {
+    for a in args {
+        match a {
+            Meta::Path(p) if p.is_ident(key) => return Some(true),
+            Meta::NameValue(nv) if nv.path.is_ident(key) => {
+                if let Expr::Lit(ExprLit {
+                    lit: Lit::Bool(b), ..
+                }) = &nv.value
+                {
+                    return Some(b.value);
+                }
             }
+            _ => {}
         }
     }
-
     None
 }
 
-fn read_prefix_from_workspace_metadata() -> Option<String> {
-    // Only works when the crate being compiled is in/under a workspace
-    // (path deps / workspace members). For git deps, this likely won’t find caller workspace.
-    let mut dir = std::path::PathBuf::from(std::env::var("CARGO_MANIFEST_DIR").ok()?);
-    loop {
-        let cargo = dir.join("Cargo.toml");
-        if cargo.exists() {
-            let text = std::fs::read_to_string(&cargo).ok()?;
-            let v: toml::Value = toml::from_str(&text).ok()?;
-            if let Some(prefix) = v
-                .get("workspace")
-                .and_then(|w| w.get("metadata"))
-                .and_then(|m| m.get("symbaker"))
-                .and_then(|s| s.get("prefix"))
-                .and_then(|p| p.as_str())
-            {
-                trace_emit(format!(
-                    "workspace metadata prefix found in {}: {:?}",
-                    cargo.display(),
-                    prefix
-                ));
-                return Some(prefix.to_string());
-            }
-        }
-        if !dir.pop() {
-            break;
+/// Decides whether unprefixed compat aliases should be emitted, honoring the
+/// `compat_alias_until` version gate against `CARGO_PKG_VERSION`.
+fn compat_alias_enabled(cfg: &Config, attr: Option<bool>) -> Result<bool, syn::Error> {
+    if !attr.or(cfg.compat_alias).unwrap_or(false) {
+        return Ok(false);
+    }
+    let Some(until) = &cfg.compat_alias_until else {
+        return Ok(true);
+    };
+    let version = env_var("CARGO_PKG_VERSION").unwrap_or_default();
+    match alias::version_before(&version, until) {
+        Some(true) => Ok(true),
+        Some(false) => {
+            trace_emit(format!(
+                "compat_alias disabled: CARGO_PKG_VERSION={:?} reached compat_alias_until={:?}",
+                version, until
+            ));
+            Ok(false)
         }
+        None => Err(syn::Error::new(
+            proc_macro2::Span::call_site(),
+            format!(
+                "symbaker: cannot compare CARGO_PKG_VERSION {:?} with compat_alias_until {:?}",
+                version, until
+            ),
+        )),
     }
-    trace_emit("workspace metadata prefix not found while walking parent Cargo.toml files");
-    None
 }
 
-fn read_prefix_from_package_metadata() -> Option<String> {
-    let dir = std::env::var("CARGO_MANIFEST_DIR").ok()?;
-    let cargo = std::path::Path::new(&dir).join("Cargo.toml");
-    let text = std::fs::read_to_string(cargo).ok()?;
-    let v: toml::Value = toml::from_str(&text).ok()?;
-    v.get("package")
-        .and_then(|p| p.get("metadata"))
-        .and_then(|m| m.get("symbaker"))
-        .and_then(|s| s.get("prefix"))
-        .and_then(|p| p.as_str())
-        .map(|s| s.to_string())
+/// Old prefixes from `[prefix_aliases]` (`old = "new"`) whose new prefix is `prefix`.
+fn prefix_aliases_for(cfg: &Config, prefix: &str) -> Vec<String> {
+    let mut olds: Vec<String> = cfg
+        .prefix_aliases
+        .iter()
+        .flatten()
+        .filter(|(_, new)| sanitize(new) == prefix)
+        .map(|(old, _)| sanitize(old))
+        .filter(|old| old != prefix)
+        .collect();
+    olds.sort();
+    olds.dedup();
+    olds
 }
 
-fn read_package_prefers_own_prefix() -> bool {
-    let dir = match std::env::var("CARGO_MANIFEST_DIR") {
-        Ok(v) => v,
-        Err(_) => return false,
-    };
-    let cargo = std::path::Path::new(&dir).join("Cargo.toml");
-    let text = match std::fs::read_to_string(cargo) {
-        Ok(v) => v,
-        Err(_) => return false,
-    };
-    let v: toml::Value = match toml::from_str(&text) {
-        Ok(v) => v,
-        Err(_) => return false,
-    };
-    v.get("package")
-        .and_then(|p| p.get("metadata"))
-        .and_then(|m| m.get("symbaker"))
-        .and_then(|s| s.get("prefer_package_prefix"))
-        .and_then(|b| b.as_bool())
-        .unwrap_or(false)
+/// Builds one forwarding wrapper per `(kind, alias)` pair, skipping aliases
+/// that would collide with the real export. Returns `(alias, wrapper)` pairs.
+fn alias_wrappers(
+    f: &ItemFn,
+    export: &str,
+    aliases: &[(&str, String)],
+    trace_ctx: &str,
+    in_impl: bool,
+) -> Result<Vec<(String, ItemFn)>, syn::Error> {
+    let mut out = Vec::new();
+    for (kind, alias) in aliases {
+        if alias == export || out.iter().any(|(a, _)| a == alias) {
+            continue;
+        }
+        let mut wrapper = alias::forwarding_wrapper(f, alias, in_impl)?;
+        push_export_name(&mut wrapper.attrs, alias.clone());
+        trace_emit(format!(
+            "{} {}={:?} forwards_to={:?}",
+            trace_ctx, kind, alias, export
+        ));
+        out.push((alias.clone(), wrapper));
+    }
+    Ok(out)
 }
 
-fn resolve_prefix(attr_prefix: Option<String>) -> (String, String, PrefixSource) {
-    trace_bootstrap();
+/// Errors when `export` matches a `deny` entry (exact name or `*`/`?` glob).
+fn check_denied(cfg: &Config, export: &str, item: &impl quote::ToTokens) -> Result<(), syn::Error> {
+    let Some(pattern) = cfg
+        .deny
+        .iter()
+        .flatten()
+        .find(|p| filter::wildcard_match(p, export))
+    else {
+        return Ok(());
+    };
+    Err(syn::Error::new_spanned(
+        item,
+        format!(
+            "symbaker: export {:?} matches denied name {:?} from symbaker.toml `deny`",
+            export, pattern
+        ),
+    ))
+}
 
-    let cfg = load_config();
-    trace_emit(format!(
-        "resolve_prefix input attr_prefix={:?} config.prefix={:?} config.sep={:?} config.priority={:?} config.overrides_keys={:?}",
-        attr_prefix,
-        cfg.prefix,
-        cfg.sep,
-        cfg.priority,
-        cfg.overrides
-            .as_ref()
-            .map(|m| m.keys().cloned().collect::<Vec<_>>())
-    ));
+/// `export_name`/`no_mangle`, bare or in the `unsafe(...)` form.
+fn is_symbol_name_attr(a: &syn::Attribute) -> bool {
+    let is_name = |p: &syn::Path| p.is_ident("export_name") || p.is_ident("no_mangle");
+    if is_name(a.path()) {
+        return true;
+    }
+    match &a.meta {
+        Meta::List(l) if l.path.is_ident("unsafe") => l
+            .parse_args::<Meta>()
+            .map(|m| is_name(m.path()))
+            .unwrap_or(false),
+        _ => false,
+    }
+}
 
-    let sep = cfg.sep.clone().unwrap_or_else(|| "__".into());
-    let prio = cfg.priority.clone().unwrap_or_else(default_priority);
-    let env_prefix = std::env::var("SYMBAKER_PREFIX").ok();
-    let top_package = top_level_package_name();
-    let workspace_prefix = read_prefix_from_workspace_metadata();
-    let crate_name = std::env::var("CARGO_PKG_NAME").unwrap_or_else(|_| "crate".into());
-    let package_prefix = read_prefix_from_package_metadata();
-    let override_prefix = cfg
-        .overrides
-        .as_ref()
-        .and_then(|m| m.get(&crate_name))
-        .cloned();
+/// Edition 2024 only accepts `export_name` as `#[unsafe(export_name = ...)]`;
+/// older editions get the bare form so pre-1.82 compilers still work.
+fn export_name_attr(export: &str, unsafe_form: bool) -> syn::Attribute {
+    if unsafe_form {
+        syn::parse_quote!(#[unsafe(export_name = #export)])
+    } else {
+        syn::parse_quote!(#[export_name = #export])
+    }
+}
 
-    trace_emit(format!(
-        "resolved candidates env_prefix={:?} top_package={:?} workspace_prefix={:?} package_prefix={:?} override_prefix={:?} crate={:?} sep={:?}",
-        env_prefix, top_package, workspace_prefix, package_prefix, override_prefix, crate_name, sep
-    ));
+fn edition_needs_unsafe_attrs() -> bool {
+    read_package_edition()
+        .and_then(|e| e.parse::<u32>().ok())
+        .is_some_and(|e| e >= 2024)
+}
 
-    if let Some(p) = &override_prefix {
-        let chosen = sanitize(p);
-        trace_emit(format!(
-            "selected source=override(crate={:?}) raw={:?} sanitized={:?}",
-            crate_name, p, chosen
-        ));
-        return (chosen, sep, PrefixSource::Override);
-    }
+fn push_export_name(attrs: &mut Vec<syn::Attribute>, export: String) {
+    // Add/override export_name, replacing any user-written symbol name.
+    attrs.retain(|a| !is_symbol_name_attr(a));
+    attrs.push(export_name_attr(&export, edition_needs_unsafe_attrs()));
+}
 
-    // Per-crate opt-out of inherited top-level prefix.
-    // If set, package prefix wins (or crate name fallback if no explicit prefix).
-    if read_package_prefers_own_prefix() {
-        if let Some(p) = &package_prefix {
-            let chosen = sanitize(p);
-            trace_emit(format!(
-                "selected source=prefer_package_prefix(package) raw={:?} sanitized={:?}",
-                p, chosen
-            ));
-            return (chosen, sep, PrefixSource::PreferPackagePrefixPackage);
-        }
-        let chosen = sanitize(&crate_name);
-        trace_emit(format!(
-            "selected source=prefer_package_prefix(crate_fallback) raw={:?} sanitized={:?}",
-            crate_name, chosen
-        ));
-        return (chosen, sep, PrefixSource::PreferPackagePrefixCrateFallback);
+/// Appends rustdoc naming the baked export (after any existing docs) and a
+/// `doc(alias)` so rustdoc search finds the item by its exported name.
+fn push_export_docs(
+    attrs: &mut Vec<syn::Attribute>,
+    rust_name: &str,
+    export: &str,
+    source: PrefixSource,
+    sep: &str,
+) {
+    let text = format!(
+        " Exported as `{}` (symbaker: prefix source {:?}, sep `{}`).",
+        export, source, sep
+    );
+    if attrs.iter().any(|a| a.path().is_ident("doc")) {
+        attrs.push(syn::parse_quote!(#[doc = ""]));
     }
-
-    // Note: “config” here means the parsed file via SYMBAKER_CONFIG;
-    // env overrides come via SYMBAKER_PREFIX.
-    for key in prio {
-        match key.as_str() {
-            "attr" => {
-                if let Some(p) = &attr_prefix {
-                    let chosen = sanitize(p);
-                    trace_emit(format!(
-                        "selected source=attr raw={:?} sanitized={:?}",
-                        p, chosen
-                    ));
-                    return (chosen, sep, PrefixSource::Attr);
-                }
-            }
-            "env_prefix" => {
-                if let Some(p) = &env_prefix {
-                    let chosen = sanitize(p);
-                    trace_emit(format!(
-                        "selected source=env_prefix raw={:?} sanitized={:?}",
-                        p, chosen
-                    ));
-                    return (chosen, sep, PrefixSource::EnvPrefix);
-                }
-            }
-            "config" => {
-                if let Some(p) = &cfg.prefix {
-                    let chosen = sanitize(p);
-                    trace_emit(format!(
-                        "selected source=config raw={:?} sanitized={:?}",
-                        p, chosen
-                    ));
-                    return (chosen, sep, PrefixSource::Config);
-                }
-            }
-            "top_package" => {
-                if let Some(p) = &top_package {
-                    let chosen = sanitize(p);
-                    trace_emit(format!(
-                        "selected source=top_package raw={:?} sanitized={:?}",
-                        p, chosen
-                    ));
-                    return (chosen, sep, PrefixSource::TopPackage);
-                }
-            }
-            "workspace" => {
-                if let Some(p) = &workspace_prefix {
-                    let chosen = sanitize(p);
-                    trace_emit(format!(
-                        "selected source=workspace raw={:?} sanitized={:?}",
-                        p, chosen
-                    ));
-                    return (chosen, sep, PrefixSource::Workspace);
-                }
-            }
-            "package" => {
-                if let Some(p) = &package_prefix {
-                    let chosen = sanitize(p);
-                    trace_emit(format!(
-                        "selected source=package raw={:?} sanitized={:?}",
-                        p, chosen
-                    ));
-                    return (chosen, sep, PrefixSource::Package);
-                }
-            }
-            "crate" => {
-                let chosen = sanitize(&crate_name);
-                trace_emit(format!(
-                    "selected source=crate raw={:?} sanitized={:?}",
-                    crate_name, chosen
-                ));
-                return (chosen, sep, PrefixSource::Crate);
-            }
-            _ => trace_emit(format!("priority key {:?} is unknown and ignored", key)),
-        }
+    attrs.push(syn::parse_quote!(#[doc = #text]));
+    if export != rust_name {
+        attrs.push(syn::parse_quote!(#[doc(alias = #export)]));
     }
+}
 
-    let chosen = sanitize(&crate_name);
-    trace_emit(format!(
-        "selected source=crate_fallback_after_priority raw={:?} sanitized={:?}",
-        crate_name, chosen
-    ));
-    (chosen, sep, PrefixSource::CrateFallbackAfterPriority)
+/// Removes a `#[symbaker(export_const)]` marker, reporting whether there was
+/// one. Consts are only exported with it: a `static` cannot stand in for a
+/// const in array lengths, patterns or const generics.
+fn take_export_const(attrs: &mut Vec<syn::Attribute>) -> bool {
+    let before = attrs.len();
+    attrs.retain(|a| {
+        !(a.path()
+            .segments
+            .last()
+            .is_some_and(|s| s.ident == "symbaker")
+            && a.parse_args::<syn::Ident>()
+                .is_ok_and(|i| i == "export_const"))
+    });
+    attrs.len() != before
 }
 
-fn parse_attr_prefix(args: &Punctuated<Meta, Token![,]>) -> Option<String> {
-    for a in args {
-        if let Meta::NameValue(nv) = a {
-            if nv.path.is_ident("prefix") {
-                if let Expr::Lit(ExprLit {
-                    lit: Lit::Str(s), ..
-                }) = &nv.value
-                {
-                    return Some(s.value());
-                }
-            }
-        }
+/// [`take_export_const`] on a `const` or `static`; a static is already
+/// exported, so the marker is only dropped there.
+fn take_item_export_const(item: &mut syn::Item) -> bool {
+    match item {
+        syn::Item::Const(c) => take_export_const(&mut c.attrs),
+        syn::Item::Static(st) => take_export_const(&mut st.attrs),
+        _ => false,
     }
-    None
 }
 
-fn push_export_name(fn_item: &mut ItemFn, export: String) {
-    // Add/override export_name
-    fn_item.attrs.retain(|a| !a.path().is_ident("export_name"));
-    fn_item
-        .attrs
-        .push(syn::parse_quote!(#[export_name = #export]));
+/// Rewrites `const` as `static` so it gets a symbol the loader can look up.
+fn const_to_static(c: &syn::ItemConst) -> syn::ItemStatic {
+    syn::ItemStatic {
+        attrs: c.attrs.clone(),
+        vis: c.vis.clone(),
+        static_token: Token![static](c.const_token.span),
+        mutability: syn::StaticMutability::None,
+        ident: c.ident.clone(),
+        colon_token: c.colon_token,
+        ty: c.ty.clone(),
+        eq_token: c.eq_token,
+        expr: c.expr.clone(),
+        semi_token: c.semi_token,
+    }
 }
 
 #[proc_macro]
@@ -555,17 +587,15 @@ pub fn resolved_prefix(input: TokenStream) -> TokenStream {
         .to_compile_error()
         .into();
     }
-
-    warn_if_not_initialized();
-    if let Err(e) = validate_required_config() {
-        return e.to_compile_error().into();
+    if symbaker_disabled() {
+        return TokenStream::from(quote!(""));
     }
 
-    let (prefix, _, source) = resolve_prefix(None);
-    warn_on_dependency_fallback(source);
-    if let Err(e) = enforce_inherited_prefix(source) {
-        return e.to_compile_error().into();
-    }
+    warn_if_not_initialized();
+    let prefix = match load_config_checked().and_then(|cfg| resolve_prefix_checked(&cfg, None)) {
+        Ok((prefix, ..)) => prefix,
+        Err(e) => return e.to_compile_error().into(),
+    };
 
     let lit = syn::LitStr::new(&prefix, proc_macro2::Span::call_site());
     TokenStream::from(quote!(#lit))
@@ -578,17 +608,15 @@ pub fn assert_resolved_prefix_len(input: TokenStream) -> TokenStream {
         Ok(v) => v,
         Err(e) => return syn::Error::new_spanned(max, e).to_compile_error().into(),
     };
-
-    warn_if_not_initialized();
-    if let Err(e) = validate_required_config() {
-        return e.to_compile_error().into();
+    if symbaker_disabled() {
+        return TokenStream::new();
     }
 
-    let (prefix, _, source) = resolve_prefix(None);
-    warn_on_dependency_fallback(source);
-    if let Err(e) = enforce_inherited_prefix(source) {
-        return e.to_compile_error().into();
-    }
+    warn_if_not_initialized();
+    let prefix = match load_config_checked().and_then(|cfg| resolve_prefix_checked(&cfg, None)) {
+        Ok((prefix, ..)) => prefix,
+        Err(e) => return e.to_compile_error().into(),
+    };
 
     if prefix.len() > max_len {
         return syn::Error::new(
@@ -609,119 +637,1098 @@ pub fn assert_resolved_prefix_len(input: TokenStream) -> TokenStream {
 
 #[proc_macro_attribute]
 pub fn symbaker(attr: TokenStream, item: TokenStream) -> TokenStream {
+    if symbaker_disabled() {
+        return item;
+    }
     let args = parse_macro_input!(attr with Punctuated::<Meta, Token![,]>::parse_terminated);
-    let mut f = parse_macro_input!(item as ItemFn);
+    let f = parse_macro_input!(item as ItemFn);
+    expand_symbaker(&args, f).into()
+}
 
+fn expand_symbaker(args: &Punctuated<Meta, Token![,]>, mut f: ItemFn) -> proc_macro2::TokenStream {
     warn_if_not_initialized();
 
-    if let Err(e) = validate_required_config() {
-        return e.to_compile_error().into();
-    }
-
     if !f.sig.generics.params.is_empty() {
         return syn::Error::new_spanned(
             &f.sig.generics,
             "symbaker: generic functions not supported",
         )
-        .to_compile_error()
-        .into();
+        .to_compile_error();
     }
 
-    let attr_prefix = parse_attr_prefix(&args);
-    let (prefix, sep, source) = resolve_prefix(attr_prefix);
-    warn_on_dependency_fallback(source);
-    if let Err(e) = enforce_inherited_prefix(source) {
-        return e.to_compile_error().into();
+    let attr_prefix = parse_attr_prefix(args);
+    let mut cfg = match load_config_checked() {
+        Ok(cfg) => cfg,
+        Err(e) => return e.to_compile_error(),
+    };
+    if let Some(raw) = parse_attr_bool(args, "raw") {
+        cfg.raw_prefix = raw;
     }
+    if let Err(e) = apply_attr_config(args, &mut cfg) {
+        return e.to_compile_error();
+    }
+    let (prefix, sep, suffix, source) = match resolve_prefix_checked(&cfg, attr_prefix.clone()) {
+        Ok(resolved) => resolved,
+        Err(e) => return e.to_compile_error(),
+    };
+    let explain = parse_attr_bool(args, "explain").unwrap_or(false).then(|| {
+        explain_prefix(
+            &cfg,
+            attr_prefix.as_deref(),
+            (&prefix, &sep, &suffix, source),
+        )
+    });
+    let attr_suffix = parse_attr_str(args, "suffix");
+    let suffix = attr_suffix.clone().unwrap_or(suffix);
+    let no_alias = parse_attr_bool(args, "no_alias").unwrap_or(false)
+        || read_package_metadata_bool("no_alias");
+    let compat_alias = match compat_alias_enabled(&cfg, parse_attr_bool(args, "compat_alias")) {
+        Ok(v) => v && !no_alias,
+        Err(e) => return e.to_compile_error(),
+    };
+    let docs = parse_attr_bool(args, "docs").or(cfg.docs).unwrap_or(true);
+    let old_prefixes = if no_alias {
+        Vec::new()
+    } else {
+        prefix_aliases_for(&cfg, &prefix)
+    };
+
+    let template = match parse_attr_template(args) {
+        Ok(t) => t,
+        Err(e) => return e.to_compile_error(),
+    };
 
     let rust_name = f.sig.ident.to_string();
-    let export = format!("{prefix}{sep}{rust_name}");
+    let render_name = |p: &str| match &template {
+        Some((tpl, vars)) => vars.render(tpl, p, &sep, "", &rust_name, &suffix),
+        None => format!("{p}{sep}{rust_name}{suffix}"),
+    };
+    let export = render_export(&cfg, &prefix, &sep, render_name);
     trace_emit(format!(
         "macro=symbaker function={:?} resolved_prefix={:?} export_name={:?}",
         rust_name, prefix, export
     ));
-    if trace_hard_fail() {
-        return trace_compile_error(format!(
-            "symbaker trace: macro=symbaker crate={:?} function={:?} prefix={:?} export={:?} top_package={:?} workspace={:?} package={:?} env_prefix={:?}",
-            std::env::var("CARGO_PKG_NAME").ok(),
+    if let Some((tpl, _)) = &template {
+        trace_emit(format!(
+            "macro=symbaker function={:?} template={:?} template_placeholders={:?}",
+            rust_name,
+            tpl,
+            filter::used_placeholders(tpl)
+        ));
+    }
+    let hard_error = trace_hard(
+        &f.sig.ident,
+        format!(
+            "macro=symbaker crate={:?} function={:?} prefix={:?} export={:?} top_package={:?} workspace={:?} package={:?} env_prefix={:?}",
+            env_var("CARGO_PKG_NAME").ok(),
             rust_name,
             prefix,
             export,
             top_level_package_name(),
             read_prefix_from_workspace_metadata(),
             read_prefix_from_package_metadata(),
-            std::env::var("SYMBAKER_PREFIX").ok(),
+            env_var("SYMBAKER_PREFIX").ok(),
+        ),
+    );
+    let mut aliases = Vec::<(&str, String)>::new();
+    if compat_alias {
+        aliases.push(("compat_alias", rust_name.clone()));
+    }
+    for old in &old_prefixes {
+        let alias = render_export(&cfg, old, &sep, render_name);
+        aliases.push(("prefix_alias", alias));
+    }
+    let weak_alias = parse_attr_str(args, "alias");
+    if let Some(weak) = &weak_alias {
+        if weak == &export || aliases.iter().any(|(_, a)| a == weak) {
+            return syn::Error::new_spanned(
+                &f.sig.ident,
+                format!("symbaker: `alias` {weak:?} is already exported by this function"),
+            )
+            .to_compile_error();
+        }
+        aliases.push(("weak_alias", weak.clone()));
+    }
+    for name in std::iter::once(&export).chain(aliases.iter().map(|(_, a)| a)) {
+        if let Err(e) = check_denied(&cfg, name, &f.sig.ident) {
+            return e.to_compile_error();
+        }
+    }
+    if let Some(mut lines) = explain {
+        if let Some(s) = &attr_suffix {
+            lines.push(format!(
+                "suffix attribute = {s:?} (replaces the configured suffix)"
+            ));
+        }
+        lines.push(format!(
+            "template = {:?}",
+            template
+                .as_ref()
+                .map_or("{prefix}{sep}{name}{suffix}", |(tpl, _)| tpl.as_str())
         ));
+        lines.push(format!("export = {export:?}"));
+        for (kind, alias) in &aliases {
+            lines.push(format!("{kind} = {alias:?}"));
+        }
+        return explain_error(&f.sig.ident, format!("fn `{rust_name}`"), &lines);
+    }
+    let mut wrappers: Vec<ItemFn> =
+        match alias_wrappers(&f, &export, &aliases, "macro=symbaker", false) {
+            Ok(w) => w.into_iter().map(|(_, w)| w).collect(),
+            Err(e) => return e.to_compile_error(),
+        };
+    // The weak alias's wrapper is only the fallback for targets without weak
+    // symbols, and for dynamic libraries, whose export list drops the weak
+    // `global_asm!` trampoline.
+    let weak_shim = match weak_alias.map(|weak| {
+        let fallback = wrappers.pop().expect("weak alias wrapper");
+        if builds_dynamic_lib() {
+            trace_emit(format!(
+                "macro=symbaker function={rust_name:?} alias={weak:?} exported as a strong forwarder: the crate builds a dynamic library"
+            ));
+            Ok(quote!(#fallback))
+        } else {
+            alias::weak_alias(&f, &weak, &fallback)
+        }
+    }) {
+        Some(Ok(shim)) => shim,
+        Some(Err(e)) => return e.to_compile_error(),
+        None => proc_macro2::TokenStream::new(),
+    };
+    if docs {
+        push_export_docs(&mut f.attrs, &rust_name, &export, source, &sep);
     }
-    push_export_name(&mut f, export);
+    push_export_name(&mut f.attrs, export);
+
+    quote!(#f #(#wrappers)* #weak_shim #hard_error)
+}
 
-    TokenStream::from(quote!(#f))
+fn async_export_error(item: &impl quote::ToTokens) -> syn::Error {
+    syn::Error::new_spanned(
+        item,
+        "symbaker_module: async functions cannot be C exports (an `async fn` returns a future, not its output); remove `async`, or skip them with `exclude_async = true`",
+    )
+}
+
+/// Rewrites `pub use` re-exports of functions the module rules select into
+/// forwarding functions of the same name, which the module loop then
+/// exports like any other. Only targets defined in inline submodules can be
+/// resolved; other names (types, functions in other files) stay re-exports.
+fn forward_reexports(
+    items: &mut Vec<syn::Item>,
+    module_rules: &filter::ModuleRules,
+    module_name: &str,
+) -> Result<(), syn::Error> {
+    let mut rewrites = Vec::<(usize, Vec<syn::Item>)>::new();
+    for (i, it) in items.iter().enumerate() {
+        let syn::Item::Use(u) = it else {
+            continue;
+        };
+        if !matches!(u.vis, syn::Visibility::Public(_)) {
+            continue;
+        }
+        let mut replacement = Vec::new();
+        let mut forwarded = false;
+        for leaf in alias::use_leaves(&u.tree, u.leading_colon.is_some()) {
+            let Some(name) = leaf.rename.as_ref() else {
+                replacement.push(leaf.to_use(&u.attrs, &u.vis));
+                continue;
+            };
+            let Some((source, target)) = leaf.resolve_inline_fn(items) else {
+                trace_emit(format!(
+                    "macro=symbaker_module module={:?} use={:?} skipped reason=use_unresolved",
+                    module_name,
+                    name.to_string()
+                ));
+                replacement.push(leaf.to_use(&u.attrs, &u.vis));
+                continue;
+            };
+            let mut visible = target.clone();
+            visible.sig.ident = name.clone();
+            let skip = module_rules
+                .skip_reason(module_name, &syn::Item::Fn(visible.clone()))
+                .or_else(|| (!visible.sig.generics.params.is_empty()).then_some("generic"));
+            if let Some(reason) = skip {
+                trace_emit(format!(
+                    "macro=symbaker_module module={:?} use={:?} skipped reason={}",
+                    module_name,
+                    name.to_string(),
+                    reason
+                ));
+                replacement.push(leaf.to_use(&u.attrs, &u.vis));
+                continue;
+            }
+            if target.sig.asyncness.is_some() {
+                return Err(async_export_error(name));
+            }
+            let attrs: Vec<syn::Attribute> = u
+                .attrs
+                .iter()
+                .chain(target.attrs.iter().filter(|a| a.path().is_ident("doc")))
+                .cloned()
+                .collect();
+            replacement.push(syn::Item::Fn(
+                leaf.forwarding_fn(target, source, &attrs, &u.vis)?,
+            ));
+            forwarded = true;
+        }
+        if forwarded {
+            rewrites.push((i, replacement));
+        }
+    }
+    for (i, replacement) in rewrites.into_iter().rev() {
+        items.splice(i..=i, replacement);
+    }
+    Ok(())
 }
 
 #[proc_macro_attribute]
 pub fn symbaker_module(attr: TokenStream, item: TokenStream) -> TokenStream {
     let args = parse_macro_input!(attr with Punctuated::<Meta, Token![,]>::parse_terminated);
-    let mut m = parse_macro_input!(item as ItemMod);
+    match parse_macro_input!(item as syn::Item) {
+        syn::Item::Mod(m) => expand_symbaker_module(&args, m).into(),
+        syn::Item::Impl(imp) => expand_symbaker_impl(&args, imp).into(),
+        other => syn::Error::new_spanned(
+            other,
+            "symbaker_module: expected an inline `mod x { ... }` or an `impl Type { ... }` block",
+        )
+        .to_compile_error()
+        .into(),
+    }
+}
 
-    warn_if_not_initialized();
+/// `#[symbaker_modules(a, b, ...)]` on a parent module: expands each listed
+/// inline submodule as if it carried `#[symbaker_module(...)]` with the
+/// remaining arguments, so shared rules are written once.
+#[proc_macro_attribute]
+pub fn symbaker_modules(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let args = parse_macro_input!(attr with Punctuated::<ModulesArg, Token![,]>::parse_terminated);
+    let parent = parse_macro_input!(item as ItemMod);
+    expand_symbaker_modules(args, parent).into()
+}
+
+/// Bare `#[symbaker_module]` flags; a submodule with one of these names is
+/// listed quoted instead.
+const MODULE_FLAGS: [&str; 6] = [
+    "raw",
+    "explain",
+    "no_alias",
+    "compat_alias",
+    "docs",
+    "exclude_async",
+];
+
+/// One `#[symbaker_modules]` argument: a submodule, bare (`game`) or quoted
+/// (`"game"`), or an argument for `#[symbaker_module]`.
+enum ModulesArg {
+    Module(syn::Ident),
+    Rule(Box<Meta>),
+}
+
+impl syn::parse::Parse for ModulesArg {
+    fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
+        if input.peek(syn::LitStr) {
+            let lit: syn::LitStr = input.parse()?;
+            return lit.parse().map(ModulesArg::Module).map_err(|_| {
+                syn::Error::new_spanned(&lit, "symbaker_modules: expected a submodule name")
+            });
+        }
+        match input.parse()? {
+            Meta::Path(path) => match path.get_ident() {
+                Some(ident) if MODULE_FLAGS.contains(&ident.to_string().as_str()) => {
+                    Ok(ModulesArg::Rule(Box::new(Meta::Path(path))))
+                }
+                Some(ident) => Ok(ModulesArg::Module(ident.clone())),
+                None => Err(syn::Error::new_spanned(
+                    path,
+                    "symbaker_modules: name a direct submodule of this module, e.g. `game`",
+                )),
+            },
+            rule => Ok(ModulesArg::Rule(Box::new(rule))),
+        }
+    }
+}
 
-    if let Err(e) = validate_required_config() {
-        return e.to_compile_error().into();
+fn expand_symbaker_modules(
+    args: Punctuated<ModulesArg, Token![,]>,
+    mut parent: ItemMod,
+) -> proc_macro2::TokenStream {
+    let mut names = Vec::<syn::Ident>::new();
+    let mut rules = Punctuated::<Meta, Token![,]>::new();
+    for arg in args {
+        match arg {
+            ModulesArg::Module(name) => names.push(name),
+            ModulesArg::Rule(rule) => rules.push(*rule),
+        }
+    }
+    if names.is_empty() {
+        return syn::Error::new(
+            proc_macro2::Span::call_site(),
+            "symbaker_modules: expected the submodules to prefix, e.g. `#[symbaker_modules(game, audio)]`",
+        )
+        .to_compile_error();
     }
 
-    let attr_prefix = parse_attr_prefix(&args);
-    let module_rules = match filter::parse_module_rules(&args) {
-        Ok(f) => f,
-        Err(e) => return e.to_compile_error().into(),
+    let items = match &mut parent.content {
+        Some((_, items)) => items,
+        None => {
+            return syn::Error::new_spanned(
+                &parent,
+                "symbaker_modules: must be inline `mod x { ... }`",
+            )
+            .to_compile_error();
+        }
     };
-    let (prefix, sep, source) = resolve_prefix(attr_prefix);
-    warn_on_dependency_fallback(source);
-    if let Err(e) = enforce_inherited_prefix(source) {
-        return e.to_compile_error().into();
+    let mut expanded = Vec::<syn::Ident>::new();
+    for it in items.iter_mut() {
+        let syn::Item::Mod(sub) = it else {
+            continue;
+        };
+        if !names.contains(&sub.ident) {
+            continue;
+        }
+        expanded.push(sub.ident.clone());
+        *it = syn::Item::Verbatim(expand_symbaker_module(&rules, sub.clone()));
+    }
+    let missing = names.iter().filter(|n| !expanded.contains(n)).map(|name| {
+        syn::Error::new_spanned(
+            name,
+            format!(
+                "symbaker_modules: no inline submodule named {name} in mod `{}`",
+                parent.ident
+            ),
+        )
+    });
+    if let Some(err) = missing.reduce(|mut all, e| {
+        all.combine(e);
+        all
+    }) {
+        return err.to_compile_error();
+    }
+    quote!(#parent)
+}
+
+/// Prefix, rules and export options shared by every item of one
+/// `#[symbaker_module]` expansion, whether on a `mod` or an `impl`.
+struct ModuleExport {
+    cfg: Config,
+    rules: filter::ModuleRules,
+    prefix: String,
+    sep: String,
+    source: PrefixSource,
+    compat_alias: bool,
+    docs: bool,
+    old_prefixes: Vec<String>,
+    explain: Option<Vec<String>>,
+    attr_suffix: Option<String>,
+}
+
+impl ModuleExport {
+    fn resolve(args: &Punctuated<Meta, Token![,]>, module_name: &str) -> Result<Self, syn::Error> {
+        warn_if_not_initialized();
+
+        let attr_prefix = parse_attr_prefix(args);
+        let mut rules = filter::parse_module_rules(args)?;
+        let mut cfg = load_config_checked()?;
+        if let Some(raw) = parse_attr_bool(args, "raw") {
+            cfg.raw_prefix = raw;
+        }
+        apply_attr_config(args, &mut cfg)?;
+        let (prefix, sep, suffix, source) = resolve_prefix_checked(&cfg, attr_prefix.clone())?;
+        let explain = parse_attr_bool(args, "explain").unwrap_or(false).then(|| {
+            explain_prefix(
+                &cfg,
+                attr_prefix.as_deref(),
+                (&prefix, &sep, &suffix, source),
+            )
+        });
+        let attr_suffix = rules.suffix.clone();
+        rules.suffix.get_or_insert(suffix);
+        if let Some(tpl) = &rules.template {
+            trace_emit(format!(
+                "macro=symbaker_module module={:?} template={:?} template_placeholders={:?}",
+                module_name,
+                tpl,
+                filter::used_placeholders(tpl)
+            ));
+        }
+        let no_alias = parse_attr_bool(args, "no_alias").unwrap_or(false)
+            || read_package_metadata_bool("no_alias");
+        let compat_alias =
+            compat_alias_enabled(&cfg, parse_attr_bool(args, "compat_alias"))? && !no_alias;
+        let docs = parse_attr_bool(args, "docs").or(cfg.docs).unwrap_or(true);
+        let old_prefixes = if no_alias {
+            Vec::new()
+        } else {
+            prefix_aliases_for(&cfg, &prefix)
+        };
+        Ok(Self {
+            cfg,
+            rules,
+            prefix,
+            sep,
+            source,
+            compat_alias,
+            docs,
+            old_prefixes,
+            explain,
+            attr_suffix,
+        })
+    }
+
+    fn render(&self, prefix: &str, module_name: &str, rust_name: &str) -> String {
+        render_export(&self.cfg, prefix, &self.sep, |p| {
+            self.rules
+                .render_export_name(p, &self.sep, module_name, rust_name)
+        })
+    }
+
+    /// Names one function and returns its `(alias, wrapper)` pairs. `in_impl`
+    /// makes the wrappers call `Self::name` so they can sit next to a method.
+    fn export_fn(
+        &self,
+        module_name: &str,
+        f: &mut ItemFn,
+        in_impl: bool,
+        explained: &mut Vec<String>,
+        hard_errors: &mut Vec<proc_macro2::TokenStream>,
+    ) -> Result<Vec<(String, ItemFn)>, syn::Error> {
+        let rust_name = f.sig.ident.to_string();
+        if let Some(a) = &f.sig.asyncness {
+            return Err(async_export_error(a));
+        }
+
+        let export = self.render(&self.prefix, module_name, &rust_name);
+        trace_emit(format!(
+            "macro=symbaker_module module={:?} function={:?} resolved_prefix={:?} export_name={:?}",
+            module_name, rust_name, self.prefix, export
+        ));
+        hard_errors.extend(trace_hard(
+            &f.sig.ident,
+            format!(
+                "macro=symbaker_module crate={:?} module={:?} function={:?} prefix={:?} export={:?} top_package={:?} workspace={:?} package={:?} env_prefix={:?}",
+                env_var("CARGO_PKG_NAME").ok(),
+                module_name,
+                rust_name,
+                self.prefix,
+                export,
+                top_level_package_name(),
+                read_prefix_from_workspace_metadata(),
+                read_prefix_from_package_metadata(),
+                env_var("SYMBAKER_PREFIX").ok(),
+            ),
+        ));
+        let mut aliases = Vec::<(&str, String)>::new();
+        if self.compat_alias {
+            aliases.push(("compat_alias", rust_name.clone()));
+        }
+        for old in &self.old_prefixes {
+            aliases.push(("prefix_alias", self.render(old, module_name, &rust_name)));
+        }
+        for name in std::iter::once(&export).chain(aliases.iter().map(|(_, a)| a)) {
+            check_denied(&self.cfg, name, &f.sig.ident)?;
+        }
+        let trace_ctx = format!("macro=symbaker_module module={:?}", module_name);
+        let wrappers = alias_wrappers(f, &export, &aliases, &trace_ctx, in_impl)?;
+        if self.docs {
+            push_export_docs(&mut f.attrs, &rust_name, &export, self.source, &self.sep);
+        }
+        explained.push(format!("fn `{rust_name}` -> {export:?}"));
+        push_export_name(&mut f.attrs, export);
+        Ok(wrappers)
+    }
+
+    /// The `explain` report, or `None` when `explain` was not requested.
+    fn explain_lines(&self, explained: Vec<String>) -> Option<Vec<String>> {
+        let mut lines = self.explain.clone()?;
+        if let Some(s) = &self.attr_suffix {
+            lines.push(format!(
+                "suffix attribute = {s:?} (replaces the configured suffix)"
+            ));
+        }
+        lines.push(format!(
+            "template = {:?}",
+            self.rules
+                .template
+                .as_deref()
+                .unwrap_or("{prefix}{sep}{name}{suffix}")
+        ));
+        lines.extend(explained);
+        Some(lines)
+    }
+}
+
+fn expand_symbaker_module(
+    args: &Punctuated<Meta, Token![,]>,
+    mut m: ItemMod,
+) -> proc_macro2::TokenStream {
+    if symbaker_disabled() {
+        // Marker attributes (`include_attr`, `requires_attr`,
+        // `#[symbaker(export_const)]`) only mean something to the rules; drop
+        // them so the module still compiles.
+        if let Some((_, items)) = &mut m.content {
+            let rules = filter::parse_module_rules(args).ok();
+            for item in items.iter_mut() {
+                if let Some(rules) = &rules {
+                    rules.strip_markers(item);
+                }
+                take_item_export_const(item);
+            }
+        }
+        return quote!(#m);
     }
+
     let module_name = m.ident.to_string();
+    let ctx = match ModuleExport::resolve(args, &module_name) {
+        Ok(ctx) => ctx,
+        Err(e) => return e.to_compile_error(),
+    };
+    let module_rules = &ctx.rules;
 
     let items = match &mut m.content {
         Some((_, items)) => items,
         None => {
             return syn::Error::new_spanned(&m, "symbaker_module: must be inline `mod x { ... }`")
-                .to_compile_error()
-                .into();
+                .to_compile_error();
         }
     };
 
+    if let Err(e) = forward_reexports(items, module_rules, &module_name) {
+        return e.to_compile_error();
+    }
+    let named = items.iter().filter_map(|it| match it {
+        syn::Item::Const(c) => Some(&c.ident),
+        syn::Item::Static(st) => Some(&st.ident),
+        syn::Item::Fn(f) => Some(&f.sig.ident),
+        _ => None,
+    });
+    if let Err(e) = module_rules.check_only_names(named) {
+        return e.to_compile_error();
+    }
+
+    let mut wrappers = Vec::<(String, syn::Item)>::new();
+    let mut explained = Vec::<String>::new();
+    let mut hard_errors = Vec::<proc_macro2::TokenStream>::new();
     for it in items.iter_mut() {
+        let skip = module_rules.skip_reason(&module_name, it);
+        module_rules.strip_markers(it);
+        let export_const = take_item_export_const(it);
+        if let syn::Item::Const(c) = it {
+            if export_const {
+                if !matches!(c.vis, syn::Visibility::Public(_)) || !c.generics.params.is_empty() {
+                    hard_errors.push(
+                        syn::Error::new_spanned(
+                            &c.ident,
+                            "symbaker_module: #[symbaker(export_const)] needs a non-generic `pub const`",
+                        )
+                        .to_compile_error(),
+                    );
+                    continue;
+                }
+                if skip.is_none() {
+                    trace_emit(format!(
+                        "macro=symbaker_module module={:?} const={:?} exported as static (export_const)",
+                        module_name,
+                        c.ident.to_string()
+                    ));
+                    *it = syn::Item::Static(const_to_static(c));
+                }
+            }
+        }
+        if let syn::Item::Static(st) = it {
+            let rust_name = st.ident.to_string();
+            if let Some(reason) = skip {
+                trace_emit(format!(
+                    "macro=symbaker_module module={:?} static={:?} skipped reason={}",
+                    module_name, rust_name, reason
+                ));
+                continue;
+            }
+            if !matches!(st.vis, syn::Visibility::Public(_)) {
+                trace_emit(format!(
+                    "macro=symbaker_module module={:?} static={:?} skipped: not pub",
+                    module_name, rust_name
+                ));
+                continue;
+            }
+            let export = ctx.render(&ctx.prefix, &module_name, &rust_name);
+            trace_emit(format!(
+                "macro=symbaker_module module={:?} static={:?} resolved_prefix={:?} export_name={:?}",
+                module_name, rust_name, ctx.prefix, export
+            ));
+            if let Err(e) = check_denied(&ctx.cfg, &export, &st.ident) {
+                return e.to_compile_error();
+            }
+            if ctx.docs {
+                push_export_docs(&mut st.attrs, &rust_name, &export, ctx.source, &ctx.sep);
+            }
+            explained.push(format!("static `{rust_name}` -> {export:?}"));
+            push_export_name(&mut st.attrs, export);
+            continue;
+        }
         if let syn::Item::Fn(f) = it {
-            let rust_name = f.sig.ident.to_string();
-            if !module_rules.should_prefix(&module_name, &rust_name) {
+            if let Some(reason) = skip {
+                trace_emit(format!(
+                    "macro=symbaker_module module={:?} function={:?} skipped reason={}",
+                    module_name,
+                    f.sig.ident.to_string(),
+                    reason
+                ));
                 continue;
             }
             if !f.sig.generics.params.is_empty() {
                 continue;
             }
+            match ctx.export_fn(&module_name, f, false, &mut explained, &mut hard_errors) {
+                Ok(w) => wrappers.extend(w.into_iter().map(|(a, w)| (a, syn::Item::Fn(w)))),
+                Err(e) => return e.to_compile_error(),
+            }
+        }
+    }
+    // User items keep their order; generated ones go last, by export name.
+    wrappers.sort_by(|a, b| a.0.cmp(&b.0));
+    items.extend(wrappers.into_iter().map(|(_, w)| w));
+
+    if let Some(lines) = ctx.explain_lines(explained) {
+        return explain_error(&m.ident, format!("mod `{module_name}`"), &lines);
+    }
+
+    quote!(#m #(#hard_errors)*)
+}
+
+/// `#[symbaker_module]` on an inherent `impl Type { ... }`: each associated
+/// function is named like a function in `mod Type`, so `{module}` in a
+/// template is the type's name.
+fn expand_symbaker_impl(
+    args: &Punctuated<Meta, Token![,]>,
+    mut imp: syn::ItemImpl,
+) -> proc_macro2::TokenStream {
+    if symbaker_disabled() {
+        if let Ok(rules) = filter::parse_module_rules(args) {
+            for item in imp.items.iter_mut() {
+                if let syn::ImplItem::Fn(method) = item {
+                    let mut f = method_as_item(method);
+                    rules.strip_markers(&mut f);
+                    method.attrs = item_attrs(f);
+                }
+            }
+        }
+        return quote!(#imp);
+    }
+
+    if let Some((_, path, _)) = &imp.trait_ {
+        return syn::Error::new_spanned(
+            path,
+            "symbaker_module: expected an inherent `impl Type { ... }`, not a trait impl",
+        )
+        .to_compile_error();
+    }
+    if !imp.generics.params.is_empty() {
+        return syn::Error::new_spanned(
+            &imp.generics,
+            "symbaker_module: generic impl blocks have no single symbol per function",
+        )
+        .to_compile_error();
+    }
+    let module_name = match &*imp.self_ty {
+        syn::Type::Path(tp) if tp.qself.is_none() => match tp.path.segments.last() {
+            Some(seg) => seg.ident.to_string(),
+            None => unreachable!("a type path has at least one segment"),
+        },
+        other => {
+            return syn::Error::new_spanned(
+                other,
+                "symbaker_module: the impl's self type must be a named type",
+            )
+            .to_compile_error();
+        }
+    };
+    let ctx = match ModuleExport::resolve(args, &module_name) {
+        Ok(ctx) => ctx,
+        Err(e) => return e.to_compile_error(),
+    };
 
-            let export = module_rules.render_export_name(&prefix, &sep, &module_name, &rust_name);
+    let named = imp.items.iter().filter_map(|it| match it {
+        syn::ImplItem::Fn(method) => Some(&method.sig.ident),
+        _ => None,
+    });
+    if let Err(e) = ctx.rules.check_only_names(named) {
+        return e.to_compile_error();
+    }
+
+    let mut wrappers = Vec::<(String, syn::ImplItem)>::new();
+    let mut explained = Vec::<String>::new();
+    let mut hard_errors = Vec::<proc_macro2::TokenStream>::new();
+    for item in imp.items.iter_mut() {
+        let syn::ImplItem::Fn(method) = item else {
+            continue;
+        };
+        let mut it = method_as_item(method);
+        let skip = ctx.rules.skip_reason(&module_name, &it);
+        ctx.rules.strip_markers(&mut it);
+        let syn::Item::Fn(f) = &mut it else {
+            unreachable!("method_as_item builds an `Item::Fn`");
+        };
+        if let Some(reason) = skip {
             trace_emit(format!(
-                "macro=symbaker_module module={:?} function={:?} resolved_prefix={:?} export_name={:?}",
-                module_name, rust_name, prefix, export
+                "macro=symbaker_module module={:?} function={:?} skipped reason={}",
+                module_name,
+                f.sig.ident.to_string(),
+                reason
             ));
-            if trace_hard_fail() {
-                return trace_compile_error(format!(
-                    "symbaker trace: macro=symbaker_module crate={:?} module={:?} function={:?} prefix={:?} export={:?} top_package={:?} workspace={:?} package={:?} env_prefix={:?}",
-                    std::env::var("CARGO_PKG_NAME").ok(),
-                    module_name,
-                    rust_name,
-                    prefix,
-                    export,
-                    top_level_package_name(),
-                    read_prefix_from_workspace_metadata(),
-                    read_prefix_from_package_metadata(),
-                    std::env::var("SYMBAKER_PREFIX").ok(),
-                ));
+        } else if f.sig.generics.params.is_empty() {
+            match ctx.export_fn(&module_name, f, true, &mut explained, &mut hard_errors) {
+                Ok(w) => wrappers.extend(w.into_iter().map(|(a, w)| (a, syn::parse_quote!(#w)))),
+                Err(e) => return e.to_compile_error(),
             }
-            push_export_name(f, export);
         }
+        method.attrs = item_attrs(it);
+    }
+    wrappers.sort_by(|a, b| a.0.cmp(&b.0));
+    imp.items.extend(wrappers.into_iter().map(|(_, w)| w));
+
+    if let Some(lines) = ctx.explain_lines(explained) {
+        return explain_error(&imp.self_ty, format!("impl `{module_name}`"), &lines);
+    }
+
+    quote!(#imp #(#hard_errors)*)
+}
+
+/// A method as a free `Item::Fn`, so the module rules can filter it.
+fn method_as_item(method: &syn::ImplItemFn) -> syn::Item {
+    syn::Item::Fn(ItemFn {
+        attrs: method.attrs.clone(),
+        vis: method.vis.clone(),
+        sig: method.sig.clone(),
+        block: Box::new(method.block.clone()),
+    })
+}
+
+fn item_attrs(item: syn::Item) -> Vec<syn::Attribute> {
+    match item {
+        syn::Item::Fn(f) => f.attrs,
+        _ => unreachable!("method_as_item builds an `Item::Fn`"),
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    TokenStream::from(quote!(#m))
+    fn bake(prefix: &str, sep: &str, name: &str) -> String {
+        let cfg = Config {
+            normalize_exports: Some(true),
+            ..Config::default()
+        };
+        render_export(&cfg, &sanitize(prefix), sep, |p| format!("{p}{sep}{name}"))
+    }
+
+    fn expand(args: proc_macro2::TokenStream, item: proc_macro2::TokenStream) -> String {
+        use syn::parse::Parser;
+        let args = Punctuated::<Meta, Token![,]>::parse_terminated
+            .parse2(args)
+            .unwrap();
+        let f: ItemFn = syn::parse2(item).unwrap();
+        let file: syn::File = syn::parse2(expand_symbaker(&args, f)).unwrap();
+        prettyplease::unparse(&file)
+    }
+
+    #[test]
+    fn export_docs_follow_existing_docs() {
+        let text = expand(
+            quote!(prefix = "hdr"),
+            quote! {
+                /// Runs every frame.
+                pub extern "C" fn frame_hook() {}
+            },
+        );
+        let existing = text.find("Runs every frame.").expect(&text);
+        let baked = text
+            .find("Exported as `hdr__frame_hook` (symbaker: prefix source Attr, sep `__`).")
+            .expect(&text);
+        assert!(existing < baked, "baked docs must come last:\n{text}");
+        assert!(
+            text.contains("#[doc(alias = \"hdr__frame_hook\")]"),
+            "{text}"
+        );
+    }
+
+    #[test]
+    fn export_docs_can_be_disabled() {
+        let text = expand(
+            quote!(prefix = "hdr", docs = false),
+            quote!(
+                pub extern "C" fn frame_hook() {}
+            ),
+        );
+        assert!(text.contains("hdr__frame_hook"), "{text}");
+        assert!(!text.contains("Exported as"), "{text}");
+        assert!(!text.contains("doc(alias"), "{text}");
+    }
+
+    #[test]
+    fn attribute_suffix_is_appended_to_export() {
+        let text = expand(
+            quote!(prefix = "hdr", suffix = "_x"),
+            quote!(
+                pub extern "C" fn frame_hook() {}
+            ),
+        );
+        assert!(
+            text.contains("#[export_name = \"hdr__frame_hook_x\"]"),
+            "{text}"
+        );
+    }
+
+    #[test]
+    fn attribute_template_fills_env_placeholders() {
+        let text = expand(
+            quote!(
+                prefix = "hdr",
+                template = "{prefix}{sep}{crate}_{name}{suffix}"
+            ),
+            quote!(
+                pub extern "C" fn frame_hook() {}
+            ),
+        );
+        assert!(
+            text.contains("#[export_name = \"hdr__symbaker_frame_hook\"]"),
+            "{text}"
+        );
+        let text = expand(
+            quote!(prefix = "hdr", template = "{module}_{name}"),
+            quote!(
+                pub extern "C" fn frame_hook() {}
+            ),
+        );
+        assert!(
+            text.contains("only available in `symbaker_module`"),
+            "{text}"
+        );
+    }
+
+    #[test]
+    fn never_returning_functions_are_exported_and_forwarded() {
+        let text = expand(
+            quote!(prefix = "hdr", compat_alias = true),
+            quote!(
+                pub extern "C" fn panic_handler() -> ! {
+                    loop {}
+                }
+            ),
+        );
+        assert!(
+            text.contains("#[export_name = \"hdr__panic_handler\"]"),
+            "{text}"
+        );
+        assert!(
+            text.contains("fn __symbaker_alias_panic_handler() -> !"),
+            "{text}"
+        );
+    }
+
+    #[test]
+    fn weak_alias_emits_trampoline_with_forwarding_fallback() {
+        let text = expand(
+            quote!(prefix = "hdr", alias = "ssbusync_frame_hook"),
+            quote!(
+                pub extern "C" fn frame_hook() {}
+            ),
+        );
+        assert!(text.contains(".weak ssbusync_frame_hook"), "{text}");
+        assert!(text.contains("sym frame_hook"), "{text}");
+        assert!(
+            text.contains("#[export_name = \"ssbusync_frame_hook\"]"),
+            "{text}"
+        );
+        assert!(text.contains("weak aliases are unavailable"), "{text}");
+
+        let text = expand(
+            quote!(prefix = "hdr", alias = "hdr__frame_hook"),
+            quote!(
+                pub extern "C" fn frame_hook() {}
+            ),
+        );
+        assert!(text.contains("already exported by this function"), "{text}");
+    }
+
+    /// Expansion of a module using most `symbaker_module` features, compared
+    /// with `tests/expand/module_all.expanded.rs` so changes in emission order
+    /// show up as a diff. Regenerate with `SNAPSHOT=overwrite`.
+    #[test]
+    fn module_expansion_matches_snapshot() {
+        use syn::parse::Parser;
+        let args = Punctuated::<Meta, Token![,]>::parse_terminated
+            .parse2(quote!(
+                prefix = "hdr",
+                compat_alias = true,
+                exclude_glob = "*internal*",
+                template = "{prefix}{sep}{module}_{name}{suffix}",
+                suffix = "_v1"
+            ))
+            .unwrap();
+        let m: ItemMod = syn::parse2(quote! {
+            mod plugin {
+                /// Frames since boot.
+                pub static FRAME: u32 = 0;
+
+                pub const VERSION: u32 = 3;
+
+                pub extern "C" fn zeta() {}
+
+                pub extern "C" fn alpha(x: i32) -> i32 {
+                    x
+                }
+
+                pub extern "C" fn internal_reset() {}
+
+                mod imp {
+                    pub extern "C" fn reexported() {}
+                }
+
+                pub use imp::reexported;
+
+                pub struct Handle;
+            }
+        })
+        .unwrap();
+        let file: syn::File = syn::parse2(expand_symbaker_module(&args, m)).unwrap();
+        let text = prettyplease::unparse(&file);
+
+        let path = concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/expand/module_all.expanded.rs"
+        );
+        if std::env::var("SNAPSHOT").as_deref() == Ok("overwrite") {
+            std::fs::create_dir_all(std::path::Path::new(path).parent().unwrap()).unwrap();
+            std::fs::write(path, &text).unwrap();
+        }
+        let expected = std::fs::read_to_string(path).unwrap_or_default();
+        assert!(
+            text == expected,
+            "expansion differs from {path} (rerun with SNAPSHOT=overwrite if intended):\n{text}"
+        );
+    }
+
+    #[test]
+    fn impl_aliases_forward_through_self() {
+        use syn::parse::Parser;
+        let args = Punctuated::<Meta, Token![,]>::parse_terminated
+            .parse2(quote!(prefix = "hdr", compat_alias = true))
+            .unwrap();
+        let imp: syn::ItemImpl = syn::parse2(quote! {
+            impl Plugin {
+                pub extern "C" fn on_frame(dt: f32) {}
+            }
+        })
+        .unwrap();
+        let file: syn::File = syn::parse2(expand_symbaker_impl(&args, imp)).unwrap();
+        let text = prettyplease::unparse(&file);
+        assert!(
+            text.contains("#[export_name = \"hdr__on_frame\"]"),
+            "{text}"
+        );
+        assert!(text.contains("Self::on_frame(__symbaker_arg0)"), "{text}");
+    }
+
+    #[test]
+    fn user_symbol_name_attrs_are_replaced_in_either_form() {
+        let text = expand(
+            quote!(prefix = "hdr"),
+            quote!(
+                #[unsafe(no_mangle)]
+                #[unsafe(export_name = "frame")]
+                #[no_mangle]
+                pub extern "C" fn frame_hook() {}
+            ),
+        );
+        assert!(!text.contains("no_mangle"), "{text}");
+        assert!(!text.contains("\"frame\""), "{text}");
+        assert!(
+            text.contains("#[export_name = \"hdr__frame_hook\"]"),
+            "{text}"
+        );
+
+        let attr = export_name_attr("hdr__frame_hook", true);
+        assert_eq!(
+            quote!(#attr).to_string(),
+            quote!(#[unsafe(export_name = "hdr__frame_hook")]).to_string()
+        );
+    }
+
+    #[test]
+    fn raw_prefix_is_not_sanitized() {
+        let text = expand(
+            quote!(prefix = "$hdr", raw = true),
+            quote!(
+                pub extern "C" fn frame_hook() {}
+            ),
+        );
+        assert!(
+            text.contains("#[export_name = \"$hdr__frame_hook\"]"),
+            "{text}"
+        );
+
+        let text = expand(
+            quote!(prefix = "$hdr"),
+            quote!(
+                pub extern "C" fn frame_hook() {}
+            ),
+        );
+        assert!(
+            text.contains("#[export_name = \"_hdr__frame_hook\"]"),
+            "{text}"
+        );
+    }
+
+    #[test]
+    fn denied_export_is_a_compile_error() {
+        let cfg = Config {
+            deny: Some(vec!["main".into(), "nn*".into()]),
+            ..Config::default()
+        };
+        let ident = quote::format_ident!("main");
+        let err = check_denied(&cfg, "main", &ident).unwrap_err();
+        assert!(err.to_string().contains("denied name \"main\""), "{err}");
+        let err = check_denied(&cfg, "nnMain", &ident).unwrap_err();
+        assert!(err.to_string().contains("\"nn*\""), "{err}");
+        assert!(check_denied(&cfg, "hdr__main", &ident).is_ok());
+        assert!(check_denied(&Config::default(), "main", &ident).is_ok());
+    }
+
+    #[test]
+    fn normalize_exports_table_is_stable() {
+        let cases = [
+            ("hdr", "__", "frame_hook", "hdr__frame_hook"),
+            ("hdr_", "__", "frame_hook", "hdr__frame_hook"),
+            ("hdr___", "__", "frame_hook", "hdr__frame_hook"),
+            ("hdr__", "_", "frame_hook", "hdr__frame_hook"),
+            ("hdr_", "_", "frame_hook", "hdr__frame_hook"),
+            ("hdr___", "___", "frame_hook", "hdr___frame_hook"),
+            ("hdr__", "", "frame_hook", "hdr__frame_hook"),
+            ("my mod ", "__", "f", "my_mod__f"),
+            ("my  mod", "__", "f", "my__mod__f"),
+            ("9lives_", "__", "f", "_9lives__f"),
+            ("hdr-", "__", "_private", "hdr___private"),
+            ("", "__", "f", "___f"),
+            ("___", "__", "f", "_____f"),
+            ("hdr_", "::", "f", "hdr_::f"),
+        ];
+        for (prefix, sep, name, expected) in cases {
+            assert_eq!(
+                bake(prefix, sep, name),
+                expected,
+                "prefix={prefix:?} sep={sep:?} name={name:?}"
+            );
+            let once = bake(prefix, sep, name);
+            assert_eq!(once, bake(prefix, sep, name), "unstable for {prefix:?}");
+        }
+    }
+
+    #[test]
+    fn normalize_exports_disabled_keeps_naive_concatenation() {
+        let cfg = Config::default();
+        let export = render_export(&cfg, "hdr_", "__", |p| format!("{p}__frame_hook"));
+        assert_eq!(export, "hdr___frame_hook");
+    }
 }
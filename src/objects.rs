@@ -0,0 +1,512 @@
+//! Native symbol-table readers for object files, shared libraries and
+//! archives, so `cargo symdump` needs no nm/objdump for them. Shared by
+//! `out.rs` and the integration tests (which pull this file in with `#[path]`).
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+
+pub fn read_u16_le(bytes: &[u8], off: usize) -> Option<u16> {
+    let end = off.checked_add(2)?;
+    let chunk = bytes.get(off..end)?;
+    Some(u16::from_le_bytes([chunk[0], chunk[1]]))
+}
+
+pub fn read_u32_le(bytes: &[u8], off: usize) -> Option<u32> {
+    let end = off.checked_add(4)?;
+    let chunk = bytes.get(off..end)?;
+    Some(u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+}
+
+pub fn read_u64_le(bytes: &[u8], off: usize) -> Option<u64> {
+    let end = off.checked_add(8)?;
+    let chunk = bytes.get(off..end)?;
+    Some(u64::from_le_bytes([
+        chunk[0], chunk[1], chunk[2], chunk[3], chunk[4], chunk[5], chunk[6], chunk[7],
+    ]))
+}
+
+/// `base + off` for offsets read from the file, `None` on overflow so a
+/// corrupt header is rejected instead of wrapping around.
+fn at(base: usize, off: usize) -> Option<usize> {
+    base.checked_add(off)
+}
+
+/// The NUL-terminated UTF-8 string at `off`, read no further than `max_end`;
+/// `None` when empty or out of bounds.
+pub fn cstr_at(bytes: &[u8], off: usize, max_end: usize) -> Option<String> {
+    if off >= max_end || off >= bytes.len() {
+        return None;
+    }
+    let mut end = off;
+    while end < max_end && end < bytes.len() {
+        if bytes[end] == 0 {
+            break;
+        }
+        end += 1;
+    }
+    if end <= off {
+        return None;
+    }
+    std::str::from_utf8(&bytes[off..end])
+        .ok()
+        .map(|s| s.to_string())
+}
+
+/// A format [`defined_symbols`] reads, told apart by its magic.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ObjectFormat {
+    Elf,
+    Pe,
+    Coff,
+    MachO,
+    Archive,
+}
+
+impl ObjectFormat {
+    pub fn name(self) -> &'static str {
+        match self {
+            ObjectFormat::Elf => "ELF",
+            ObjectFormat::Pe => "PE",
+            ObjectFormat::Coff => "COFF",
+            ObjectFormat::MachO => "Mach-O",
+            ObjectFormat::Archive => "ar",
+        }
+    }
+}
+
+const ELF_MAGIC: &[u8] = b"\x7fELF";
+const AR_MAGIC: &[u8] = b"!<arch>\n";
+/// `MH_MAGIC` and `MH_MAGIC_64`; only little-endian Mach-O is read.
+const MACHO_MAGICS: [u32; 2] = [0xfeed_face, 0xfeed_facf];
+/// COFF machine types of the objects rustc emits for Windows targets: i386,
+/// ARMv7, x86_64 and AArch64.
+const COFF_MACHINES: [u16; 4] = [0x14c, 0x1c4, 0x8664, 0xaa64];
+/// The class id of `ANON_OBJECT_HEADER_BIGOBJ`, the `/bigobj` COFF header.
+const BIGOBJ_CLASS_ID: [u8; 16] = [
+    0xc7, 0xa1, 0xba, 0xd1, 0xee, 0xba, 0xa9, 0x4b, 0xaf, 0x20, 0xfa, 0xf6, 0x6a, 0xa4, 0xdc, 0xb8,
+];
+
+fn is_bigobj(data: &[u8]) -> bool {
+    read_u16_le(data, 0) == Some(0)
+        && read_u16_le(data, 2) == Some(0xffff)
+        && read_u16_le(data, 4).is_some_and(|v| v >= 2)
+        && data.get(12..28) == Some(&BIGOBJ_CLASS_ID[..])
+}
+
+pub fn object_format(data: &[u8]) -> Option<ObjectFormat> {
+    if data.starts_with(ELF_MAGIC) {
+        return Some(ObjectFormat::Elf);
+    }
+    if data.starts_with(AR_MAGIC) {
+        return Some(ObjectFormat::Archive);
+    }
+    if data.starts_with(b"MZ") {
+        let pe = read_u32_le(data, 0x3c)? as usize;
+        return (data.get(pe..pe.checked_add(4)?)? == b"PE\0\0").then_some(ObjectFormat::Pe);
+    }
+    if read_u32_le(data, 0).is_some_and(|m| MACHO_MAGICS.contains(&m)) {
+        return Some(ObjectFormat::MachO);
+    }
+    if is_bigobj(data) || read_u16_le(data, 0).is_some_and(|m| COFF_MACHINES.contains(&m)) {
+        return Some(ObjectFormat::Coff);
+    }
+    None
+}
+
+/// Defined global symbols, read natively: the exports of an ELF shared
+/// object (`.dynsym`) or PE image (export table), the `.symtab` globals of an
+/// ELF object, the external definitions of a COFF or Mach-O object, and all
+/// of those over the objects in an archive (an `.rlib` or `.lib`). Names are
+/// deduplicated in file order. `None` when no reader understands `data`.
+pub fn defined_symbols(data: &[u8]) -> Option<(ObjectFormat, Vec<String>)> {
+    let format = object_format(data)?;
+    let names = match format {
+        ObjectFormat::Elf => elf_symbols(data)?,
+        ObjectFormat::Pe => pe_exports(data)?,
+        ObjectFormat::Coff => coff_symbols(data)?,
+        ObjectFormat::MachO => macho_symbols(data)?,
+        ObjectFormat::Archive => archive_members(data)?
+            .into_iter()
+            // `lib.rmeta`, import stubs and nested archives are skipped.
+            .filter_map(|(_, member)| defined_symbols(member))
+            .filter(|(format, _)| *format != ObjectFormat::Archive)
+            .flat_map(|(_, names)| names)
+            .collect(),
+    };
+    let mut seen = HashSet::<String>::new();
+    let names = names
+        .into_iter()
+        .filter(|n| seen.insert(n.clone()))
+        .collect();
+    Some((format, names))
+}
+
+/// [`defined_symbols`] of the file at `path`.
+pub fn read_defined_symbols(path: &Path) -> Result<Option<(ObjectFormat, Vec<String>)>, String> {
+    let data = fs::read(path).map_err(|e| format!("read {}: {e}", path.display()))?;
+    Ok(defined_symbols(&data))
+}
+
+/// Whether [`defined_symbols`] recognizes the file at `path`.
+pub fn is_native_readable(path: &Path) -> bool {
+    fs::read(path).is_ok_and(|data| object_format(&data).is_some())
+}
+
+/// The exported (for libraries) or defined global (for objects and
+/// archives) symbols of `path`; an error for formats no native reader knows.
+pub fn read_exports(path: &Path) -> Result<Vec<String>, String> {
+    read_defined_symbols(path)?
+        .map(|(_, names)| names)
+        .ok_or_else(|| {
+            format!(
+                "{}: not an ELF, PE, COFF, Mach-O or ar file",
+                path.display()
+            )
+        })
+}
+
+const ET_REL: u16 = 1;
+const SHT_SYMTAB: u32 = 2;
+const SHT_DYNSYM: u32 = 11;
+/// `STB_GLOBAL`, `STB_WEAK` and `STB_GNU_UNIQUE`.
+const GLOBAL_BINDS: [u8; 3] = [1, 2, 10];
+
+/// Defined GLOBAL/WEAK symbols of a little-endian ELF file: `.dynsym` for
+/// executables and shared objects (what the loader resolves; `.symtab` when
+/// there is none), `.symtab` for relocatable objects.
+fn elf_symbols(data: &[u8]) -> Option<Vec<String>> {
+    let wide = match data.get(4)? {
+        1 => false,
+        2 => true,
+        _ => return None,
+    };
+    if *data.get(5)? != 1 {
+        return None;
+    }
+    let e_type = read_u16_le(data, 16)?;
+    let (shoff, shentsize, shnum) = if wide {
+        (
+            read_u64_le(data, 0x28)? as usize,
+            read_u16_le(data, 0x3a)? as usize,
+            read_u16_le(data, 0x3c)? as usize,
+        )
+    } else {
+        (
+            read_u32_le(data, 0x20)? as usize,
+            read_u16_le(data, 0x2e)? as usize,
+            read_u16_le(data, 0x30)? as usize,
+        )
+    };
+    // (sh_type, sh_offset, sh_size, sh_link) of every section header.
+    let sections = (0..shnum)
+        .map(|i| {
+            let base = at(shoff, i.checked_mul(shentsize)?)?;
+            let sh_type = read_u32_le(data, at(base, 4)?)?;
+            Some(if wide {
+                (
+                    sh_type,
+                    read_u64_le(data, at(base, 0x18)?)? as usize,
+                    read_u64_le(data, at(base, 0x20)?)? as usize,
+                    read_u32_le(data, at(base, 0x28)?)? as usize,
+                )
+            } else {
+                (
+                    sh_type,
+                    read_u32_le(data, at(base, 0x10)?)? as usize,
+                    read_u32_le(data, at(base, 0x14)?)? as usize,
+                    read_u32_le(data, at(base, 0x18)?)? as usize,
+                )
+            })
+        })
+        .collect::<Option<Vec<_>>>()?;
+    let wanted: &[u32] = if e_type == ET_REL {
+        &[SHT_SYMTAB]
+    } else {
+        &[SHT_DYNSYM, SHT_SYMTAB]
+    };
+    let Some(&(_, symoff, symsize, link)) = wanted
+        .iter()
+        .find_map(|t| sections.iter().find(|s| s.0 == *t))
+    else {
+        return Some(Vec::new());
+    };
+    let &(_, stroff, strsize, _) = sections.get(link)?;
+    let strend = stroff.checked_add(strsize)?;
+    let entsize = if wide { 24 } else { 16 };
+    let mut names = Vec::new();
+    // Entry 0 is the reserved null symbol.
+    for i in 1..symsize / entsize {
+        let base = at(symoff, i.checked_mul(entsize)?)?;
+        let name = read_u32_le(data, base)? as usize;
+        let (info, shndx) = if wide {
+            (*data.get(at(base, 4)?)?, read_u16_le(data, at(base, 6)?)?)
+        } else {
+            (*data.get(at(base, 12)?)?, read_u16_le(data, at(base, 14)?)?)
+        };
+        if shndx == 0 || !GLOBAL_BINDS.contains(&(info >> 4)) {
+            continue;
+        }
+        if let Some(name) = cstr_at(data, stroff.saturating_add(name), strend) {
+            names.push(name);
+        }
+    }
+    Some(names)
+}
+
+/// The names in a PE image's export table, as `objdump -p` lists them.
+fn pe_exports(data: &[u8]) -> Option<Vec<String>> {
+    let coff = (read_u32_le(data, 0x3c)? as usize).checked_add(4)?;
+    let nsections = read_u16_le(data, at(coff, 2)?)? as usize;
+    let optional = at(coff, 20)?;
+    let sections = at(optional, read_u16_le(data, at(coff, 16)?)? as usize)?;
+    // Data directory 0 is the export table.
+    let directory = match read_u16_le(data, optional)? {
+        0x10b => at(optional, 96)?,
+        0x20b => at(optional, 112)?,
+        _ => return None,
+    };
+    if read_u32_le(data, at(directory, 4)?)? == 0 {
+        return Some(Vec::new());
+    }
+    let offset_of = |rva: u32| -> Option<usize> {
+        (0..nsections).find_map(|i| {
+            let header = at(sections, i.checked_mul(40)?)?;
+            let span = read_u32_le(data, at(header, 8)?)?.max(read_u32_le(data, at(header, 16)?)?);
+            let start = read_u32_le(data, at(header, 12)?)?;
+            let raw = read_u32_le(data, at(header, 20)?)? as usize;
+            let delta = rva.checked_sub(start).filter(|d| *d < span)?;
+            raw.checked_add(delta as usize)
+        })
+    };
+    let exports = offset_of(read_u32_le(data, directory)?)?;
+    let count = read_u32_le(data, at(exports, 24)?)? as usize;
+    let names = offset_of(read_u32_le(data, at(exports, 32)?)?)?;
+    Some(
+        (0..count)
+            .filter_map(|i| {
+                let name = offset_of(read_u32_le(data, at(names, i.checked_mul(4)?)?)?)?;
+                cstr_at(data, name, data.len())
+            })
+            .collect(),
+    )
+}
+
+const IMAGE_SYM_CLASS_EXTERNAL: u8 = 2;
+
+/// Defined external symbols of a COFF object, regular or `/bigobj`.
+fn coff_symbols(data: &[u8]) -> Option<Vec<String>> {
+    let bigobj = is_bigobj(data);
+    let (symoff, count, entsize) = if bigobj {
+        (read_u32_le(data, 48)?, read_u32_le(data, 52)?, 20)
+    } else {
+        (read_u32_le(data, 8)?, read_u32_le(data, 12)?, 18)
+    };
+    let (symoff, count) = (symoff as usize, count as usize);
+    // The string table follows the symbols, led by its own size.
+    let strtab = symoff.checked_add(count.checked_mul(entsize)?)?;
+    let strend = strtab.saturating_add(read_u32_le(data, strtab).unwrap_or(0) as usize);
+    let mut names = Vec::new();
+    let mut i = 0usize;
+    while i < count {
+        let base = at(symoff, i.checked_mul(entsize)?)?;
+        let (section, class, aux) = if bigobj {
+            (
+                read_u32_le(data, at(base, 12)?)? as i32,
+                *data.get(at(base, 18)?)?,
+                *data.get(at(base, 19)?)?,
+            )
+        } else {
+            (
+                read_u16_le(data, at(base, 12)?)? as i16 as i32,
+                *data.get(at(base, 16)?)?,
+                *data.get(at(base, 17)?)?,
+            )
+        };
+        i += 1 + aux as usize;
+        if section <= 0 || class != IMAGE_SYM_CLASS_EXTERNAL {
+            continue;
+        }
+        let short = data.get(base..at(base, 8)?)?;
+        let name = if short[..4] == [0; 4] {
+            cstr_at(data, at(strtab, read_u32_le(short, 4)? as usize)?, strend)
+        } else {
+            let end = short.iter().position(|b| *b == 0).unwrap_or(8);
+            std::str::from_utf8(&short[..end]).ok().map(str::to_string)
+        };
+        names.extend(name);
+    }
+    Some(names)
+}
+
+const LC_SYMTAB: u32 = 2;
+const N_STAB: u8 = 0xe0;
+const N_TYPE: u8 = 0x0e;
+const N_SECT: u8 = 0x0e;
+const N_EXT: u8 = 0x01;
+
+/// Defined external symbols of a little-endian Mach-O file, keeping the
+/// platform's leading `_` as nm prints it.
+fn macho_symbols(data: &[u8]) -> Option<Vec<String>> {
+    let wide = read_u32_le(data, 0)? == 0xfeed_facf;
+    let ncmds = read_u32_le(data, 16)? as usize;
+    let mut cmd = if wide { 32 } else { 28 };
+    for _ in 0..ncmds {
+        let size = read_u32_le(data, at(cmd, 4)?)? as usize;
+        if read_u32_le(data, cmd)? != LC_SYMTAB {
+            if size == 0 {
+                return None;
+            }
+            cmd = at(cmd, size)?;
+            continue;
+        }
+        let symoff = read_u32_le(data, at(cmd, 8)?)? as usize;
+        let nsyms = read_u32_le(data, at(cmd, 12)?)? as usize;
+        let stroff = read_u32_le(data, at(cmd, 16)?)? as usize;
+        let strend = stroff.checked_add(read_u32_le(data, at(cmd, 20)?)? as usize)?;
+        let entsize = if wide { 16 } else { 12 };
+        let mut names = Vec::new();
+        for i in 0..nsyms {
+            let base = at(symoff, i.checked_mul(entsize)?)?;
+            let strx = read_u32_le(data, base)? as usize;
+            let n_type = *data.get(at(base, 4)?)?;
+            if n_type & N_STAB != 0 || n_type & N_EXT == 0 || n_type & N_TYPE != N_SECT {
+                continue;
+            }
+            names.extend(cstr_at(data, stroff.saturating_add(strx), strend));
+        }
+        return Some(names);
+    }
+    Some(Vec::new())
+}
+
+/// The members of an `ar` archive (GNU, BSD or the MSVC `lib` flavour) as
+/// `(name, contents)`, without its symbol indexes and long-name table.
+pub fn archive_members(data: &[u8]) -> Option<Vec<(String, &[u8])>> {
+    let mut members = Vec::new();
+    let mut long_names: &[u8] = &[];
+    let mut off = AR_MAGIC.len();
+    while at(off, 60).is_some_and(|end| end <= data.len()) {
+        let header = &data[off..off + 60];
+        if &header[58..60] != b"`\n" {
+            return None;
+        }
+        let raw_name = String::from_utf8_lossy(&header[..16])
+            .trim_end()
+            .to_string();
+        let size: usize = String::from_utf8_lossy(&header[48..58])
+            .trim()
+            .parse()
+            .ok()?;
+        let start = off + 60;
+        let end = at(start, size)?;
+        let mut body = data.get(start..end)?;
+        off = at(end, size & 1)?;
+        if raw_name == "//" {
+            long_names = body;
+            continue;
+        }
+        let name = if let Some(len) = raw_name.strip_prefix("#1/") {
+            // BSD: the name leads the member's data.
+            let (name, rest) = body.split_at(len.parse::<usize>().ok()?.min(body.len()));
+            body = rest;
+            String::from_utf8_lossy(name)
+                .trim_end_matches('\0')
+                .to_string()
+        } else if let Some(index) = raw_name
+            .strip_prefix('/')
+            .and_then(|i| i.parse::<usize>().ok())
+        {
+            let tail = long_names.get(index..)?;
+            let end = tail
+                .iter()
+                .position(|b| *b == b'\n' || *b == 0)
+                .unwrap_or(tail.len());
+            String::from_utf8_lossy(&tail[..end])
+                .trim_end_matches('/')
+                .to_string()
+        } else {
+            raw_name.clone()
+        };
+        if matches!(raw_name.as_str(), "/" | "/SYM64/") || name.starts_with("__.SYMDEF") {
+            continue;
+        }
+        members.push((name.trim_end_matches('/').to_string(), body));
+    }
+    Some(members)
+}
+
+/// `path::to::item` for a legacy-mangled Rust symbol (`_ZN...E`, with one
+/// more `_` on Mach-O) without its trailing `h<hash>`; `None` for anything
+/// else, e.g. v0 (`_R...`) or C names.
+pub fn demangle_legacy(symbol: &str) -> Option<String> {
+    let mut rest = symbol
+        .strip_prefix("__ZN")
+        .or_else(|| symbol.strip_prefix("_ZN"))?;
+    let mut parts = Vec::<&str>::new();
+    while !rest.starts_with('E') {
+        let digits = rest.bytes().take_while(u8::is_ascii_digit).count();
+        let len: usize = rest[..digits].parse().ok()?;
+        let end = at(digits, len)?;
+        parts.push(rest.get(digits..end)?);
+        rest = &rest[end..];
+    }
+    let is_hash = |p: &str| {
+        p.len() == 17 && p.starts_with('h') && p[1..].bytes().all(|b| b.is_ascii_hexdigit())
+    };
+    if parts.last().is_some_and(|p| is_hash(p)) {
+        parts.pop();
+    }
+    Some(
+        parts
+            .into_iter()
+            .map(unescape_legacy)
+            .collect::<Vec<_>>()
+            .join("::"),
+    )
+}
+
+/// Undoes legacy mangling's escapes: `$LT$`-style codes, `$u7e$` code
+/// points, `..` for `::` and the `_` that guards a leading `$`.
+fn unescape_legacy(ident: &str) -> String {
+    let mut rest = if ident.starts_with("_$") {
+        &ident[1..]
+    } else {
+        ident
+    };
+    let mut out = String::new();
+    while let Some(ch) = rest.chars().next() {
+        if let Some(tail) = rest.strip_prefix("..") {
+            out.push_str("::");
+            rest = tail;
+            continue;
+        }
+        if ch == '$' {
+            if let Some(end) = rest[1..].find('$') {
+                let code = &rest[1..1 + end];
+                let decoded = match code {
+                    "SP" => Some('@'),
+                    "BP" => Some('*'),
+                    "RF" => Some('&'),
+                    "LT" => Some('<'),
+                    "GT" => Some('>'),
+                    "LP" => Some('('),
+                    "RP" => Some(')'),
+                    "C" => Some(','),
+                    _ => code
+                        .strip_prefix('u')
+                        .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+                        .and_then(char::from_u32),
+                };
+                if let Some(decoded) = decoded {
+                    out.push(decoded);
+                    rest = &rest[end + 2..];
+                    continue;
+                }
+            }
+        }
+        out.push(ch);
+        rest = &rest[ch.len_utf8()..];
+    }
+    out
+}
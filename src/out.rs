@@ -1,8 +1,11 @@
+use crate::objects::{self, cstr_at, read_u16_le, read_u32_le, read_u64_le};
 use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
 use std::ffi::OsString;
 use std::fs;
 use std::path::{Path, PathBuf};
 use std::process::Command;
+use std::sync::{Mutex, OnceLock};
 
 const DT_NULL: u64 = 0;
 const DT_STRTAB: u64 = 5;
@@ -29,44 +32,419 @@ pub fn manifest_path_from_args(args: &[OsString]) -> Option<PathBuf> {
     find_flag_value(args, "--manifest-path")
 }
 
-pub fn discover_top_package_name(args: &[OsString]) -> Option<String> {
-    let mut cmd = Command::new("cargo");
-    cmd.args(["metadata", "--format-version", "1", "--no-deps"]);
+/// Values of every `-p`/`--package` flag in `args`.
+fn package_flags(args: &[OsString]) -> Vec<String> {
+    let mut out = Vec::new();
+    let mut i = 0usize;
+    while i < args.len() {
+        let cur = args[i].to_string_lossy();
+        if (cur == "-p" || cur == "--package") && i + 1 < args.len() {
+            out.push(args[i + 1].to_string_lossy().to_string());
+            i += 2;
+            continue;
+        }
+        if let Some(v) = cur.strip_prefix("--package=") {
+            out.push(v.to_string());
+        }
+        i += 1;
+    }
+    out
+}
+
+/// `--features`/`-F` (also joined, `-Ffoo`)/`--all-features`/
+/// `--no-default-features` from the cargo args, so metadata resolves the same
+/// feature set as the build.
+pub fn feature_args(args: &[OsString]) -> Vec<OsString> {
+    let mut out = Vec::new();
+    let mut i = 0usize;
+    while i < args.len() {
+        let cur = args[i].to_string_lossy();
+        if (cur == "--features" || cur == "-F") && i + 1 < args.len() {
+            out.push(OsString::from("--features"));
+            out.push(args[i + 1].clone());
+            i += 2;
+            continue;
+        }
+        if let Some(list) = cur.strip_prefix("-F").filter(|l| !l.is_empty()) {
+            out.push(OsString::from("--features"));
+            out.push(OsString::from(list.strip_prefix('=').unwrap_or(list)));
+            i += 1;
+            continue;
+        }
+        if cur.starts_with("--features=")
+            || cur == "--all-features"
+            || cur == "--no-default-features"
+        {
+            out.push(args[i].clone());
+        }
+        i += 1;
+    }
+    out
+}
+
+/// `--offline`, `--frozen` and `--locked` from the cargo args: `cargo
+/// metadata` may touch the network or `Cargo.lock` only when the build may.
+pub fn lockfile_args(args: &[OsString]) -> Vec<OsString> {
+    args.iter()
+        .filter(|a| matches!(a.to_str(), Some("--offline" | "--frozen" | "--locked")))
+        .cloned()
+        .collect()
+}
+
+/// Whether cargo runs without the network for `args`: `--offline`, `--frozen`
+/// or `CARGO_NET_OFFLINE=true`.
+fn offline_requested(args: &[OsString]) -> bool {
+    args.iter().any(|a| a == "--offline" || a == "--frozen")
+        || std::env::var("CARGO_NET_OFFLINE").is_ok_and(|v| v.trim() == "true")
+}
+
+/// A failed `cargo metadata`. `offline` means it failed in offline mode,
+/// which usually points at a dependency missing from the registry cache
+/// rather than a broken manifest.
+#[derive(Clone, Debug)]
+pub struct MetadataError {
+    pub offline: bool,
+    message: String,
+}
+
+impl std::fmt::Display for MetadataError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.offline {
+            write!(
+                f,
+                "cargo metadata failed in offline mode; a dependency is likely missing from the local registry cache (build once without --offline/--frozen to fetch it): {}",
+                self.message
+            )
+        } else {
+            f.write_str(&self.message)
+        }
+    }
+}
+
+type MetadataCache = HashMap<Vec<OsString>, Result<Value, MetadataError>>;
+
+/// `cargo metadata` results by command line, so one symdump invocation runs
+/// each query once.
+static METADATA_CACHE: OnceLock<Mutex<MetadataCache>> = OnceLock::new();
+
+/// `cargo metadata` as the build `args` would see it: the same
+/// `--manifest-path`, `--offline`/`--frozen`/`--locked` and, unless
+/// `no_deps`, features. Cached per command line.
+pub fn cargo_metadata(args: &[OsString], no_deps: bool) -> Result<Value, MetadataError> {
+    let mut argv: Vec<OsString> = ["metadata", "--format-version", "1"]
+        .map(OsString::from)
+        .to_vec();
+    if no_deps {
+        argv.push(OsString::from("--no-deps"));
+    } else {
+        argv.extend(feature_args(args));
+    }
+    argv.extend(lockfile_args(args));
     if let Some(manifest) = manifest_path_from_args(args) {
-        cmd.arg("--manifest-path");
-        cmd.arg(manifest);
+        argv.push(OsString::from("--manifest-path"));
+        argv.push(manifest.into_os_string());
     }
-    let out = cmd.output().ok()?;
-    if !out.status.success() {
-        return None;
+    let cache = METADATA_CACHE.get_or_init(Default::default);
+    if let Some(hit) = cache.lock().unwrap_or_else(|e| e.into_inner()).get(&argv) {
+        return hit.clone();
     }
+    let failed = |message: String, offline: bool| MetadataError { offline, message };
+    let result = match Command::new("cargo").args(&argv).output() {
+        Err(e) => Err(failed(format!("cargo metadata: {e}"), false)),
+        Ok(out) if !out.status.success() => {
+            let stderr = String::from_utf8_lossy(&out.stderr);
+            Err(failed(
+                format!(
+                    "cargo metadata exited with {}: {}",
+                    out.status,
+                    stderr.trim()
+                ),
+                offline_requested(args) && stderr.contains("offline"),
+            ))
+        }
+        Ok(out) => serde_json::from_slice(&out.stdout)
+            .map_err(|e| failed(format!("parse metadata json: {e}"), false)),
+    };
+    cache
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .insert(argv, result.clone());
+    result
+}
 
-    let parsed: Value = serde_json::from_slice(&out.stdout).ok()?;
-    let root_id = parsed
-        .get("resolve")
-        .and_then(|r| r.get("root"))
-        .and_then(|v| v.as_str())
-        .map(|s| s.to_string())
-        .or_else(|| {
-            parsed
-                .get("workspace_default_members")
-                .and_then(|v| v.as_array())
-                .and_then(|arr| arr.first())
-                .and_then(|v| v.as_str())
-                .map(|s| s.to_string())
-        })?;
+fn workspace_metadata(args: &[OsString]) -> Option<Value> {
+    cargo_metadata(args, true).ok()
+}
 
-    parsed
+fn package_name_by_id<'a>(metadata: &'a Value, id: &str) -> Option<&'a str> {
+    metadata
         .get("packages")
         .and_then(|v| v.as_array())?
         .iter()
-        .find(|p| p.get("id").and_then(|v| v.as_str()) == Some(root_id.as_str()))
+        .find(|p| p.get("id").and_then(|v| v.as_str()) == Some(id))
         .and_then(|p| p.get("name"))
         .and_then(|v| v.as_str())
-        .map(|s| s.to_string())
 }
 
-pub fn all_nros(target_dir: &Path, profile: Option<&str>) -> Result<Vec<PathBuf>, String> {
+/// The top package named by `cargo metadata`: the resolve root, else the only
+/// default member. Several default members are `Err` with their names, since
+/// picking one would depend on cargo's member order.
+fn top_package_from_metadata(metadata: &Value) -> Result<Option<String>, Vec<String>> {
+    if let Some(root) = metadata
+        .get("resolve")
+        .and_then(|r| r.get("root"))
+        .and_then(|v| v.as_str())
+    {
+        return Ok(package_name_by_id(metadata, root).map(|s| s.to_string()));
+    }
+    let members = metadata
+        .get("workspace_default_members")
+        .and_then(|v| v.as_array())
+        .map(|arr| arr.iter().filter_map(|v| v.as_str()).collect::<Vec<_>>())
+        .unwrap_or_default();
+    match members.as_slice() {
+        [] => Ok(None),
+        [only] => Ok(package_name_by_id(metadata, only).map(|s| s.to_string())),
+        many => Err(many
+            .iter()
+            .map(|id| package_name_by_id(metadata, id).unwrap_or(id).to_string())
+            .collect()),
+    }
+}
+
+/// [`top_package_from_metadata`] without cargo, for when `cargo metadata`
+/// fails: the manifest's own `[package]`, else the only one of its
+/// workspace's `default-members` (or `members`). A trailing `/*` member glob
+/// takes every directory below it holding a `Cargo.toml`.
+fn top_package_from_manifest(manifest: &Path) -> Result<Option<String>, Vec<String>> {
+    let read = |path: &Path| {
+        fs::read_to_string(path)
+            .ok()
+            .and_then(|text| text.parse::<toml::Table>().ok())
+    };
+    let package_name = |doc: &toml::Table| {
+        doc.get("package")?
+            .get("name")?
+            .as_str()
+            .map(str::to_string)
+    };
+    let Some(doc) = read(manifest) else {
+        return Ok(None);
+    };
+    if let Some(name) = package_name(&doc) {
+        return Ok(Some(name));
+    }
+    let root = manifest.parent().unwrap_or(Path::new("."));
+    let workspace = doc.get("workspace");
+    let members = workspace
+        .and_then(|w| w.get("default-members"))
+        .or_else(|| workspace.and_then(|w| w.get("members")))
+        .and_then(|v| v.as_array())
+        .into_iter()
+        .flatten()
+        .filter_map(|v| v.as_str());
+    let mut dirs = Vec::<PathBuf>::new();
+    for member in members {
+        let Some(parent) = member.strip_suffix("/*") else {
+            dirs.push(root.join(member));
+            continue;
+        };
+        let mut found: Vec<PathBuf> = fs::read_dir(root.join(parent))
+            .into_iter()
+            .flatten()
+            .flatten()
+            .map(|e| e.path())
+            .filter(|p| p.join("Cargo.toml").is_file())
+            .collect();
+        found.sort();
+        dirs.extend(found);
+    }
+    let mut names: Vec<String> = dirs
+        .iter()
+        .filter_map(|dir| read(&dir.join("Cargo.toml")))
+        .filter_map(|doc| package_name(&doc))
+        .collect();
+    names.sort();
+    names.dedup();
+    match names.len() {
+        0 => Ok(None),
+        1 => Ok(names.pop()),
+        _ => Err(names),
+    }
+}
+
+/// The manifest `cargo metadata` would read for `args`: `--manifest-path`,
+/// else the nearest `Cargo.toml` from the current directory up.
+fn manifest_for_args(args: &[OsString]) -> Option<PathBuf> {
+    if let Some(manifest) = manifest_path_from_args(args) {
+        return Some(manifest);
+    }
+    let mut dir = std::env::current_dir().ok()?;
+    loop {
+        let manifest = dir.join("Cargo.toml");
+        if manifest.is_file() {
+            return Some(manifest);
+        }
+        if !dir.pop() {
+            return None;
+        }
+    }
+}
+
+/// The top package for `args`: a single `-p`/`--package`, else what
+/// `cargo metadata` names (shared with the dependency tree, so cargo runs
+/// once). When metadata fails, e.g. `--offline` with an incomplete registry
+/// cache, the workspace manifest is read directly instead.
+pub fn discover_top_package_name(args: &[OsString]) -> Option<String> {
+    if let [only] = package_flags(args).as_slice() {
+        return Some(only.clone());
+    }
+    let found = match cargo_metadata(args, false) {
+        Ok(metadata) => top_package_from_metadata(&metadata),
+        Err(e) => {
+            let manifest = manifest_for_args(args)?;
+            eprintln!(
+                "warning: {e}; reading the top package from {} instead",
+                manifest.display()
+            );
+            top_package_from_manifest(&manifest)
+        }
+    };
+    match found {
+        Ok(name) => name,
+        Err(candidates) => {
+            eprintln!(
+                "warning: several workspace default members ({}); not guessing a top package. Pass --package <name> or --top-package <name>, or set SYMBAKER_TOP_PACKAGE",
+                candidates.join(", ")
+            );
+            None
+        }
+    }
+}
+
+/// Names of the workspace members `cargo metadata` reports for `args`.
+pub fn workspace_package_names(args: &[OsString]) -> Result<Vec<String>, String> {
+    let metadata = cargo_metadata(args, true).map_err(|e| e.to_string())?;
+    Ok(metadata
+        .get("packages")
+        .and_then(|v| v.as_array())
+        .map(|arr| {
+            arr.iter()
+                .filter_map(|p| p.get("name").and_then(|v| v.as_str()))
+                .map(|s| s.to_string())
+                .collect()
+        })
+        .unwrap_or_default())
+}
+
+/// File names (`-` as `_`) of the `cdylib`/`dylib` targets of the workspace
+/// members, or only of the `-p`/`--package` ones when given.
+pub fn workspace_dylib_names(args: &[OsString]) -> Vec<String> {
+    let Some(metadata) = workspace_metadata(args) else {
+        return Vec::new();
+    };
+    let selected = package_flags(args);
+    let mut out = Vec::new();
+    for pkg in metadata
+        .get("packages")
+        .and_then(|v| v.as_array())
+        .into_iter()
+        .flatten()
+    {
+        let name = pkg.get("name").and_then(|v| v.as_str()).unwrap_or_default();
+        if !selected.is_empty() && !selected.iter().any(|s| s == name) {
+            continue;
+        }
+        for target in pkg
+            .get("targets")
+            .and_then(|v| v.as_array())
+            .into_iter()
+            .flatten()
+        {
+            let dylib = target
+                .get("kind")
+                .and_then(|v| v.as_array())
+                .into_iter()
+                .flatten()
+                .any(|k| matches!(k.as_str(), Some("cdylib") | Some("dylib")));
+            if let Some(t) = target
+                .get("name")
+                .and_then(|v| v.as_str())
+                .filter(|_| dylib)
+            {
+                out.push(t.replace('-', "_"));
+            }
+        }
+    }
+    out
+}
+
+/// Dynamic libraries (or `.nro`s) named after one of `lib_names` that were
+/// written under `target_dir` at or after `since`. A `deps/` copy is dropped
+/// when the same file was also uplifted into the profile directory.
+pub fn fresh_dylibs(
+    target_dir: &Path,
+    lib_names: &[String],
+    since: std::time::SystemTime,
+) -> Vec<PathBuf> {
+    let mut found = Vec::<PathBuf>::new();
+    let mut stack = vec![target_dir.to_path_buf()];
+    while let Some(dir) = stack.pop() {
+        let Ok(entries) = fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let Ok(meta) = entry.metadata() else {
+                continue;
+            };
+            if meta.is_dir() {
+                let skip = matches!(
+                    path.file_name().and_then(|n| n.to_str()),
+                    Some("build") | Some("incremental") | Some(".fingerprint")
+                );
+                if !skip {
+                    stack.push(path);
+                }
+                continue;
+            }
+            let ext = path.extension().and_then(|s| s.to_str());
+            if !matches!(ext, Some("so") | Some("dylib") | Some("dll") | Some("nro")) {
+                continue;
+            }
+            let stem = path
+                .file_stem()
+                .and_then(|s| s.to_str())
+                .unwrap_or_default();
+            let stem = stem.strip_prefix("lib").unwrap_or(stem);
+            if !lib_names.iter().any(|n| n == stem) {
+                continue;
+            }
+            if meta.modified().is_ok_and(|m| m >= since) {
+                found.push(path);
+            }
+        }
+    }
+    let uplifted = |p: &PathBuf| {
+        let Some(deps) = p
+            .parent()
+            .filter(|d| d.file_name() == Some("deps".as_ref()))
+        else {
+            return false;
+        };
+        match (deps.parent(), p.file_name()) {
+            (Some(profile), Some(name)) => found.contains(&profile.join(name)),
+            _ => false,
+        }
+    };
+    let mut out: Vec<PathBuf> = found.iter().filter(|p| !uplifted(p)).cloned().collect();
+    out.sort();
+    out
+}
+
+/// Every `.nro` under `target_dir`, keeping only paths with a `profile_dir`
+/// component (cargo's directory name, e.g. `debug` for `dev`) when given.
+pub fn all_nros(target_dir: &Path, profile_dir: Option<&str>) -> Result<Vec<PathBuf>, String> {
     if !target_dir.exists() {
         return Err(format!(
             "target dir does not exist: {}",
@@ -92,7 +470,7 @@ pub fn all_nros(target_dir: &Path, profile: Option<&str>) -> Result<Vec<PathBuf>
             if !has_nro_extension(&path) {
                 continue;
             }
-            if let Some(p) = profile {
+            if let Some(p) = profile_dir {
                 let has_profile_segment = path.components().any(|c| c.as_os_str() == p);
                 if !has_profile_segment {
                     continue;
@@ -112,8 +490,30 @@ pub fn all_nros(target_dir: &Path, profile: Option<&str>) -> Result<Vec<PathBuf>
     Ok(out)
 }
 
-fn pick_nm() -> Option<String> {
-    for tool in ["llvm-nm", "nm", "rust-nm", "aarch64-none-elf-nm"] {
+/// Picks the most recently modified `.nro` from [`all_nros`].
+pub fn newest_nro(target_dir: &Path, profile_dir: Option<&str>) -> Result<PathBuf, String> {
+    let mut newest: Option<(PathBuf, std::time::SystemTime)> = None;
+    for p in all_nros(target_dir, profile_dir)? {
+        let mtime = fs::metadata(&p)
+            .and_then(|m| m.modified())
+            .map_err(|e| format!("metadata {}: {e}", p.display()))?;
+        match &newest {
+            Some((_, t)) if *t >= mtime => {}
+            _ => newest = Some((p, mtime)),
+        }
+    }
+    newest
+        .map(|(p, _)| p)
+        .ok_or_else(|| format!("no .nro files found under {}", target_dir.display()))
+}
+
+/// `nm` flavours tried, in order, by [`pick_nm`].
+pub const NM_CANDIDATES: [&str; 4] = ["llvm-nm", "nm", "rust-nm", "aarch64-none-elf-nm"];
+/// `objdump` flavours tried, in order, by [`pick_objdump`].
+pub const OBJDUMP_CANDIDATES: [&str; 2] = ["llvm-objdump", "objdump"];
+
+pub fn pick_nm() -> Option<String> {
+    for tool in NM_CANDIDATES {
         if Command::new(tool).arg("--version").output().is_ok() {
             return Some(tool.to_string());
         }
@@ -121,8 +521,8 @@ fn pick_nm() -> Option<String> {
     None
 }
 
-fn pick_objdump() -> Option<String> {
-    for tool in ["llvm-objdump", "objdump"] {
+pub fn pick_objdump() -> Option<String> {
+    for tool in OBJDUMP_CANDIDATES {
         if Command::new(tool).arg("--version").output().is_ok() {
             return Some(tool.to_string());
         }
@@ -130,6 +530,20 @@ fn pick_objdump() -> Option<String> {
     None
 }
 
+/// Explains that no symbol tool is installed, or `None` when `nm` or
+/// `objdump` is available. Without one only `.nro` files and what the
+/// native readers in `objects.rs` recognize can be read.
+pub fn missing_symbol_tools() -> Option<String> {
+    if pick_nm().is_some() || pick_objdump().is_some() {
+        return None;
+    }
+    Some(format!(
+        "no nm/objdump found in PATH (searched {} and {}); only .nro, ELF, PE, COFF, Mach-O and ar/lib archive inputs can be read without them. Install LLVM tools (`rustup component add llvm-tools` then `cargo install cargo-binutils` for rust-nm) or your platform's binutils",
+        NM_CANDIDATES.join(", "),
+        OBJDUMP_CANDIDATES.join(", ")
+    ))
+}
+
 fn parse_nm_symbols(text: &str) -> Vec<String> {
     let mut symbols = Vec::<String>::new();
     for line in text.lines() {
@@ -159,6 +573,22 @@ fn run_nm(tool: &str, path: &Path, args: &[&str]) -> Result<Vec<String>, String>
     Ok(parse_nm_symbols(&String::from_utf8_lossy(&output.stdout)))
 }
 
+/// Global symbols defined in an object or archive (e.g. an `.rlib`): read
+/// natively, else via `nm`; empty when neither can read it. An empty native
+/// list also goes to `nm`, as [`extract_exports`] does: the native readers
+/// skip members they cannot parse, such as LLVM bitcode or big-endian ELF.
+pub fn defined_global_symbols(path: &Path) -> Result<Vec<String>, String> {
+    if let Some((_, symbols)) = objects::read_defined_symbols(path)? {
+        if !symbols.is_empty() {
+            return Ok(symbols);
+        }
+    }
+    match pick_nm() {
+        Some(nm) => run_nm(&nm, path, &["-g", "--defined-only"]),
+        None => Ok(Vec::new()),
+    }
+}
+
 fn parse_objdump_exports(text: &str) -> Vec<String> {
     let mut symbols = Vec::<String>::new();
     for line in text.lines() {
@@ -187,37 +617,13 @@ fn has_nro_extension(path: &Path) -> bool {
         .unwrap_or(false)
 }
 
-fn read_u32_le(bytes: &[u8], off: usize) -> Option<u32> {
-    let end = off.checked_add(4)?;
-    let chunk = bytes.get(off..end)?;
-    Some(u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
-}
-
-fn cstr_at(bytes: &[u8], off: usize, max_end: usize) -> Option<String> {
-    if off >= max_end || off >= bytes.len() {
-        return None;
-    }
-    let mut end = off;
-    while end < max_end && end < bytes.len() {
-        if bytes[end] == 0 {
-            break;
-        }
-        end += 1;
-    }
-    if end <= off {
-        return None;
-    }
-    std::str::from_utf8(&bytes[off..end])
-        .ok()
-        .map(|s| s.to_string())
-}
-
 #[derive(Clone, Debug)]
 struct NroSymbol {
     name: String,
     value: u64,
     st_type: u8,
     st_bind: u8,
+    st_visibility: u8,
     size: u64,
     shndx: u16,
 }
@@ -244,43 +650,96 @@ fn bind_name(st_bind: u8) -> &'static str {
     }
 }
 
-fn read_u64_le(bytes: &[u8], off: usize) -> Option<u64> {
-    let end = off.checked_add(8)?;
-    let chunk = bytes.get(off..end)?;
-    Some(u64::from_le_bytes([
-        chunk[0], chunk[1], chunk[2], chunk[3], chunk[4], chunk[5], chunk[6], chunk[7],
-    ]))
+const STV_HIDDEN: u8 = 2;
+
+fn visibility_name(st_visibility: u8) -> &'static str {
+    match st_visibility {
+        0 => "DEFAULT",
+        1 => "INTERNAL",
+        2 => "HIDDEN",
+        3 => "PROTECTED",
+        _ => "UNKNOWN",
+    }
+}
+
+/// Structural checks that catch truncated or corrupted `.nro` files before
+/// they are dumped as a short symbol list. NROs carry no checksum of their
+/// own (only their build id, which cannot be recomputed), so this checks the
+/// header instead: the `NRO0` magic, the size field against the file length
+/// (trailing asset data is allowed), segment bounds and the `MOD0` header.
+pub fn verify_nro_integrity(path: &Path) -> Result<(), String> {
+    let data = fs::read(path).map_err(|e| format!("read {}: {e}", path.display()))?;
+    verify_nro_integrity_bytes(&data)
 }
 
-fn read_u16_le(bytes: &[u8], off: usize) -> Option<u16> {
-    let end = off.checked_add(2)?;
-    let chunk = bytes.get(off..end)?;
-    Some(u16::from_le_bytes([chunk[0], chunk[1]]))
+fn verify_nro_integrity_bytes(data: &[u8]) -> Result<(), String> {
+    if data.get(0x10..0x14) != Some(b"NRO0".as_slice()) {
+        return Err("missing NRO0 magic".to_string());
+    }
+    let field = |off: usize| read_u32_le(data, off).map(|v| v as usize);
+    let size = field(0x18).ok_or_else(|| "header is truncated".to_string())?;
+    if size > data.len() {
+        return Err(format!(
+            "header says 0x{size:X} bytes but the file has 0x{:X} (truncated?)",
+            data.len()
+        ));
+    }
+    let mut end = 0usize;
+    for (name, off) in [("text", 0x20), ("ro", 0x28), ("data", 0x30)] {
+        let (loc, len) = field(off)
+            .zip(field(off + 4))
+            .ok_or_else(|| "header is truncated".to_string())?;
+        if loc < end || loc.saturating_add(len) > size {
+            return Err(format!(
+                "{name} segment 0x{loc:X}+0x{len:X} overlaps the previous segment or ends past 0x{size:X}"
+            ));
+        }
+        end = loc + len;
+    }
+    let modoff = field(4).ok_or_else(|| "header is truncated".to_string())?;
+    if data.get(modoff..modoff.saturating_add(4)) != Some(b"MOD0".as_slice()) {
+        return Err(format!("no MOD0 header at 0x{modoff:X}"));
+    }
+    Ok(())
 }
 
 fn parse_nro_symbols(path: &Path) -> Result<Vec<NroSymbol>, String> {
     let data = fs::read(path).map_err(|e| format!("read {}: {e}", path.display()))?;
+    parse_nro_symbols_bytes(&data)
+}
+
+fn parse_nro_symbols_bytes(data: &[u8]) -> Result<Vec<NroSymbol>, String> {
+    match nro_image(data)? {
+        Some(full) => nro_symbols_in_image(&full),
+        None => Ok(Vec::new()),
+    }
+}
+
+/// The NRO's text, ro and data segments laid out at their load offsets,
+/// which is what `st_value` and the MOD0/dynamic offsets index. `None` for
+/// files that are not an NRO or whose segments run past the end.
+fn nro_image(data: &[u8]) -> Result<Option<Vec<u8>>, String> {
     let magic = data
         .get(0x10..0x14)
         .ok_or_else(|| "short file".to_string())?;
     if magic != b"NRO0" {
-        return Ok(Vec::new());
+        return Ok(None);
     }
 
     // NRO section descriptors match the nxo64 loader layout:
     // tloc/tsize @ 0x20, rloc/rsize @ 0x28, dloc/dsize @ 0x30.
-    let tloc = read_u32_le(&data, 0x20).ok_or_else(|| "invalid text offset".to_string())? as usize;
-    let tsize = read_u32_le(&data, 0x24).ok_or_else(|| "invalid text size".to_string())? as usize;
-    let rloc = read_u32_le(&data, 0x28).ok_or_else(|| "invalid ro offset".to_string())? as usize;
-    let rsize = read_u32_le(&data, 0x2c).ok_or_else(|| "invalid ro size".to_string())? as usize;
-    let dloc = read_u32_le(&data, 0x30).ok_or_else(|| "invalid data offset".to_string())? as usize;
-    let dsize = read_u32_le(&data, 0x34).ok_or_else(|| "invalid data size".to_string())? as usize;
+    let tloc = read_u32_le(data, 0x20).ok_or_else(|| "invalid text offset".to_string())? as usize;
+    let tsize = read_u32_le(data, 0x24).ok_or_else(|| "invalid text size".to_string())? as usize;
+    let rloc = read_u32_le(data, 0x28).ok_or_else(|| "invalid ro offset".to_string())? as usize;
+    let rsize = read_u32_le(data, 0x2c).ok_or_else(|| "invalid ro size".to_string())? as usize;
+    let dloc = read_u32_le(data, 0x30).ok_or_else(|| "invalid data offset".to_string())? as usize;
+    let dsize = read_u32_le(data, 0x34).ok_or_else(|| "invalid data size".to_string())? as usize;
 
     let text_end = tloc.saturating_add(tsize);
     let ro_end = rloc.saturating_add(rsize);
     let data_end = dloc.saturating_add(dsize);
     if text_end > data.len() || ro_end > data.len() || data_end > data.len() {
-        return Ok(Vec::new());
+        return Ok(None);
     }
 
     let text = &data[tloc..text_end];
@@ -301,8 +760,11 @@ fn parse_nro_symbols(path: &Path) -> Result<Vec<NroSymbol>, String> {
         full.truncate(dloc);
     }
     full.extend_from_slice(dataseg);
+    Ok(Some(full))
+}
 
-    let modoff = read_u32_le(&full, 4).ok_or_else(|| "missing MOD0 offset".to_string())? as usize;
+fn nro_symbols_in_image(full: &[u8]) -> Result<Vec<NroSymbol>, String> {
+    let modoff = read_u32_le(full, 4).ok_or_else(|| "missing MOD0 offset".to_string())? as usize;
     let mod_magic = full
         .get(modoff..modoff.saturating_add(4))
         .ok_or_else(|| "invalid MOD0 offset".to_string())?;
@@ -310,8 +772,8 @@ fn parse_nro_symbols(path: &Path) -> Result<Vec<NroSymbol>, String> {
         return Ok(Vec::new());
     }
 
-    let dynamic_rel = read_u32_le(&full, modoff + 4)
-        .ok_or_else(|| "invalid dynamic offset".to_string())? as usize;
+    let dynamic_rel =
+        read_u32_le(full, modoff + 4).ok_or_else(|| "invalid dynamic offset".to_string())? as usize;
     let dynamic_off = modoff.saturating_add(dynamic_rel);
     if dynamic_off >= full.len() {
         return Ok(Vec::new());
@@ -322,8 +784,8 @@ fn parse_nro_symbols(path: &Path) -> Result<Vec<NroSymbol>, String> {
     let mut symtab = None::<usize>;
     let mut off = dynamic_off;
     while off.saturating_add(16) <= full.len() {
-        let tag = read_u64_le(&full, off).unwrap_or(DT_NULL);
-        let val = read_u64_le(&full, off + 8).unwrap_or(0);
+        let tag = read_u64_le(full, off).unwrap_or(DT_NULL);
+        let val = read_u64_le(full, off + 8).unwrap_or(0);
         off += 16;
         if tag == DT_NULL {
             break;
@@ -358,25 +820,27 @@ fn parse_nro_symbols(path: &Path) -> Result<Vec<NroSymbol>, String> {
     let mut out = Vec::<NroSymbol>::new();
     for i in 0..count {
         let base = dynsym_off + i * entry_size;
-        let name_idx = read_u32_le(&full, base).unwrap_or(0) as usize;
+        let name_idx = read_u32_le(full, base).unwrap_or(0) as usize;
         if name_idx == 0 {
             continue;
         }
         let st_info = full.get(base + 4).copied().unwrap_or(0);
-        let st_shndx = read_u16_le(&full, base + 6).unwrap_or(0);
-        let st_value = read_u64_le(&full, base + 8).unwrap_or(0);
-        let st_size = read_u64_le(&full, base + 16).unwrap_or(0);
+        let st_other = full.get(base + 5).copied().unwrap_or(0);
+        let st_shndx = read_u16_le(full, base + 6).unwrap_or(0);
+        let st_value = read_u64_le(full, base + 8).unwrap_or(0);
+        let st_size = read_u64_le(full, base + 16).unwrap_or(0);
         if st_shndx == 0 {
             continue;
         }
         let name_off = dynstr_off.saturating_add(name_idx);
-        if let Some(name) = cstr_at(&full, name_off, dynstr_end) {
+        if let Some(name) = cstr_at(full, name_off, dynstr_end) {
             if !name.is_empty() {
                 out.push(NroSymbol {
                     name,
                     value: st_value,
                     st_type: st_info & 0x0f,
                     st_bind: st_info >> 4,
+                    st_visibility: st_other & 0x03,
                     size: st_size,
                     shndx: st_shndx,
                 });
@@ -393,6 +857,137 @@ fn parse_nro_symbols(path: &Path) -> Result<Vec<NroSymbol>, String> {
     Ok(out)
 }
 
+const SELFTEST_SYMBOLS: [&str; 2] = ["symbaker_selftest__alpha", "symbaker_selftest__beta"];
+
+/// Builds a minimal NRO image (header, MOD0, dynamic table, dynsym, dynstr)
+/// that exports [`SELFTEST_SYMBOLS`], used to check the native parser.
+fn selftest_nro_blob() -> Vec<u8> {
+    let modoff = 0x80usize;
+    let dynamic_off = 0x90usize;
+    let symtab_off = 0xD0usize;
+    let strtab_off = symtab_off + 3 * 24;
+    let mut strtab = vec![0u8];
+    let mut name_idx = Vec::<u32>::new();
+    for name in SELFTEST_SYMBOLS {
+        name_idx.push(strtab.len() as u32);
+        strtab.extend_from_slice(name.as_bytes());
+        strtab.push(0);
+    }
+    let total = strtab_off + strtab.len();
+
+    let mut blob = vec![0u8; total];
+    let put_u32 = |b: &mut Vec<u8>, off: usize, v: u32| {
+        b[off..off + 4].copy_from_slice(&v.to_le_bytes());
+    };
+    let put_u64 = |b: &mut Vec<u8>, off: usize, v: u64| {
+        b[off..off + 8].copy_from_slice(&v.to_le_bytes());
+    };
+    put_u32(&mut blob, 4, modoff as u32);
+    blob[0x10..0x14].copy_from_slice(b"NRO0");
+    put_u32(&mut blob, 0x20, 0);
+    put_u32(&mut blob, 0x24, total as u32);
+    put_u32(&mut blob, 0x28, total as u32);
+    put_u32(&mut blob, 0x30, total as u32);
+    blob[modoff..modoff + 4].copy_from_slice(b"MOD0");
+    put_u32(&mut blob, modoff + 4, (dynamic_off - modoff) as u32);
+
+    let dynamic = [
+        (DT_STRTAB, strtab_off as u64),
+        (DT_STRSZ, strtab.len() as u64),
+        (DT_SYMTAB, symtab_off as u64),
+        (DT_NULL, 0),
+    ];
+    for (i, (tag, val)) in dynamic.iter().enumerate() {
+        put_u64(&mut blob, dynamic_off + i * 16, *tag);
+        put_u64(&mut blob, dynamic_off + i * 16 + 8, *val);
+    }
+
+    // Entry 0 stays the reserved null symbol.
+    for (i, idx) in name_idx.iter().enumerate() {
+        let base = symtab_off + (i + 1) * 24;
+        put_u32(&mut blob, base, *idx);
+        blob[base + 4] = (1 << 4) | 2; // GLOBAL FUNC
+        blob[base + 6..base + 8].copy_from_slice(&1u16.to_le_bytes());
+        put_u64(&mut blob, base + 8, 0x1000 * (i as u64 + 1));
+        put_u64(&mut blob, base + 16, 0x10);
+    }
+    blob[strtab_off..].copy_from_slice(&strtab);
+    blob
+}
+
+/// Round-trips an embedded NRO image through the native parser.
+pub fn nro_parser_selftest() -> Result<(), String> {
+    let rows = parse_nro_symbols_bytes(&selftest_nro_blob())?;
+    let names: Vec<&str> = rows.iter().map(|r| r.name.as_str()).collect();
+    if names != SELFTEST_SYMBOLS {
+        return Err(format!(
+            "expected {:?}, parser returned {:?}",
+            SELFTEST_SYMBOLS, names
+        ));
+    }
+    Ok(())
+}
+
+fn hidden_global_functions_in(rows: &[NroSymbol]) -> Vec<String> {
+    let mut names = Vec::<String>::new();
+    for row in rows {
+        if bind_name(row.st_bind) == "GLOBAL"
+            && type_name(row.st_type) == "FUNC"
+            && row.st_visibility == STV_HIDDEN
+            && !names.iter().any(|n| n == &row.name)
+        {
+            names.push(row.name.clone());
+        }
+    }
+    names
+}
+
+/// GLOBAL functions whose `st_other` visibility is HIDDEN: they show up in
+/// `.dynsym` but the loader will not resolve them. Empty for non-`.nro` files.
+pub fn hidden_global_functions(path: &Path) -> Result<Vec<String>, String> {
+    if !has_nro_extension(path) {
+        return Ok(Vec::new());
+    }
+    Ok(hidden_global_functions_in(&parse_nro_symbols(path)?))
+}
+
+/// Names with more than one GLOBAL definition, with each definition's address.
+fn duplicate_global_definitions_in(rows: &[NroSymbol]) -> Vec<(String, Vec<u64>)> {
+    let mut by_name = BTreeMap::<&str, Vec<u64>>::new();
+    for row in rows {
+        if bind_name(row.st_bind) == "GLOBAL" {
+            by_name.entry(&row.name).or_default().push(row.value);
+        }
+    }
+    by_name
+        .into_iter()
+        .filter(|(_, addrs)| addrs.len() > 1)
+        .map(|(name, addrs)| (name.to_string(), addrs))
+        .collect()
+}
+
+/// Symbols defined GLOBAL more than once in one `.nro`'s `.dynsym`, e.g. a
+/// prefixed export equal to another crate's `#[no_mangle]` name. The loader
+/// binds only one of them. Empty for non-`.nro` files.
+pub fn duplicate_global_definitions(path: &Path) -> Result<Vec<(String, Vec<u64>)>, String> {
+    if !has_nro_extension(path) {
+        return Ok(Vec::new());
+    }
+    Ok(duplicate_global_definitions_in(&parse_nro_symbols(path)?))
+}
+
+/// Parses an `.nro`'s `.dynsym` and returns how many rows it has; what
+/// `cargo symdump bench` times.
+pub fn nro_symbol_count(path: &Path) -> Result<usize, String> {
+    if !has_nro_extension(path) {
+        return Err(format!(
+            "{} is not an .nro (bench times the native .nro parser)",
+            path.display()
+        ));
+    }
+    Ok(parse_nro_symbols(path)?.len())
+}
+
 fn parse_nro_exports(path: &Path) -> Result<Vec<String>, String> {
     let rows = parse_nro_symbols(path)?;
     let mut names = Vec::<String>::new();
@@ -465,103 +1060,397 @@ fn alt_symbol_source_for_nro(path: &Path) -> Option<PathBuf> {
 }
 
 pub fn exported_symbols(path: &Path) -> Result<Vec<String>, String> {
-    let mut symbols = Vec::<String>::new();
-    if path.extension().and_then(|s| s.to_str()) == Some("nro") {
-        symbols = parse_nro_exports(path)?;
-    }
-    if symbols.is_empty() {
-        if let Some(nm) = pick_nm() {
-            let tries: [&[&str]; 4] = [
-                &["-g", "--defined-only"],
-                &["-D", "--defined-only"],
-                &["-gD"],
-                &["-g"],
-            ];
-            for t in tries {
-                symbols = run_nm(&nm, path, t)?;
-                if !symbols.is_empty() {
-                    break;
-                }
+    Ok(extract_exports(path)?.symbols)
+}
+
+/// An artifact's exports and the strategy that found them, e.g.
+/// `nro parser` or `llvm-nm -g --defined-only`.
+pub struct Extraction {
+    pub symbols: Vec<String>,
+    pub strategy: String,
+}
+
+/// [`exported_symbols`], also reporting which extractor produced the list:
+/// the native `.nro` parser first, then the native ELF/PE/COFF/Mach-O/ar
+/// readers, then nm with progressively looser flags, then `objdump -p`.
+pub fn extract_exports(path: &Path) -> Result<Extraction, String> {
+    let is_nro = path.extension().and_then(|s| s.to_str()) == Some("nro");
+    let found = |symbols: Vec<String>, strategy: String| Extraction { symbols, strategy };
+    let mut tried = Vec::<String>::new();
+    if is_nro {
+        let symbols = parse_nro_exports(path)?;
+        if !symbols.is_empty() {
+            return Ok(found(symbols, "nro parser".to_string()));
+        }
+        tried.push("nro parser".to_string());
+    }
+    match objects::read_defined_symbols(path)? {
+        Some((format, symbols)) => {
+            let reader = format!("native {} reader", format.name());
+            if !symbols.is_empty() {
+                return Ok(found(symbols, reader));
+            }
+            tried.push(reader);
+        }
+        None if !is_nro => {
+            if let Some(missing) = missing_symbol_tools() {
+                return Err(format!("cannot read {}: {missing}", path.display()));
+            }
+        }
+        None => {}
+    }
+    if let Some(nm) = pick_nm() {
+        tried.push(nm.clone());
+        let tries: [&[&str]; 4] = [
+            &["-g", "--defined-only"],
+            &["-D", "--defined-only"],
+            &["-gD"],
+            &["-g"],
+        ];
+        for t in tries {
+            let symbols = run_nm(&nm, path, t)?;
+            if !symbols.is_empty() {
+                return Ok(found(symbols, format!("{nm} {}", t.join(" "))));
             }
         }
     }
 
-    if symbols.is_empty() {
-        if let Some(objdump) = pick_objdump() {
-            let out = Command::new(objdump)
-                .args(["-p"])
-                .arg(path)
-                .output()
-                .map_err(|e| format!("failed to run objdump: {e}"))?;
-            if out.status.success() {
-                symbols = parse_objdump_exports(&String::from_utf8_lossy(&out.stdout));
+    if let Some(objdump) = pick_objdump() {
+        tried.push(objdump.clone());
+        let out = Command::new(&objdump)
+            .args(["-p"])
+            .arg(path)
+            .output()
+            .map_err(|e| format!("failed to run objdump: {e}"))?;
+        if out.status.success() {
+            let symbols = parse_objdump_exports(&String::from_utf8_lossy(&out.stdout));
+            if !symbols.is_empty() {
+                return Ok(found(symbols, format!("{objdump} -p")));
             }
         }
     }
 
-    if symbols.is_empty() && path.extension().and_then(|s| s.to_str()) == Some("nro") {
-        symbols = parse_nro_exports(path)?;
+    Err(format!(
+        "no exported symbols found in {} ({} ran but found nothing)",
+        path.display(),
+        tried.join(", ")
+    ))
+}
+
+/// Symbol name -> `st_size` for an `.nro`; `None` for other artifacts, which
+/// carry no size information here.
+pub fn nro_symbol_sizes(path: &Path) -> Option<HashMap<String, u64>> {
+    if path.extension().and_then(|s| s.to_str()) != Some("nro") {
+        return None;
     }
+    let rows = parse_nro_symbols(path).ok()?;
+    Some(rows.into_iter().map(|r| (r.name, r.size)).collect())
+}
 
-    if symbols.is_empty() {
-        return Err(
-            "could not extract exported symbols from artifact (nm/objdump/nro parser found nothing)".to_string(),
-        );
+/// Leading bytes of a symbol that [`nro_symbol_fingerprints`] hashes.
+pub const FINGERPRINT_BYTES: usize = 64;
+
+/// What a defined symbol looks like in one `.nro`: its `st_size` and an
+/// fnv1a32 hash of its first [`FINGERPRINT_BYTES`] bytes. For functions the
+/// immediates of AArch64 PC-relative instructions are masked first, so the
+/// same code placed at another address hashes the same. This is a heuristic:
+/// equal fingerprints strongly suggest, but do not prove, equal code.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SymbolFingerprint {
+    pub size: u64,
+    pub hash: String,
+}
+
+/// Symbol name -> [`SymbolFingerprint`] for an `.nro`; `None` for other
+/// artifacts or unreadable files. Symbols outside the image are left out.
+pub fn nro_symbol_fingerprints(path: &Path) -> Option<HashMap<String, SymbolFingerprint>> {
+    if !has_nro_extension(path) {
+        return None;
     }
-    Ok(symbols)
-}
-
-pub fn write_exports_sidecar(path: &Path) -> Result<PathBuf, String> {
-    let symbols = match exported_symbols(path) {
-        Ok(s) => s,
-        Err(original_err) => {
-            if path.extension().and_then(|s| s.to_str()) == Some("nro") {
-                if let Some(alt) = alt_symbol_source_for_nro(path) {
-                    exported_symbols(&alt).map_err(|e| {
-                        format!(
-                            "{original_err}; fallback '{}' also failed: {e}",
-                            alt.display()
-                        )
-                    })?
-                } else {
-                    return Err(original_err);
-                }
-            } else {
-                return Err(original_err);
-            }
-        }
+    let data = fs::read(path).ok()?;
+    let image = nro_image(&data).ok()??;
+    let rows = nro_symbols_in_image(&image).ok()?;
+    Some(symbol_fingerprints_in(&image, &rows))
+}
+
+fn symbol_fingerprints_in(image: &[u8], rows: &[NroSymbol]) -> HashMap<String, SymbolFingerprint> {
+    let mut out = HashMap::<String, SymbolFingerprint>::new();
+    for row in rows {
+        let Ok(start) = usize::try_from(row.value) else {
+            continue;
+        };
+        let len = match usize::try_from(row.size) {
+            Ok(0) => FINGERPRINT_BYTES,
+            Ok(size) => size.min(FINGERPRINT_BYTES),
+            Err(_) => FINGERPRINT_BYTES,
+        };
+        let end = start.saturating_add(len).min(image.len());
+        let Some(bytes) = image.get(start..end).filter(|b| !b.is_empty()) else {
+            continue;
+        };
+        let bytes = if type_name(row.st_type) == "FUNC" {
+            mask_pc_relative(bytes)
+        } else {
+            bytes.to_vec()
+        };
+        out.entry(row.name.clone()).or_insert(SymbolFingerprint {
+            size: row.size,
+            hash: fnv1a32_bytes(&bytes),
+        });
+    }
+    out
+}
+
+/// Clears the address-dependent immediates of AArch64 PC-relative branches,
+/// `adr`/`adrp` and literal loads, leaving opcodes and registers.
+fn mask_pc_relative(code: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(code.len());
+    let mut words = code.chunks_exact(4);
+    for word in &mut words {
+        let insn = u32::from_le_bytes([word[0], word[1], word[2], word[3]]);
+        let cond_branch = insn & 0xFF00_0010 == 0x5400_0000; // b.cond
+        let compare_branch = insn & 0x7E00_0000 == 0x3400_0000; // cbz, cbnz
+        let literal_load = insn & 0x3B00_0000 == 0x1800_0000; // ldr (literal)
+        let masked = if insn & 0x7C00_0000 == 0x1400_0000 {
+            insn & 0xFC00_0000 // b, bl
+        } else if insn & 0x1F00_0000 == 0x1000_0000 {
+            insn & 0x9F00_001F // adr, adrp
+        } else if insn & 0x7E00_0000 == 0x3600_0000 {
+            insn & 0xFFF8_001F // tbz, tbnz
+        } else if cond_branch || compare_branch || literal_load {
+            insn & 0xFF00_001F
+        } else {
+            insn
+        };
+        out.extend_from_slice(&masked.to_le_bytes());
+    }
+    out.extend_from_slice(words.remainder());
+    out
+}
+
+/// Keeps symbols of `path` whose size is at least `min_size`. Without size
+/// information (see [`nro_symbol_sizes`]) the list is returned unchanged.
+pub fn filter_min_size(path: &Path, symbols: Vec<String>, min_size: Option<u64>) -> Vec<String> {
+    let Some(min) = min_size else {
+        return symbols;
     };
-    let out_path = path
+    let Some(sizes) = nro_symbol_sizes(path) else {
+        return symbols;
+    };
+    symbols
+        .into_iter()
+        .filter(|s| sizes.get(s).is_some_and(|size| *size >= min))
+        .collect()
+}
+
+/// Default `--sidecar-name`: `<file>.exports.txt` next to the artifact.
+pub const DEFAULT_SIDECAR_NAME: &str = "{filename}.exports.txt";
+
+fn fnv1a32_bytes(bytes: &[u8]) -> String {
+    let mut h: u32 = 0x811c_9dc5;
+    for b in bytes {
+        h ^= u32::from(*b);
+        h = h.wrapping_mul(0x0100_0193);
+    }
+    format!("{h:08x}")
+}
+
+/// Where the sidecar for `artifact` goes: `pattern` with `{filename}`,
+/// `{stem}`, `{parent}` (the artifact's directory name) and `{hash}` (fnv1a32
+/// of the artifact's bytes) filled in. Relative results are resolved against
+/// the artifact's directory.
+pub fn sidecar_path(artifact: &Path, pattern: &str) -> Result<PathBuf, String> {
+    let dir = artifact
         .parent()
-        .ok_or_else(|| "invalid artifact path".to_string())?
-        .join(format!(
-            "{}.exports.txt",
-            path.file_name()
+        .ok_or_else(|| "invalid artifact path".to_string())?;
+    let file_token = |p: Option<&std::ffi::OsStr>| {
+        p.and_then(|s| s.to_str())
+            .map(str::to_string)
+            .ok_or_else(|| format!("invalid artifact file name: {}", artifact.display()))
+    };
+    let mut out = String::new();
+    let mut rest = pattern;
+    while let Some(open) = rest.find('{') {
+        out.push_str(&rest[..open]);
+        let close = rest[open..]
+            .find('}')
+            .ok_or_else(|| format!("unclosed `{{` in sidecar name {pattern:?}"))?;
+        let value = match &rest[open + 1..open + close] {
+            "filename" => file_token(artifact.file_name())?,
+            "stem" => file_token(artifact.file_stem())?,
+            "parent" => dir
+                .canonicalize()
+                .unwrap_or_else(|_| dir.to_path_buf())
+                .file_name()
                 .and_then(|s| s.to_str())
-                .ok_or_else(|| "invalid artifact file name".to_string())?
-        ));
-    let body = if symbols.is_empty() {
-        String::new()
-    } else {
-        format!("{}\n", symbols.join("\n"))
+                .unwrap_or_default()
+                .to_string(),
+            "hash" => fnv1a32_bytes(
+                &fs::read(artifact).map_err(|e| format!("read {}: {e}", artifact.display()))?,
+            ),
+            other => {
+                return Err(format!(
+                    "unknown placeholder {{{other}}} in sidecar name {pattern:?} (expected {{filename}}, {{stem}}, {{parent}} or {{hash}})"
+                ))
+            }
+        };
+        out.push_str(&value);
+        rest = &rest[open + close + 1..];
+    }
+    out.push_str(rest);
+    // Lexically fold `..` so that patterns reaching out of sibling
+    // directories compare equal when they name the same file.
+    let mut path = PathBuf::new();
+    for part in dir.join(out).components() {
+        match part {
+            std::path::Component::ParentDir
+                if matches!(
+                    path.components().next_back(),
+                    Some(std::path::Component::Normal(_))
+                ) =>
+            {
+                path.pop();
+            }
+            std::path::Component::CurDir => {}
+            other => path.push(other),
+        }
+    }
+    Ok(path)
+}
+
+/// [`extract_exports`], falling back for a `.nro` that cannot be read to
+/// the `.nso`/`.so`/`.elf` it was packed from, when one sits beside it.
+pub fn extract_dump_exports(path: &Path) -> Result<Extraction, String> {
+    let original_err = match extract_exports(path) {
+        Ok(found) => return Ok(found),
+        Err(e) => e,
     };
+    if path.extension().and_then(|s| s.to_str()) != Some("nro") {
+        return Err(original_err);
+    }
+    let Some(alt) = alt_symbol_source_for_nro(path) else {
+        return Err(original_err);
+    };
+    extract_exports(&alt).map_err(|e| {
+        format!(
+            "{original_err}; fallback '{}' also failed: {e}",
+            alt.display()
+        )
+    })
+}
+
+/// Writes `symbols`, one per line, to [`sidecar_path`]`(path, name_pattern)`.
+/// Symbols with an entry in `annotations` get it as a trailing `  # ...`
+/// comment; the rest are left bare.
+pub fn write_exports_sidecar(
+    path: &Path,
+    symbols: &[String],
+    annotations: Option<&BTreeMap<String, String>>,
+    name_pattern: &str,
+) -> Result<PathBuf, String> {
+    let out_path = sidecar_path(path, name_pattern)?;
+    if let Some(dir) = out_path.parent() {
+        fs::create_dir_all(dir).map_err(|e| format!("mkdir {}: {e}", dir.display()))?;
+    }
+    let body = sidecar_body(symbols, annotations);
     fs::write(&out_path, body).map_err(|e| format!("write {}: {e}", out_path.display()))?;
     Ok(out_path)
 }
 
-pub fn write_symbol_log(path: &Path, out_path: &Path) -> Result<PathBuf, String> {
+fn sidecar_body(symbols: &[String], annotations: Option<&BTreeMap<String, String>>) -> String {
+    let mut body = String::new();
+    for symbol in symbols {
+        body.push_str(symbol);
+        if let Some(note) = annotations.and_then(|a| a.get(symbol)) {
+            body.push_str("  # ");
+            body.push_str(note);
+        }
+        body.push('\n');
+    }
+    body
+}
+
+/// How a path appears in output: relative to `root` (with `/` separators)
+/// when inside it, otherwise absolute with `home` shown as `~`. Windows
+/// verbatim prefixes (`\\?\C:\`, `\\?\UNC\`) are dropped first, so a
+/// canonicalized path still matches a plain root.
+#[derive(Clone, Debug, Default)]
+pub struct PathStyle {
+    pub root: Option<PathBuf>,
+    pub home: Option<PathBuf>,
+}
+
+impl PathStyle {
+    pub fn show(&self, path: &Path) -> String {
+        let path = without_verbatim_prefix(path);
+        let inside = |base: &Option<PathBuf>| {
+            let base = without_verbatim_prefix(base.as_deref()?);
+            let rel = path.strip_prefix(base).ok()?;
+            let parts: Vec<String> = rel
+                .components()
+                .map(|c| c.as_os_str().to_string_lossy().to_string())
+                .collect();
+            Some(parts.join("/"))
+        };
+        if let Some(rel) = inside(&self.root) {
+            return if rel.is_empty() { ".".to_string() } else { rel };
+        }
+        if let Some(rel) = inside(&self.home) {
+            return if rel.is_empty() {
+                "~".to_string()
+            } else {
+                format!("~/{rel}")
+            };
+        }
+        path.display().to_string()
+    }
+}
+
+fn without_verbatim_prefix(path: &Path) -> PathBuf {
+    let text = path.to_string_lossy();
+    if let Some(rest) = text.strip_prefix(r"\\?\UNC\") {
+        return PathBuf::from(format!(r"\\{rest}"));
+    }
+    match text.strip_prefix(r"\\?\") {
+        Some(rest) => PathBuf::from(rest),
+        None => path.to_path_buf(),
+    }
+}
+
+/// `HOME`, or `USERPROFILE` on Windows.
+pub fn home_dir() -> Option<PathBuf> {
+    ["HOME", "USERPROFILE"]
+        .into_iter()
+        .filter_map(std::env::var_os)
+        .find(|v| !v.is_empty())
+        .map(PathBuf::from)
+}
+
+pub fn write_symbol_log(
+    path: &Path,
+    out_path: &Path,
+    min_size: Option<u64>,
+    paths: &PathStyle,
+) -> Result<PathBuf, String> {
     let mut body = String::new();
     body.push_str("# symbaker sym.log\n");
-    body.push_str(&format!("# source={}\n", path.display()));
+    body.push_str(&format!("# source={}\n", paths.show(path)));
     if path.extension().and_then(|s| s.to_str()) == Some("nro") {
         let rows = parse_nro_symbols(path)?;
-        body.push_str("# format: address type bind size name\n");
+        if let Some(min) = min_size {
+            body.push_str(&format!("# min_size=0x{min:X}\n"));
+        }
+        body.push_str("# format: address type bind visibility size name\n");
         for row in rows {
+            if min_size.is_some_and(|min| row.size < min) {
+                continue;
+            }
             body.push_str(&format!(
-                "0x{0:016X} {1} {2} 0x{3:X} {4}\n",
+                "0x{0:016X} {1} {2} {3} 0x{4:X} {5}\n",
                 row.value,
                 type_name(row.st_type),
                 bind_name(row.st_bind),
+                visibility_name(row.st_visibility),
                 row.size,
                 row.name
             ));
@@ -578,3 +1467,638 @@ pub fn write_symbol_log(path: &Path, out_path: &Path) -> Result<PathBuf, String>
     fs::write(out_path, body).map_err(|e| format!("write {}: {e}", out_path.display()))?;
     Ok(out_path.to_path_buf())
 }
+
+/// Writes `sym.stable.log`: export names only, sorted and deduplicated, one
+/// section per artifact ordered by file name, with `# source=` reduced to
+/// that file name. Identical export sets give byte-identical files on any
+/// machine, so it can be checked in and diffed.
+pub fn write_stable_symbol_log(
+    rows: &[(PathBuf, Vec<String>)],
+    out_path: &Path,
+) -> Result<PathBuf, String> {
+    let mut sections: Vec<(String, Vec<String>)> = rows
+        .iter()
+        .map(|(path, symbols)| {
+            let name = path
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_else(|| path.display().to_string());
+            let mut symbols = symbols.clone();
+            symbols.sort();
+            symbols.dedup();
+            (name, symbols)
+        })
+        .collect();
+    sections.sort();
+
+    let mut body = String::new();
+    body.push_str("# symbaker sym.stable.log\n");
+    body.push_str("# format: source=<file name> then sorted symbol names\n");
+    for (name, symbols) in sections {
+        body.push_str(&format!("\n# source={name}\n"));
+        for sym in symbols {
+            body.push_str(&sym);
+            body.push('\n');
+        }
+    }
+    fs::write(out_path, body).map_err(|e| format!("write {}: {e}", out_path.display()))?;
+    Ok(out_path.to_path_buf())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn feature_args_accept_every_cargo_spelling() {
+        let args = [
+            "build",
+            "--features",
+            "a,b",
+            "-F",
+            "c",
+            "-Fd",
+            "-F=e",
+            "--features=f",
+            "--no-default-features",
+            "-p",
+            "app",
+        ]
+        .map(OsString::from);
+        assert_eq!(
+            feature_args(&args),
+            [
+                "--features",
+                "a,b",
+                "--features",
+                "c",
+                "--features",
+                "d",
+                "--features",
+                "e",
+                "--features=f",
+                "--no-default-features"
+            ]
+        );
+    }
+
+    #[test]
+    fn st_other_low_bits_are_visibility() {
+        let mut blob = selftest_nro_blob();
+        // Second selftest symbol: dynsym entry 2, st_other at +5. The high
+        // bits are not visibility and must be masked off.
+        blob[0xD0 + 2 * 24 + 5] = 0xF0 | STV_HIDDEN;
+        let rows = parse_nro_symbols_bytes(&blob).unwrap();
+        let vis: Vec<&str> = rows
+            .iter()
+            .map(|r| visibility_name(r.st_visibility))
+            .collect();
+        assert_eq!(vis, ["DEFAULT", "HIDDEN"]);
+        assert_eq!(hidden_global_functions_in(&rows), [SELFTEST_SYMBOLS[1]]);
+    }
+
+    #[test]
+    fn fingerprints_ignore_placement_but_not_code() {
+        let ret = 0xD65F_03C0u32;
+        let add = 0x9100_0400u32; // add x0, x0, #1
+        let mov = 0xD280_00A0u32; // mov x0, #5
+        let bl = |imm: u32| 0x9400_0000 | imm;
+        let adrp = |page: u32| 0x9000_0001 | ((page & 3) << 29) | ((page >> 2) << 5);
+        let mut image = vec![0u8; 0x500];
+        let mut put = |at: usize, code: &[u32]| {
+            for (i, insn) in code.iter().enumerate() {
+                image[at + i * 4..at + i * 4 + 4].copy_from_slice(&insn.to_le_bytes());
+            }
+        };
+        put(0x100, &[adrp(1), bl(0x40), add, ret]);
+        put(0x200, &[adrp(7), bl(0x80), add, ret]);
+        put(0x300, &[adrp(1), bl(0x40), mov, ret]);
+        put(0x400, &[bl(0x40)]);
+        put(0x410, &[bl(0x80)]);
+        let row = |name: &str, value: u64, st_type: u8, size: u64| NroSymbol {
+            name: name.to_string(),
+            value,
+            st_type,
+            st_bind: 1,
+            st_visibility: 0,
+            size,
+            shndx: 1,
+        };
+        let rows = [
+            row("here", 0x100, 2, 16),
+            row("moved", 0x200, 2, 16),
+            row("changed", 0x300, 2, 16),
+            row("data_a", 0x400, 1, 4),
+            row("data_b", 0x410, 1, 4),
+            row("past_end", 0x900, 2, 16),
+        ];
+        let fps = symbol_fingerprints_in(&image, &rows);
+        assert_eq!(fps["here"], fps["moved"]);
+        assert_ne!(fps["here"], fps["changed"]);
+        // Only code is normalized; objects compare their raw bytes.
+        assert_ne!(fps["data_a"], fps["data_b"]);
+        assert!(!fps.contains_key("past_end"));
+    }
+
+    #[test]
+    fn min_size_keeps_symbols_at_or_above_threshold() {
+        let mut blob = selftest_nro_blob();
+        // Second selftest symbol: st_size at +16.
+        blob[0xD0 + 2 * 24 + 16] = 0x40;
+        let dir = std::env::temp_dir().join(format!("symbaker_min_size_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let nro = dir.join("min_size.nro");
+        fs::write(&nro, &blob).unwrap();
+
+        let all: Vec<String> = SELFTEST_SYMBOLS.iter().map(|s| s.to_string()).collect();
+        assert_eq!(filter_min_size(&nro, all.clone(), None), all);
+        assert_eq!(
+            filter_min_size(&nro, all.clone(), Some(0x40)),
+            [SELFTEST_SYMBOLS[1]]
+        );
+        let so = dir.join("min_size.so");
+        assert_eq!(filter_min_size(&so, all.clone(), Some(0x40)), all);
+
+        let log = write_symbol_log(
+            &nro,
+            &dir.join("sym.log"),
+            Some(0x11),
+            &PathStyle::default(),
+        )
+        .unwrap();
+        let log = fs::read_to_string(log).unwrap();
+        assert!(log.contains(SELFTEST_SYMBOLS[1]), "{log}");
+        assert!(!log.contains(SELFTEST_SYMBOLS[0]), "{log}");
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn several_default_members_are_not_guessed() {
+        let fixture = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests")
+            .join("plugin_workspace");
+        let args = |extra: &[&str]| {
+            let mut v = vec![
+                OsString::from("--manifest-path"),
+                fixture.join("Cargo.toml").into_os_string(),
+            ];
+            v.extend(extra.iter().map(OsString::from));
+            v
+        };
+
+        let metadata = workspace_metadata(&args(&[])).expect("cargo metadata");
+        assert_eq!(
+            top_package_from_metadata(&metadata),
+            Err(vec!["plugin_a".to_string(), "plugin_b".to_string()])
+        );
+        assert_eq!(discover_top_package_name(&args(&[])), None);
+        assert_eq!(
+            discover_top_package_name(&args(&["-p", "plugin_b"])).as_deref(),
+            Some("plugin_b")
+        );
+        assert_eq!(
+            workspace_package_names(&args(&[])).unwrap(),
+            ["plugin_a", "plugin_b"]
+        );
+    }
+
+    #[test]
+    fn broken_manifest_falls_back_to_workspace_members() {
+        let dir = std::env::temp_dir().join(format!("symbaker_broken_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let package = |path: &str, name: &str, deps: &str| {
+            let crate_dir = dir.join(path);
+            fs::create_dir_all(crate_dir.join("src")).unwrap();
+            fs::write(crate_dir.join("src").join("lib.rs"), "").unwrap();
+            fs::write(
+                crate_dir.join("Cargo.toml"),
+                format!("[package]\nname = \"{name}\"\nversion = \"0.1.0\"\nedition = \"2021\"\n\n[dependencies]\n{deps}"),
+            )
+            .unwrap();
+        };
+        let manifest = dir.join("Cargo.toml");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(&manifest, "[workspace]\nmembers = [\"plugins/*\"]\n").unwrap();
+        package(
+            "plugins/one",
+            "plugin_one",
+            "gone = { path = \"../gone\" }\n",
+        );
+        let args = vec![
+            OsString::from("--manifest-path"),
+            manifest.clone().into_os_string(),
+        ];
+
+        let err = cargo_metadata(&args, false).unwrap_err();
+        assert!(!err.offline, "{err}");
+        assert_eq!(
+            discover_top_package_name(&args).as_deref(),
+            Some("plugin_one")
+        );
+        package("plugins/two", "plugin_two", "");
+        assert_eq!(
+            top_package_from_manifest(&manifest),
+            Err(vec!["plugin_one".to_string(), "plugin_two".to_string()])
+        );
+        assert_eq!(
+            top_package_from_manifest(&dir.join("plugins").join("two").join("Cargo.toml")),
+            Ok(Some("plugin_two".to_string()))
+        );
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn offline_metadata_failures_say_so() {
+        let dir = std::env::temp_dir().join(format!("symbaker_offline_{}", std::process::id()));
+        fs::create_dir_all(dir.join("src")).unwrap();
+        fs::write(dir.join("src").join("lib.rs"), "").unwrap();
+        let manifest = dir.join("Cargo.toml");
+        fs::write(
+            &manifest,
+            "[package]\nname = \"offline_app\"\nversion = \"0.1.0\"\nedition = \"2021\"\n\n[dependencies]\nsymbaker_uncached_dependency = \"1\"\n",
+        )
+        .unwrap();
+        let args = [
+            "build",
+            "--offline",
+            "--locked",
+            "--manifest-path",
+            manifest.to_str().unwrap(),
+        ]
+        .map(OsString::from)
+        .to_vec();
+        assert_eq!(lockfile_args(&args), ["--offline", "--locked"]);
+
+        let err = cargo_metadata(&args, false).unwrap_err();
+        assert!(err.offline, "{err}");
+        assert!(err.to_string().contains("registry cache"), "{err}");
+        assert_eq!(
+            discover_top_package_name(&args).as_deref(),
+            Some("offline_app")
+        );
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn stable_log_ignores_directories_and_input_order() {
+        let dir = std::env::temp_dir().join(format!("symbaker_stable_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let syms = |v: &[&str]| v.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        let a = vec![
+            (
+                PathBuf::from("/one/b.nro"),
+                syms(&["zeta", "alpha", "zeta"]),
+            ),
+            (PathBuf::from("/one/deep/a.nro"), syms(&["mid"])),
+        ];
+        let b = vec![
+            (PathBuf::from("C:/two/a.nro"), syms(&["mid"])),
+            (PathBuf::from("other/b.nro"), syms(&["alpha", "zeta"])),
+        ];
+        let first = write_stable_symbol_log(&a, &dir.join("a.log")).unwrap();
+        let second = write_stable_symbol_log(&b, &dir.join("b.log")).unwrap();
+        let first = fs::read_to_string(first).unwrap();
+        assert_eq!(first, fs::read_to_string(second).unwrap());
+        assert_eq!(
+            first,
+            "# symbaker sym.stable.log\n\
+             # format: source=<file name> then sorted symbol names\n\
+             \n# source=a.nro\nmid\n\
+             \n# source=b.nro\nalpha\nzeta\n"
+        );
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn repeated_global_definitions_are_clashes() {
+        let row = |name: &str, bind: u8, value: u64| NroSymbol {
+            name: name.to_string(),
+            value,
+            st_type: 2,
+            st_bind: bind,
+            st_visibility: 0,
+            size: 0x10,
+            shndx: 1,
+        };
+        let rows = [
+            row("hdr__init", 1, 0x1000),
+            row("hdr__init", 1, 0x2000),
+            row("weak_hook", 1, 0x3000),
+            row("weak_hook", 2, 0x4000),
+            row("unique", 1, 0x5000),
+        ];
+        assert_eq!(
+            duplicate_global_definitions_in(&rows),
+            [("hdr__init".to_string(), vec![0x1000, 0x2000])]
+        );
+    }
+
+    #[test]
+    fn sidecar_annotates_only_known_symbols() {
+        let symbols = ["hdr__init".to_string(), "memcpy".to_string()];
+        let notes = BTreeMap::from([("hdr__init".to_string(), "crate=hdr fn=init".to_string())]);
+        assert_eq!(
+            sidecar_body(&symbols, Some(&notes)),
+            "hdr__init  # crate=hdr fn=init\nmemcpy\n"
+        );
+        assert_eq!(sidecar_body(&symbols, None), "hdr__init\nmemcpy\n");
+        assert_eq!(sidecar_body(&[], Some(&notes)), "");
+    }
+
+    #[test]
+    fn sidecar_name_pattern_fills_placeholders() {
+        let dir = std::env::temp_dir().join(format!("symdump_sidecar_{}", std::process::id()));
+        let build = dir.join("v2");
+        fs::create_dir_all(&build).unwrap();
+        let artifact = build.join("plugin.nro");
+        fs::write(&artifact, b"a").unwrap();
+
+        let default = sidecar_path(&artifact, DEFAULT_SIDECAR_NAME);
+        let named = sidecar_path(&artifact, "../agg/{parent}_{stem}.{hash}.txt");
+        let bad = sidecar_path(&artifact, "{name}.txt");
+        let _ = fs::remove_dir_all(&dir);
+
+        assert_eq!(default.unwrap(), build.join("plugin.nro.exports.txt"));
+        assert_eq!(
+            named.unwrap(),
+            dir.join("agg").join("v2_plugin.e40c292c.txt")
+        );
+        assert!(bad.unwrap_err().contains("unknown placeholder {name}"));
+    }
+
+    #[test]
+    fn paths_inside_the_root_become_relative() {
+        let style = PathStyle {
+            root: Some(PathBuf::from("/home/me/ws")),
+            home: Some(PathBuf::from("/home/me")),
+        };
+        let inside = Path::new("/home/me/ws/target/release/plugin.nro");
+        assert_eq!(style.show(inside), "target/release/plugin.nro");
+        assert_eq!(style.show(Path::new("/home/me/ws")), ".");
+        // Outside the root: absolute, with the home directory redacted.
+        assert_eq!(
+            style.show(Path::new("/home/me/other/a.nro")),
+            "~/other/a.nro"
+        );
+        assert_eq!(style.show(Path::new("/opt/sdk/a.nro")), "/opt/sdk/a.nro");
+        assert_eq!(
+            PathStyle::default().show(inside),
+            "/home/me/ws/target/release/plugin.nro"
+        );
+    }
+
+    #[test]
+    fn verbatim_prefixes_are_dropped() {
+        assert_eq!(
+            without_verbatim_prefix(Path::new(r"\\?\C:\ws\a.nro")),
+            PathBuf::from(r"C:\ws\a.nro")
+        );
+        assert_eq!(
+            without_verbatim_prefix(Path::new(r"\\?\UNC\server\share\a.nro")),
+            PathBuf::from(r"\\server\share\a.nro")
+        );
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn unc_paths_match_a_plain_root() {
+        let style = PathStyle {
+            root: Some(PathBuf::from(r"\\server\share\ws")),
+            home: None,
+        };
+        assert_eq!(
+            style.show(Path::new(r"\\?\UNC\server\share\ws\target\a.nro")),
+            "target/a.nro"
+        );
+        assert_eq!(
+            style.show(Path::new(r"\\other\share\a.nro")),
+            r"\\other\share\a.nro"
+        );
+    }
+
+    #[test]
+    fn integrity_check_catches_truncation_and_bad_headers() {
+        let mut nro = selftest_nro_blob();
+        let len = nro.len() as u32;
+        nro[0x18..0x1C].copy_from_slice(&len.to_le_bytes());
+        assert_eq!(verify_nro_integrity_bytes(&nro), Ok(()));
+
+        // Trailing asset data is fine; a cut-off download is not.
+        let mut with_assets = nro.clone();
+        with_assets.extend_from_slice(b"ASET");
+        assert_eq!(verify_nro_integrity_bytes(&with_assets), Ok(()));
+        let truncated = &nro[..nro.len() - 8];
+        assert!(verify_nro_integrity_bytes(truncated)
+            .unwrap_err()
+            .contains("truncated"));
+
+        let mut bad_mod = nro.clone();
+        bad_mod[0x80..0x84].copy_from_slice(b"XXXX");
+        assert!(verify_nro_integrity_bytes(&bad_mod)
+            .unwrap_err()
+            .contains("no MOD0 header"));
+        assert!(verify_nro_integrity_bytes(b"not an nro at all")
+            .unwrap_err()
+            .contains("NRO0"));
+    }
+
+    fn coff_symbol(name: [u8; 8], section: i16, class: u8, aux: u8) -> Vec<u8> {
+        let mut sym = name.to_vec();
+        sym.extend_from_slice(&0u32.to_le_bytes());
+        sym.extend_from_slice(&section.to_le_bytes());
+        sym.extend_from_slice(&0x20u16.to_le_bytes());
+        sym.extend_from_slice(&[class, aux]);
+        sym
+    }
+
+    /// An x86_64 COFF object: `symbols` (already encoded) then a string
+    /// table holding `strings`.
+    fn coff_object(symbols: &[Vec<u8>], count: u32, strings: &[u8]) -> Vec<u8> {
+        let mut obj = Vec::new();
+        obj.extend_from_slice(&0x8664u16.to_le_bytes());
+        obj.extend_from_slice(&[0; 6]);
+        obj.extend_from_slice(&20u32.to_le_bytes());
+        obj.extend_from_slice(&count.to_le_bytes());
+        obj.extend_from_slice(&[0; 4]);
+        symbols.iter().for_each(|s| obj.extend_from_slice(s));
+        obj.extend_from_slice(&(4 + strings.len() as u32).to_le_bytes());
+        obj.extend_from_slice(strings);
+        obj
+    }
+
+    fn ar_member(name: &str, body: &[u8]) -> Vec<u8> {
+        let mut member = format!(
+            "{name:<16}{:<12}{:<6}{:<6}{:<8}{:<10}`\n",
+            0,
+            0,
+            0,
+            644,
+            body.len()
+        )
+        .into_bytes();
+        member.extend_from_slice(body);
+        if body.len() % 2 == 1 {
+            member.push(b'\n');
+        }
+        member
+    }
+
+    #[test]
+    fn native_reader_lists_coff_definitions_inside_archives() {
+        let first = coff_object(
+            &[
+                coff_symbol(*b"hook\0\0\0\0", 1, 2, 1),
+                vec![0xff; 18],
+                coff_symbol([0, 0, 0, 0, 4, 0, 0, 0], 1, 2, 0),
+                coff_symbol(*b"extern_u", 0, 2, 0),
+                coff_symbol(*b"local\0\0\0", 1, 3, 0),
+            ],
+            5,
+            b"prefix__long_symbol_name\0",
+        );
+        assert_eq!(
+            objects::defined_symbols(&first),
+            Some((
+                objects::ObjectFormat::Coff,
+                vec!["hook".to_string(), "prefix__long_symbol_name".to_string()]
+            ))
+        );
+
+        let second = coff_object(
+            &[
+                coff_symbol(*b"hook\0\0\0\0", 2, 2, 0),
+                coff_symbol(*b"second\0\0", 1, 2, 0),
+            ],
+            2,
+            b"",
+        );
+        let mut bsd_body = b"second.o\0\0\0\0".to_vec();
+        bsd_body.extend_from_slice(&second);
+        let mut lib = b"!<arch>\n".to_vec();
+        lib.extend(ar_member("/", b"symbol index"));
+        lib.extend(ar_member("//", b"a_very_long_member_name.o/\n"));
+        lib.extend(ar_member("/0", &first));
+        lib.extend(ar_member("lib.rmeta/", b"rust metadata"));
+        lib.extend(ar_member("#1/12", &bsd_body));
+
+        let members = objects::archive_members(&lib).unwrap();
+        let names: Vec<&str> = members.iter().map(|(n, _)| n.as_str()).collect();
+        assert_eq!(
+            names,
+            ["a_very_long_member_name.o", "lib.rmeta", "second.o"]
+        );
+        assert_eq!(
+            objects::defined_symbols(&lib),
+            Some((
+                objects::ObjectFormat::Archive,
+                vec![
+                    "hook".to_string(),
+                    "prefix__long_symbol_name".to_string(),
+                    "second".to_string()
+                ]
+            ))
+        );
+        assert_eq!(objects::defined_symbols(b"not an object at all"), None);
+    }
+
+    #[test]
+    fn native_reader_rejects_offsets_that_overflow() {
+        // ELF64 whose section header table starts just below usize::MAX.
+        let mut elf = vec![0u8; 64];
+        elf[..6].copy_from_slice(b"\x7fELF\x02\x01");
+        elf[0x28..0x30].copy_from_slice(&(u64::MAX - 2).to_le_bytes());
+        elf[0x3a..0x3c].copy_from_slice(&64u16.to_le_bytes());
+        elf[0x3c..0x3e].copy_from_slice(&1u16.to_le_bytes());
+        assert_eq!(objects::defined_symbols(&elf), None);
+
+        // Archive member claiming a size that wraps the next header offset.
+        let mut ar = b"!<arch>\n".to_vec();
+        ar.extend(format!("{:<16}{:<32}{:<10}`\n", "a.o/", "", usize::MAX).bytes());
+        assert_eq!(objects::archive_members(&ar), None);
+
+        assert_eq!(
+            objects::demangle_legacy(&format!("_ZN{}xE", usize::MAX)),
+            None
+        );
+    }
+
+    #[test]
+    fn native_reader_lists_macho_and_pe_exports() {
+        let put = |buf: &mut Vec<u8>, off: usize, bytes: &[u8]| {
+            if buf.len() < off + bytes.len() {
+                buf.resize(off + bytes.len(), 0);
+            }
+            buf[off..off + bytes.len()].copy_from_slice(bytes);
+        };
+
+        let mut macho = Vec::new();
+        put(&mut macho, 0, &0xfeed_facfu32.to_le_bytes());
+        put(&mut macho, 16, &1u32.to_le_bytes());
+        let strings = b"\0_exported\0_undef\0_local\0_stab\0";
+        for (i, v) in [2u32, 24, 56, 4, 120, strings.len() as u32]
+            .iter()
+            .enumerate()
+        {
+            put(&mut macho, 32 + 4 * i, &v.to_le_bytes());
+        }
+        // (strx, n_type): defined external, undefined, private, stab.
+        for (i, (strx, n_type)) in [(1u32, 0x0fu8), (11, 0x01), (18, 0x0e), (25, 0x25)]
+            .into_iter()
+            .enumerate()
+        {
+            put(&mut macho, 56 + 16 * i, &strx.to_le_bytes());
+            put(&mut macho, 60 + 16 * i, &[n_type, 1]);
+        }
+        put(&mut macho, 120, strings);
+        assert_eq!(
+            objects::defined_symbols(&macho),
+            Some((objects::ObjectFormat::MachO, vec!["_exported".to_string()]))
+        );
+
+        // PE32+ with one section holding the export directory at rva 0x1000.
+        let mut pe = Vec::new();
+        put(&mut pe, 0, b"MZ");
+        put(&mut pe, 0x3c, &0x40u32.to_le_bytes());
+        put(&mut pe, 0x40, b"PE\0\0");
+        put(&mut pe, 0x44, &0x8664u16.to_le_bytes());
+        put(&mut pe, 0x46, &1u16.to_le_bytes());
+        put(&mut pe, 0x54, &0xf0u16.to_le_bytes());
+        put(&mut pe, 0x58, &0x20bu16.to_le_bytes());
+        put(&mut pe, 0x58 + 112, &0x1000u32.to_le_bytes());
+        put(&mut pe, 0x58 + 116, &0x100u32.to_le_bytes());
+        for (i, v) in [0x100u32, 0x1000, 0x100, 0x200].iter().enumerate() {
+            put(&mut pe, 0x148 + 8 + 4 * i, &v.to_le_bytes());
+        }
+        put(&mut pe, 0x200 + 24, &2u32.to_le_bytes());
+        put(&mut pe, 0x200 + 32, &0x1040u32.to_le_bytes());
+        put(&mut pe, 0x240, &0x1060u32.to_le_bytes());
+        put(&mut pe, 0x244, &0x1070u32.to_le_bytes());
+        put(&mut pe, 0x260, b"plugin__init\0");
+        put(&mut pe, 0x270, b"plugin__frame\0");
+        pe.resize(0x300, 0);
+        assert_eq!(
+            objects::defined_symbols(&pe),
+            Some((
+                objects::ObjectFormat::Pe,
+                vec!["plugin__init".to_string(), "plugin__frame".to_string()]
+            ))
+        );
+    }
+
+    #[test]
+    fn legacy_symbols_demangle_without_their_hash() {
+        assert_eq!(
+            objects::demangle_legacy("_ZN11fixture_app5hooks8on_frame17h0123456789abcdefE")
+                .as_deref(),
+            Some("fixture_app::hooks::on_frame")
+        );
+        assert_eq!(
+            objects::demangle_legacy(
+                "_ZN53_$LT$fixture_app..Foo$u20$as$u20$core..fmt..Debug$GT$3fmt17h0123456789abcdefE"
+            )
+            .as_deref(),
+            Some("<fixture_app::Foo as core::fmt::Debug>::fmt")
+        );
+        assert_eq!(objects::demangle_legacy("plugin__init"), None);
+    }
+}
@@ -1,5 +1,8 @@
 use std::path::Path;
 
+#[doc(hidden)]
+pub mod resolve;
+
 fn truthy(v: &str) -> bool {
     matches!(
         v.trim().to_ascii_lowercase().as_str(),
@@ -15,8 +18,58 @@ fn setup_hint() -> &'static str {
     "Run `cargo install --git https://github.com/BlankMauser/symbaker --bin cargo-symdump --force` then `cargo symdump init --prefix <your_prefix>` from workspace root."
 }
 
-/// Returns Ok(()) when symbaker one-time init markers are present and valid.
+/// Semver compatibility key: the major version, or `0.minor` before 1.0.
+fn compat_key(version: &str) -> Option<(u64, u64)> {
+    let mut parts = version.trim().split('.');
+    let major: u64 = parts.next()?.parse().ok()?;
+    let minor: u64 = parts.next().unwrap_or("0").parse().ok()?;
+    Some(if major == 0 { (0, minor) } else { (major, 0) })
+}
+
+/// Emits a `cargo:warning` when the `symbaker` version in
+/// `SYMBAKER_MACRO_VERSION` (set by `cargo symdump`, which ships with the
+/// proc macro) is semver-incompatible with this `symbaker-build`.
+fn warn_on_macro_version_mismatch() {
+    let Some(macro_version) = env("SYMBAKER_MACRO_VERSION") else {
+        return;
+    };
+    let ours = env!("CARGO_PKG_VERSION");
+    if compat_key(&macro_version) != compat_key(ours) {
+        println!(
+            "cargo:warning=symbaker {macro_version} and symbaker-build {ours} are incompatible versions; config handling may differ. Use matching versions of both crates."
+        );
+    }
+}
+
+/// Emits a `cargo:warning` for each renamed key (see
+/// [`resolve::DEPRECATED_KEYS`]) used in a parsed `symbaker.toml`.
+pub fn validate_deprecated_keys(raw: &toml::Value) {
+    for (old, new) in resolve::deprecated_keys_in(raw) {
+        println!("cargo:warning=symbaker: config key '{old}' is deprecated; use '{new}' instead");
+    }
+}
+
+fn warn_on_deprecated_config_keys() {
+    let Some(raw) = env("SYMBAKER_CONFIG")
+        .and_then(|p| std::fs::read_to_string(p).ok())
+        .and_then(|text| toml::from_str::<toml::Value>(&text).ok())
+    else {
+        return;
+    };
+    validate_deprecated_keys(&raw);
+}
+
+/// Returns Ok(()) when symbaker one-time init markers are present and valid,
+/// or (with a `cargo:warning`) when `SYMBAKER_DISABLE=1` turns symbaker off.
 pub fn check_initialized() -> Result<(), String> {
+    warn_on_macro_version_mismatch();
+    warn_on_deprecated_config_keys();
+    if env("SYMBAKER_DISABLE").is_some_and(|v| truthy(&v)) {
+        println!(
+            "cargo:warning=symbaker: SYMBAKER_DISABLE=1, exports in this build are not prefixed"
+        );
+        return Ok(());
+    }
     let initialized = env("SYMBAKER_INITIALIZED")
         .map(|v| truthy(&v))
         .unwrap_or(false);
@@ -27,8 +80,16 @@ pub fn check_initialized() -> Result<(), String> {
         ));
     }
 
-    let cfg = env("SYMBAKER_CONFIG")
-        .ok_or_else(|| format!("symbaker-build: missing SYMBAKER_CONFIG. {}", setup_hint()))?;
+    // `cargo symdump init --metadata` keeps config in the workspace manifest.
+    let Some(cfg) = env("SYMBAKER_CONFIG") else {
+        if resolve::read_workspace_metadata_table().is_none() {
+            return Err(format!(
+                "symbaker-build: missing SYMBAKER_CONFIG. {}",
+                setup_hint()
+            ));
+        }
+        return check_enforce_inherit();
+    };
     if !Path::new(&cfg).exists() {
         return Err(format!(
             "symbaker-build: SYMBAKER_CONFIG points to missing file: {}. {}",
@@ -47,6 +108,10 @@ pub fn check_initialized() -> Result<(), String> {
         ));
     }
 
+    check_enforce_inherit()
+}
+
+fn check_enforce_inherit() -> Result<(), String> {
     let enforce_inherit = env("SYMBAKER_ENFORCE_INHERIT")
         .map(|v| truthy(&v))
         .unwrap_or(false);
@@ -60,15 +125,279 @@ pub fn check_initialized() -> Result<(), String> {
     Ok(())
 }
 
+/// Every env var read by symbaker: setup markers, `symbaker.toml` keys
+/// overridable through `SYMBAKER_*`, and trace switches.
+const SYMBAKER_ENV_KEYS: [&str; 28] = [
+    "SYMBAKER_DISABLE",
+    "SYMBAKER_INITIALIZED",
+    "SYMBAKER_CONFIG",
+    "SYMBAKER_REQUIRE_CONFIG",
+    "SYMBAKER_STRICT_CONFIG",
+    "SYMBAKER_ENFORCE_INHERIT",
+    "SYMBAKER_TOP_PACKAGE",
+    "SYMBAKER_PARENT_PACKAGES",
+    "SYMBAKER_PREFIX",
+    "SYMBAKER_SEP",
+    "SYMBAKER_SUFFIX",
+    "SYMBAKER_PRIORITY",
+    "SYMBAKER_OVERRIDES",
+    "SYMBAKER_NORMALIZE_EXPORTS",
+    "SYMBAKER_RAW_PREFIX",
+    "SYMBAKER_UNIQUENESS",
+    "SYMBAKER_COMPAT_ALIAS",
+    "SYMBAKER_COMPAT_ALIAS_UNTIL",
+    "SYMBAKER_PREFIX_ALIASES",
+    "SYMBAKER_DOCS",
+    "SYMBAKER_DENY",
+    "SYMBAKER_TRACE",
+    "SYMBAKER_TRACE_FILE",
+    "SYMBAKER_TRACE_HARD",
+    "SYMBAKER_TRACE_REDACT",
+    "SYMBAKER_MACRO_VERSION",
+    "SYMBAKER_PROFILE",
+    "SYMBAKER_TARGET_ARCH",
+];
+
+/// Emits `cargo:rerun-if-env-changed` for every env var symbaker reads, and
+/// `cargo:rerun-if-changed` for the config file and this package's
+/// `Cargo.toml` (its `[package.metadata.symbaker]` feeds resolution).
+///
+/// Once a build script prints any `rerun-if` line, cargo stops rerunning it
+/// on arbitrary package changes; add your own `rerun-if-changed` lines too.
+pub fn set_rerun_triggers() {
+    for key in SYMBAKER_ENV_KEYS {
+        println!("cargo:rerun-if-env-changed={key}");
+    }
+    if let Some(name) = env("CARGO_PKG_NAME") {
+        println!(
+            "cargo:rerun-if-env-changed={}",
+            resolve::crate_override_env_key(&name)
+        );
+    }
+    if let Some(cfg) = env("SYMBAKER_CONFIG") {
+        if Path::new(&cfg).exists() {
+            println!("cargo:rerun-if-changed={cfg}");
+        }
+    }
+    if let Some(dir) = env("CARGO_MANIFEST_DIR") {
+        println!(
+            "cargo:rerun-if-changed={}",
+            Path::new(&dir).join("Cargo.toml").display()
+        );
+    }
+}
+
+fn print_consumed_inputs() {
+    let (envs, files) = resolve::consumed_inputs();
+    for key in envs {
+        println!("cargo:rerun-if-env-changed={key}");
+    }
+    for file in files {
+        println!("cargo:rerun-if-changed={}", file.display());
+    }
+}
+
+/// Passes the build-script-only `CARGO_CFG_TARGET_ARCH` and `PROFILE` on to
+/// the crate as `SYMBAKER_TARGET_ARCH`/`SYMBAKER_PROFILE`, for the
+/// `{target_arch}`/`{profile}` template placeholders. Values already set
+/// (e.g. by `cargo symdump`) win.
+fn export_template_env() {
+    for (key, build_key) in [
+        ("SYMBAKER_TARGET_ARCH", "CARGO_CFG_TARGET_ARCH"),
+        ("SYMBAKER_PROFILE", "PROFILE"),
+    ] {
+        if let Some(value) = env(key).or_else(|| env(build_key)) {
+            println!("cargo:rustc-env={key}={value}");
+        }
+    }
+}
+
+/// [`set_rerun_triggers`] plus the env vars and files (config, workspace and
+/// package manifests) that resolving this package's prefix actually reads.
+///
+/// Proc macros cannot declare their inputs on stable Rust, so without these
+/// lines cargo/sccache may reuse a build made under a different prefix. It
+/// also forwards the target arch and profile for templates.
+pub fn emit_rerun_directives() {
+    set_rerun_triggers();
+    export_template_env();
+    if let Ok(cfg) = resolve::try_load_config() {
+        let _ = resolve::resolve_prefix(&cfg, None);
+    }
+    print_consumed_inputs();
+}
+
 /// Panics with an actionable message when the workspace is not symbaker-initialized.
+///
+/// Also exports `SYMBAKER_CONFIG_HASH` (see [`resolve::config_hash`]) to the
+/// crate being built, e.g. to embed in a version string with `env!`.
 pub fn require_initialized() {
     // Make changes in setup env/config retrigger build-script checks.
-    println!("cargo:rerun-if-env-changed=SYMBAKER_INITIALIZED");
-    println!("cargo:rerun-if-env-changed=SYMBAKER_CONFIG");
-    println!("cargo:rerun-if-env-changed=SYMBAKER_REQUIRE_CONFIG");
-    println!("cargo:rerun-if-env-changed=SYMBAKER_ENFORCE_INHERIT");
+    emit_rerun_directives();
 
     if let Err(msg) = check_initialized() {
         panic!("{msg}");
     }
+    if let Some(hash) = env("SYMBAKER_CONFIG").and_then(|p| resolve::config_hash(&p)) {
+        println!("cargo:rustc-env=SYMBAKER_CONFIG_HASH={hash}");
+    }
+}
+
+/// Resolves the symbol prefix for the crate whose build script is running,
+/// using the same priority chain as `#[symbaker]` (without an attribute prefix).
+///
+/// Emits the same rerun lines as [`set_rerun_triggers`].
+pub fn prefix() -> Result<String, String> {
+    set_rerun_triggers();
+    if env("CARGO_MANIFEST_DIR").is_none() {
+        return Err(
+            "symbaker-build: CARGO_MANIFEST_DIR is not set; call prefix() from build.rs".into(),
+        );
+    }
+
+    let require_cfg = env("SYMBAKER_REQUIRE_CONFIG")
+        .map(|v| truthy(&v))
+        .unwrap_or(false);
+    match env("SYMBAKER_CONFIG") {
+        Some(cfg) if require_cfg && !Path::new(&cfg).exists() => {
+            return Err(format!(
+                "symbaker-build: SYMBAKER_CONFIG points to missing file: {}. {}",
+                cfg,
+                setup_hint()
+            ));
+        }
+        None if require_cfg => {
+            return Err(format!(
+                "symbaker-build: SYMBAKER_REQUIRE_CONFIG=1 but SYMBAKER_CONFIG is missing. {}",
+                setup_hint()
+            ));
+        }
+        _ => {}
+    }
+
+    warn_on_deprecated_config_keys();
+    let cfg = resolve::try_load_config().map_err(|e| format!("symbaker-build: {e}"))?;
+    let (prefix, _, _, _) = resolve::resolve_prefix(&cfg, None);
+    print_consumed_inputs();
+    Ok(prefix)
+}
+
+/// Like [`prefix`], but panics with the resolution error.
+pub fn require_prefix() -> String {
+    prefix().unwrap_or_else(|msg| panic!("{msg}"))
+}
+
+/// Generates an `unsafe extern "C"` block declaring prefixed exports, for
+/// writing to `$OUT_DIR/bindings.rs` and pulling in with `include!`. Edition
+/// 2024 requires the `unsafe`; earlier editions accept it since Rust 1.82.
+///
+/// Each entry is `(name, signature)`, where `signature` is everything after
+/// the function name (`"(x: i32) -> i32"`); an empty one becomes `()`. The
+/// prefix and names go through the same `sanitize` as the macros.
+pub fn generate_extern_block(prefix: &str, sep: &str, symbols: &[(&str, &str)]) -> String {
+    let prefix = resolve::sanitize(prefix);
+    let mut out = String::new();
+    out.push_str("#[allow(non_snake_case)]\n");
+    out.push_str("unsafe extern \"C\" {\n");
+    for (name, signature) in symbols {
+        let signature = signature.trim();
+        let signature = if signature.is_empty() {
+            "()"
+        } else {
+            signature
+        };
+        out.push_str(&format!(
+            "    pub fn {}{}{}{};\n",
+            prefix,
+            sep,
+            resolve::sanitize(name),
+            signature
+        ));
+    }
+    out.push_str("}\n");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn env_names_in(text: &str, out: &mut Vec<String>) {
+        for (i, _) in text.match_indices("\"SYMBAKER_") {
+            let name: String = text[i + 1..]
+                .chars()
+                .take_while(|c| c.is_ascii_uppercase() || *c == '_')
+                .collect();
+            if !out.contains(&name) {
+                out.push(name);
+            }
+        }
+    }
+
+    #[test]
+    fn extern_block_declares_prefixed_functions() {
+        let block = generate_extern_block(
+            "my-mod",
+            "__",
+            &[
+                ("frame_hook", ""),
+                ("add", "(a: i32, b: i32) -> i32"),
+                ("bad-name", "()"),
+            ],
+        );
+        assert_eq!(
+            block,
+            "#[allow(non_snake_case)]\n\
+             unsafe extern \"C\" {\n    \
+             pub fn my_mod__frame_hook();\n    \
+             pub fn my_mod__add(a: i32, b: i32) -> i32;\n    \
+             pub fn my_mod__bad_name();\n\
+             }\n"
+        );
+    }
+
+    #[test]
+    fn deprecated_keys_are_reported_with_replacement() {
+        let raw: toml::Value =
+            toml::from_str("prefix = \"hdr\"\n[prefix_override]\nfoo = \"bar\"\n").unwrap();
+        assert_eq!(
+            resolve::deprecated_keys_in(&raw),
+            [("prefix_override", "overrides")]
+        );
+        let raw: toml::Value = toml::from_str("[overrides]\nfoo = \"bar\"\n").unwrap();
+        assert!(resolve::deprecated_keys_in(&raw).is_empty());
+    }
+
+    #[test]
+    fn compat_key_follows_semver() {
+        assert_eq!(compat_key("0.1.0"), compat_key("0.1.7"));
+        assert_ne!(compat_key("0.1.0"), compat_key("0.2.0"));
+        assert_eq!(compat_key("1.2.0"), compat_key("1.9.3"));
+        assert_ne!(compat_key("1.0.0"), compat_key("2.0.0"));
+        assert_eq!(compat_key("nope"), None);
+    }
+
+    #[test]
+    fn rerun_triggers_cover_every_env_var_read() {
+        let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+        let mut names = Vec::new();
+        for dir in [root.join("src"), root.join("..").join("src")] {
+            for entry in std::fs::read_dir(&dir).unwrap() {
+                let path = entry.unwrap().path();
+                if path.extension().and_then(|e| e.to_str()) == Some("rs") {
+                    env_names_in(&std::fs::read_to_string(&path).unwrap(), &mut names);
+                }
+            }
+        }
+        // Trailing-`_` matches are `Env::prefixed("SYMBAKER_")` itself; crate
+        // overrides get their own per-package trigger.
+        names.retain(|n| !n.ends_with('_') && !n.starts_with(resolve::CRATE_OVERRIDE_ENV_PREFIX));
+        assert!(!names.is_empty());
+        for name in names {
+            assert!(
+                SYMBAKER_ENV_KEYS.contains(&name.as_str()),
+                "{name} is read but not in SYMBAKER_ENV_KEYS"
+            );
+        }
+    }
 }
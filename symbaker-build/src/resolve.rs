@@ -0,0 +1,1764 @@
+//! Prefix resolution shared by the `symbaker` proc macros and
+//! [`crate::prefix`]. Not a stable API.
+
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    env::VarError,
+    fs::OpenOptions,
+    io::Write,
+    path::{Path, PathBuf},
+    sync::{Mutex, OnceLock},
+};
+
+use figment::{
+    providers::{Env, Format, Serialized, Toml},
+    Figment,
+};
+use serde::Deserialize;
+
+/// Env vars and files consulted so far, for dependency tracking.
+static INPUTS: Mutex<(BTreeSet<String>, BTreeSet<PathBuf>)> =
+    Mutex::new((BTreeSet::new(), BTreeSet::new()));
+
+/// `std::env::var` that records `key` as an input of the expansion.
+pub fn env_var(key: &str) -> Result<String, VarError> {
+    if let Ok(mut inputs) = INPUTS.lock() {
+        inputs.0.insert(key.to_string());
+    }
+    std::env::var(key)
+}
+
+fn record_file(path: &Path) {
+    if let Ok(mut inputs) = INPUTS.lock() {
+        inputs.1.insert(path.to_path_buf());
+    }
+}
+
+/// Every env var (set or not) and existing file read by resolution so far.
+pub fn consumed_inputs() -> (Vec<String>, Vec<PathBuf>) {
+    match INPUTS.lock() {
+        Ok(inputs) => (
+            inputs.0.iter().cloned().collect(),
+            inputs.1.iter().cloned().collect(),
+        ),
+        Err(_) => (Vec::new(), Vec::new()),
+    }
+}
+
+#[derive(Debug, Deserialize, Default)]
+pub struct Config {
+    pub prefix: Option<String>,
+    pub sep: Option<String>,
+    /// Appended after the Rust name of every export, e.g. `_x`.
+    pub suffix: Option<String>,
+    pub priority: Option<Vec<String>>,
+    pub overrides: Option<HashMap<String, String>>,
+    pub normalize_exports: Option<bool>,
+    pub compat_alias: Option<bool>,
+    pub compat_alias_until: Option<String>,
+    pub prefix_aliases: Option<HashMap<String, String>>,
+    pub docs: Option<bool>,
+    pub uniqueness: Option<String>,
+    pub deny: Option<Vec<String>>,
+    /// `SYMBAKER_RAW_PREFIX=1` or `raw = true` on the attribute: use the
+    /// prefix verbatim instead of sanitizing it into a C identifier.
+    #[serde(skip)]
+    pub raw_prefix: bool,
+    /// `SYMBAKER_PREFIX`, the "env_prefix" priority source. It never
+    /// replaces the file `prefix` (the "config" source); the priority list
+    /// decides between them.
+    #[serde(skip)]
+    pub env_prefix: Option<String>,
+    /// Where each scalar setting with an env override came from.
+    #[serde(skip)]
+    pub origins: BTreeMap<&'static str, ConfigOrigin>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConfigOrigin {
+    /// The `SYMBAKER_CONFIG` file or `[workspace.metadata.symbaker]`.
+    File,
+    Env,
+    /// `[package.metadata.symbaker]` of the crate being expanded.
+    Package,
+    /// The `sep` argument of the attribute being expanded.
+    Attr,
+    Default,
+}
+
+/// Applies the scalar env overrides read outside figment: `SYMBAKER_SEP` and
+/// `SYMBAKER_SUFFIX` replace the file values, `SYMBAKER_PREFIX` becomes
+/// [`Config::env_prefix`].
+fn apply_scalar_env_overrides(cfg: &mut Config, env: impl Fn(&str) -> Option<String>) {
+    let env = |key: &str| env(key).filter(|v| !v.trim().is_empty());
+
+    let sep_origin = match env("SYMBAKER_SEP") {
+        Some(sep) => {
+            cfg.sep = Some(sep);
+            ConfigOrigin::Env
+        }
+        None if cfg.sep.is_some() => ConfigOrigin::File,
+        None => ConfigOrigin::Default,
+    };
+    cfg.origins.insert("sep", sep_origin);
+
+    let suffix_origin = match env("SYMBAKER_SUFFIX") {
+        Some(suffix) => {
+            cfg.suffix = Some(suffix);
+            ConfigOrigin::Env
+        }
+        None if cfg.suffix.is_some() => ConfigOrigin::File,
+        None => ConfigOrigin::Default,
+    };
+    cfg.origins.insert("suffix", suffix_origin);
+
+    cfg.env_prefix = env("SYMBAKER_PREFIX");
+    let prefix_origin = match (&cfg.prefix, &cfg.env_prefix) {
+        (Some(_), _) => ConfigOrigin::File,
+        (None, Some(_)) => ConfigOrigin::Env,
+        (None, None) => ConfigOrigin::Default,
+    };
+    cfg.origins.insert("prefix", prefix_origin);
+}
+
+/// What every [`crate_override_env_key`] starts with.
+pub const CRATE_OVERRIDE_ENV_PREFIX: &str = "SYMBAKER_OVERRIDE_";
+
+/// The env var `cargo symdump --override <crate>=<prefix>` sets for `crate`.
+pub fn crate_override_env_key(crate_name: &str) -> String {
+    format!(
+        "{CRATE_OVERRIDE_ENV_PREFIX}{}",
+        crate_name.to_ascii_uppercase().replace('-', "_")
+    )
+}
+
+/// `[overrides]` key applied to every crate without an entry of its own.
+const OVERRIDE_CATCH_ALL: &str = "*";
+
+/// Rejects an `[overrides]` table whose only key is the catch-all: that is a
+/// workspace-wide prefix, which `prefix` already expresses.
+fn validate_overrides(cfg: &Config) -> Result<(), String> {
+    match &cfg.overrides {
+        Some(m) if m.len() == 1 && m.contains_key(OVERRIDE_CATCH_ALL) => Err(format!(
+            "[overrides] {OVERRIDE_CATCH_ALL:?} cannot be the only entry; set `prefix` for a single prefix, or list the crates that are exceptions"
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Adds the crate's `SYMBAKER_OVERRIDE_<CRATE>` prefix, if set, to
+/// `cfg.overrides`, replacing any file entry for it.
+fn apply_crate_override_env(cfg: &mut Config, env: impl Fn(&str) -> Option<String>) {
+    let Some(crate_name) = env("CARGO_PKG_NAME") else {
+        return;
+    };
+    let key = crate_override_env_key(&crate_name);
+    let Some(prefix) = env(&key).filter(|v| !v.trim().is_empty()) else {
+        return;
+    };
+    trace_emit(format!("load_config override from {key}={prefix:?}"));
+    cfg.overrides
+        .get_or_insert_with(HashMap::new)
+        .insert(crate_name, prefix);
+}
+
+/// Falls back to `[package.metadata.symbaker] suffix` when neither the env,
+/// the config file nor the workspace metadata set one.
+fn apply_package_metadata_suffix(cfg: &mut Config) {
+    if cfg.suffix.is_some() {
+        return;
+    }
+    if let Some(suffix) = read_package_metadata_str("suffix") {
+        cfg.suffix = Some(suffix);
+        cfg.origins.insert("suffix", ConfigOrigin::Package);
+    }
+}
+
+fn trace_config_origins(cfg: &Config) {
+    let origin = |field| cfg.origins.get(field).copied();
+    trace_emit(format!(
+        "config field=sep source={:?} value={:?}",
+        origin("sep"),
+        cfg.sep.as_deref().unwrap_or("__")
+    ));
+    trace_emit(format!(
+        "config field=suffix source={:?} value={:?}",
+        origin("suffix"),
+        cfg.suffix.as_deref().unwrap_or("")
+    ));
+    trace_emit(format!(
+        "config field=prefix source={:?} file={:?} env={:?} (priority order picks \"config\" vs \"env_prefix\")",
+        origin("prefix"),
+        cfg.prefix,
+        cfg.env_prefix
+    ));
+}
+
+/// Renamed config keys as `(old, new)`. The old names are no longer read.
+pub const DEPRECATED_KEYS: &[(&str, &str)] = &[("prefix_override", "overrides")];
+
+/// Deprecated top-level keys present in a parsed `symbaker.toml`.
+pub fn deprecated_keys_in(raw: &toml::Value) -> Vec<(&'static str, &'static str)> {
+    let Some(table) = raw.as_table() else {
+        return Vec::new();
+    };
+    DEPRECATED_KEYS
+        .iter()
+        .copied()
+        .filter(|(old, _)| table.contains_key(*old))
+        .collect()
+}
+
+/// Every top-level `symbaker.toml` key [`Config`] reads.
+pub const CONFIG_KEYS: &[&str] = &[
+    "prefix",
+    "sep",
+    "suffix",
+    "priority",
+    "overrides",
+    "normalize_exports",
+    "compat_alias",
+    "compat_alias_until",
+    "prefix_aliases",
+    "docs",
+    "uniqueness",
+    "deny",
+];
+
+/// Levenshtein distance, for "did you mean" hints.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut prev = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let cur = row[j + 1];
+            row[j + 1] = (prev + usize::from(ca != *cb)).min(row[j] + 1).min(cur + 1);
+            prev = cur;
+        }
+    }
+    row[b.len()]
+}
+
+/// One message per top-level key of a parsed `symbaker.toml` that [`Config`]
+/// does not read, naming the renamed or closest known key when there is one.
+pub fn unknown_config_keys(raw: &toml::Value) -> Vec<String> {
+    let Some(table) = raw.as_table() else {
+        return Vec::new();
+    };
+    table
+        .keys()
+        .filter(|k| !CONFIG_KEYS.contains(&k.as_str()))
+        .map(|key| {
+            let renamed = DEPRECATED_KEYS.iter().find(|(old, _)| old == key);
+            let closest = CONFIG_KEYS
+                .iter()
+                .map(|k| (edit_distance(key, k), *k))
+                .filter(|(d, _)| *d <= 2)
+                .min();
+            match (renamed, closest) {
+                (Some((_, new)), _) => format!("unknown key {key:?} (renamed to {new:?})"),
+                (None, Some((_, k))) => format!("unknown key {key:?} (did you mean {k:?}?)"),
+                (None, None) => format!("unknown key {key:?}"),
+            }
+        })
+        .collect()
+}
+
+/// `SYMBAKER_STRICT_CONFIG=1`: errors when the `SYMBAKER_CONFIG` file has
+/// keys [`Config`] does not read. By default they are ignored so older
+/// releases accept newer configs; a typo such as `priorty` then silently
+/// changes resolution. A missing or unparsable file is left to the loader.
+pub fn check_strict_config() -> Result<(), String> {
+    if !truthy_env("SYMBAKER_STRICT_CONFIG") {
+        return Ok(());
+    }
+    let Ok(path) = env_var("SYMBAKER_CONFIG") else {
+        return Ok(());
+    };
+    let Some(raw) = std::fs::read_to_string(&path)
+        .ok()
+        .and_then(|text| toml::from_str::<toml::Value>(&text).ok())
+    else {
+        return Ok(());
+    };
+    let unknown = unknown_config_keys(&raw);
+    if unknown.is_empty() {
+        return Ok(());
+    }
+    Err(format!("{path}: {}", unknown.join("; ")))
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum PrefixSource {
+    Override,
+    PreferPackagePrefixPackage,
+    PreferPackagePrefixCrateFallback,
+    Attr,
+    EnvPrefix,
+    Config,
+    TopPackage,
+    ParentPackage,
+    RootPackage,
+    Workspace,
+    Package,
+    Crate,
+    CrateFallbackAfterPriority,
+}
+
+pub fn sanitize(s: &str) -> String {
+    let mut out: String = s
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    if out.is_empty() {
+        out.push('_');
+    }
+    if out.chars().next().unwrap().is_ascii_digit() {
+        out.insert(0, '_');
+    }
+    out
+}
+
+pub fn warn_export_rewritten(detail: String) {
+    static DID_WARN: OnceLock<()> = OnceLock::new();
+    if DID_WARN.get().is_some() {
+        return;
+    }
+    let _ = DID_WARN.set(());
+    let crate_name = env_var("CARGO_PKG_NAME").unwrap_or_else(|_| "unknown".into());
+    eprintln!(
+        "warning: symbaker rewrote exports in crate {:?}: {}. Adjust prefix/sep if the resulting names are not what you expect.",
+        crate_name, detail
+    );
+}
+
+pub fn sanitize_checked(cfg: &Config, raw: &str) -> String {
+    sanitize_checked_with(cfg, raw, warn_export_rewritten)
+}
+
+/// [`sanitize_checked`], reporting a changed prefix through `warn` whether or
+/// not `normalize_exports` is on.
+fn sanitize_checked_with(cfg: &Config, raw: &str, warn: impl FnOnce(String)) -> String {
+    if cfg.raw_prefix {
+        trace_emit(format!("prefix_raw kept={:?}", raw));
+        return raw.to_string();
+    }
+    let chosen = sanitize(raw);
+    if chosen != raw {
+        trace_emit(format!("prefix_sanitize raw={:?} result={:?}", raw, chosen));
+        warn(format!("prefix {:?} was sanitized to {:?}", raw, chosen));
+    }
+    chosen
+}
+
+fn trace_enabled() -> bool {
+    match std::env::var("SYMBAKER_TRACE") {
+        Ok(v) => {
+            let n = v.trim().to_ascii_lowercase();
+            matches!(n.as_str(), "1" | "true" | "yes" | "on")
+        }
+        Err(_) => false,
+    }
+}
+
+/// Writes one trace line. Every line starts with `crate="<name>"` so
+/// `cargo symdump` can attribute it even when parallel rustc processes
+/// interleave their output in the shared trace file.
+pub fn trace_emit(line: impl AsRef<str>) {
+    if trace_enabled() {
+        trace_record(line);
+    }
+}
+
+/// Writes a trace line to stderr and `SYMBAKER_TRACE_FILE` even when
+/// `SYMBAKER_TRACE` is off (used by `SYMBAKER_TRACE_HARD`).
+pub fn trace_record(line: impl AsRef<str>) {
+    let crate_name = std::env::var("CARGO_PKG_NAME").unwrap_or_default();
+    let mut msg = format!("[symbaker] crate={:?} {}", crate_name, line.as_ref());
+    if let Some(redactor) = trace_redactor() {
+        msg = redactor.redact(&msg);
+    }
+    eprintln!("{msg}");
+
+    let path = match std::env::var("SYMBAKER_TRACE_FILE") {
+        Ok(v) if !v.trim().is_empty() => v,
+        _ => return,
+    };
+
+    // One write per line: appends of a whole line do not tear across processes.
+    if let Ok(mut file) = OpenOptions::new().create(true).append(true).open(path) {
+        let _ = file.write_all(format!("{msg}\n").as_bytes());
+    }
+}
+
+/// The redactor for this process's trace lines when `SYMBAKER_TRACE_REDACT`
+/// is on.
+fn trace_redactor() -> Option<&'static Redactor> {
+    static REDACTOR: OnceLock<Option<Redactor>> = OnceLock::new();
+    REDACTOR
+        .get_or_init(|| {
+            let on = std::env::var("SYMBAKER_TRACE_REDACT")
+                .is_ok_and(|v| matches!(v.trim(), "1" | "true" | "yes" | "on"));
+            on.then(Redactor::from_env)
+        })
+        .as_ref()
+}
+
+/// Rewrites local paths in text meant to be shared: the workspace root
+/// becomes `<workspace>`, the home directory `~`, and every component of any
+/// other absolute path an 8-digit hash in angle brackets. The same path
+/// always redacts to the same text, so redacted lines stay correlatable.
+#[derive(Clone, Debug, Default)]
+pub struct Redactor {
+    pub workspace: Option<String>,
+    pub home: Option<String>,
+}
+
+impl Redactor {
+    /// `HOME`/`USERPROFILE`, and the nearest directory at or above
+    /// `CARGO_MANIFEST_DIR` whose `Cargo.toml` has a `[workspace]` table.
+    pub fn from_env() -> Self {
+        let home = ["HOME", "USERPROFILE"]
+            .into_iter()
+            .filter_map(|k| std::env::var(k).ok())
+            .find(|v| !v.is_empty());
+        let workspace = std::env::var("CARGO_MANIFEST_DIR").ok().and_then(|dir| {
+            Path::new(&dir)
+                .ancestors()
+                .find(|d| {
+                    std::fs::read_to_string(d.join("Cargo.toml"))
+                        .ok()
+                        .and_then(|t| toml::from_str::<toml::Value>(&t).ok())
+                        .is_some_and(|v| v.get("workspace").is_some())
+                })
+                .map(|d| d.to_string_lossy().to_string())
+        });
+        Self { workspace, home }
+    }
+
+    pub fn redact(&self, text: &str) -> String {
+        let mut out = text.to_string();
+        // The workspace usually lives under the home directory: replace it first.
+        for (root, with) in [(&self.workspace, "<workspace>"), (&self.home, "~")] {
+            let Some(root) = root.as_deref() else {
+                continue;
+            };
+            let root = root.trim_end_matches(['/', '\\']);
+            if root.is_empty() {
+                continue;
+            }
+            out = replace_path_root(&out, root, with);
+            // `{:?}` output doubles the backslashes of Windows paths.
+            let escaped = format!("{root:?}");
+            let escaped = &escaped[1..escaped.len() - 1];
+            if escaped != root {
+                out = replace_path_root(&out, escaped, with);
+            }
+        }
+        hash_absolute_paths(&out)
+    }
+}
+
+/// Characters that continue a path component.
+fn in_component(c: char) -> bool {
+    c.is_alphanumeric() || matches!(c, '_' | '-' | '.' | '~' | '+' | '@' | '\u{fffd}')
+}
+
+/// Characters that end a path printed in a trace line.
+fn ends_path(c: char) -> bool {
+    c.is_whitespace() || matches!(c, '"' | '\'' | ',' | ')' | ']' | '}' | ';')
+}
+
+/// Replaces each occurrence of the path `root` in `text`, but only where it
+/// is a whole path: not preceded by a path character and not followed by
+/// more of its last component.
+fn replace_path_root(text: &str, root: &str, with: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(at) = rest.find(root) {
+        let before = rest[..at].chars().last().or_else(|| out.chars().last());
+        let after = rest[at + root.len()..].chars().next();
+        let whole = !before.is_some_and(|c| in_component(c) || matches!(c, '/' | '\\' | ':'))
+            && !after.is_some_and(in_component);
+        out.push_str(&rest[..at]);
+        out.push_str(if whole { with } else { root });
+        rest = &rest[at + root.len()..];
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Hashes every component of the absolute paths left in `text`. A path starts
+/// with `/` or a drive (`C:\`, `C:/`) at the start of a value and runs up to
+/// a quote, whitespace or closing bracket; separators and drive letters are
+/// kept.
+fn hash_absolute_paths(text: &str) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let mut out = String::with_capacity(text.len());
+    let mut i = 0;
+    while i < chars.len() {
+        let at_value = i == 0 || matches!(chars[i - 1], '"' | '\'' | '=' | ' ' | '(' | '[' | ',');
+        let drive = chars[i].is_ascii_alphabetic()
+            && chars.get(i + 1) == Some(&':')
+            && matches!(chars.get(i + 2), Some('/' | '\\'));
+        if !at_value || !(chars[i] == '/' || drive) {
+            out.push(chars[i]);
+            i += 1;
+            continue;
+        }
+        if drive {
+            out.extend(&chars[i..i + 2]);
+            i += 2;
+        }
+        let mut component = String::new();
+        while i < chars.len() && !ends_path(chars[i]) {
+            let c = chars[i];
+            if matches!(c, '/' | '\\') {
+                if !component.is_empty() {
+                    out.push_str(&format!("<{}>", fnv1a32_hex(&component)));
+                    component.clear();
+                }
+                out.push(c);
+            } else {
+                component.push(c);
+            }
+            i += 1;
+        }
+        if !component.is_empty() {
+            out.push_str(&format!("<{}>", fnv1a32_hex(&component)));
+        }
+    }
+    out
+}
+
+fn trace_bootstrap() {
+    static DID_TRACE: OnceLock<()> = OnceLock::new();
+    if DID_TRACE.get().is_some() || !trace_enabled() {
+        return;
+    }
+    let _ = DID_TRACE.set(());
+    trace_emit(format!(
+        "env CARGO_PKG_NAME={:?} CARGO_MANIFEST_DIR={:?} CARGO_PRIMARY_PACKAGE={:?} SYMBAKER_TOP_PACKAGE={:?} SYMBAKER_PREFIX={:?} SYMBAKER_CONFIG={:?} SYMBAKER_PRIORITY={:?}",
+        std::env::var("CARGO_PKG_NAME").ok(),
+        std::env::var("CARGO_MANIFEST_DIR").ok(),
+        std::env::var("CARGO_PRIMARY_PACKAGE").ok(),
+        std::env::var("SYMBAKER_TOP_PACKAGE").ok(),
+        std::env::var("SYMBAKER_PREFIX").ok(),
+        std::env::var("SYMBAKER_CONFIG").ok(),
+        std::env::var("SYMBAKER_PRIORITY").ok(),
+    ));
+    trace_emit(format!(
+        "env_fingerprint={:?}",
+        env_fingerprint(
+            |k| std::env::var(k).ok(),
+            std::env::vars_os().filter_map(|(k, _)| k.into_string().ok())
+        )
+    ));
+    if let Some(hash) = std::env::var("SYMBAKER_CONFIG")
+        .ok()
+        .and_then(|p| config_hash(&p))
+    {
+        trace_emit(format!("config_hash={hash:?}"));
+    }
+}
+
+/// Short content hash of the config file at `path`; `None` when it cannot be
+/// read. Lets `cargo symdump` spot crates expanded under an older config.
+pub fn config_hash(path: &str) -> Option<String> {
+    let body = std::fs::read(path).ok()?;
+    Some(fnv1a32_hex(&String::from_utf8_lossy(&body)))
+}
+
+/// Keys that only steer tracing and never change a name.
+const FINGERPRINT_IGNORED: [&str; 4] = [
+    "SYMBAKER_TRACE",
+    "SYMBAKER_TRACE_FILE",
+    "SYMBAKER_TRACE_HARD",
+    "SYMBAKER_TRACE_REDACT",
+];
+
+/// Hash over everything that can change a resolved name and is the same for
+/// every crate of one build: each set `SYMBAKER_*` key (sorted, tracing
+/// switches excluded), every `SYMBAKER_OVERRIDE_<CRATE>` among `names` (the
+/// env var names set for the build) and the content of the `SYMBAKER_CONFIG`
+/// file. Per-crate `CARGO_*` values are left out so that crates of one build
+/// agree.
+pub fn env_fingerprint(
+    env: impl Fn(&str) -> Option<String>,
+    names: impl IntoIterator<Item = String>,
+) -> String {
+    let mut keys: Vec<String> = crate::SYMBAKER_ENV_KEYS
+        .into_iter()
+        .filter(|k| !FINGERPRINT_IGNORED.contains(k))
+        .map(str::to_string)
+        .chain(
+            names
+                .into_iter()
+                .filter(|n| n.starts_with(CRATE_OVERRIDE_ENV_PREFIX)),
+        )
+        .collect();
+    keys.sort_unstable();
+    keys.dedup();
+    let mut input = String::new();
+    for key in keys {
+        if let Some(v) = env(&key) {
+            input.push_str(&format!("{key}={v}\n"));
+        }
+    }
+    if let Some(hash) = env("SYMBAKER_CONFIG").and_then(|p| config_hash(&p)) {
+        input.push_str(&format!("config_hash={hash}\n"));
+    }
+    fnv1a32_hex(&input)
+}
+
+pub fn truthy_env(key: &str) -> bool {
+    match env_var(key) {
+        Ok(v) => {
+            let n = v.trim().to_ascii_lowercase();
+            matches!(n.as_str(), "1" | "true" | "yes" | "on")
+        }
+        Err(_) => false,
+    }
+}
+
+pub fn load_config() -> Config {
+    match try_load_config() {
+        Ok(cfg) => cfg,
+        Err(e) => {
+            trace_emit(format!("load_config extract error: {}", e));
+            let mut cfg = Config::default();
+            apply_scalar_env_overrides(&mut cfg, |k| env_var(k).ok());
+            apply_package_metadata_suffix(&mut cfg);
+            trace_config_origins(&cfg);
+            cfg
+        }
+    }
+}
+
+/// Merges the `SYMBAKER_CONFIG` file with `SYMBAKER_*` env overrides.
+pub fn try_load_config() -> Result<Config, String> {
+    trace_bootstrap();
+
+    // Highest-level “shared” config file path
+    let cfg_path = env_var("SYMBAKER_CONFIG").ok();
+    trace_emit(format!("load_config SYMBAKER_CONFIG={:?}", cfg_path));
+
+    let mut fig = Figment::new();
+
+    // `[workspace.metadata.symbaker]` keys other than `prefix` (which stays
+    // the "workspace" priority source) as the lowest layer.
+    if let Some(mut table) = read_workspace_metadata_table() {
+        table.remove("prefix");
+        fig = fig.merge(Serialized::defaults(table));
+    }
+
+    // Optional file config
+    if let Some(p) = cfg_path.clone() {
+        let exists = Path::new(&p).exists();
+        if exists {
+            record_file(Path::new(&p));
+        }
+        trace_emit(format!(
+            "load_config merging file path={:?} exists={}",
+            p, exists
+        ));
+        fig = fig.merge(Toml::file(p));
+    }
+
+    // Optional env overrides, e.g. SYMBAKER_PRIORITY. The scalar
+    // SYMBAKER_PREFIX/SYMBAKER_SEP/SYMBAKER_SUFFIX are applied below instead,
+    // so that their precedence over the file is explicit and traced.
+    for key in crate::SYMBAKER_ENV_KEYS {
+        let _ = env_var(key);
+    }
+    fig = fig.merge(Env::prefixed("SYMBAKER_").ignore(&["prefix", "sep", "suffix"]));
+
+    let mut cfg = fig.extract::<Config>().map_err(|e| e.to_string())?;
+    validate_overrides(&cfg)?;
+    apply_scalar_env_overrides(&mut cfg, |k| env_var(k).ok());
+    apply_crate_override_env(&mut cfg, |k| env_var(k).ok());
+    apply_package_metadata_suffix(&mut cfg);
+    trace_config_origins(&cfg);
+    if let Some(raw) = cfg_path
+        .and_then(|p| std::fs::read_to_string(p).ok())
+        .and_then(|text| toml::from_str::<toml::Value>(&text).ok())
+    {
+        for (old, new) in deprecated_keys_in(&raw) {
+            trace_emit(format!("load_config deprecated key {old:?} (use {new:?})"));
+        }
+    }
+    cfg.raw_prefix = truthy_env("SYMBAKER_RAW_PREFIX");
+    trace_emit(format!(
+        "load_config extracted prefix={:?} sep={:?} priority={:?}",
+        cfg.prefix, cfg.sep, cfg.priority
+    ));
+    Ok(cfg)
+}
+
+/// Priority keys in their default order. See [`priority_keys`] for every key
+/// `priority` accepts.
+pub fn default_priority() -> Vec<String> {
+    vec![
+        "override".into(),       // [overrides] entry for this crate
+        "prefer_package".into(), // [package.metadata.symbaker] prefer_package_prefix
+        "attr".into(),
+        "env_prefix".into(),  // SYMBAKER_PREFIX
+        "config".into(),      // SYMBAKER_CONFIG file
+        "top_package".into(), // top-level package being built
+        "workspace".into(),
+        "package".into(),
+        "crate".into(),
+    ]
+}
+
+/// `cfg.priority` (or [`default_priority`]) as the resolver walks it. A list
+/// that does not mention `override`/`prefer_package` keeps them in front, as
+/// before they were keys.
+pub fn effective_priority(cfg: &Config) -> Vec<String> {
+    let mut prio = cfg.priority.clone().unwrap_or_else(default_priority);
+    for key in ["prefer_package", "override"] {
+        if !prio.iter().any(|k| k == key) {
+            prio.insert(0, key.to_string());
+        }
+    }
+    prio
+}
+
+/// Every key `priority` accepts: [`default_priority`] plus the opt-in
+/// `parent_package`/`root_package`.
+pub fn priority_keys() -> Vec<String> {
+    let mut keys = default_priority();
+    keys.extend(["parent_package".into(), "root_package".into()]);
+    keys
+}
+
+/// Parses a comma-separated `priority` list, rejecting unknown and repeated
+/// keys.
+pub fn parse_priority_list(raw: &str) -> Result<Vec<String>, String> {
+    let known = priority_keys();
+    let mut out = Vec::<String>::new();
+    for key in raw.split(',').map(str::trim) {
+        if !known.iter().any(|k| k == key) {
+            return Err(format!(
+                "unknown priority key {:?} (expected one of {})",
+                key,
+                known.join(", ")
+            ));
+        }
+        if out.iter().any(|k| k == key) {
+            return Err(format!("duplicate priority key {:?}", key));
+        }
+        out.push(key.to_string());
+    }
+    Ok(out)
+}
+
+/// `SYMBAKER_PARENT_PACKAGES`: comma-separated `child=parent` package names,
+/// one per crate that (transitively) uses symbaker. `cargo symdump` fills it
+/// from `cargo metadata`.
+fn parent_packages() -> HashMap<String, String> {
+    env_var("SYMBAKER_PARENT_PACKAGES")
+        .unwrap_or_default()
+        .split(',')
+        .filter_map(|pair| {
+            let (child, parent) = pair.split_once('=')?;
+            let (child, parent) = (child.trim(), parent.trim());
+            (!child.is_empty() && !parent.is_empty()).then(|| (child.into(), parent.into()))
+        })
+        .collect()
+}
+
+/// The package that pulled this crate in.
+pub fn parent_package_name() -> Option<String> {
+    let name = env_var("CARGO_PKG_NAME").ok()?;
+    parent_packages().remove(&name)
+}
+
+/// The last ancestor reached by following parents up from this crate.
+pub fn root_package_name() -> Option<String> {
+    let parents = parent_packages();
+    let mut current = env_var("CARGO_PKG_NAME").ok()?;
+    let mut seen = BTreeSet::new();
+    while let Some(parent) = parents.get(&current) {
+        if !seen.insert(current.clone()) {
+            break;
+        }
+        current = parent.clone();
+    }
+    (!seen.is_empty()).then_some(current)
+}
+
+pub fn top_level_package_name() -> Option<String> {
+    detect_top_level_package_name()
+}
+
+pub fn detect_top_level_package_name() -> Option<String> {
+    if let Ok(v) = env_var("SYMBAKER_TOP_PACKAGE") {
+        if !v.trim().is_empty() {
+            return Some(v);
+        }
+    }
+
+    if env_var("CARGO_PRIMARY_PACKAGE").is_ok() {
+        if let Ok(v) = env_var("CARGO_PKG_NAME") {
+            if !v.trim().is_empty() {
+                return Some(v);
+            }
+        }
+    }
+
+    None
+}
+
+pub fn read_prefix_from_workspace_metadata() -> Option<String> {
+    // Only works when the crate being compiled is in/under a workspace
+    // (path deps / workspace members). For git deps, this likely won’t find caller workspace.
+    let mut dir = PathBuf::from(env_var("CARGO_MANIFEST_DIR").ok()?);
+    loop {
+        let cargo = dir.join("Cargo.toml");
+        if cargo.exists() {
+            record_file(&cargo);
+            let text = std::fs::read_to_string(&cargo).ok()?;
+            let v: toml::Value = toml::from_str(&text).ok()?;
+            if let Some(prefix) = v
+                .get("workspace")
+                .and_then(|w| w.get("metadata"))
+                .and_then(|m| m.get("symbaker"))
+                .and_then(|s| s.get("prefix"))
+                .and_then(|p| p.as_str())
+            {
+                trace_emit(format!(
+                    "workspace metadata prefix found in {}: {:?}",
+                    cargo.display(),
+                    prefix
+                ));
+                return Some(prefix.to_string());
+            }
+        }
+        if !dir.pop() {
+            break;
+        }
+    }
+    trace_emit("workspace metadata prefix not found while walking parent Cargo.toml files");
+    None
+}
+
+/// The nearest `[workspace.metadata.symbaker]` table above the crate being built.
+pub fn read_workspace_metadata_table() -> Option<toml::Table> {
+    let mut dir = PathBuf::from(env_var("CARGO_MANIFEST_DIR").ok()?);
+    loop {
+        let cargo = dir.join("Cargo.toml");
+        if cargo.exists() {
+            record_file(&cargo);
+            let table = std::fs::read_to_string(&cargo)
+                .ok()
+                .and_then(|text| toml::from_str::<toml::Value>(&text).ok())
+                .and_then(|v| {
+                    v.get("workspace")?
+                        .get("metadata")?
+                        .get("symbaker")?
+                        .as_table()
+                        .cloned()
+                });
+            if table.is_some() {
+                return table;
+            }
+        }
+        if !dir.pop() {
+            return None;
+        }
+    }
+}
+
+pub fn read_prefix_from_package_metadata() -> Option<String> {
+    read_package_metadata_str("prefix")
+}
+
+pub fn read_package_metadata_str(key: &str) -> Option<String> {
+    let dir = env_var("CARGO_MANIFEST_DIR").ok()?;
+    let cargo = Path::new(&dir).join("Cargo.toml");
+    record_file(&cargo);
+    let text = std::fs::read_to_string(cargo).ok()?;
+    let v: toml::Value = toml::from_str(&text).ok()?;
+    v.get("package")
+        .and_then(|p| p.get("metadata"))
+        .and_then(|m| m.get("symbaker"))
+        .and_then(|s| s.get(key))
+        .and_then(|p| p.as_str())
+        .map(|s| s.to_string())
+}
+
+fn read_package_prefers_own_prefix() -> bool {
+    read_package_metadata_bool("prefer_package_prefix")
+}
+
+pub fn read_package_metadata_bool(key: &str) -> bool {
+    let dir = match env_var("CARGO_MANIFEST_DIR") {
+        Ok(v) => v,
+        Err(_) => return false,
+    };
+    let cargo = Path::new(&dir).join("Cargo.toml");
+    record_file(&cargo);
+    let text = match std::fs::read_to_string(cargo) {
+        Ok(v) => v,
+        Err(_) => return false,
+    };
+    let v: toml::Value = match toml::from_str(&text) {
+        Ok(v) => v,
+        Err(_) => return false,
+    };
+    v.get("package")
+        .and_then(|p| p.get("metadata"))
+        .and_then(|m| m.get("symbaker"))
+        .and_then(|s| s.get(key))
+        .and_then(|b| b.as_bool())
+        .unwrap_or(false)
+}
+
+/// `package.edition` of the crate being expanded, following
+/// `edition.workspace = true` to the nearest `[workspace.package]`. `None`
+/// when the manifest cannot be read; cargo's own default is 2015.
+pub fn read_package_edition() -> Option<String> {
+    let mut dir = PathBuf::from(env_var("CARGO_MANIFEST_DIR").ok()?);
+    let cargo = dir.join("Cargo.toml");
+    record_file(&cargo);
+    let text = std::fs::read_to_string(cargo).ok()?;
+    let v: toml::Value = toml::from_str(&text).ok()?;
+    let edition = v.get("package").and_then(|p| p.get("edition"));
+    let inherited = edition
+        .and_then(|e| e.get("workspace"))
+        .and_then(|w| w.as_bool())
+        .unwrap_or(false);
+    if !inherited {
+        return Some(
+            edition
+                .and_then(|e| e.as_str())
+                .unwrap_or("2015")
+                .to_string(),
+        );
+    }
+    while dir.pop() {
+        let cargo = dir.join("Cargo.toml");
+        if !cargo.exists() {
+            continue;
+        }
+        record_file(&cargo);
+        let text = std::fs::read_to_string(&cargo).ok()?;
+        let v: toml::Value = toml::from_str(&text).ok()?;
+        if let Some(ws) = v.get("workspace") {
+            return ws
+                .get("package")
+                .and_then(|p| p.get("edition"))
+                .and_then(|e| e.as_str())
+                .map(|s| s.to_string());
+        }
+    }
+    None
+}
+
+/// Whether the crate being expanded builds a dynamic library: its `[lib]
+/// crate-type` lists `cdylib` or `dylib`. Only those have a dynamic export
+/// list, which keeps Rust items but drops `global_asm!` symbols.
+pub fn builds_dynamic_lib() -> bool {
+    let Ok(dir) = env_var("CARGO_MANIFEST_DIR") else {
+        return false;
+    };
+    let cargo = Path::new(&dir).join("Cargo.toml");
+    record_file(&cargo);
+    let Some(v) = std::fs::read_to_string(cargo)
+        .ok()
+        .and_then(|text| toml::from_str::<toml::Value>(&text).ok())
+    else {
+        return false;
+    };
+    v.get("lib")
+        .and_then(|l| l.get("crate-type"))
+        .and_then(|t| t.as_array())
+        .is_some_and(|types| {
+            types
+                .iter()
+                .any(|t| matches!(t.as_str(), Some("cdylib") | Some("dylib")))
+        })
+}
+
+/// Hashes `input` with 32-bit FNV-1a (offset basis `0x811c9dc5`, prime
+/// `0x01000193`) over its UTF-8 bytes, rendered as 8 lowercase hex digits.
+pub fn fnv1a32_hex(input: &str) -> String {
+    let mut h: u32 = 0x811c_9dc5;
+    for b in input.bytes() {
+        h ^= u32::from(b);
+        h = h.wrapping_mul(0x0100_0193);
+    }
+    format!("{h:08x}")
+}
+
+/// Input for `uniqueness = "hash"`: `top_package`, crate name and crate
+/// version joined with `/` (e.g. `hdr/hdr_core/0.3.1`).
+pub fn prefix_hash_input(top_package: &str, crate_name: &str, version: &str) -> String {
+    format!("{top_package}/{crate_name}/{version}")
+}
+
+/// Applies the configured `uniqueness` mode to an already resolved prefix.
+fn apply_uniqueness(cfg: &Config, prefix: String) -> String {
+    match cfg.uniqueness.as_deref().map(str::trim) {
+        None | Some("") | Some("none") => prefix,
+        Some("hash") => {
+            let crate_name = env_var("CARGO_PKG_NAME").unwrap_or_else(|_| "crate".into());
+            let version = env_var("CARGO_PKG_VERSION").unwrap_or_default();
+            // Build scripts never see CARGO_PRIMARY_PACKAGE, so fall back to the
+            // crate itself to keep `symbaker_build::prefix()` in agreement.
+            let top = top_level_package_name().unwrap_or_else(|| crate_name.clone());
+            let input = prefix_hash_input(&top, &crate_name, &version);
+            let hash = fnv1a32_hex(&input);
+            let hashed = format!("{prefix}_{hash}");
+            trace_emit(format!(
+                "prefix_hash base={:?} input={:?} algorithm=\"fnv1a32\" hash={:?} result={:?}",
+                prefix, input, hash, hashed
+            ));
+            hashed
+        }
+        Some(other) => {
+            static DID_WARN: OnceLock<()> = OnceLock::new();
+            if DID_WARN.set(()).is_ok() {
+                eprintln!(
+                    "warning: symbaker: unknown uniqueness mode {:?} (expected \"none\" or \"hash\"); leaving prefixes unchanged",
+                    other
+                );
+            }
+            prefix
+        }
+    }
+}
+
+/// Every prefix the priority loop can pick from, besides the attribute.
+struct PrefixCandidates {
+    env_prefix: Option<String>,
+    top_package: Option<String>,
+    parent_package: Option<String>,
+    root_package: Option<String>,
+    workspace_prefix: Option<String>,
+    package_prefix: Option<String>,
+    override_prefix: Option<String>,
+    crate_name: String,
+}
+
+impl PrefixCandidates {
+    fn gather(cfg: &Config) -> Self {
+        let crate_name = env_var("CARGO_PKG_NAME").unwrap_or_else(|_| "crate".into());
+        PrefixCandidates {
+            env_prefix: cfg.env_prefix.clone(),
+            top_package: top_level_package_name(),
+            parent_package: parent_package_name(),
+            root_package: root_package_name(),
+            workspace_prefix: read_prefix_from_workspace_metadata(),
+            package_prefix: read_prefix_from_package_metadata(),
+            override_prefix: crate_override(cfg, &crate_name),
+            crate_name,
+        }
+    }
+}
+
+/// `crate_name`'s `[overrides]` entry, else the catch-all `"*"` entry.
+fn crate_override(cfg: &Config, crate_name: &str) -> Option<String> {
+    let overrides = cfg.overrides.as_ref()?;
+    if let Some(prefix) = overrides.get(crate_name) {
+        return Some(prefix.clone());
+    }
+    let prefix = overrides.get(OVERRIDE_CATCH_ALL)?;
+    trace_emit(format!(
+        "override crate={crate_name:?} matched catch-all {OVERRIDE_CATCH_ALL:?} prefix={prefix:?}"
+    ));
+    Some(prefix.clone())
+}
+
+/// Spells out how [`resolve_prefix`] named this expansion: every priority key
+/// in order (see [`effective_priority`]) with its candidate, marking the
+/// winner, then the separator and suffix. `resolved`
+/// is what [`resolve_prefix`] returned for the same inputs. One entry per
+/// line; used by `#[symbaker(explain)]`.
+pub fn explain_prefix(
+    cfg: &Config,
+    attr_prefix: Option<&str>,
+    resolved: (&str, &str, &str, PrefixSource),
+) -> Vec<String> {
+    let c = PrefixCandidates::gather(cfg);
+    let prio = effective_priority(cfg);
+    let (prefix, sep, suffix, source) = resolved;
+    let mut lines = vec![format!("crate {:?}", c.crate_name)];
+
+    let mark = |won: bool| if won { "  <- selected" } else { "" };
+    lines.push(format!("priority = {:?}", prio));
+    for key in &prio {
+        let (value, won) = match key.as_str() {
+            "override" => (
+                c.override_prefix.clone(),
+                matches!(source, PrefixSource::Override),
+            ),
+            // Only a candidate when the package sets `prefer_package_prefix`.
+            "prefer_package" => (
+                read_package_prefers_own_prefix()
+                    .then(|| c.package_prefix.clone().unwrap_or(c.crate_name.clone())),
+                matches!(
+                    source,
+                    PrefixSource::PreferPackagePrefixPackage
+                        | PrefixSource::PreferPackagePrefixCrateFallback
+                ),
+            ),
+            "attr" => (
+                attr_prefix.map(str::to_string),
+                matches!(source, PrefixSource::Attr),
+            ),
+            "env_prefix" => (
+                c.env_prefix.clone(),
+                matches!(source, PrefixSource::EnvPrefix),
+            ),
+            "config" => (cfg.prefix.clone(), matches!(source, PrefixSource::Config)),
+            "top_package" => (
+                c.top_package.clone(),
+                matches!(source, PrefixSource::TopPackage),
+            ),
+            "parent_package" => (
+                c.parent_package.clone(),
+                matches!(source, PrefixSource::ParentPackage),
+            ),
+            "root_package" => (
+                c.root_package.clone(),
+                matches!(source, PrefixSource::RootPackage),
+            ),
+            "workspace" => (
+                c.workspace_prefix.clone(),
+                matches!(source, PrefixSource::Workspace),
+            ),
+            "package" => (
+                c.package_prefix.clone(),
+                matches!(source, PrefixSource::Package),
+            ),
+            "crate" => (
+                Some(c.crate_name.clone()),
+                matches!(source, PrefixSource::Crate),
+            ),
+            _ => {
+                lines.push(format!("  {key}: unknown key, ignored"));
+                continue;
+            }
+        };
+        lines.push(format!("  {key} = {value:?}{}", mark(won)));
+    }
+    if matches!(source, PrefixSource::CrateFallbackAfterPriority) {
+        lines.push(format!(
+            "no priority key matched; fell back to the crate name {:?}",
+            c.crate_name
+        ));
+    }
+    lines.push(format!("prefix = {prefix:?} (source {source:?})"));
+    let origin = |field| {
+        cfg.origins
+            .get(field)
+            .map_or_else(|| "unset".to_string(), |o| format!("{o:?}"))
+    };
+    lines.push(format!("sep = {sep:?} (from {})", origin("sep")));
+    lines.push(format!("suffix = {suffix:?} (from {})", origin("suffix")));
+    lines
+}
+
+/// Resolves `(prefix, sep, suffix, source)` for the crate being expanded.
+/// The suffix is empty unless configured.
+pub fn resolve_prefix(
+    cfg: &Config,
+    attr_prefix: Option<String>,
+) -> (String, String, String, PrefixSource) {
+    let (prefix, sep, source) = resolve_base_prefix(cfg, attr_prefix);
+    let suffix = cfg.suffix.clone().unwrap_or_default();
+    trace_emit(format!(
+        "selected suffix={:?} source={:?}",
+        suffix,
+        cfg.origins.get("suffix")
+    ));
+    (apply_uniqueness(cfg, prefix), sep, suffix, source)
+}
+
+fn resolve_base_prefix(
+    cfg: &Config,
+    attr_prefix: Option<String>,
+) -> (String, String, PrefixSource) {
+    trace_emit(format!(
+        "resolve_prefix input attr_prefix={:?} config.prefix={:?} config.sep={:?} config.priority={:?} config.overrides_keys={:?}",
+        attr_prefix,
+        cfg.prefix,
+        cfg.sep,
+        cfg.priority,
+        cfg.overrides
+            .as_ref()
+            .map(|m| m.keys().cloned().collect::<Vec<_>>())
+    ));
+
+    let sep = cfg.sep.clone().unwrap_or_else(|| "__".into());
+    let prio = effective_priority(cfg);
+    let PrefixCandidates {
+        env_prefix,
+        top_package,
+        parent_package,
+        root_package,
+        workspace_prefix,
+        package_prefix,
+        override_prefix,
+        crate_name,
+    } = PrefixCandidates::gather(cfg);
+
+    trace_emit(format!(
+        "resolved candidates env_prefix={:?} top_package={:?} parent_package={:?} root_package={:?} workspace_prefix={:?} package_prefix={:?} override_prefix={:?} crate={:?} sep={:?}",
+        env_prefix, top_package, parent_package, root_package, workspace_prefix, package_prefix, override_prefix, crate_name, sep
+    ));
+
+    // Note: “config” here means the parsed file via SYMBAKER_CONFIG;
+    // SYMBAKER_PREFIX is only ever the separate "env_prefix" source.
+    for key in prio {
+        match key.as_str() {
+            "override" => {
+                if let Some(p) = &override_prefix {
+                    let chosen = sanitize_checked(cfg, p);
+                    trace_emit(format!(
+                        "selected source=override(crate={:?}) raw={:?} sanitized={:?}",
+                        crate_name, p, chosen
+                    ));
+                    return (chosen, sep, PrefixSource::Override);
+                }
+            }
+            // Per-crate opt-out of inherited top-level prefix.
+            // If set, package prefix wins (or crate name fallback if no explicit prefix).
+            "prefer_package" => {
+                if !read_package_prefers_own_prefix() {
+                    continue;
+                }
+                if let Some(p) = &package_prefix {
+                    let chosen = sanitize_checked(cfg, p);
+                    trace_emit(format!(
+                        "selected source=prefer_package_prefix(package) raw={:?} sanitized={:?}",
+                        p, chosen
+                    ));
+                    return (chosen, sep, PrefixSource::PreferPackagePrefixPackage);
+                }
+                let chosen = sanitize_checked(cfg, &crate_name);
+                trace_emit(format!(
+                    "selected source=prefer_package_prefix(crate_fallback) raw={:?} sanitized={:?}",
+                    crate_name, chosen
+                ));
+                return (chosen, sep, PrefixSource::PreferPackagePrefixCrateFallback);
+            }
+            "attr" => {
+                if let Some(p) = &attr_prefix {
+                    let chosen = sanitize_checked(cfg, p);
+                    trace_emit(format!(
+                        "selected source=attr raw={:?} sanitized={:?}",
+                        p, chosen
+                    ));
+                    return (chosen, sep, PrefixSource::Attr);
+                }
+            }
+            "env_prefix" => {
+                if let Some(p) = &env_prefix {
+                    let chosen = sanitize_checked(cfg, p);
+                    trace_emit(format!(
+                        "selected source=env_prefix raw={:?} sanitized={:?}",
+                        p, chosen
+                    ));
+                    return (chosen, sep, PrefixSource::EnvPrefix);
+                }
+            }
+            "config" => {
+                if let Some(p) = &cfg.prefix {
+                    let chosen = sanitize_checked(cfg, p);
+                    trace_emit(format!(
+                        "selected source=config raw={:?} sanitized={:?}",
+                        p, chosen
+                    ));
+                    return (chosen, sep, PrefixSource::Config);
+                }
+            }
+            "top_package" => {
+                if let Some(p) = &top_package {
+                    let chosen = sanitize_checked(cfg, p);
+                    trace_emit(format!(
+                        "selected source=top_package raw={:?} sanitized={:?}",
+                        p, chosen
+                    ));
+                    return (chosen, sep, PrefixSource::TopPackage);
+                }
+            }
+            "parent_package" => {
+                if let Some(p) = &parent_package {
+                    let chosen = sanitize_checked(cfg, p);
+                    trace_emit(format!(
+                        "selected source=parent_package raw={:?} sanitized={:?}",
+                        p, chosen
+                    ));
+                    return (chosen, sep, PrefixSource::ParentPackage);
+                }
+            }
+            "root_package" => {
+                if let Some(p) = &root_package {
+                    let chosen = sanitize_checked(cfg, p);
+                    trace_emit(format!(
+                        "selected source=root_package raw={:?} sanitized={:?}",
+                        p, chosen
+                    ));
+                    return (chosen, sep, PrefixSource::RootPackage);
+                }
+            }
+            "workspace" => {
+                if let Some(p) = &workspace_prefix {
+                    let chosen = sanitize_checked(cfg, p);
+                    trace_emit(format!(
+                        "selected source=workspace raw={:?} sanitized={:?}",
+                        p, chosen
+                    ));
+                    return (chosen, sep, PrefixSource::Workspace);
+                }
+            }
+            "package" => {
+                if let Some(p) = &package_prefix {
+                    let chosen = sanitize_checked(cfg, p);
+                    trace_emit(format!(
+                        "selected source=package raw={:?} sanitized={:?}",
+                        p, chosen
+                    ));
+                    return (chosen, sep, PrefixSource::Package);
+                }
+            }
+            "crate" => {
+                let chosen = sanitize_checked(cfg, &crate_name);
+                trace_emit(format!(
+                    "selected source=crate raw={:?} sanitized={:?}",
+                    crate_name, chosen
+                ));
+                return (chosen, sep, PrefixSource::Crate);
+            }
+            _ => trace_emit(format!("priority key {:?} is unknown and ignored", key)),
+        }
+    }
+
+    let chosen = sanitize_checked(cfg, &crate_name);
+    trace_emit(format!(
+        "selected source=crate_fallback_after_priority raw={:?} sanitized={:?}",
+        crate_name, chosen
+    ));
+    (chosen, sep, PrefixSource::CrateFallbackAfterPriority)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Known answers pin the algorithm so every host derives the same names.
+    #[test]
+    fn fnv1a32_known_answers() {
+        assert_eq!(fnv1a32_hex(""), "811c9dc5");
+        assert_eq!(fnv1a32_hex("a"), "e40c292c");
+        assert_eq!(fnv1a32_hex("foobar"), "bf9cf968");
+    }
+
+    fn with_env<'a>(vars: &'a [(&str, &str)]) -> impl Fn(&str) -> Option<String> + 'a {
+        move |key| {
+            vars.iter()
+                .find(|(k, _)| *k == key)
+                .map(|(_, v)| v.to_string())
+        }
+    }
+
+    fn fingerprint(vars: &[(&str, &str)]) -> String {
+        env_fingerprint(with_env(vars), vars.iter().map(|(k, _)| k.to_string()))
+    }
+
+    #[test]
+    fn env_fingerprint_ignores_trace_switches_and_tracks_config_content() {
+        let base = fingerprint(&[("SYMBAKER_PREFIX", "hdr")]);
+        assert_eq!(
+            base,
+            fingerprint(&[
+                ("SYMBAKER_TRACE", "1"),
+                ("SYMBAKER_TRACE_FILE", "/tmp/x.log"),
+                ("SYMBAKER_PREFIX", "hdr"),
+            ])
+        );
+        assert_ne!(base, fingerprint(&[("SYMBAKER_PREFIX", "other")]));
+        let overridden = fingerprint(&[
+            ("SYMBAKER_PREFIX", "hdr"),
+            ("SYMBAKER_OVERRIDE_DEP_LIB", "dep"),
+        ]);
+        assert_ne!(
+            base, overridden,
+            "crate overrides must feed the fingerprint"
+        );
+        assert_ne!(
+            overridden,
+            fingerprint(&[
+                ("SYMBAKER_PREFIX", "hdr"),
+                ("SYMBAKER_OVERRIDE_DEP_LIB", "other"),
+            ])
+        );
+
+        let dir = std::env::temp_dir().join(format!("symbaker_fp_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let cfg = dir.join("symbaker.toml");
+        let cfg_str = cfg.to_str().unwrap();
+        std::fs::write(&cfg, "prefix = \"a\"\n").unwrap();
+        let first = fingerprint(&[("SYMBAKER_CONFIG", cfg_str)]);
+        std::fs::write(&cfg, "prefix = \"b\"\n").unwrap();
+        let second = fingerprint(&[("SYMBAKER_CONFIG", cfg_str)]);
+        assert_eq!(config_hash(cfg_str), Some(fnv1a32_hex("prefix = \"b\"\n")));
+        let _ = std::fs::remove_dir_all(&dir);
+        assert_ne!(first, second, "config content must feed the fingerprint");
+        assert_eq!(config_hash(cfg_str), None);
+    }
+
+    #[test]
+    fn sanitized_prefix_warns_without_normalize_exports() {
+        let cfg = Config::default();
+        assert_eq!(cfg.normalize_exports, None);
+        let mut warned = None;
+        let chosen = sanitize_checked_with(&cfg, "my mod", |w| warned = Some(w));
+        assert_eq!(chosen, "my_mod");
+        assert_eq!(
+            warned.as_deref(),
+            Some("prefix \"my mod\" was sanitized to \"my_mod\"")
+        );
+
+        let mut warned = None;
+        sanitize_checked_with(&cfg, "hdr", |w| warned = Some(w));
+        assert_eq!(warned, None, "an unchanged prefix is not reported");
+    }
+
+    #[test]
+    fn env_sep_beats_file_sep() {
+        let mut cfg = Config {
+            sep: Some("__".into()),
+            ..Config::default()
+        };
+        apply_scalar_env_overrides(&mut cfg, with_env(&[("SYMBAKER_SEP", "_")]));
+        assert_eq!(cfg.sep.as_deref(), Some("_"));
+        assert_eq!(cfg.origins["sep"], ConfigOrigin::Env);
+
+        let mut cfg = Config {
+            sep: Some("__".into()),
+            ..Config::default()
+        };
+        apply_scalar_env_overrides(&mut cfg, with_env(&[("SYMBAKER_SEP", " ")]));
+        assert_eq!(cfg.sep.as_deref(), Some("__"));
+        assert_eq!(cfg.origins["sep"], ConfigOrigin::File);
+
+        let mut cfg = Config::default();
+        apply_scalar_env_overrides(&mut cfg, with_env(&[]));
+        assert_eq!(cfg.origins["sep"], ConfigOrigin::Default);
+    }
+
+    #[test]
+    fn env_suffix_beats_file_suffix() {
+        let mut cfg = Config {
+            suffix: Some("_x".into()),
+            ..Config::default()
+        };
+        apply_scalar_env_overrides(&mut cfg, with_env(&[("SYMBAKER_SUFFIX", "_y")]));
+        assert_eq!(cfg.suffix.as_deref(), Some("_y"));
+        assert_eq!(cfg.origins["suffix"], ConfigOrigin::Env);
+        let (_, _, suffix, _) = resolve_prefix(&cfg, Some("hdr".into()));
+        assert_eq!(suffix, "_y");
+
+        let mut cfg = Config::default();
+        apply_scalar_env_overrides(&mut cfg, with_env(&[]));
+        assert_eq!(cfg.origins["suffix"], ConfigOrigin::Default);
+        let (_, _, suffix, _) = resolve_prefix(&cfg, Some("hdr".into()));
+        assert_eq!(suffix, "");
+    }
+
+    #[test]
+    fn env_prefix_and_file_prefix_follow_priority_order() {
+        let resolve = |priority: &[&str]| {
+            let mut cfg = Config {
+                prefix: Some("file".into()),
+                priority: Some(priority.iter().map(|p| p.to_string()).collect()),
+                ..Config::default()
+            };
+            apply_scalar_env_overrides(&mut cfg, with_env(&[("SYMBAKER_PREFIX", "env")]));
+            assert_eq!(cfg.prefix.as_deref(), Some("file"));
+            assert_eq!(cfg.origins["prefix"], ConfigOrigin::File);
+            let (prefix, _, _, source) = resolve_prefix(&cfg, None);
+            (prefix, format!("{source:?}"))
+        };
+        assert_eq!(
+            resolve(&["env_prefix", "config"]),
+            ("env".to_string(), "EnvPrefix".to_string())
+        );
+        assert_eq!(
+            resolve(&["config", "env_prefix"]),
+            ("file".to_string(), "Config".to_string())
+        );
+        assert_eq!(
+            resolve(&["attr", "config"]),
+            ("file".to_string(), "Config".to_string())
+        );
+        assert_eq!(
+            resolve(&["env_prefix"]),
+            ("env".to_string(), "EnvPrefix".to_string())
+        );
+    }
+
+    #[test]
+    fn crate_override_env_replaces_file_entry() {
+        let key = crate_override_env_key("ssbu-sync");
+        assert_eq!(key.strip_suffix("_OVERRIDE_SSBU_SYNC"), Some("SYMBAKER"));
+        let mut cfg = Config {
+            overrides: Some(HashMap::from([
+                ("ssbu-sync".to_string(), "file".to_string()),
+                ("other".to_string(), "kept".to_string()),
+            ])),
+            ..Config::default()
+        };
+        apply_crate_override_env(
+            &mut cfg,
+            with_env(&[("CARGO_PKG_NAME", "ssbu-sync"), (&key, "hdr")]),
+        );
+        let overrides = cfg.overrides.unwrap();
+        assert_eq!(overrides["ssbu-sync"], "hdr");
+        assert_eq!(overrides["other"], "kept");
+
+        let mut cfg = Config::default();
+        apply_crate_override_env(
+            &mut cfg,
+            with_env(&[("CARGO_PKG_NAME", "a"), (&crate_override_env_key("b"), "x")]),
+        );
+        assert!(cfg.overrides.is_none());
+    }
+
+    #[test]
+    fn config_keys_match_config_fields() {
+        /// Captures the field names serde's derive hands to
+        /// `deserialize_struct`; `#[serde(skip)]` fields are not among them.
+        struct FieldNames<'a>(&'a mut &'static [&'static str]);
+
+        impl<'de> serde::Deserializer<'de> for FieldNames<'_> {
+            type Error = serde::de::value::Error;
+
+            fn deserialize_any<V: serde::de::Visitor<'de>>(
+                self,
+                _: V,
+            ) -> Result<V::Value, Self::Error> {
+                Err(serde::de::Error::custom("not a struct"))
+            }
+
+            fn deserialize_struct<V: serde::de::Visitor<'de>>(
+                self,
+                _: &'static str,
+                fields: &'static [&'static str],
+                _: V,
+            ) -> Result<V::Value, Self::Error> {
+                *self.0 = fields;
+                Err(serde::de::Error::custom("fields captured"))
+            }
+
+            serde::forward_to_deserialize_any! {
+                bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str
+                string bytes byte_buf option unit unit_struct newtype_struct
+                seq tuple tuple_struct map enum identifier ignored_any
+            }
+        }
+
+        let mut fields: &'static [&'static str] = &[];
+        let _ = Config::deserialize(FieldNames(&mut fields));
+        assert_eq!(fields, CONFIG_KEYS);
+    }
+
+    #[test]
+    fn unknown_config_keys_suggest_known_ones() {
+        let raw: toml::Value = toml::from_str(
+            "prefix = \"hdr\"\npriorty = [\"config\"]\nprefix_override = {}\nzzz = 1\n",
+        )
+        .unwrap();
+        let mut found = unknown_config_keys(&raw);
+        found.sort();
+        assert_eq!(
+            found,
+            [
+                "unknown key \"prefix_override\" (renamed to \"overrides\")",
+                "unknown key \"priorty\" (did you mean \"priority\"?)",
+                "unknown key \"zzz\"",
+            ]
+        );
+    }
+
+    #[test]
+    fn catch_all_override_applies_only_without_an_exact_entry() {
+        let crate_name = env_var("CARGO_PKG_NAME").unwrap_or_else(|_| "crate".into());
+        let cfg = |entries: &[(&str, &str)]| Config {
+            prefix: Some("file".into()),
+            overrides: Some(
+                entries
+                    .iter()
+                    .map(|(k, v)| (k.to_string(), v.to_string()))
+                    .collect(),
+            ),
+            ..Config::default()
+        };
+        let resolve = |cfg: &Config| {
+            let (prefix, _, _, source) = resolve_prefix(cfg, None);
+            (prefix, format!("{source:?}"))
+        };
+        assert_eq!(
+            resolve(&cfg(&[("*", "hdr"), ("other", "x")])),
+            ("hdr".to_string(), "Override".to_string())
+        );
+        assert_eq!(
+            resolve(&cfg(&[("*", "hdr"), (&crate_name, "pinned")])),
+            ("pinned".to_string(), "Override".to_string())
+        );
+        assert_eq!(
+            resolve(&cfg(&[("other", "x")])),
+            ("file".to_string(), "Config".to_string())
+        );
+
+        assert!(validate_overrides(&cfg(&[("*", "hdr")]))
+            .unwrap_err()
+            .contains("cannot be the only entry"));
+        assert!(validate_overrides(&cfg(&[("*", "hdr"), ("other", "x")])).is_ok());
+    }
+
+    #[test]
+    fn override_is_a_movable_priority_key() {
+        let crate_name = env_var("CARGO_PKG_NAME").unwrap_or_else(|_| "crate".into());
+        let resolve = |priority: Option<&[&str]>, attr: Option<&str>| {
+            let cfg = Config {
+                prefix: Some("file".into()),
+                priority: priority.map(|p| p.iter().map(|k| k.to_string()).collect()),
+                overrides: Some(HashMap::from([(crate_name.clone(), "pinned".to_string())])),
+                ..Config::default()
+            };
+            let (prefix, _, _, source) = resolve_prefix(&cfg, attr.map(str::to_string));
+            (prefix, format!("{source:?}"))
+        };
+        let pinned = ("pinned".to_string(), "Override".to_string());
+        let attr = ("hdr".to_string(), "Attr".to_string());
+
+        // Default order, and lists predating the key, keep overrides first.
+        assert_eq!(resolve(None, Some("hdr")), pinned);
+        assert_eq!(resolve(Some(&["attr", "config"]), Some("hdr")), pinned);
+        // Moved behind `attr`: a per-item prefix beats the crate override...
+        assert_eq!(
+            resolve(Some(&["attr", "override", "config"]), Some("hdr")),
+            attr
+        );
+        // ...but still wins over everything after it.
+        assert_eq!(resolve(Some(&["attr", "override", "config"]), None), pinned);
+        assert_eq!(
+            resolve(Some(&["config", "override"]), None),
+            ("file".to_string(), "Config".to_string())
+        );
+
+        let cfg = Config {
+            priority: Some(vec!["attr".into(), "override".into()]),
+            ..Config::default()
+        };
+        assert_eq!(
+            effective_priority(&cfg),
+            ["prefer_package", "attr", "override"]
+        );
+        assert!(priority_keys().iter().any(|k| k == "prefer_package"));
+    }
+
+    #[test]
+    fn redactor_replaces_nested_roots_and_hashes_other_paths() {
+        let r = Redactor {
+            workspace: Some("/home/ana/src/ws".into()),
+            home: Some("/home/ana/".into()),
+        };
+        let line = r#"env CARGO_MANIFEST_DIR=Some("/home/ana/src/ws/crates/a") SYMBAKER_CONFIG=Some("/home/ana/.config/s.toml") sibling=/home/ana/src/ws2 cache=Some("/opt/ci/cache") again=/opt/ci"#;
+        let opt = format!("/<{}>/<{}>", fnv1a32_hex("opt"), fnv1a32_hex("ci"));
+        assert_eq!(
+            r.redact(line),
+            format!(
+                r#"env CARGO_MANIFEST_DIR=Some("<workspace>/crates/a") SYMBAKER_CONFIG=Some("~/.config/s.toml") sibling=~/src/ws2 cache=Some("{opt}/<{}>") again={opt}"#,
+                fnv1a32_hex("cache")
+            )
+        );
+        assert_eq!(r.redact(line), r.redact(line));
+        // Only whole paths count: `/mnt/home/ana` is not under the home directory.
+        assert!(!r.redact("dir=/mnt/home/ana").contains('~'));
+        assert_eq!(
+            r.redact("url=https://example.com/a"),
+            "url=https://example.com/a"
+        );
+    }
+
+    #[test]
+    fn redactor_handles_escaped_and_lossy_paths() {
+        let r = Redactor {
+            workspace: Some(r"C:\Users\ana\ws".into()),
+            home: Some(r"C:\Users\ana".into()),
+        };
+        assert_eq!(
+            r.redact(r#"dir=Some("C:\\Users\\ana\\ws\\a") cfg=Some("C:\\Users\\ana\\s.toml")"#),
+            r#"dir=Some("<workspace>\\a") cfg=Some("~\\s.toml")"#
+        );
+        assert_eq!(
+            r.redact(r#"cfg=Some("D:/tmp/s.toml")"#),
+            format!(
+                r#"cfg=Some("D:/<{}>/<{}>")"#,
+                fnv1a32_hex("tmp"),
+                fnv1a32_hex("s.toml")
+            )
+        );
+
+        let r = Redactor {
+            workspace: None,
+            home: Some("/home/jürgen".into()),
+        };
+        assert_eq!(
+            r.redact("dir=\"/home/jürgen/caf\u{fffd}/x\""),
+            "dir=\"~/caf\u{fffd}/x\""
+        );
+        assert_eq!(
+            r.redact("dir=/srv/\u{fffd}\u{fffd}/jürgen"),
+            format!(
+                "dir=/<{}>/<{}>/<{}>",
+                fnv1a32_hex("srv"),
+                fnv1a32_hex("\u{fffd}\u{fffd}"),
+                fnv1a32_hex("jürgen")
+            )
+        );
+        // `{:?}` escapes of non-UTF-8 bytes are hashed as plain text.
+        assert!(!r.redact(r#"dir=Some("/srv/\u{301}x")"#).contains("srv"));
+    }
+
+    #[test]
+    fn priority_list_rejects_unknown_and_repeated_keys() {
+        assert_eq!(
+            parse_priority_list("attr, crate").unwrap(),
+            ["attr", "crate"]
+        );
+        let unknown = parse_priority_list("attr,cfg").unwrap_err();
+        assert!(
+            unknown.contains("unknown priority key \"cfg\""),
+            "{unknown}"
+        );
+        let repeated = parse_priority_list("crate,crate").unwrap_err();
+        assert!(repeated.contains("duplicate priority key"), "{repeated}");
+    }
+
+    #[test]
+    fn prefix_hash_is_stable_for_inputs() {
+        let input = prefix_hash_input("hdr", "hdr_core", "0.3.1");
+        assert_eq!(input, "hdr/hdr_core/0.3.1");
+        assert_eq!(fnv1a32_hex(&input), "ab097b4b");
+        assert_ne!(
+            fnv1a32_hex(&input),
+            fnv1a32_hex(&prefix_hash_input("hdr", "hdr_core", "0.3.2"))
+        );
+        assert_eq!(fnv1a32_hex(&input).len(), 8);
+    }
+}
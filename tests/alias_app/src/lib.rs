@@ -0,0 +1,28 @@
+use std::sync::atomic::{AtomicI32, Ordering};
+
+use symbaker::{symbaker, symbaker_module};
+
+static CALLS: AtomicI32 = AtomicI32::new(0);
+
+#[symbaker(compat_alias = true)]
+pub extern "C" fn legacy_add(a: i32, b: i32) -> i32 {
+    CALLS.fetch_add(1, Ordering::SeqCst);
+    a + b
+}
+
+#[symbaker(alias = "legacy_mul_v0")]
+pub extern "C" fn legacy_mul(a: i32, b: i32) -> i32 {
+    a * b
+}
+
+#[symbaker_module(compat_alias)]
+mod hooks {
+    pub unsafe extern "C" fn legacy_read(ptr: *const i32) -> i32 {
+        super::CALLS.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+        *ptr
+    }
+}
+
+pub fn calls() -> i32 {
+    CALLS.load(Ordering::SeqCst)
+}
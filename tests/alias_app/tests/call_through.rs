@@ -0,0 +1,23 @@
+use alias_app::calls;
+
+extern "C" {
+    fn legacy_add(a: i32, b: i32) -> i32;
+    #[link_name = "alias_app__legacy_add"]
+    fn prefixed_add(a: i32, b: i32) -> i32;
+    fn legacy_read(ptr: *const i32) -> i32;
+    #[link_name = "alias_app__legacy_read"]
+    fn prefixed_read(ptr: *const i32) -> i32;
+}
+
+#[test]
+fn alias_and_prefixed_export_share_implementation() {
+    let before = calls();
+    let value = 7;
+    unsafe {
+        assert_eq!(legacy_add(2, 3), 5);
+        assert_eq!(prefixed_add(2, 3), 5);
+        assert_eq!(legacy_read(&value), 7);
+        assert_eq!(prefixed_read(&value), 7);
+    }
+    assert_eq!(calls() - before, 4);
+}
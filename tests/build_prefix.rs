@@ -0,0 +1,112 @@
+use std::ffi::OsStr;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::Mutex;
+
+#[allow(dead_code)]
+#[path = "../src/objects.rs"]
+mod objects;
+
+/// Both tests set process env that `symbaker_build::prefix()` reads.
+static ENV_LOCK: Mutex<()> = Mutex::new(());
+
+fn find_dynamic_lib(root: &Path, stem: &str) -> Option<PathBuf> {
+    fs::read_dir(root)
+        .ok()?
+        .flatten()
+        .map(|e| e.path())
+        .find(|p| {
+            matches!(
+                p.extension().and_then(OsStr::to_str),
+                Some("dll") | Some("so") | Some("dylib")
+            ) && p
+                .file_name()
+                .and_then(OsStr::to_str)
+                .is_some_and(|n| n.contains(stem))
+        })
+}
+
+#[test]
+fn build_script_prefix_matches_macro_resolution() {
+    let _guard = ENV_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    for key in [
+        "SYMBAKER_CONFIG",
+        "SYMBAKER_PRIORITY",
+        "SYMBAKER_TOP_PACKAGE",
+        "SYMBAKER_REQUIRE_CONFIG",
+    ] {
+        std::env::remove_var(key);
+    }
+
+    std::env::set_var("SYMBAKER_PREFIX", "my-plugin");
+    assert_eq!(symbaker_build::prefix().as_deref(), Ok("my_plugin"));
+    assert_eq!(symbaker_build::require_prefix(), "my_plugin");
+
+    std::env::set_var("SYMBAKER_REQUIRE_CONFIG", "1");
+    let err = symbaker_build::prefix().expect_err("missing config must be an error");
+    assert!(err.contains("SYMBAKER_CONFIG"), "{err}");
+}
+
+#[test]
+fn build_script_prefix_matches_fixture_exports() {
+    let _guard = ENV_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    let fixture = root.join("tests").join("fixture_app");
+    let target_dir = fixture.join("target").join("build_prefix");
+    fs::create_dir_all(&target_dir)
+        .unwrap_or_else(|e| panic!("mkdir {}: {e}", target_dir.display()));
+
+    for (case, config) in [
+        (
+            "config",
+            "prefix = \"hdr-x\"\npriority = [\"config\", \"crate\"]\n",
+        ),
+        ("crate", "prefix = \"hdr\"\npriority = [\"crate\"]\n"),
+    ] {
+        let cfg = target_dir.join(format!("{case}.toml"));
+        fs::write(&cfg, config).unwrap_or_else(|e| panic!("write {}: {e}", cfg.display()));
+        let status = Command::new("cargo")
+            .arg("build")
+            .arg("--manifest-path")
+            .arg(fixture.join("Cargo.toml"))
+            .arg("--target-dir")
+            .arg(&target_dir)
+            .env_remove("SYMBAKER_PREFIX")
+            .env_remove("SYMBAKER_PRIORITY")
+            .env_remove("SYMBAKER_TOP_PACKAGE")
+            .env_remove("SYMBAKER_REQUIRE_CONFIG")
+            .env("SYMBAKER_CONFIG", &cfg)
+            .status()
+            .expect("failed to run cargo build for fixture_app");
+        assert!(status.success(), "fixture_app build failed ({case})");
+
+        let artifact_root = target_dir.join("debug");
+        let lib = find_dynamic_lib(&artifact_root, "fixture_app").unwrap_or_else(|| {
+            panic!(
+                "could not find built dynamic library under {}",
+                artifact_root.display()
+            )
+        });
+        let exports = objects::read_exports(&lib).unwrap_or_else(|e| panic!("{e}"));
+
+        // What a build script of fixture_app would see.
+        for key in [
+            "SYMBAKER_PREFIX",
+            "SYMBAKER_PRIORITY",
+            "SYMBAKER_TOP_PACKAGE",
+            "SYMBAKER_REQUIRE_CONFIG",
+        ] {
+            std::env::remove_var(key);
+        }
+        std::env::set_var("SYMBAKER_CONFIG", &cfg);
+        std::env::set_var("CARGO_PKG_NAME", "fixture_app");
+        std::env::set_var("CARGO_MANIFEST_DIR", &fixture);
+        let prefix = symbaker_build::prefix().unwrap_or_else(|e| panic!("{case}: {e}"));
+        let expected = format!("{prefix}__auto_named");
+        assert!(
+            exports.contains(&expected),
+            "{case}: build script prefix {prefix:?} does not match the macro's exports {exports:?}"
+        );
+    }
+}
@@ -0,0 +1,4 @@
+#[no_mangle]
+pub extern "C" fn app_calls_mid() -> i32 {
+    chain_mid::mid_calls_dep()
+}
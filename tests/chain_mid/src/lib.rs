@@ -0,0 +1,3 @@
+pub fn mid_calls_dep() -> i32 {
+    dep_lib::dep_exported()
+}
@@ -0,0 +1,83 @@
+use std::ffi::OsStr;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+#[allow(dead_code)]
+#[path = "../src/objects.rs"]
+mod objects;
+
+fn is_dynamic_lib(path: &Path) -> bool {
+    matches!(
+        path.extension().and_then(OsStr::to_str),
+        Some("dll") | Some("so") | Some("dylib")
+    )
+}
+
+fn find_dynamic_lib(root: &Path, stem: &str) -> Option<PathBuf> {
+    fs::read_dir(root)
+        .ok()?
+        .flatten()
+        .map(|e| e.path())
+        .find(|p| {
+            is_dynamic_lib(p)
+                && p.file_name()
+                    .and_then(OsStr::to_str)
+                    .is_some_and(|n| n.contains(stem))
+        })
+}
+
+#[test]
+fn compat_alias_exports_both_names_and_forwards() {
+    let root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    let fixture = root.join("tests").join("alias_app");
+
+    let status = Command::new("cargo")
+        .arg("build")
+        .arg("--manifest-path")
+        .arg(fixture.join("Cargo.toml"))
+        .env_remove("SYMBAKER_PREFIX")
+        .env_remove("SYMBAKER_CONFIG")
+        .env_remove("SYMBAKER_PRIORITY")
+        .env_remove("SYMBAKER_TOP_PACKAGE")
+        .status()
+        .expect("failed to build alias_app");
+    assert!(status.success(), "alias_app build failed");
+
+    let artifact_root = fixture.join("target").join("debug");
+    let lib = find_dynamic_lib(&artifact_root, "alias_app").unwrap_or_else(|| {
+        panic!(
+            "could not find alias_app dynamic library under {}",
+            artifact_root.display()
+        )
+    });
+    // The dynamic export table, i.e. what a runtime loader can resolve.
+    let symbols = objects::read_exports(&lib).unwrap_or_else(|e| panic!("{e}"));
+    for sym in [
+        "alias_app__legacy_add",
+        "alias_app__legacy_read",
+        "legacy_add",
+        "legacy_read",
+        "alias_app__legacy_mul",
+        "legacy_mul_v0",
+    ] {
+        assert!(
+            symbols.iter().any(|s| s == sym),
+            "missing {sym:?} in {}",
+            lib.display()
+        );
+    }
+
+    // The fixture's own test links both names and checks they share one body.
+    let status = Command::new("cargo")
+        .arg("test")
+        .arg("--manifest-path")
+        .arg(fixture.join("Cargo.toml"))
+        .env_remove("SYMBAKER_PREFIX")
+        .env_remove("SYMBAKER_CONFIG")
+        .env_remove("SYMBAKER_PRIORITY")
+        .env_remove("SYMBAKER_TOP_PACKAGE")
+        .status()
+        .expect("failed to test alias_app");
+    assert!(status.success(), "alias_app call-through test failed");
+}
@@ -4,3 +4,9 @@ use symbaker::symbaker;
 pub extern "C" fn dep_exported() -> i32 {
     7
 }
+
+/// Exported without symbaker, as a dependency that never adopted it would.
+#[no_mangle]
+pub extern "C" fn dep_raw_export() -> i32 {
+    8
+}
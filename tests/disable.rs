@@ -0,0 +1,147 @@
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+
+#[allow(dead_code)]
+#[path = "../src/objects.rs"]
+mod objects;
+
+#[test]
+fn disabled_build_keeps_unprefixed_names() {
+    let root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    let fixture = root.join("tests").join("fixture_app");
+    let target_dir = fixture.join("target").join("disabled");
+    let trace_file = target_dir.join("trace.log");
+    fs::create_dir_all(&target_dir)
+        .unwrap_or_else(|e| panic!("mkdir {}: {e}", target_dir.display()));
+    let _ = fs::remove_file(&trace_file);
+    // The trace is only written when fixture_app is recompiled.
+    let status = Command::new("cargo")
+        .args(["clean", "-p", "fixture_app", "--manifest-path"])
+        .arg(fixture.join("Cargo.toml"))
+        .arg("--target-dir")
+        .arg(&target_dir)
+        .status()
+        .expect("failed to run cargo clean for fixture_app");
+    assert!(status.success(), "cargo clean -p fixture_app failed");
+
+    // Strict settings that would fail the build if any check still ran.
+    let out = Command::new("cargo")
+        .arg("build")
+        .arg("--manifest-path")
+        .arg(fixture.join("Cargo.toml"))
+        .arg("--target-dir")
+        .arg(&target_dir)
+        .env_remove("SYMBAKER_TOP_PACKAGE")
+        .env_remove("SYMBAKER_TRACE_HARD")
+        .env("SYMBAKER_DISABLE", "1")
+        .env("SYMBAKER_PREFIX", "should_not_appear")
+        .env("SYMBAKER_REQUIRE_CONFIG", "1")
+        .env("SYMBAKER_ENFORCE_INHERIT", "1")
+        .env("SYMBAKER_CONFIG", target_dir.join("missing.toml"))
+        .env("SYMBAKER_TRACE", "1")
+        .env("SYMBAKER_TRACE_FILE", &trace_file)
+        .output()
+        .expect("failed to run cargo build for fixture_app");
+    assert!(
+        out.status.success(),
+        "disabled build failed:\n{}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+
+    let rlib = target_dir.join("debug").join("libfixture_app.rlib");
+    let text = objects::read_exports(&rlib)
+        .unwrap_or_else(|e| panic!("{e}"))
+        .iter()
+        .map(|s| objects::demangle_legacy(s).unwrap_or_else(|| s.clone()))
+        .collect::<Vec<_>>()
+        .join("\n");
+    for name in [
+        "fixture_app::auto_named",
+        "fixture_app::attr_named",
+        "fixture_app::hooks::on_frame",
+    ] {
+        assert!(text.contains(name), "missing unprefixed {name} in:\n{text}");
+    }
+    assert!(
+        !text.contains("should_not_appear") && !text.contains("custom__attr_named"),
+        "disabled build still prefixed exports:\n{text}"
+    );
+
+    let trace = fs::read_to_string(&trace_file).unwrap_or_default();
+    let disabled = trace
+        .lines()
+        .filter(|l| l.contains("disabled SYMBAKER_DISABLE=1"))
+        .count();
+    assert_eq!(disabled, 1, "expected one disablement line in:\n{trace}");
+    assert!(
+        !trace.contains("macro=symbaker"),
+        "an item was still expanded:\n{trace}"
+    );
+}
+
+/// Builds a throwaway crate with `lib_rs` under SYMBAKER_DISABLE=1.
+fn build_disabled_probe(name: &str, lib_rs: &str) -> std::process::Output {
+    let root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    let target_dir = root
+        .join("tests")
+        .join("fixture_app")
+        .join("target")
+        .join("disabled");
+    let probe = target_dir.join(name);
+    fs::create_dir_all(probe.join("src"))
+        .unwrap_or_else(|e| panic!("mkdir {}: {e}", probe.display()));
+    fs::write(
+        probe.join("Cargo.toml"),
+        format!(
+            "[package]\nname = \"{name}\"\nversion = \"0.0.0\"\nedition = \"2021\"\n\n[workspace]\n\n[dependencies]\nsymbaker = {{ path = {:?} }}\n",
+            root.display().to_string()
+        ),
+    )
+    .unwrap_or_else(|e| panic!("write {}: {e}", probe.display()));
+    fs::write(probe.join("src").join("lib.rs"), lib_rs)
+        .unwrap_or_else(|e| panic!("write {}: {e}", probe.display()));
+
+    Command::new("cargo")
+        .arg("build")
+        .arg("--manifest-path")
+        .arg(probe.join("Cargo.toml"))
+        .arg("--target-dir")
+        .arg(&target_dir)
+        .env_remove("SYMBAKER_TOP_PACKAGE")
+        .env_remove("SYMBAKER_TRACE_HARD")
+        .env("SYMBAKER_DISABLE", "1")
+        .env("SYMBAKER_PREFIX", "should_not_appear")
+        .env("SYMBAKER_REQUIRE_CONFIG", "1")
+        .env("SYMBAKER_CONFIG", target_dir.join("missing.toml"))
+        .output()
+        .unwrap_or_else(|e| panic!("failed to run cargo build for {name}: {e}"))
+}
+
+#[test]
+fn disabled_build_resolves_an_empty_prefix() {
+    // Fails to compile unless the macro expands to "".
+    let out = build_disabled_probe(
+        "prefix_probe",
+        "const _: () = assert!(symbaker::resolved_prefix!().is_empty());\n",
+    );
+    assert!(
+        out.status.success(),
+        "resolved_prefix!() was not empty under SYMBAKER_DISABLE=1:\n{}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+}
+
+#[test]
+fn disabled_build_strips_export_const_markers() {
+    // A marker left behind is an unknown attribute and fails the build.
+    let out = build_disabled_probe(
+        "export_const_probe",
+        "#[symbaker::symbaker_module]\npub mod exports {\n    #[symbaker(export_const)]\n    pub const BUILD_ID: u32 = 7;\n\n    #[symbaker(export_const)]\n    pub static API_VERSION: u32 = 3;\n}\n",
+    );
+    assert!(
+        out.status.success(),
+        "#[symbaker(export_const)] survived SYMBAKER_DISABLE=1:\n{}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+}
@@ -0,0 +1,21 @@
+use symbaker::{symbaker, symbaker_module};
+
+#[symbaker]
+pub extern "C" fn plain() -> i32 {
+    1
+}
+
+#[symbaker(compat_alias)]
+#[unsafe(no_mangle)]
+pub extern "C" fn renamed() -> i32 {
+    2
+}
+
+#[symbaker_module]
+pub mod hooks {
+    pub extern "C" fn on_frame() -> i32 {
+        3
+    }
+
+    pub static FRAME: i32 = 4;
+}
@@ -0,0 +1,66 @@
+use std::ffi::OsStr;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+#[allow(dead_code)]
+#[path = "../src/objects.rs"]
+mod objects;
+
+fn find_dynamic_lib(root: &Path, stem: &str) -> Option<PathBuf> {
+    fs::read_dir(root)
+        .ok()?
+        .flatten()
+        .map(|e| e.path())
+        .find(|p| {
+            matches!(
+                p.extension().and_then(OsStr::to_str),
+                Some("dll") | Some("so") | Some("dylib")
+            ) && p
+                .file_name()
+                .and_then(OsStr::to_str)
+                .is_some_and(|n| n.contains(stem))
+        })
+}
+
+#[test]
+fn edition_2024_crate_gets_unsafe_export_attributes() {
+    let root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    let fixture = root.join("tests").join("edition2024_app");
+
+    // Bare `#[export_name]` is a hard error in 2024, so building at all
+    // checks the emitted form; `-D warnings` rules out deprecation lints.
+    let status = Command::new("cargo")
+        .arg("build")
+        .arg("--manifest-path")
+        .arg(fixture.join("Cargo.toml"))
+        .env("RUSTFLAGS", "-D warnings")
+        .env_remove("SYMBAKER_PREFIX")
+        .env_remove("SYMBAKER_CONFIG")
+        .env_remove("SYMBAKER_PRIORITY")
+        .env_remove("SYMBAKER_TOP_PACKAGE")
+        .status()
+        .expect("failed to build edition2024_app");
+    assert!(status.success(), "edition2024_app build failed");
+
+    let artifact_root = fixture.join("target").join("debug");
+    let lib = find_dynamic_lib(&artifact_root, "edition2024_app").unwrap_or_else(|| {
+        panic!(
+            "could not find built dynamic library under {}",
+            artifact_root.display()
+        )
+    });
+    let symbols = objects::read_exports(&lib).unwrap_or_else(|e| panic!("{e}"));
+    for sym in [
+        "edition2024_app__plain",
+        "edition2024_app__renamed",
+        "renamed",
+        "edition2024_app__on_frame",
+        "edition2024_app__FRAME",
+    ] {
+        assert!(
+            symbols.iter().any(|s| s == sym),
+            "missing {sym:?} in {symbols:?}"
+        );
+    }
+}
@@ -0,0 +1,45 @@
+mod plugin {
+    /// Frames since boot.
+    ///
+    /// Exported as `hdr__plugin_FRAME_v1` (symbaker: prefix source Attr, sep `__`).
+    #[doc(alias = "hdr__plugin_FRAME_v1")]
+    #[export_name = "hdr__plugin_FRAME_v1"]
+    pub static FRAME: u32 = 0;
+    pub const VERSION: u32 = 3;
+    /// Exported as `hdr__plugin_zeta_v1` (symbaker: prefix source Attr, sep `__`).
+    #[doc(alias = "hdr__plugin_zeta_v1")]
+    #[export_name = "hdr__plugin_zeta_v1"]
+    pub extern "C" fn zeta() {}
+    /// Exported as `hdr__plugin_alpha_v1` (symbaker: prefix source Attr, sep `__`).
+    #[doc(alias = "hdr__plugin_alpha_v1")]
+    #[export_name = "hdr__plugin_alpha_v1"]
+    pub extern "C" fn alpha(x: i32) -> i32 {
+        x
+    }
+    pub extern "C" fn internal_reset() {}
+    mod imp {
+        pub extern "C" fn reexported() {}
+    }
+    /// Exported as `hdr__plugin_reexported_v1` (symbaker: prefix source Attr, sep `__`).
+    #[doc(alias = "hdr__plugin_reexported_v1")]
+    #[export_name = "hdr__plugin_reexported_v1"]
+    pub extern "C" fn reexported() {
+        imp::reexported()
+    }
+    pub struct Handle;
+    #[doc(hidden)]
+    #[export_name = "alpha"]
+    pub extern "C" fn __symbaker_alias_alpha(__symbaker_arg0: i32) -> i32 {
+        alpha(__symbaker_arg0)
+    }
+    #[doc(hidden)]
+    #[export_name = "reexported"]
+    pub extern "C" fn __symbaker_alias_reexported() {
+        reexported()
+    }
+    #[doc(hidden)]
+    #[export_name = "zeta"]
+    pub extern "C" fn __symbaker_alias_zeta() {
+        zeta()
+    }
+}
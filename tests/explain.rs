@@ -0,0 +1,24 @@
+use std::path::PathBuf;
+
+#[test]
+fn explain_reports_candidates_for_one_invocation() {
+    let root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    // Give the priority loop an env and a config candidate next to the attribute.
+    std::env::set_var("SYMBAKER_PREFIX", "env_p");
+    std::env::set_var(
+        "SYMBAKER_CONFIG",
+        root.join("tests").join("ui").join("explain_config.toml"),
+    );
+    std::env::set_var("SYMBAKER_INITIALIZED", "1");
+    for key in [
+        "SYMBAKER_PRIORITY",
+        "SYMBAKER_SEP",
+        "SYMBAKER_SUFFIX",
+        "SYMBAKER_TOP_PACKAGE",
+    ] {
+        std::env::remove_var(key);
+    }
+
+    let t = trybuild::TestCases::new();
+    t.compile_fail("tests/ui/explain_fn.rs");
+}
@@ -0,0 +1,3 @@
+fn main() {
+    symbaker_build::emit_rerun_directives();
+}
@@ -1,4 +1,4 @@
-use symbaker::symbaker;
+use symbaker::{symbaker, symbaker_module};
 
 #[symbaker]
 pub extern "C" fn auto_named() -> i32 {
@@ -9,3 +9,27 @@ pub extern "C" fn auto_named() -> i32 {
 pub extern "C" fn attr_named() -> i32 {
     2
 }
+
+#[symbaker(suffix = "_v2")]
+pub extern "C" fn attr_suffixed() -> i32 {
+    3
+}
+
+#[symbaker(template = "{prefix}{sep}{name}_{target_arch}_{profile}")]
+pub extern "C" fn templated() -> i32 {
+    6
+}
+
+#[symbaker_module]
+pub mod hooks {
+    pub extern "C" fn on_frame() -> i32 {
+        4
+    }
+}
+
+#[symbaker_module(suffix = "_m")]
+pub mod ruled {
+    pub extern "C" fn on_ruled() -> i32 {
+        5
+    }
+}
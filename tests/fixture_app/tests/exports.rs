@@ -0,0 +1,6 @@
+#[test]
+fn exports_are_callable_from_rust() {
+    assert_eq!(fixture_app::auto_named(), 1);
+    assert_eq!(fixture_app::attr_named(), 2);
+    assert_eq!(fixture_app::hooks::on_frame(), 4);
+}
@@ -3,29 +3,16 @@ use std::fs;
 use std::path::{Path, PathBuf};
 use std::process::Command;
 
-fn pick_nm_tool() -> Option<&'static str> {
-    for tool in ["llvm-nm", "nm", "rust-nm", "aarch64-none-elf-nm"] {
-        if Command::new(tool).arg("--version").output().is_ok() {
-            return Some(tool);
-        }
-    }
-    None
-}
-
-fn pick_objdump_tool() -> Option<&'static str> {
-    for tool in ["llvm-objdump", "objdump"] {
-        if Command::new(tool).arg("--version").output().is_ok() {
-            return Some(tool);
-        }
-    }
-    None
-}
+#[allow(dead_code)]
+#[path = "../src/objects.rs"]
+mod objects;
 
+#[allow(clippy::match_like_matches_macro)]
 fn is_dynamic_lib(path: &Path) -> bool {
-    matches!(
-        path.extension().and_then(OsStr::to_str),
-        Some("dll") | Some("so") | Some("dylib")
-    )
+    match path.extension().and_then(OsStr::to_str) {
+        Some("dll") | Some("so") | Some("dylib") => true,
+        _ => false,
+    }
 }
 
 fn newest_dynamic_lib(root: &Path, stem: &str) -> Option<PathBuf> {
@@ -60,28 +47,6 @@ fn newest_dynamic_lib(root: &Path, stem: &str) -> Option<PathBuf> {
     best.map(|(p, _)| p)
 }
 
-fn read_exports(lib: &Path) -> Option<String> {
-    if lib.extension().and_then(OsStr::to_str) == Some("dll") {
-        let objdump = pick_objdump_tool()?;
-        let out = Command::new(objdump).args(["-p"]).arg(lib).output().ok()?;
-        if !out.status.success() {
-            return None;
-        }
-        return Some(String::from_utf8_lossy(&out.stdout).to_string());
-    }
-
-    let nm = pick_nm_tool()?;
-    let out = Command::new(nm)
-        .args(["-g", "--defined-only"])
-        .arg(lib)
-        .output()
-        .ok()?;
-    if !out.status.success() {
-        return None;
-    }
-    Some(String::from_utf8_lossy(&out.stdout).to_string())
-}
-
 #[test]
 fn dependency_symbol_uses_host_package_prefix_and_writes_sidecar() {
     let root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
@@ -110,8 +75,9 @@ fn dependency_symbol_uses_host_package_prefix_and_writes_sidecar() {
         )
     });
 
-    let exports = read_exports(&lib)
-        .unwrap_or_else(|| panic!("failed reading exports from {}", lib.display()));
+    let exports = objects::read_exports(&lib)
+        .unwrap_or_else(|e| panic!("{e}"))
+        .join("\n");
     assert!(
         exports.contains("host_app__dep_exported"),
         "expected dependency export to use host prefix; artifact: {}",
@@ -122,9 +88,11 @@ fn dependency_symbol_uses_host_package_prefix_and_writes_sidecar() {
     fs::copy(&lib, &nro)
         .unwrap_or_else(|e| panic!("copy {} -> {}: {e}", lib.display(), nro.display()));
 
-    let status = Command::new("cargo")
-        .args(["run", "--bin", "cargo-symdump", "--", "dump"])
+    // Run from host_app so its `.symbaker/` is written there, not in this repo.
+    let status = Command::new(env!("CARGO_BIN_EXE_cargo-symdump"))
+        .arg("dump")
         .arg(&nro)
+        .current_dir(&host)
         .status()
         .expect("failed to run cargo-symdump dump");
     assert!(status.success(), "cargo-symdump dump failed");
@@ -173,8 +141,9 @@ fn workspace_prefix_overrides_dependency_prefix_without_top_package_env() {
         )
     });
 
-    let exports = read_exports(&lib)
-        .unwrap_or_else(|| panic!("failed reading exports from {}", lib.display()));
+    let exports = objects::read_exports(&lib)
+        .unwrap_or_else(|e| panic!("{e}"))
+        .join("\n");
     assert!(
         exports.contains("hdr__dep_exported"),
         "expected workspace prefix on dependency export; artifact: {}",
@@ -186,3 +155,121 @@ fn workspace_prefix_overrides_dependency_prefix_without_top_package_env() {
         lib.display()
     );
 }
+
+#[test]
+fn parent_and_root_package_priority_keys_follow_parent_chain() {
+    let root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    // chain_app -> chain_mid -> dep_lib; cargo symdump derives the parents
+    // from `cargo metadata`.
+    let app = root.join("tests").join("chain_app");
+
+    for (key, expected) in [
+        ("parent_package", "chain_mid__dep_exported"),
+        ("root_package", "chain_app__dep_exported"),
+    ] {
+        let target_dir = app.join("target").join(key);
+        fs::create_dir_all(&target_dir)
+            .unwrap_or_else(|e| panic!("mkdir {}: {e}", target_dir.display()));
+        let cfg = target_dir.join("symbaker.toml");
+        fs::write(&cfg, format!("priority = [\"{key}\", \"crate\"]\n"))
+            .unwrap_or_else(|e| panic!("write {}: {e}", cfg.display()));
+
+        let out = Command::new(env!("CARGO_BIN_EXE_cargo-symdump"))
+            .args(["build", "--manifest-path"])
+            .arg(app.join("Cargo.toml"))
+            .arg("--target-dir")
+            .arg(&target_dir)
+            .env_remove("SYMBAKER_PREFIX")
+            .env_remove("SYMBAKER_PRIORITY")
+            .env_remove("SYMBAKER_TOP_PACKAGE")
+            .env_remove("SYMBAKER_PARENT_PACKAGES")
+            .env("SYMBAKER_CONFIG", &cfg)
+            .output()
+            .expect("failed to run cargo-symdump build");
+        assert!(
+            out.status.success(),
+            "chain_app build failed:\n{}",
+            String::from_utf8_lossy(&out.stderr)
+        );
+
+        let artifact_root = target_dir.join("debug");
+        let lib = newest_dynamic_lib(&artifact_root, "chain_app").unwrap_or_else(|| {
+            panic!(
+                "could not find chain_app artifact under {}",
+                artifact_root.display()
+            )
+        });
+        let exports = objects::read_exports(&lib)
+            .unwrap_or_else(|e| panic!("{e}"))
+            .join("\n");
+        assert!(
+            exports.contains(expected),
+            "priority {key:?} should export {expected}; artifact: {}",
+            lib.display()
+        );
+    }
+}
+
+#[test]
+fn traced_workspace_build_writes_inheritance_graph() {
+    let root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    let workspace = root.join("tests").join("workspace_host");
+    let target_dir = workspace.join("target").join("graph");
+    // ssbusync is only traced when it actually recompiles.
+    let status = Command::new("cargo")
+        .args(["clean", "-q", "-p", "ssbusync", "--manifest-path"])
+        .arg(workspace.join("Cargo.toml"))
+        .arg("--target-dir")
+        .arg(&target_dir)
+        .status()
+        .expect("failed to run cargo clean");
+    assert!(status.success(), "cargo clean -p ssbusync failed");
+
+    let out = Command::new(env!("CARGO_BIN_EXE_cargo-symdump"))
+        .args(["--trace", "build", "-p", "host_ws", "--manifest-path"])
+        .arg(workspace.join("Cargo.toml"))
+        .arg("--target-dir")
+        .arg(&target_dir)
+        .env_remove("SYMBAKER_PREFIX")
+        .env_remove("SYMBAKER_CONFIG")
+        .env_remove("SYMBAKER_PRIORITY")
+        .env_remove("SYMBAKER_TOP_PACKAGE")
+        .output()
+        .expect("failed to run cargo-symdump build");
+    assert!(
+        out.status.success(),
+        "{}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+
+    let symbaker_dir = workspace.join(".symbaker");
+    let graph = fs::read_to_string(symbaker_dir.join("graph.dot"))
+        .unwrap_or_else(|e| panic!("read graph.dot: {e}"));
+    assert!(graph.starts_with("digraph symbaker {"), "{graph}");
+    assert!(graph.contains("\"host_ws\" [style=dashed];"), "{graph}");
+    assert!(
+        graph.contains("\"ssbusync\" [label=\"ssbusync\\nhost_ws (top_package)\"];"),
+        "{graph}"
+    );
+    assert!(
+        graph.contains("\"host_ws\" -> \"ssbusync\" [color=forestgreen, label=\"top_package\"];"),
+        "{graph}"
+    );
+
+    let report: toml::Value =
+        toml::from_str(&fs::read_to_string(symbaker_dir.join("resolution.toml")).unwrap()).unwrap();
+    let ssbusync = report["crates"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|c| c["name"].as_str() == Some("ssbusync"))
+        .expect("ssbusync not reported");
+    assert_eq!(ssbusync["inherited_from"].as_str(), Some("host_ws"));
+    let symbaker = ssbusync["dependencies"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|d| d["name"].as_str() == Some("symbaker"))
+        .expect("symbaker dependency missing");
+    assert_eq!(symbaker["uses_symbaker"].as_bool(), Some(false));
+}
@@ -0,0 +1,8 @@
+#[test]
+fn module_rule_errors_are_spanned() {
+    std::env::set_var("SYMBAKER_INITIALIZED", "1");
+    std::env::remove_var("SYMBAKER_REQUIRE_CONFIG");
+
+    let t = trybuild::TestCases::new();
+    t.compile_fail("tests/ui/module_*.rs");
+}
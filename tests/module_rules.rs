@@ -3,29 +3,16 @@ use std::fs;
 use std::path::{Path, PathBuf};
 use std::process::Command;
 
-fn pick_nm_tool() -> Option<&'static str> {
-    for tool in ["llvm-nm", "nm", "rust-nm", "aarch64-none-elf-nm"] {
-        if Command::new(tool).arg("--version").output().is_ok() {
-            return Some(tool);
-        }
-    }
-    None
-}
-
-fn pick_objdump_tool() -> Option<&'static str> {
-    for tool in ["llvm-objdump", "objdump"] {
-        if Command::new(tool).arg("--version").output().is_ok() {
-            return Some(tool);
-        }
-    }
-    None
-}
+#[allow(dead_code)]
+#[path = "../src/objects.rs"]
+mod objects;
 
+#[allow(clippy::match_like_matches_macro)]
 fn is_dynamic_lib(path: &Path) -> bool {
-    matches!(
-        path.extension().and_then(OsStr::to_str),
-        Some("dll") | Some("so") | Some("dylib")
-    )
+    match path.extension().and_then(OsStr::to_str) {
+        Some("dll") | Some("so") | Some("dylib") => true,
+        _ => false,
+    }
 }
 
 fn newest_dynamic_lib(root: &Path, stem: &str) -> Option<PathBuf> {
@@ -84,29 +71,9 @@ fn module_rules_control_prefixing_and_template() {
         )
     });
 
-    let text = if lib.extension().and_then(OsStr::to_str) == Some("dll") {
-        let Some(objdump) = pick_objdump_tool() else {
-            panic!("no objdump-compatible tool found");
-        };
-        let out = Command::new(objdump)
-            .args(["-p"])
-            .arg(&lib)
-            .output()
-            .unwrap_or_else(|e| panic!("failed to run {objdump}: {e}"));
-        assert!(out.status.success(), "objdump failed for {}", lib.display());
-        String::from_utf8_lossy(&out.stdout).to_string()
-    } else {
-        let Some(nm) = pick_nm_tool() else {
-            panic!("no nm-compatible tool found");
-        };
-        let out = Command::new(nm)
-            .args(["-g", "--defined-only"])
-            .arg(&lib)
-            .output()
-            .unwrap_or_else(|e| panic!("failed to run {nm}: {e}"));
-        assert!(out.status.success(), "nm failed for {}", lib.display());
-        String::from_utf8_lossy(&out.stdout).to_string()
-    };
+    let text = objects::read_exports(&lib)
+        .unwrap_or_else(|e| panic!("{e}"))
+        .join("\n");
 
     assert!(
         text.contains("rules_app__exports_keep_one_x"),
@@ -116,6 +83,18 @@ fn module_rules_control_prefixing_and_template() {
         text.contains("rules_app__exports_special_x"),
         "missing special export"
     );
+    assert!(
+        text.contains("rules_app__exports_API_VERSION_x"),
+        "missing static export"
+    );
+    assert!(
+        text.contains("rules_app__exports_BUILD_ID_x"),
+        "missing export_const export"
+    );
+    assert!(
+        !text.contains("MAX_SLOTS"),
+        "an unmarked const was exported"
+    );
     assert!(
         !text.contains("rules_app__exports_keep_skip_x"),
         "exclude glob failed"
@@ -124,4 +103,92 @@ fn module_rules_control_prefixing_and_template() {
         !text.contains("rules_app__exports_other_x"),
         "include regex failed"
     );
+    assert!(
+        text.contains("rules_app__marked_only"),
+        "include_attr marker ignored"
+    );
+    assert!(
+        !text.contains("rules_app__unmarked"),
+        "include_attr should limit prefixing to marked items"
+    );
+    assert!(
+        text.contains("rules_app__mixed_marked") && text.contains("rules_app__glob_matched"),
+        "include_attr should union with name includes"
+    );
+    assert!(
+        !text.contains("rules_app__mixed_hidden"),
+        "exclude glob should beat include_attr"
+    );
+    assert!(
+        !text.contains("rules_app__mixed_plain"),
+        "unmarked, unmatched item was prefixed"
+    );
+    assert!(
+        text.contains("plug__rules_app__credited_fn"),
+        "{{crate}} placeholder not rendered"
+    );
+    assert!(
+        text.contains("rules_app_separated_fn") && !text.contains("ignored_separated_fn"),
+        "module `sep`/`priority` arguments were not applied"
+    );
+    assert!(
+        text.contains("rules_app__only_init") && text.contains("rules_app__only_frame"),
+        "functions listed in `only` were not prefixed"
+    );
+    assert!(
+        !text.contains("rules_app__only_unlisted"),
+        "function missing from `only` was prefixed"
+    );
+    assert!(
+        text.contains("rules_app__never_returns"),
+        "`-> !` function was not prefixed"
+    );
+    assert!(
+        text.contains("rules_app__abi_c"),
+        "marked extern \"C\" function was not prefixed"
+    );
+    assert!(
+        !text.contains("rules_app__abi_rust"),
+        "non-extern function passed the abi filter"
+    );
+    assert!(
+        !text.contains("rules_app__abi_unmarked"),
+        "function without the required attribute was prefixed"
+    );
+    assert!(
+        text.contains("rules_app__via_use") && text.contains("rules_app__via_rename"),
+        "`pub use` re-exports were not exported"
+    );
+    assert!(
+        text.contains("rules_app__via_token"),
+        "re-export whose signature names a submodule type was not exported"
+    );
+    assert!(
+        !text.contains("rules_app__renamed_target"),
+        "renamed re-export must use its visible name"
+    );
+    assert!(
+        !text.contains("rules_app__use_private"),
+        "excluded re-export was exported"
+    );
+    assert!(
+        text.contains("rules_app__shared_tick") && text.contains("rules_app__shared_mix"),
+        "symbaker_modules did not apply the rules to every listed submodule"
+    );
+    assert!(
+        !text.contains("rules_app__game_local"),
+        "shared include_glob was not applied"
+    );
+    assert!(
+        !text.contains("rules_app__shared_skipped"),
+        "unlisted submodule was prefixed"
+    );
+    assert!(
+        text.contains("rules_app__Controller_poll_input"),
+        "impl block method was not prefixed with the type name as {{module}}"
+    );
+    assert!(
+        !text.contains("rules_app__Controller_internal_reset"),
+        "exclude glob was not applied inside the impl block"
+    );
 }
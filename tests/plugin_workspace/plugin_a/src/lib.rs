@@ -0,0 +1 @@
+pub extern "C" fn plugin_main() {}
@@ -1,12 +1,23 @@
-use symbaker::symbaker_module;
+use symbaker::{symbaker_module, symbaker_modules};
 
 #[symbaker_module(
-    include_regex = "^keep_,special$",
+    include_regex = "^keep_,special$,^API_VERSION$,^BUILD_ID$,^MAX_SLOTS$",
     exclude_glob = "*skip*",
     template = "{prefix}{sep}{module}_{name}{suffix}",
     suffix = "_x"
 )]
 mod exports {
+    // A static is exported anyway; the marker is just dropped.
+    #[symbaker(export_const)]
+    pub static API_VERSION: u32 = 3;
+
+    #[symbaker(export_const)]
+    pub const BUILD_ID: u32 = 7;
+
+    // Matches the rules but stays a const: array lengths need one.
+    pub const MAX_SLOTS: usize = 4;
+    pub static SLOTS: [u8; MAX_SLOTS] = [0; MAX_SLOTS];
+
     pub extern "C" fn keep_one() -> i32 {
         1
     }
@@ -23,3 +34,161 @@ mod exports {
         4
     }
 }
+
+#[symbaker_module(include_attr = "export_api")]
+mod marked {
+    #[export_api]
+    pub extern "C" fn marked_only() -> i32 {
+        5
+    }
+
+    pub extern "C" fn unmarked() -> i32 {
+        6
+    }
+}
+
+#[symbaker_module(include_attr = "export_api", include_glob = "glob_*", exclude_glob = "*hidden*")]
+mod mixed {
+    #[export_api]
+    pub extern "C" fn mixed_marked() -> i32 {
+        7
+    }
+
+    pub extern "C" fn glob_matched() -> i32 {
+        8
+    }
+
+    #[export_api]
+    pub extern "C" fn mixed_hidden() -> i32 {
+        9
+    }
+
+    pub extern "C" fn mixed_plain() -> i32 {
+        10
+    }
+}
+
+#[symbaker_module(prefix = "plug", template = "{prefix}{sep}{crate}__{name}")]
+mod credited {
+    pub extern "C" fn credited_fn() -> i32 {
+        11
+    }
+}
+
+#[symbaker_module(prefix = "ignored", sep = "_", priority = "crate")]
+mod separated {
+    pub extern "C" fn separated_fn() -> i32 {
+        27
+    }
+}
+
+#[symbaker_module(only = "only_init, only_frame")]
+mod only {
+    pub extern "C" fn only_init() -> i32 {
+        12
+    }
+
+    pub extern "C" fn only_frame() -> i32 {
+        13
+    }
+
+    pub extern "C" fn only_unlisted() -> i32 {
+        14
+    }
+}
+
+#[symbaker_module]
+mod diverging {
+    pub extern "C" fn never_returns() -> ! {
+        loop {}
+    }
+}
+
+#[symbaker_module(abi = "C", requires_attr = "export_candidate", exclude_async = true)]
+mod structural {
+    #[export_candidate]
+    pub extern "C" fn abi_c() -> i32 {
+        15
+    }
+
+    #[export_candidate]
+    pub fn abi_rust() -> i32 {
+        16
+    }
+
+    pub extern "C" fn abi_unmarked() -> i32 {
+        17
+    }
+}
+
+#[symbaker_module(exclude_glob = "*private*")]
+mod facade {
+    mod imp {
+        pub extern "C" fn via_use() -> i32 {
+            18
+        }
+
+        pub extern "C" fn renamed_target() -> i32 {
+            19
+        }
+
+        pub extern "C" fn use_private() -> i32 {
+            20
+        }
+
+        pub struct Handle;
+
+        #[repr(C)]
+        pub struct Token {
+            pub id: i32,
+        }
+
+        pub extern "C" fn via_token(token: Token) -> i32 {
+            token.id
+        }
+    }
+
+    pub use imp::{renamed_target as via_rename, use_private, via_token, via_use, Handle};
+
+    pub fn handle() -> Handle {
+        Handle
+    }
+}
+
+#[symbaker_modules(game, "audio", include_glob = "shared_*")]
+mod subsystems {
+    pub mod game {
+        pub extern "C" fn shared_tick() -> i32 {
+            21
+        }
+
+        pub extern "C" fn game_local() -> i32 {
+            22
+        }
+    }
+
+    pub mod audio {
+        pub extern "C" fn shared_mix() -> i32 {
+            23
+        }
+    }
+
+    pub mod untouched {
+        pub extern "C" fn shared_skipped() -> i32 {
+            24
+        }
+    }
+}
+
+pub struct Controller;
+
+#[symbaker_module(exclude_glob = "*internal*", template = "{prefix}{sep}{module}_{name}")]
+impl Controller {
+    pub extern "C" fn poll_input() -> i32 {
+        25
+    }
+
+    pub extern "C" fn internal_reset() -> i32 {
+        26
+    }
+}
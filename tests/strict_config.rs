@@ -0,0 +1,77 @@
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+
+/// Builds fixture_app with `SYMBAKER_REQUIRE_CONFIG=1` and `config` as the
+/// `SYMBAKER_CONFIG` file; returns whether it succeeded and its stderr.
+fn build_strict(case: &str, config: &str) -> (bool, String) {
+    build_fixture(case, config, &[])
+}
+
+/// [`build_strict`] that also sets `SYMBAKER_STRICT_CONFIG=1`.
+fn build_strict_config(case: &str, config: &str) -> (bool, String) {
+    build_fixture(case, config, &[("SYMBAKER_STRICT_CONFIG", "1")])
+}
+
+fn build_fixture(case: &str, config: &str, env: &[(&str, &str)]) -> (bool, String) {
+    let root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    let fixture = root.join("tests").join("fixture_app");
+    let target_dir = fixture.join("target").join(format!("strict_{case}"));
+    fs::create_dir_all(&target_dir)
+        .unwrap_or_else(|e| panic!("mkdir {}: {e}", target_dir.display()));
+    let cfg = target_dir.join("symbaker.toml");
+    fs::write(&cfg, config).unwrap_or_else(|e| panic!("write {}: {e}", cfg.display()));
+
+    let out = Command::new("cargo")
+        .arg("build")
+        .arg("--manifest-path")
+        .arg(fixture.join("Cargo.toml"))
+        .arg("--target-dir")
+        .arg(&target_dir)
+        .env_remove("SYMBAKER_PREFIX")
+        .env_remove("SYMBAKER_PRIORITY")
+        .env_remove("SYMBAKER_TOP_PACKAGE")
+        .env_remove("SYMBAKER_ENFORCE_INHERIT")
+        .env_remove("SYMBAKER_TRACE_HARD")
+        .env_remove("SYMBAKER_STRICT_CONFIG")
+        .env("SYMBAKER_REQUIRE_CONFIG", "1")
+        .env("SYMBAKER_CONFIG", &cfg)
+        .envs(env.iter().copied())
+        .output()
+        .expect("failed to run cargo build for fixture_app");
+    (
+        out.status.success(),
+        String::from_utf8_lossy(&out.stderr).to_string(),
+    )
+}
+
+#[test]
+fn strict_mode_rejects_crate_fallback_prefix() {
+    let (ok, stderr) = build_strict("fallback", "priority = [\"config\"]\n");
+    assert!(!ok, "fallback-only resolution must fail in strict mode");
+    assert!(
+        stderr.contains("no `priority` source produced a prefix"),
+        "{stderr}"
+    );
+}
+
+#[test]
+fn strict_mode_rejects_unloadable_config() {
+    let (ok, stderr) = build_strict("unloadable", "priority = 5\n");
+    assert!(!ok, "an unloadable config must fail in strict mode");
+    assert!(
+        stderr.contains("the config could not be loaded"),
+        "{stderr}"
+    );
+}
+
+#[test]
+fn strict_mode_rejects_unknown_config_keys() {
+    let (ok, stderr) =
+        build_strict_config("unknown_key", "prefix = \"hdr\"\npriorty = [\"config\"]\n");
+    assert!(!ok, "a typo'd key must fail with SYMBAKER_STRICT_CONFIG=1");
+    assert!(
+        stderr.contains("unknown key \"priorty\" (did you mean \"priority\"?)"),
+        "{stderr}"
+    );
+}
@@ -3,24 +3,11 @@ use std::fs;
 use std::path::{Path, PathBuf};
 use std::process::Command;
 
-fn pick_nm_tool() -> Option<&'static str> {
-    for tool in ["llvm-nm", "nm", "rust-nm", "aarch64-none-elf-nm"] {
-        if Command::new(tool).arg("--version").output().is_ok() {
-            return Some(tool);
-        }
-    }
-    None
-}
-
-fn pick_objdump_tool() -> Option<&'static str> {
-    for tool in ["llvm-objdump", "objdump"] {
-        if Command::new(tool).arg("--version").output().is_ok() {
-            return Some(tool);
-        }
-    }
-    None
-}
+#[allow(dead_code)]
+#[path = "../src/objects.rs"]
+mod objects;
 
+#[allow(clippy::match_like_matches_macro)]
 fn is_dynamic_lib(path: &Path) -> bool {
     match path.extension().and_then(OsStr::to_str) {
         Some("dll") | Some("so") | Some("dylib") => true,
@@ -71,8 +58,11 @@ fn exported_symbols_are_prefixed() {
         .arg(fixture.join("Cargo.toml"))
         .env_remove("SYMBAKER_PREFIX")
         .env_remove("SYMBAKER_CONFIG")
+        .env_remove("SYMBAKER_SUFFIX")
         .env_remove("SYMBAKER_PRIORITY")
         .env_remove("SYMBAKER_TOP_PACKAGE")
+        .env_remove("SYMBAKER_TARGET_ARCH")
+        .env_remove("SYMBAKER_PROFILE")
         .status()
         .expect("failed to run cargo build for fixture_app");
     assert!(status.success(), "fixture_app build failed");
@@ -85,31 +75,9 @@ fn exported_symbols_are_prefixed() {
         )
     });
 
-    let text = if lib.extension().and_then(OsStr::to_str) == Some("dll") {
-        let Some(objdump) = pick_objdump_tool() else {
-            eprintln!("skipping: no objdump-compatible tool found in PATH");
-            return;
-        };
-        let out = Command::new(objdump)
-            .args(["-p"])
-            .arg(&lib)
-            .output()
-            .unwrap_or_else(|e| panic!("failed to run {objdump}: {e}"));
-        assert!(out.status.success(), "objdump failed for {}", lib.display());
-        String::from_utf8_lossy(&out.stdout).to_string()
-    } else {
-        let Some(nm) = pick_nm_tool() else {
-            eprintln!("skipping: no nm-compatible tool found in PATH");
-            return;
-        };
-        let out = Command::new(nm)
-            .args(["-g", "--defined-only"])
-            .arg(&lib)
-            .output()
-            .unwrap_or_else(|e| panic!("failed to run {nm}: {e}"));
-        assert!(out.status.success(), "nm failed for {}", lib.display());
-        String::from_utf8_lossy(&out.stdout).to_string()
-    };
+    let text = objects::read_exports(&lib)
+        .unwrap_or_else(|e| panic!("{e}"))
+        .join("\n");
 
     assert!(
         text.contains("fixture_app__auto_named"),
@@ -121,4 +89,208 @@ fn exported_symbols_are_prefixed() {
         "missing attribute-prefixed symbol in {}",
         lib.display()
     );
+    assert!(
+        text.contains("fixture_app__attr_suffixed_v2"),
+        "missing attribute-suffixed symbol in {}",
+        lib.display()
+    );
+    // `{target_arch}`/`{profile}` come from the build script under plain cargo.
+    let templated = format!("fixture_app__templated_{}_debug", std::env::consts::ARCH);
+    assert!(
+        text.contains(&templated),
+        "missing {templated} in {}",
+        lib.display()
+    );
+}
+
+#[test]
+fn prefix_aliases_export_old_prefix_names() {
+    let root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    let fixture = root.join("tests").join("fixture_app");
+    let target_dir = fixture.join("target").join("prefix_alias");
+    fs::create_dir_all(&target_dir)
+        .unwrap_or_else(|e| panic!("mkdir {}: {e}", target_dir.display()));
+    let cfg = target_dir.join("symbaker.toml");
+    fs::write(
+        &cfg,
+        "prefix = \"hdr\"\n\n[prefix_aliases]\nssbusync = \"hdr\"\n",
+    )
+    .unwrap_or_else(|e| panic!("write {}: {e}", cfg.display()));
+
+    let status = Command::new("cargo")
+        .arg("build")
+        .arg("--manifest-path")
+        .arg(fixture.join("Cargo.toml"))
+        .arg("--target-dir")
+        .arg(&target_dir)
+        .env_remove("SYMBAKER_PREFIX")
+        .env_remove("SYMBAKER_PRIORITY")
+        .env_remove("SYMBAKER_TOP_PACKAGE")
+        .env("SYMBAKER_CONFIG", &cfg)
+        .status()
+        .expect("failed to run cargo build for fixture_app");
+    assert!(status.success(), "fixture_app build failed");
+
+    let artifact_root = target_dir.join("debug");
+    let lib = newest_dynamic_lib(&artifact_root, "fixture_app").unwrap_or_else(|| {
+        panic!(
+            "could not find built dynamic library under {}",
+            artifact_root.display()
+        )
+    });
+    let text = objects::read_exports(&lib)
+        .unwrap_or_else(|e| panic!("{e}"))
+        .join("\n");
+
+    assert!(
+        text.contains("hdr__auto_named"),
+        "missing new-prefix export"
+    );
+    assert!(
+        text.contains("ssbusync__auto_named"),
+        "missing old-prefix alias export"
+    );
+    assert!(
+        !text.contains("ssbusync__attr_named"),
+        "attribute-prefixed export should not get an hdr alias"
+    );
+}
+
+#[test]
+fn hash_uniqueness_appends_stable_hash() {
+    let root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    let fixture = root.join("tests").join("fixture_app");
+    let target_dir = fixture.join("target").join("uniqueness_hash");
+    fs::create_dir_all(&target_dir)
+        .unwrap_or_else(|e| panic!("mkdir {}: {e}", target_dir.display()));
+    let cfg = target_dir.join("symbaker.toml");
+    fs::write(&cfg, "prefix = \"hdr\"\nuniqueness = \"hash\"\n")
+        .unwrap_or_else(|e| panic!("write {}: {e}", cfg.display()));
+
+    let status = Command::new("cargo")
+        .arg("build")
+        .arg("--manifest-path")
+        .arg(fixture.join("Cargo.toml"))
+        .arg("--target-dir")
+        .arg(&target_dir)
+        .env_remove("SYMBAKER_PREFIX")
+        .env_remove("SYMBAKER_PRIORITY")
+        .env_remove("SYMBAKER_TOP_PACKAGE")
+        .env("SYMBAKER_CONFIG", &cfg)
+        .status()
+        .expect("failed to run cargo build for fixture_app");
+    assert!(status.success(), "fixture_app build failed");
+
+    let artifact_root = target_dir.join("debug");
+    let lib = newest_dynamic_lib(&artifact_root, "fixture_app").unwrap_or_else(|| {
+        panic!(
+            "could not find built dynamic library under {}",
+            artifact_root.display()
+        )
+    });
+    let text = objects::read_exports(&lib)
+        .unwrap_or_else(|e| panic!("{e}"))
+        .join("\n");
+
+    let hash = symbaker_build::resolve::fnv1a32_hex("fixture_app/fixture_app/0.1.0");
+    let expected = format!("hdr_{hash}__auto_named");
+    assert!(text.contains(&expected), "missing {expected} in:\n{text}");
+    assert!(
+        text.contains(&format!("custom_{hash}__attr_named")),
+        "attribute prefixes are hashed too:\n{text}"
+    );
+}
+
+#[test]
+fn config_suffix_applies_unless_attribute_sets_one() {
+    let root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    let fixture = root.join("tests").join("fixture_app");
+    let target_dir = fixture.join("target").join("suffix");
+    fs::create_dir_all(&target_dir)
+        .unwrap_or_else(|e| panic!("mkdir {}: {e}", target_dir.display()));
+    let cfg = target_dir.join("symbaker.toml");
+    fs::write(&cfg, "prefix = \"hdr\"\nsuffix = \"_x\"\n")
+        .unwrap_or_else(|e| panic!("write {}: {e}", cfg.display()));
+
+    let status = Command::new("cargo")
+        .arg("build")
+        .arg("--manifest-path")
+        .arg(fixture.join("Cargo.toml"))
+        .arg("--target-dir")
+        .arg(&target_dir)
+        .env_remove("SYMBAKER_PREFIX")
+        .env_remove("SYMBAKER_SUFFIX")
+        .env_remove("SYMBAKER_PRIORITY")
+        .env_remove("SYMBAKER_TOP_PACKAGE")
+        .env("SYMBAKER_CONFIG", &cfg)
+        .status()
+        .expect("failed to run cargo build for fixture_app");
+    assert!(status.success(), "fixture_app build failed");
+
+    let artifact_root = target_dir.join("debug");
+    let lib = newest_dynamic_lib(&artifact_root, "fixture_app").unwrap_or_else(|| {
+        panic!(
+            "could not find built dynamic library under {}",
+            artifact_root.display()
+        )
+    });
+    let text = objects::read_exports(&lib)
+        .unwrap_or_else(|e| panic!("{e}"))
+        .join("\n");
+
+    for expected in [
+        "hdr__auto_named_x",
+        "custom__attr_named_x",
+        "hdr__attr_suffixed_v2",
+        "hdr__on_frame_x",
+        "hdr__on_ruled_m",
+    ] {
+        assert!(text.contains(expected), "missing {expected} in:\n{text}");
+    }
+    assert!(
+        !text.contains("attr_suffixed_v2_x") && !text.contains("attr_suffixed_x"),
+        "attribute suffix must replace the config suffix:\n{text}"
+    );
+    assert!(
+        !text.contains("on_ruled_m_x") && !text.contains("on_ruled_x"),
+        "module rule suffix must replace the config suffix:\n{text}"
+    );
+}
+
+#[test]
+fn prefix_env_change_rebuilds_without_clean() {
+    let root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    let fixture = root.join("tests").join("fixture_app");
+    let target_dir = fixture.join("target").join("rerun");
+
+    for prefix in ["rerun_one", "rerun_two"] {
+        let status = Command::new("cargo")
+            .arg("build")
+            .arg("--manifest-path")
+            .arg(fixture.join("Cargo.toml"))
+            .arg("--target-dir")
+            .arg(&target_dir)
+            .env_remove("SYMBAKER_CONFIG")
+            .env_remove("SYMBAKER_PRIORITY")
+            .env_remove("SYMBAKER_TOP_PACKAGE")
+            .env("SYMBAKER_PREFIX", prefix)
+            .status()
+            .expect("failed to run cargo build for fixture_app");
+        assert!(status.success(), "fixture_app build failed");
+
+        let artifact_root = target_dir.join("debug");
+        let lib = newest_dynamic_lib(&artifact_root, "fixture_app").unwrap_or_else(|| {
+            panic!(
+                "could not find built dynamic library under {}",
+                artifact_root.display()
+            )
+        });
+        let text = objects::read_exports(&lib)
+            .unwrap_or_else(|e| panic!("{e}"))
+            .join("\n");
+        assert!(
+            text.contains(&format!("{prefix}__auto_named")),
+            "stale exports after changing SYMBAKER_PREFIX to {prefix}:\n{text}"
+        );
+    }
 }
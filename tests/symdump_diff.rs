@@ -0,0 +1,69 @@
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+
+#[test]
+fn diff_exit_code_follows_the_selected_mode() {
+    let dir = std::env::temp_dir().join(format!("symdump_diff_{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap_or_else(|e| panic!("mkdir {}: {e}", dir.display()));
+    let listing = |name: &str, body: &str| {
+        let path = dir.join(name);
+        fs::write(&path, body).unwrap_or_else(|e| panic!("write {}: {e}", path.display()));
+        path
+    };
+    let base = listing("base.exports.txt", "hdr__init\nhdr__tick\n");
+    let grown = listing("grown.exports.txt", "hdr__init\nhdr__tick\nhdr__new\n");
+    let shrunk = listing("shrunk.exports.txt", "hdr__init\n");
+
+    let diff = |flags: &[&str], old: &PathBuf, new: &PathBuf| {
+        let out = Command::new(env!("CARGO_BIN_EXE_cargo-symdump"))
+            .arg("diff")
+            .args(flags)
+            .arg(old)
+            .arg(new)
+            .output()
+            .expect("failed to run cargo-symdump diff");
+        (
+            out.status.code(),
+            String::from_utf8_lossy(&out.stdout).to_string(),
+        )
+    };
+
+    let (code, stdout) = diff(&[], &base, &shrunk);
+    assert_eq!(code, Some(5), "removals fail by default:\n{stdout}");
+    assert_eq!(stdout, "- hdr__tick\n");
+    assert_eq!(diff(&[], &base, &grown).0, Some(0));
+    assert_eq!(diff(&[], &base, &base).0, Some(0));
+
+    let (code, stdout) = diff(&["--strict"], &base, &grown);
+    assert_eq!(code, Some(5), "--strict fails on additions:\n{stdout}");
+    assert_eq!(stdout, "+ hdr__new\n");
+    assert_eq!(diff(&["--strict"], &base, &base).0, Some(0));
+
+    let quiet = Command::new(env!("CARGO_BIN_EXE_cargo-symdump"))
+        .args(["-q", "diff"])
+        .arg(&base)
+        .arg(&grown)
+        .output()
+        .expect("failed to run cargo-symdump -q diff");
+    assert_eq!(quiet.status.code(), Some(0));
+    assert!(quiet.stdout.is_empty(), "-q printed to stdout");
+
+    // Only names the filters select are compared, on both sides.
+    let (code, stdout) = diff(&["--exclude-glob", "*tick"], &base, &shrunk);
+    assert_eq!(code, Some(0), "excluded removal still failed:\n{stdout}");
+    assert_eq!(stdout, "diff: 0 removed, 0 added\n");
+    let (code, stdout) = diff(&["--strict", "--include-regex", "^hdr__n"], &base, &grown);
+    assert_eq!(code, Some(5));
+    assert_eq!(stdout, "+ hdr__new\n");
+
+    assert_eq!(diff(&["--additions-only"], &base, &grown).0, Some(5));
+    assert_eq!(diff(&["--additions-only"], &base, &shrunk).0, Some(0));
+
+    assert_eq!(
+        diff(&["--strict", "--additions-only"], &base, &grown).0,
+        Some(2)
+    );
+    assert_eq!(diff(&[], &base, &dir.join("missing.txt")).0, Some(6));
+    let _ = fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,51 @@
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+fn unique_temp_dir(prefix: &str) -> PathBuf {
+    let ts = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or(0);
+    std::env::temp_dir().join(format!("{prefix}_{ts}_{}", std::process::id()))
+}
+
+#[test]
+fn cargo_symdump_doctor_reports_checklist() {
+    let dir = unique_temp_dir("symdump_doctor");
+    fs::create_dir_all(&dir).unwrap_or_else(|e| panic!("mkdir {}: {e}", dir.display()));
+    let cfg = dir.join("symbaker.toml");
+    fs::write(&cfg, "prefix = \"hdr\"\nsep = \"__\"\n")
+        .unwrap_or_else(|e| panic!("write {}: {e}", cfg.display()));
+
+    let out = Command::new("cargo")
+        .args(["run", "-q", "--bin", "cargo-symdump", "--", "doctor"])
+        .env("SYMBAKER_CONFIG", &cfg)
+        .env("SYMBAKER_INITIALIZED", "1")
+        .env("SYMBAKER_REQUIRE_CONFIG", "1")
+        .env("SYMBAKER_ENFORCE_INHERIT", "1")
+        .output()
+        .expect("failed to run cargo-symdump doctor");
+    let stdout = String::from_utf8_lossy(&out.stdout);
+    assert!(
+        stdout.contains("[ok]   nro parser"),
+        "nro parser self-check failed:\n{stdout}"
+    );
+    assert!(stdout.contains("[ok]   env"), "env check failed:\n{stdout}");
+    assert!(
+        stdout.contains("[ok]   config"),
+        "config check failed:\n{stdout}"
+    );
+
+    let out = Command::new("cargo")
+        .args(["run", "-q", "--bin", "cargo-symdump", "--", "doctor"])
+        .env_remove("SYMBAKER_INITIALIZED")
+        .env("SYMBAKER_CONFIG", dir.join("missing.toml"))
+        .output()
+        .expect("failed to run cargo-symdump doctor");
+    assert!(
+        !out.status.success(),
+        "doctor should exit non-zero when checks fail"
+    );
+}
@@ -0,0 +1,127 @@
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+/// A `PATH` whose symbol tools (`nm`, `objdump` and their variants) run but
+/// find nothing, so whatever a test reads must come from the native readers.
+#[cfg(unix)]
+fn path_without_symbol_tools(dir: &Path) -> std::ffi::OsString {
+    use std::os::unix::fs::PermissionsExt;
+    let bin = dir.join("fake-tools");
+    std::fs::create_dir_all(&bin).unwrap_or_else(|e| panic!("mkdir {}: {e}", bin.display()));
+    for tool in [
+        "llvm-nm",
+        "nm",
+        "rust-nm",
+        "aarch64-none-elf-nm",
+        "llvm-objdump",
+        "objdump",
+    ] {
+        let path = bin.join(tool);
+        std::fs::write(&path, "#!/bin/sh\nexit 0\n")
+            .unwrap_or_else(|e| panic!("write {}: {e}", path.display()));
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+    }
+    let mut paths = vec![bin];
+    paths.extend(std::env::split_paths(
+        &std::env::var_os("PATH").unwrap_or_default(),
+    ));
+    std::env::join_paths(paths).unwrap()
+}
+
+#[cfg(not(unix))]
+fn path_without_symbol_tools(_dir: &Path) -> std::ffi::OsString {
+    std::env::var_os("PATH").unwrap_or_default()
+}
+
+/// Runs `cargo symdump <args>` on `host`. dep_lib is cleaned first: its
+/// expansions are only traced when it actually recompiles.
+fn symdump(host: &Path, target_dir: &Path, args: &[&str]) -> Output {
+    let status = Command::new("cargo")
+        .args(["clean", "-q", "-p", "dep_lib", "--manifest-path"])
+        .arg(host.join("Cargo.toml"))
+        .arg("--target-dir")
+        .arg(target_dir)
+        .status()
+        .expect("failed to run cargo clean");
+    assert!(status.success(), "cargo clean -p dep_lib failed");
+
+    let root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    Command::new("cargo")
+        .args(["run", "-q", "--manifest-path"])
+        .arg(root.join("Cargo.toml"))
+        .args(["--bin", "cargo-symdump", "--"])
+        .args(args)
+        .arg("--manifest-path")
+        .arg(host.join("Cargo.toml"))
+        .arg("--target-dir")
+        .arg(target_dir)
+        .env("PATH", path_without_symbol_tools(target_dir))
+        .env_remove("SYMBAKER_PREFIX")
+        .env_remove("SYMBAKER_CONFIG")
+        .env_remove("SYMBAKER_PRIORITY")
+        .env_remove("SYMBAKER_TOP_PACKAGE")
+        .output()
+        .expect("failed to run cargo-symdump")
+}
+
+/// `cargo symdump --trace [extra] build` on `host`.
+fn symdump_build(host: &Path, target_dir: &Path, extra: &[&str]) -> Output {
+    let args: Vec<&str> = std::iter::once("--trace")
+        .chain(extra.iter().copied())
+        .chain(["build"])
+        .collect();
+    symdump(host, target_dir, &args)
+}
+
+#[test]
+fn raw_dependency_exports_are_reported_as_foreign() {
+    let root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    let host = root.join("tests").join("host_app");
+    let target_dir = host.join("target").join("foreign");
+    let out = symdump_build(&host, &target_dir, &[]);
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    assert!(out.status.success(), "{stderr}");
+    assert!(
+        stderr.contains("warning: foreign export dep_raw_export"),
+        "{stderr}"
+    );
+
+    let report = host.join(".symbaker").join("resolution.toml");
+    let body = std::fs::read_to_string(&report)
+        .unwrap_or_else(|e| panic!("read {}: {e}", report.display()));
+    let report: toml::Value = toml::from_str(&body).unwrap();
+    let foreign = report["foreign_exports"].as_array().unwrap();
+    let entry = |symbol: &str| {
+        foreign
+            .iter()
+            .find(|f| f["symbol"].as_str() == Some(symbol))
+            .unwrap_or_else(|| panic!("{symbol} not in foreign_exports:\n{body}"))
+    };
+    assert_eq!(
+        entry("dep_raw_export")["likely_crate"].as_str(),
+        Some("dep_lib")
+    );
+    // host_app does not use symbaker either, but it is no rlib to scan.
+    assert!(entry("host_calls_dep").get("likely_crate").is_none());
+    assert!(
+        !foreign
+            .iter()
+            .any(|f| f["symbol"].as_str() == Some("host_app__dep_exported")),
+        "{body}"
+    );
+
+    // `test` dumps no `.nro`, so it keeps the foreign exports found above.
+    let out = symdump(&host, &target_dir, &["test", "--trace"]);
+    assert!(
+        out.status.success(),
+        "{}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+    let body = std::fs::read_to_string(host.join(".symbaker").join("resolution.toml")).unwrap();
+    assert!(body.contains("dep_raw_export"), "{body}");
+
+    let out = symdump_build(&host, &target_dir, &["--strict-foreign"]);
+    assert!(!out.status.success(), "--strict-foreign passed");
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    assert!(stderr.contains("2 foreign export(s)"), "{stderr}");
+}
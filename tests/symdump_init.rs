@@ -0,0 +1,374 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+fn unique_temp_dir(prefix: &str) -> PathBuf {
+    let ts = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or(0);
+    std::env::temp_dir().join(format!("{prefix}_{ts}_{}", std::process::id()))
+}
+
+fn symdump_init(dir: &Path, args: &[&str]) -> Output {
+    let root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    Command::new("cargo")
+        .args(["run", "-q", "--manifest-path"])
+        .arg(root.join("Cargo.toml"))
+        .args(["--bin", "cargo-symdump", "--", "init"])
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .expect("failed to run cargo-symdump init")
+}
+
+fn read_toml(path: &Path) -> toml::Value {
+    let text = fs::read_to_string(path).unwrap_or_else(|e| panic!("read {}: {e}", path.display()));
+    toml::from_str(&text).unwrap_or_else(|e| panic!("parse {}: {e}", path.display()))
+}
+
+#[test]
+fn init_writes_sep_and_priority_to_symbaker_toml() {
+    let dir = unique_temp_dir("symdump_init_file");
+    fs::create_dir_all(&dir).unwrap_or_else(|e| panic!("mkdir {}: {e}", dir.display()));
+    fs::write(dir.join("Cargo.toml"), "[workspace]\n").unwrap();
+
+    let out = symdump_init(
+        &dir,
+        &["--prefix", "hdr", "--sep", "_", "--priority", "config,attr"],
+    );
+    assert!(
+        out.status.success(),
+        "{}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+    let cfg = read_toml(&dir.join("symbaker.toml"));
+    assert_eq!(cfg["sep"].as_str(), Some("_"));
+    assert_eq!(
+        cfg["priority"],
+        toml::Value::from(vec!["config".to_string(), "attr".to_string()])
+    );
+    let cargo_cfg = read_toml(&dir.join(".cargo").join("config.toml"));
+    assert!(cargo_cfg["env"].get("SYMBAKER_CONFIG").is_some());
+
+    // Pre-populated: kept without --force, rewritten with it.
+    let out = symdump_init(&dir, &["--prefix", "other", "--sep", "--"]);
+    assert!(out.status.success());
+    assert_eq!(
+        read_toml(&dir.join("symbaker.toml"))["sep"].as_str(),
+        Some("_")
+    );
+    let out = symdump_init(&dir, &["--prefix", "other", "--sep", "--", "--force"]);
+    assert!(out.status.success());
+    let cfg = read_toml(&dir.join("symbaker.toml"));
+    assert_eq!(cfg["prefix"].as_str(), Some("other"));
+    assert_eq!(cfg["sep"].as_str(), Some("--"));
+
+    let out = symdump_init(&dir, &["--priority", "config,nope", "--force"]);
+    assert!(!out.status.success(), "unknown priority key should fail");
+}
+
+#[test]
+fn init_metadata_writes_workspace_table_instead_of_config_file() {
+    let dir = unique_temp_dir("symdump_init_metadata");
+    fs::create_dir_all(&dir).unwrap_or_else(|e| panic!("mkdir {}: {e}", dir.display()));
+    let manifest = dir.join("Cargo.toml");
+    fs::write(&manifest, "[workspace]\nmembers = []\n").unwrap();
+
+    let out = symdump_init(&dir, &["--metadata", "--prefix", "hdr"]);
+    assert!(
+        out.status.success(),
+        "{}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+    assert!(!dir.join("symbaker.toml").exists());
+    let cfg = read_toml(&manifest)["workspace"]["metadata"]["symbaker"].clone();
+    assert_eq!(cfg["prefix"].as_str(), Some("hdr"));
+    let env = read_toml(&dir.join(".cargo").join("config.toml"))["env"].clone();
+    assert!(env.get("SYMBAKER_CONFIG").is_none());
+    assert!(env.get("SYMBAKER_REQUIRE_CONFIG").is_none());
+    assert_eq!(env["SYMBAKER_INITIALIZED"].as_str(), Some("1"));
+
+    // Pre-populated: kept without --force, replaced with it.
+    let out = symdump_init(&dir, &["--metadata", "--prefix", "other"]);
+    assert!(out.status.success());
+    let cfg = read_toml(&manifest)["workspace"]["metadata"]["symbaker"].clone();
+    assert_eq!(cfg["prefix"].as_str(), Some("hdr"));
+    let out = symdump_init(&dir, &["--metadata", "--prefix", "other", "--force"]);
+    assert!(out.status.success());
+    let cfg = read_toml(&manifest)["workspace"]["metadata"]["symbaker"].clone();
+    assert_eq!(cfg["prefix"].as_str(), Some("other"));
+    assert_eq!(
+        read_toml(&manifest)["workspace"]["members"]
+            .as_array()
+            .map(Vec::len),
+        Some(0)
+    );
+}
+
+#[test]
+fn init_ignores_symbaker_outputs_in_gitignore() {
+    let dir = unique_temp_dir("symdump_init_gitignore");
+    fs::create_dir_all(&dir).unwrap_or_else(|e| panic!("mkdir {}: {e}", dir.display()));
+    fs::write(dir.join("Cargo.toml"), "[workspace]\n").unwrap();
+    let gitignore = dir.join(".gitignore");
+    fs::write(&gitignore, "target/\n").unwrap();
+
+    let out = symdump_init(&dir, &["--prefix", "hdr", "--dry-run"]);
+    assert!(
+        out.status.success(),
+        "{}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+    assert!(String::from_utf8_lossy(&out.stdout).contains("/.symbaker/*"));
+    assert!(
+        !dir.join(".symbaker").exists(),
+        "--dry-run created .symbaker"
+    );
+    assert!(
+        !dir.join("symbaker.toml").exists(),
+        "--dry-run wrote config"
+    );
+    assert!(!dir.join(".cargo").exists(), "--dry-run wrote .cargo");
+    assert_eq!(fs::read_to_string(&gitignore).unwrap(), "target/\n");
+
+    for _ in 0..2 {
+        let out = symdump_init(&dir, &["--prefix", "hdr"]);
+        assert!(
+            out.status.success(),
+            "{}",
+            String::from_utf8_lossy(&out.stderr)
+        );
+    }
+    let text = fs::read_to_string(&gitignore).unwrap();
+    assert!(text.starts_with("target/\n"), "{text}");
+    assert_eq!(text.matches("/.symbaker/*").count(), 1);
+    assert_eq!(text.matches("!/.symbaker/.gitkeep").count(), 1);
+    assert_eq!(text.matches("target/**/*.exports.txt").count(), 1);
+
+    // A missing .gitignore is created; --no-gitignore leaves it alone.
+    let other = unique_temp_dir("symdump_init_new_gitignore");
+    fs::create_dir_all(&other).unwrap_or_else(|e| panic!("mkdir {}: {e}", other.display()));
+    fs::write(other.join("Cargo.toml"), "[workspace]\n").unwrap();
+    let out = symdump_init(&other, &["--prefix", "hdr", "--no-gitignore"]);
+    assert!(out.status.success());
+    assert!(!other.join(".gitignore").exists());
+    let out = symdump_init(&other, &["--prefix", "hdr"]);
+    assert!(out.status.success());
+    assert!(fs::read_to_string(other.join(".gitignore"))
+        .unwrap()
+        .contains("/.symbaker/*"));
+}
+
+#[test]
+fn init_with_build_checks_wires_symbaker_members() {
+    let root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    let dir = unique_temp_dir("symdump_init_build_checks");
+    let member = |name: &str, build_rs: bool| {
+        let crate_dir = dir.join(name);
+        fs::create_dir_all(crate_dir.join("src")).unwrap();
+        fs::write(
+            crate_dir.join("Cargo.toml"),
+            format!(
+                "[package]\nname = \"{name}\"\nversion = \"0.1.0\"\nedition = \"2021\"\n\n[dependencies]\nsymbaker = {{ path = {:?} }}\n",
+                root.display().to_string()
+            ),
+        )
+        .unwrap();
+        fs::write(crate_dir.join("src").join("lib.rs"), "").unwrap();
+        if build_rs {
+            fs::write(crate_dir.join("build.rs"), "fn main() {}\n").unwrap();
+        }
+    };
+    member("plain", false);
+    member("scripted", true);
+    fs::write(
+        dir.join("Cargo.toml"),
+        "[workspace]\nmembers = [\"plain\", \"scripted\"]\nresolver = \"2\"\n",
+    )
+    .unwrap();
+
+    let out = symdump_init(
+        &dir,
+        &["--prefix", "hdr", "--with-build-checks", "--dry-run"],
+    );
+    assert!(
+        out.status.success(),
+        "{}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+    assert!(String::from_utf8_lossy(&out.stdout).contains("require_initialized"));
+    assert!(!dir.join("plain").join("build.rs").exists());
+
+    let out = symdump_init(&dir, &["--prefix", "hdr", "--with-build-checks"]);
+    let stdout = String::from_utf8_lossy(&out.stdout);
+    assert!(
+        out.status.success(),
+        "{}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+    assert!(stdout.contains("skipping scripted"), "{stdout}");
+    assert_eq!(
+        fs::read_to_string(dir.join("scripted").join("build.rs")).unwrap(),
+        "fn main() {}\n"
+    );
+    let build_rs = fs::read_to_string(dir.join("plain").join("build.rs")).unwrap();
+    assert!(build_rs.contains("symbaker_build::require_initialized()"));
+    let manifest = read_toml(&dir.join("plain").join("Cargo.toml"));
+    let dep = &manifest["build-dependencies"]["symbaker-build"];
+    assert_eq!(
+        dep["path"].as_str().map(PathBuf::from),
+        Some(root.join("symbaker-build"))
+    );
+}
+
+#[test]
+fn init_workspace_members_only_lists_every_member_in_overrides() {
+    let dir = unique_temp_dir("symdump_init_members");
+    for name in ["zeta-plugin", "alpha"] {
+        let crate_dir = dir.join(name);
+        fs::create_dir_all(crate_dir.join("src")).unwrap();
+        fs::write(
+            crate_dir.join("Cargo.toml"),
+            format!("[package]\nname = \"{name}\"\nversion = \"0.1.0\"\nedition = \"2021\"\n"),
+        )
+        .unwrap();
+        fs::write(crate_dir.join("src").join("lib.rs"), "").unwrap();
+    }
+    fs::write(
+        dir.join("Cargo.toml"),
+        "[workspace]\nmembers = [\"zeta-plugin\", \"alpha\"]\nresolver = \"2\"\n",
+    )
+    .unwrap();
+
+    let out = symdump_init(&dir, &["--prefix", "hdr", "--workspace-members-only"]);
+    assert!(
+        out.status.success(),
+        "{}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+    let text = fs::read_to_string(dir.join("symbaker.toml")).unwrap();
+    assert!(
+        text.contains("[overrides]\nalpha = \"alpha\"\nzeta-plugin = \"zeta-plugin\"\n"),
+        "{text}"
+    );
+}
+
+#[test]
+fn init_writes_gitkeep_and_force_rewrites_it() {
+    let dir = unique_temp_dir("symdump_init_gitkeep");
+    fs::create_dir_all(&dir).unwrap_or_else(|e| panic!("mkdir {}: {e}", dir.display()));
+    fs::write(dir.join("Cargo.toml"), "[workspace]\n").unwrap();
+    let gitkeep = dir.join(".symbaker").join(".gitkeep");
+    let expected = "# This file ensures the .symbaker/ directory is tracked by git.\n";
+
+    let out = symdump_init(&dir, &["--prefix", "hdr"]);
+    assert!(
+        out.status.success(),
+        "{}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+    assert_eq!(fs::read_to_string(&gitkeep).unwrap(), expected);
+
+    fs::write(&gitkeep, "edited\n").unwrap();
+    let out = symdump_init(&dir, &["--prefix", "hdr"]);
+    assert!(out.status.success());
+    assert_eq!(fs::read_to_string(&gitkeep).unwrap(), "edited\n");
+    let out = symdump_init(&dir, &["--prefix", "hdr", "--force"]);
+    assert!(out.status.success());
+    assert_eq!(fs::read_to_string(&gitkeep).unwrap(), expected);
+}
+
+#[test]
+fn init_emit_cargo_config_previews_without_writing() {
+    let dir = unique_temp_dir("symdump_init_emit");
+    fs::create_dir_all(dir.join(".cargo"))
+        .unwrap_or_else(|e| panic!("mkdir {}: {e}", dir.display()));
+    fs::write(dir.join("Cargo.toml"), "[workspace]\n").unwrap();
+    let cargo_cfg = dir.join(".cargo").join("config.toml");
+    let original = "[alias]\nxb = \"build --release\"\n\n[env]\nSYMBAKER_INITIALIZED = \"0\"\n";
+    fs::write(&cargo_cfg, original).unwrap();
+
+    let out = symdump_init(&dir, &["--prefix", "hdr", "--emit-cargo-config"]);
+    let stdout = String::from_utf8_lossy(&out.stdout);
+    assert!(
+        out.status.success(),
+        "{}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+    assert!(stdout.contains("prefix = \"hdr\""), "{stdout}");
+    assert!(stdout.contains("SYMBAKER_CONFIG"), "{stdout}");
+    assert!(stdout.contains("SYMBAKER_INITIALIZED = \"0\""), "{stdout}");
+    assert!(!stdout.contains("build --release"), "{stdout}");
+
+    assert_eq!(fs::read_to_string(&cargo_cfg).unwrap(), original);
+    assert!(!dir.join("symbaker.toml").exists());
+    assert!(!dir.join(".gitignore").exists());
+    assert!(!dir.join(".symbaker").exists());
+}
+
+#[test]
+fn init_warns_about_legacy_cargo_config_and_needs_force_to_shadow_it() {
+    let dir = unique_temp_dir("symdump_init_legacy");
+    fs::create_dir_all(dir.join(".cargo"))
+        .unwrap_or_else(|e| panic!("mkdir {}: {e}", dir.display()));
+    fs::write(dir.join("Cargo.toml"), "[workspace]\n").unwrap();
+    let legacy = dir.join(".cargo").join("config");
+    let cargo_cfg = dir.join(".cargo").join("config.toml");
+
+    fs::write(&legacy, "[alias]\nxb = \"build --release\"\n").unwrap();
+    let out = symdump_init(&dir, &["--prefix", "hdr"]);
+    assert!(
+        out.status.success(),
+        "{}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+    assert!(String::from_utf8_lossy(&out.stderr).contains("warning: found"));
+    assert!(cargo_cfg.exists());
+
+    fs::write(&legacy, "[env]\nSYMBAKER_INITIALIZED = \"1\"\n").unwrap();
+    let out = symdump_init(&dir, &["--prefix", "hdr"]);
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    assert!(
+        !out.status.success(),
+        "legacy SYMBAKER_* entries need --force"
+    );
+    assert!(stderr.contains("SYMBAKER_INITIALIZED"), "{stderr}");
+    let out = symdump_init(&dir, &["--prefix", "hdr", "--force"]);
+    assert!(
+        out.status.success(),
+        "{}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+}
+
+#[test]
+fn init_from_virtual_workspace_member_writes_at_workspace_root() {
+    let dir = unique_temp_dir("symdump_init_virtual");
+    let member = dir.join("plugin");
+    fs::create_dir_all(member.join("src"))
+        .unwrap_or_else(|e| panic!("mkdir {}: {e}", member.display()));
+    fs::write(
+        dir.join("Cargo.toml"),
+        "[workspace]\nmembers = [\"plugin\"]\nresolver = \"2\"\n",
+    )
+    .unwrap();
+    fs::write(
+        member.join("Cargo.toml"),
+        "[package]\nname = \"plugin\"\nversion = \"0.1.0\"\nedition = \"2021\"\n",
+    )
+    .unwrap();
+    fs::write(member.join("src").join("lib.rs"), "").unwrap();
+
+    let out = symdump_init(&member.join("src"), &["--prefix", "hdr"]);
+    assert!(
+        out.status.success(),
+        "{}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+    let stdout = String::from_utf8_lossy(&out.stdout);
+    assert!(stdout.contains("virtual workspace"), "{stdout}");
+    assert!(dir.join("symbaker.toml").exists());
+    assert!(!member.join("symbaker.toml").exists());
+}
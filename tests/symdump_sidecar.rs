@@ -51,11 +51,11 @@ fn unique_temp_dir(prefix: &str) -> PathBuf {
     std::env::temp_dir().join(format!("{prefix}_{ts}_{}", std::process::id()))
 }
 
-#[test]
-fn cargo_symdump_writes_sidecar_txt_next_to_nro() {
-    let root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
-    let fixture = root.join("tests").join("fixture_app");
-
+/// Builds `tests/fixture_app` and returns its newest dynamic library.
+fn fixture_lib() -> PathBuf {
+    let fixture = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("fixture_app");
     let status = Command::new("cargo")
         .arg("build")
         .arg("--manifest-path")
@@ -65,25 +65,51 @@ fn cargo_symdump_writes_sidecar_txt_next_to_nro() {
     assert!(status.success(), "fixture_app build failed");
 
     let artifact_root = fixture.join("target").join("debug");
-    let lib = newest_dynamic_lib(&artifact_root, "fixture_app").unwrap_or_else(|| {
+    newest_dynamic_lib(&artifact_root, "fixture_app").unwrap_or_else(|| {
         panic!(
             "could not find fixture dynamic library under {}",
             artifact_root.display()
         )
-    });
+    })
+}
+
+/// A fresh directory with its own `Cargo.toml`, so a dump run from it writes
+/// `.symbaker/` there and not into this repo.
+fn temp_workspace(prefix: &str) -> PathBuf {
+    let root = unique_temp_dir(prefix);
+    fs::create_dir_all(&root).unwrap_or_else(|e| panic!("mkdir {}: {e}", root.display()));
+    let manifest = root.join("Cargo.toml");
+    fs::write(
+        &manifest,
+        "[package]\nname = \"dump_ws\"\nversion = \"0.0.0\"\n",
+    )
+    .unwrap_or_else(|e| panic!("write {}: {e}", manifest.display()));
+    root
+}
 
-    let nro = artifact_root.join("fixture_app_test.nro");
+/// `cargo-symdump` with `workspace` as its working directory.
+fn symdump(workspace: &Path) -> Command {
+    let mut cmd = Command::new(env!("CARGO_BIN_EXE_cargo-symdump"));
+    cmd.current_dir(workspace);
+    cmd
+}
+
+#[test]
+fn cargo_symdump_writes_sidecar_txt_next_to_nro() {
+    let lib = fixture_lib();
+    let dump_root = temp_workspace("symdump_sidecar");
+    let nro = dump_root.join("fixture_app_test.nro");
     fs::copy(&lib, &nro)
         .unwrap_or_else(|e| panic!("copy {} -> {}: {e}", lib.display(), nro.display()));
 
-    let status = Command::new("cargo")
-        .args(["run", "--bin", "cargo-symdump", "--", "dump"])
+    let status = symdump(&dump_root)
+        .arg("dump")
         .arg(&nro)
         .status()
         .expect("failed to run cargo-symdump");
     assert!(status.success(), "cargo-symdump dump failed");
 
-    let sidecar = artifact_root.join("fixture_app_test.nro.exports.txt");
+    let sidecar = dump_root.join("fixture_app_test.nro.exports.txt");
     assert!(
         sidecar.exists(),
         "missing sidecar file: {}",
@@ -99,26 +125,8 @@ fn cargo_symdump_writes_sidecar_txt_next_to_nro() {
 
 #[test]
 fn cargo_symdump_dump_accepts_folder_and_writes_sidecars_for_nros() {
-    let root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
-    let fixture = root.join("tests").join("fixture_app");
-
-    let status = Command::new("cargo")
-        .arg("build")
-        .arg("--manifest-path")
-        .arg(fixture.join("Cargo.toml"))
-        .status()
-        .expect("failed to build fixture_app");
-    assert!(status.success(), "fixture_app build failed");
-
-    let artifact_root = fixture.join("target").join("debug");
-    let lib = newest_dynamic_lib(&artifact_root, "fixture_app").unwrap_or_else(|| {
-        panic!(
-            "could not find fixture dynamic library under {}",
-            artifact_root.display()
-        )
-    });
-
-    let dump_root = unique_temp_dir("symdump_folder_mode");
+    let lib = fixture_lib();
+    let dump_root = temp_workspace("symdump_folder_mode");
     let sub_dir = dump_root.join("nested");
     fs::create_dir_all(&sub_dir).unwrap_or_else(|e| panic!("mkdir {}: {e}", sub_dir.display()));
 
@@ -129,15 +137,15 @@ fn cargo_symdump_dump_accepts_folder_and_writes_sidecars_for_nros() {
     fs::copy(&lib, &nro_b)
         .unwrap_or_else(|e| panic!("copy {} -> {}: {e}", lib.display(), nro_b.display()));
 
-    let status = Command::new("cargo")
-        .args(["run", "--bin", "cargo-symdump", "--", "dump"])
+    let status = symdump(&dump_root)
+        .arg("dump")
         .arg(&nro_a)
         .status()
         .expect("failed to run cargo-symdump dump");
     assert!(status.success(), "single-file dump failed unexpectedly");
 
-    let status = Command::new("cargo")
-        .args(["run", "--bin", "cargo-symdump", "--", "dump"])
+    let status = symdump(&dump_root)
+        .arg("dump")
         .arg(&dump_root)
         .status()
         .expect("failed to run cargo-symdump folder dump");
@@ -159,7 +167,7 @@ fn cargo_symdump_dump_accepts_folder_and_writes_sidecars_for_nros() {
         sidecar_b.display()
     );
 
-    let dup_log = root.join(".symbaker").join("duplicates.log");
+    let dup_log = dump_root.join(".symbaker").join("duplicates.log");
     assert!(
         dup_log.exists(),
         "missing duplicate log: {}",
@@ -172,3 +180,470 @@ fn cargo_symdump_dump_accepts_folder_and_writes_sidecars_for_nros() {
         "duplicate report missing expected symbol"
     );
 }
+
+#[test]
+fn cargo_symdump_dump_no_recursive_skips_subfolders() {
+    let lib = fixture_lib();
+    let dump_root = temp_workspace("symdump_no_recursive");
+    let sub_dir = dump_root.join("deps");
+    fs::create_dir_all(&sub_dir).unwrap_or_else(|e| panic!("mkdir {}: {e}", sub_dir.display()));
+
+    let nro_top = dump_root.join("top.nro");
+    let nro_nested = sub_dir.join("nested.nro");
+    fs::copy(&lib, &nro_top)
+        .unwrap_or_else(|e| panic!("copy {} -> {}: {e}", lib.display(), nro_top.display()));
+    fs::copy(&lib, &nro_nested)
+        .unwrap_or_else(|e| panic!("copy {} -> {}: {e}", lib.display(), nro_nested.display()));
+
+    let status = symdump(&dump_root)
+        .args(["dump", "--no-recursive"])
+        .arg(&dump_root)
+        .status()
+        .expect("failed to run cargo-symdump folder dump");
+    assert!(status.success(), "non-recursive folder dump failed");
+
+    assert!(
+        dump_root.join("top.nro.exports.txt").exists(),
+        "missing top-level sidecar"
+    );
+    assert!(
+        !sub_dir.join("nested.nro.exports.txt").exists(),
+        "--no-recursive should not descend into subfolders"
+    );
+}
+
+#[test]
+fn cargo_symdump_dump_reads_paths_from_stdin() {
+    let lib = fixture_lib();
+    let dump_root = temp_workspace("symdump_stdin");
+    let piped = dump_root.join("piped.nro");
+    let argv = dump_root.join("argv.nro");
+    let unlisted = dump_root.join("unlisted.nro");
+    for nro in [&piped, &argv, &unlisted] {
+        fs::copy(&lib, nro)
+            .unwrap_or_else(|e| panic!("copy {} -> {}: {e}", lib.display(), nro.display()));
+    }
+
+    let mut child = symdump(&dump_root)
+        .args(["dump", "-"])
+        .arg(&argv)
+        .stdin(std::process::Stdio::piped())
+        .spawn()
+        .expect("failed to run cargo-symdump stdin dump");
+    {
+        use std::io::Write;
+        let mut stdin = child.stdin.take().expect("child stdin");
+        writeln!(stdin, "{}\n\n{}", piped.display(), argv.display()).expect("write paths to stdin");
+    }
+    let status = child.wait().expect("wait for cargo-symdump");
+    assert!(status.success(), "stdin dump failed");
+
+    assert!(
+        dump_root.join("piped.nro.exports.txt").exists(),
+        "missing sidecar for stdin path"
+    );
+    assert!(
+        dump_root.join("argv.nro.exports.txt").exists(),
+        "missing sidecar for argv path"
+    );
+    assert!(
+        !dump_root.join("unlisted.nro.exports.txt").exists(),
+        "dumped a file that was never listed"
+    );
+}
+
+#[test]
+fn cargo_symdump_dump_reads_files_from_stdin_and_globs() {
+    let lib = fixture_lib();
+    let dump_root = temp_workspace("symdump_files_from");
+    let nested = dump_root.join("mods").join("a").join("deep");
+    fs::create_dir_all(&nested).unwrap_or_else(|e| panic!("mkdir {}: {e}", nested.display()));
+    let listed = dump_root.join("listed.nro");
+    let globbed = nested.join("plugin_one.nro");
+    let unmatched = nested.join("other.nro");
+    for nro in [&listed, &globbed, &unmatched] {
+        fs::copy(&lib, nro)
+            .unwrap_or_else(|e| panic!("copy {} -> {}: {e}", lib.display(), nro.display()));
+    }
+
+    let mut child = symdump(&dump_root)
+        .args(["dump", "--files-from", "-"])
+        .arg(dump_root.join("mods").join("**").join("plugin_*.nro"))
+        .stdin(std::process::Stdio::piped())
+        .spawn()
+        .expect("failed to run cargo-symdump --files-from dump");
+    {
+        use std::io::Write;
+        let mut stdin = child.stdin.take().expect("child stdin");
+        writeln!(stdin, "{}", listed.display()).expect("write paths to stdin");
+    }
+    let status = child.wait().expect("wait for cargo-symdump");
+    assert!(status.success(), "--files-from dump failed");
+
+    assert!(
+        dump_root.join("listed.nro.exports.txt").exists(),
+        "missing sidecar for --files-from path"
+    );
+    assert!(
+        nested.join("plugin_one.nro.exports.txt").exists(),
+        "missing sidecar for globbed path"
+    );
+    assert!(
+        !nested.join("other.nro.exports.txt").exists(),
+        "dumped a file the glob does not match"
+    );
+
+    let out = symdump(&dump_root)
+        .arg("dump")
+        .arg(dump_root.join("**").join("*.elf"))
+        .output()
+        .expect("failed to run cargo-symdump glob dump");
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    assert_eq!(
+        out.status.code(),
+        Some(6),
+        "empty glob is no artifact: {stderr}"
+    );
+    assert!(stderr.contains("matched no files"), "{stderr}");
+}
+
+#[cfg(unix)]
+#[test]
+fn cargo_symdump_dump_streams_events_to_fd() {
+    let lib = fixture_lib();
+    let dump_root = temp_workspace("symdump_events");
+    let (alpha, beta) = (dump_root.join("alpha.nro"), dump_root.join("beta.nro"));
+    for nro in [&alpha, &beta] {
+        fs::copy(&lib, nro)
+            .unwrap_or_else(|e| panic!("copy {} -> {}: {e}", lib.display(), nro.display()));
+    }
+    let events = dump_root.join("events.jsonl");
+
+    // The shell opens fd 3 on the events file for the dump to inherit.
+    let out = Command::new("sh")
+        .arg("-c")
+        .arg("\"$0\" dump \"$@\" 3>\"$EVENTS\"")
+        .arg(env!("CARGO_BIN_EXE_cargo-symdump"))
+        .current_dir(&dump_root)
+        .arg(&alpha)
+        .arg(&beta)
+        .env("EVENTS", &events)
+        .env("SYMBAKER_EVENT_FD", "3")
+        .output()
+        .expect("failed to run cargo-symdump with an event fd");
+    assert!(
+        out.status.success(),
+        "event dump failed:\n{}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+
+    let body =
+        fs::read_to_string(&events).unwrap_or_else(|e| panic!("read {}: {e}", events.display()));
+    let lines: Vec<serde_json::Value> = body
+        .lines()
+        .map(|l| serde_json::from_str(l).unwrap_or_else(|e| panic!("bad event {l:?}: {e}")))
+        .collect();
+    let kinds: Vec<&str> = lines.iter().map(|e| e["event"].as_str().unwrap()).collect();
+    assert_eq!(&kinds[..4], ["start", "done", "start", "done"], "{body}");
+    assert_eq!(kinds.last(), Some(&"summary"), "{body}");
+    assert!(
+        lines[0]["file"].as_str().unwrap().ends_with("alpha.nro"),
+        "{body}"
+    );
+    let per_file = lines[1]["symbols"].as_u64().unwrap();
+    assert!(per_file > 0, "{body}");
+
+    let duplicate = lines
+        .iter()
+        .find(|e| e["event"] == "duplicate")
+        .unwrap_or_else(|| panic!("identical copies should report duplicates:\n{body}"));
+    assert_eq!(duplicate["files"].as_array().unwrap().len(), 2, "{body}");
+    let summary = lines.last().unwrap();
+    assert_eq!(summary["artifacts"], 2, "{body}");
+    assert_eq!(
+        summary["total_symbols"].as_u64(),
+        Some(per_file * 2),
+        "{body}"
+    );
+
+    let out = symdump(&dump_root)
+        .arg("dump")
+        .arg(&alpha)
+        .env("SYMBAKER_EVENT_FD", "nope")
+        .output()
+        .expect("failed to run cargo-symdump with a bad event fd");
+    assert!(
+        !out.status.success(),
+        "a non-numeric SYMBAKER_EVENT_FD should fail"
+    );
+}
+
+#[test]
+fn cargo_symdump_dump_verbosity_levels() {
+    let lib = fixture_lib();
+    let dump_root = temp_workspace("symdump_verbosity");
+    let nro = dump_root.join("plugin.nro");
+    fs::copy(&lib, &nro)
+        .unwrap_or_else(|e| panic!("copy {} -> {}: {e}", lib.display(), nro.display()));
+
+    let dump = |level: &str| {
+        let out = symdump(&dump_root)
+            .args([level, "dump"])
+            .arg(&dump_root)
+            .output()
+            .expect("failed to run cargo-symdump dump");
+        assert!(
+            out.status.success(),
+            "{level} dump failed:\n{}",
+            String::from_utf8_lossy(&out.stderr)
+        );
+        String::from_utf8_lossy(&out.stdout).to_string()
+    };
+
+    let quiet = dump("-q");
+    assert!(quiet.is_empty(), "-q printed to stdout:\n{quiet}");
+    assert!(dump_root.join("plugin.nro.exports.txt").exists());
+
+    let verbose = dump("-v");
+    assert!(verbose.contains("  strategy: "), "{verbose}");
+    assert!(!verbose.contains("scanned "), "{verbose}");
+    let debug = dump("-vv");
+    assert!(debug.contains("scanned "), "{debug}");
+}
+
+#[test]
+fn cargo_symdump_artifact_flag_skips_build() {
+    let lib = fixture_lib();
+    let dump_root = temp_workspace("symdump_artifact_flag");
+    let nro = dump_root.join("packed.nro");
+    fs::copy(&lib, &nro)
+        .unwrap_or_else(|e| panic!("copy {} -> {}: {e}", lib.display(), nro.display()));
+
+    let status = symdump(&dump_root)
+        .arg("--artifact")
+        .arg(&nro)
+        .status()
+        .expect("failed to run cargo-symdump --artifact");
+    assert!(status.success(), "cargo-symdump --artifact failed");
+
+    let sidecar = dump_root.join("packed.nro.exports.txt");
+    let body = fs::read_to_string(&sidecar)
+        .unwrap_or_else(|e| panic!("failed reading {}: {e}", sidecar.display()));
+    assert!(
+        body.contains("fixture_app__auto_named"),
+        "sidecar missing expected symbol"
+    );
+}
+
+#[test]
+fn cargo_symdump_stable_log_is_identical_across_locations() {
+    let lib = fixture_lib();
+    let mut runs = Vec::new();
+    for (run, nested) in [("first", "x"), ("second", "y/z")] {
+        let dump_root = temp_workspace(&format!("symdump_stable_{run}"));
+        let sub_dir = dump_root.join(nested);
+        fs::create_dir_all(&sub_dir).unwrap_or_else(|e| panic!("mkdir {}: {e}", sub_dir.display()));
+        for nro in [dump_root.join("beta.nro"), sub_dir.join("alpha.nro")] {
+            fs::copy(&lib, &nro)
+                .unwrap_or_else(|e| panic!("copy {} -> {}: {e}", lib.display(), nro.display()));
+        }
+
+        let status = symdump(&dump_root)
+            .args(["dump", "--stable"])
+            .arg(&dump_root)
+            .status()
+            .expect("failed to run cargo-symdump dump --stable");
+        assert!(status.success(), "stable dump failed");
+        let stable_log = dump_root.join(".symbaker").join("sym.stable.log");
+        runs.push(
+            fs::read(&stable_log).unwrap_or_else(|e| panic!("read {}: {e}", stable_log.display())),
+        );
+    }
+
+    assert_eq!(runs[0], runs[1], "sym.stable.log differs between runs");
+    let body = String::from_utf8_lossy(&runs[0]);
+    let alpha = body
+        .find("# source=alpha.nro\n")
+        .expect("missing alpha section");
+    let beta = body
+        .find("# source=beta.nro\n")
+        .expect("missing beta section");
+    assert!(
+        alpha < beta,
+        "sections not sorted by artifact name:\n{body}"
+    );
+    assert!(!body.contains("0x"), "stable log has addresses:\n{body}");
+    assert!(body.contains("\nfixture_app__auto_named\n"), "{body}");
+}
+
+#[test]
+fn cargo_symdump_dump_names_missing_symbol_tools() {
+    let dump_root = temp_workspace("symdump_notools");
+    let empty_path = dump_root.join("bin");
+    fs::create_dir_all(&empty_path)
+        .unwrap_or_else(|e| panic!("mkdir {}: {e}", empty_path.display()));
+    let lib = dump_root.join("libplugin.so");
+    fs::write(&lib, b"not really a library").unwrap();
+
+    let run = |extra: &[&str]| {
+        symdump(&dump_root)
+            .arg("dump")
+            .args(extra)
+            .arg(&lib)
+            .env("PATH", &empty_path)
+            .output()
+            .expect("failed to run cargo-symdump")
+    };
+
+    let out = run(&[]);
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    assert!(!out.status.success(), "dump without tools must fail");
+    assert!(
+        stderr.contains("no nm/objdump found in PATH (searched llvm-nm, nm, rust-nm"),
+        "{stderr}"
+    );
+    assert!(stderr.contains("--allow-missing-tools"), "{stderr}");
+
+    let out = run(&["--allow-missing-tools"]);
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    let _ = fs::remove_dir_all(&dump_root);
+    assert!(
+        stderr.contains("warning: no nm/objdump found")
+            && stderr.contains("Skipping 1 unrecognized"),
+        "{stderr}"
+    );
+    assert!(
+        stderr.contains("no readable inputs left to dump"),
+        "{stderr}"
+    );
+}
+
+#[test]
+fn cargo_symdump_dump_reads_libraries_and_rlibs_without_tools() {
+    let lib = fixture_lib();
+    let rlib = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("fixture_app")
+        .join("target")
+        .join("debug")
+        .join("libfixture_app.rlib");
+
+    let dump_root = temp_workspace("symdump_native");
+    let empty_path = dump_root.join("bin");
+    fs::create_dir_all(&empty_path)
+        .unwrap_or_else(|e| panic!("mkdir {}: {e}", empty_path.display()));
+    let lib_copy = dump_root.join(lib.file_name().unwrap());
+    let rlib_copy = dump_root.join("libfixture_app.rlib");
+    fs::copy(&lib, &lib_copy).unwrap();
+    fs::copy(&rlib, &rlib_copy).unwrap();
+
+    let out = symdump(&dump_root)
+        .arg("dump")
+        .arg(&lib_copy)
+        .arg(&rlib_copy)
+        .env("PATH", &empty_path)
+        .output()
+        .expect("failed to run cargo-symdump");
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    assert!(out.status.success(), "{stderr}");
+    for input in [&lib_copy, &rlib_copy] {
+        let sidecar = PathBuf::from(format!("{}.exports.txt", input.display()));
+        let body = fs::read_to_string(&sidecar)
+            .unwrap_or_else(|e| panic!("read {}: {e}", sidecar.display()));
+        assert!(body.contains("__auto_named\n"), "{body}");
+        assert!(body.contains("__attr_named\n"), "{body}");
+    }
+    let _ = fs::remove_dir_all(&dump_root);
+}
+
+#[test]
+fn cargo_symdump_sidecar_name_separates_same_named_artifacts() {
+    let lib = fixture_lib();
+    let dump_root = temp_workspace("symdump_sidecar_name");
+    let mut nros = Vec::new();
+    for version in ["v1", "v2"] {
+        let dir = dump_root.join(version);
+        fs::create_dir_all(&dir).unwrap_or_else(|e| panic!("mkdir {}: {e}", dir.display()));
+        let nro = dir.join("plugin.nro");
+        fs::copy(&lib, &nro)
+            .unwrap_or_else(|e| panic!("copy {} -> {}: {e}", lib.display(), nro.display()));
+        nros.push(nro);
+    }
+
+    let dump = |pattern: &str| {
+        symdump(&dump_root)
+            .args(["dump", "--sidecar-name"])
+            .arg(pattern)
+            .args(&nros)
+            .output()
+            .expect("failed to run cargo-symdump dump")
+    };
+
+    let out = dump("../{stem}.exports.txt");
+    assert_eq!(
+        out.status.code(),
+        Some(2),
+        "colliding sidecars must be rejected"
+    );
+    assert!(
+        String::from_utf8_lossy(&out.stderr).contains("would both write sidecar"),
+        "{}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+
+    let out = dump("../sidecars/{parent}_{filename}.exports.txt");
+    assert!(
+        out.status.success(),
+        "{}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+    for version in ["v1", "v2"] {
+        let sidecar = dump_root
+            .join("sidecars")
+            .join(format!("{version}_plugin.nro.exports.txt"));
+        let text = fs::read_to_string(&sidecar)
+            .unwrap_or_else(|e| panic!("read {}: {e}", sidecar.display()));
+        assert!(text.contains("fixture_app__auto_named"), "{text}");
+    }
+    assert!(!nros[0].with_extension("nro.exports.txt").exists());
+}
+
+#[test]
+fn cargo_symdump_exit_codes_name_the_failure() {
+    let lib = fixture_lib();
+    let dump_root = temp_workspace("symdump_exit_codes");
+    let (alpha, beta) = (dump_root.join("alpha.nro"), dump_root.join("beta.nro"));
+    for nro in [&alpha, &beta] {
+        fs::copy(&lib, nro)
+            .unwrap_or_else(|e| panic!("copy {} -> {}: {e}", lib.display(), nro.display()));
+    }
+
+    let code = |args: &[&OsStr]| {
+        let out = symdump(&dump_root)
+            .args(args)
+            .output()
+            .expect("failed to run cargo-symdump");
+        (
+            out.status.code(),
+            String::from_utf8_lossy(&out.stderr).to_string(),
+        )
+    };
+    let dump = OsStr::new("dump");
+    let (status, stderr) = code(&[dump, alpha.as_os_str(), beta.as_os_str()]);
+    assert_eq!(
+        status,
+        Some(0),
+        "duplicates alone are not a failure: {stderr}"
+    );
+    let fail = OsStr::new("--fail-on-duplicates");
+    let (status, stderr) = code(&[dump, fail, alpha.as_os_str(), beta.as_os_str()]);
+    assert_eq!(status, Some(4), "{stderr}");
+    assert!(
+        stderr.contains("conflicting duplicate symbol(s)"),
+        "{stderr}"
+    );
+    let (status, stderr) = code(&[dump]);
+    assert_eq!(status, Some(2), "{stderr}");
+    let (status, stderr) = code(&[dump, dump_root.join("missing.nro").as_os_str()]);
+    assert_eq!(status, Some(6), "{stderr}");
+}
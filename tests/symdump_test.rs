@@ -0,0 +1,60 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+fn symdump_test(fixture: &Path, target_dir: &Path, prefix: &str, extra: &[&str]) -> Output {
+    let root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    Command::new("cargo")
+        .args(["run", "-q", "--manifest-path"])
+        .arg(root.join("Cargo.toml"))
+        .args(["--bin", "cargo-symdump", "--", "test"])
+        .args(extra)
+        .arg("--manifest-path")
+        .arg(fixture.join("Cargo.toml"))
+        .arg("--target-dir")
+        .arg(target_dir)
+        .env("SYMBAKER_PREFIX", prefix)
+        .env_remove("SYMBAKER_CONFIG")
+        .env_remove("SYMBAKER_SUFFIX")
+        .env_remove("SYMBAKER_PRIORITY")
+        .env_remove("SYMBAKER_TOP_PACKAGE")
+        .output()
+        .expect("failed to run cargo-symdump test")
+}
+
+#[test]
+fn symdump_test_dumps_freshly_built_cdylib() {
+    let root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    let fixture = root.join("tests").join("fixture_app");
+    let target_dir = fixture.join("target").join("symdump_test");
+    // A new prefix reruns fixture_app's build script, so the cdylib is fresh.
+    let ts = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or(0);
+    let prefix = format!("symdump_test_{ts}");
+
+    let out = symdump_test(&fixture, &target_dir, &prefix, &[]);
+    let stdout = String::from_utf8_lossy(&out.stdout);
+    assert!(
+        out.status.success(),
+        "{stdout}\n{}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+    let sidecar = stdout
+        .lines()
+        .filter_map(|l| l.strip_prefix("exports: "))
+        .find(|p| p.contains("fixture_app"))
+        .unwrap_or_else(|| panic!("no fixture_app sidecar in:\n{stdout}"));
+    let exports = fs::read_to_string(sidecar).unwrap_or_else(|e| panic!("read {sidecar}: {e}"));
+    assert!(
+        exports.contains(&format!("{prefix}__auto_named")),
+        "{exports}"
+    );
+
+    let out = symdump_test(&fixture, &target_dir, &prefix, &["--no-dump"]);
+    let stdout = String::from_utf8_lossy(&out.stdout);
+    assert!(out.status.success());
+    assert!(!stdout.contains("exports: "), "{stdout}");
+}
@@ -0,0 +1,98 @@
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+/// Builds a cdylib exporting `hdr__init`, `hdr__new_hook` and the foreign
+/// `hdr_foo` into `dir`.
+fn build_artifact(dir: &Path) -> std::path::PathBuf {
+    let src = dir.join("abi.rs");
+    fs::write(
+        &src,
+        "#[no_mangle] pub extern \"C\" fn hdr__init() {}\n\
+         #[no_mangle] pub extern \"C\" fn hdr__new_hook() {}\n\
+         #[no_mangle] pub extern \"C\" fn hdr_foo() {}\n",
+    )
+    .unwrap_or_else(|e| panic!("write {}: {e}", src.display()));
+    let status = Command::new(std::env::var("RUSTC").unwrap_or_else(|_| "rustc".into()))
+        .args([
+            "--crate-type",
+            "cdylib",
+            "--crate-name",
+            "abi",
+            "--edition",
+            "2021",
+        ])
+        .arg("--out-dir")
+        .arg(dir)
+        .arg(&src)
+        .status()
+        .expect("failed to run rustc");
+    assert!(
+        status.success(),
+        "rustc failed to build the verify artifact"
+    );
+    fs::read_dir(dir)
+        .unwrap()
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .find(|p| {
+            let ext = p.extension().and_then(|e| e.to_str());
+            matches!(ext, Some("so" | "dylib" | "dll"))
+        })
+        .expect("rustc wrote no dynamic library")
+}
+
+#[test]
+fn verify_labels_additions_and_sets_the_exit_status() {
+    let dir = std::env::temp_dir().join(format!("symdump_verify_{}", std::process::id()));
+    fs::create_dir_all(dir.join(".symbaker")).unwrap_or_else(|e| panic!("mkdir: {e}"));
+    fs::write(
+        dir.join("Cargo.toml"),
+        "[package]\nname = \"abi\"\nversion = \"0.1.0\"\n",
+    )
+    .unwrap();
+    fs::write(
+        dir.join(".symbaker").join("resolution.toml"),
+        "[[crates]]\nname = \"abi\"\nresolved_prefix = \"hdr\"\nsep = \"__\"\nsymbols = [\"hdr__init\"]\n",
+    )
+    .unwrap();
+    let lib = build_artifact(&dir);
+
+    let verify = |flags: &[&str]| {
+        let out = Command::new(env!("CARGO_BIN_EXE_cargo-symdump"))
+            .current_dir(&dir)
+            .arg("verify")
+            .args(flags)
+            .arg(&lib)
+            .output()
+            .expect("failed to run cargo-symdump verify");
+        (
+            out.status.code(),
+            String::from_utf8_lossy(&out.stdout).to_string(),
+        )
+    };
+
+    let (code, stdout) = verify(&[]);
+    assert_eq!(code, Some(5), "additions fail by default:\n{stdout}");
+    assert_eq!(stdout, "[ERROR] added: hdr__new_hook\n");
+
+    let (code, stdout) = verify(&["--allow-extra"]);
+    assert_eq!(code, Some(0), "--allow-extra accepts additions:\n{stdout}");
+    assert_eq!(
+        stdout,
+        "[allowed] added: hdr__new_hook\n\
+         verify: ok (1 expected export(s), 1 added, 1 artifact(s))\n"
+    );
+
+    fs::write(
+        dir.join(".symbaker").join("resolution.toml"),
+        "[[crates]]\nname = \"abi\"\nresolved_prefix = \"hdr\"\nsymbols = [\"hdr__init\", \"hdr__gone\"]\n",
+    )
+    .unwrap();
+    let (code, stdout) = verify(&["--allow-extra"]);
+    assert_eq!(code, Some(5), "removals still fail:\n{stdout}");
+    assert_eq!(
+        stdout,
+        "[ERROR] removed: hdr__gone\n[allowed] added: hdr__new_hook\n"
+    );
+    let _ = fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,88 @@
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+
+const FIXTURE_FUNCTIONS: [&str; 5] = [
+    "auto_named",
+    "attr_named",
+    "attr_suffixed",
+    "on_frame",
+    "on_ruled",
+];
+
+fn build_with_trace_hard(mode: &str) -> (bool, String, String) {
+    let root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    let fixture = root.join("tests").join("fixture_app");
+    let target_dir = fixture.join("target").join(format!("trace_hard_{mode}"));
+    fs::create_dir_all(&target_dir)
+        .unwrap_or_else(|e| panic!("mkdir {}: {e}", target_dir.display()));
+    let trace_file = target_dir.join("trace.log");
+    let _ = fs::remove_file(&trace_file);
+    // The trace is only written when fixture_app is recompiled.
+    let status = Command::new("cargo")
+        .args(["clean", "-p", "fixture_app", "--manifest-path"])
+        .arg(fixture.join("Cargo.toml"))
+        .arg("--target-dir")
+        .arg(&target_dir)
+        .status()
+        .expect("failed to run cargo clean for fixture_app");
+    assert!(status.success(), "cargo clean -p fixture_app failed");
+
+    let out = Command::new("cargo")
+        .arg("build")
+        .arg("--manifest-path")
+        .arg(fixture.join("Cargo.toml"))
+        .arg("--target-dir")
+        .arg(&target_dir)
+        .env_remove("SYMBAKER_CONFIG")
+        .env_remove("SYMBAKER_TRACE")
+        .env("SYMBAKER_PREFIX", format!("hard_{mode}"))
+        .env("SYMBAKER_TRACE_HARD", mode)
+        .env("SYMBAKER_TRACE_FILE", &trace_file)
+        .output()
+        .expect("failed to run cargo build for fixture_app");
+    let trace = fs::read_to_string(&trace_file).unwrap_or_default();
+    (
+        out.status.success(),
+        String::from_utf8_lossy(&out.stderr).to_string(),
+        trace,
+    )
+}
+
+fn assert_every_function_traced(trace: &str) {
+    for name in FIXTURE_FUNCTIONS {
+        let needle = format!("function={name:?}");
+        assert!(
+            trace
+                .lines()
+                .any(|l| l.contains("trace_hard ") && l.contains(&needle)),
+            "no trace_hard line for {name} in:\n{trace}"
+        );
+    }
+}
+
+#[test]
+fn trace_hard_reports_every_function_not_just_the_first() {
+    let (ok, stderr, trace) = build_with_trace_hard("1");
+    assert!(!ok, "SYMBAKER_TRACE_HARD=1 should fail the build");
+    for name in FIXTURE_FUNCTIONS {
+        let needle = format!("function={name:?}");
+        assert!(
+            stderr
+                .lines()
+                .any(|l| l.contains("symbaker trace:") && l.contains(&needle)),
+            "missing hard error for {name} in:\n{stderr}"
+        );
+    }
+    assert_every_function_traced(&trace);
+}
+
+#[test]
+fn trace_hard_log_records_without_failing() {
+    let (ok, stderr, trace) = build_with_trace_hard("log");
+    assert!(
+        ok,
+        "SYMBAKER_TRACE_HARD=log must not fail the build:\n{stderr}"
+    );
+    assert_every_function_traced(&trace);
+}
@@ -0,0 +1,10 @@
+use symbaker::symbaker;
+
+#[symbaker(prefix = "attr_p", explain)]
+pub extern "C" fn explained() {}
+
+// Not explained: must expand normally and add no diagnostics.
+#[symbaker(prefix = "attr_p")]
+pub extern "C" fn untouched() {}
+
+fn main() {}
@@ -0,0 +1,14 @@
+use symbaker::symbaker_module;
+
+#[symbaker_module]
+mod hooks {
+    pub async fn on_load() {}
+}
+
+// Skipped rather than rejected.
+#[symbaker_module(exclude_async = true)]
+mod skipped {
+    pub async fn on_unload() {}
+}
+
+fn main() {}
@@ -0,0 +1,21 @@
+use symbaker::symbaker_module;
+
+pub trait Hooks {
+    extern "C" fn on_frame();
+}
+
+pub struct Plugin;
+
+#[symbaker_module]
+impl Hooks for Plugin {
+    extern "C" fn on_frame() {}
+}
+
+pub struct Wrapper<T>(T);
+
+#[symbaker_module]
+impl<T> Wrapper<T> {
+    pub extern "C" fn init() {}
+}
+
+fn main() {}
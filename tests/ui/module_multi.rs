@@ -0,0 +1,24 @@
+use symbaker::symbaker_modules;
+
+#[symbaker_modules(game, audoi)]
+mod subsystems {
+    pub mod game {
+        pub extern "C" fn tick() {}
+    }
+
+    pub mod audio {
+        pub extern "C" fn mix() {}
+    }
+}
+
+#[symbaker_modules(include_glob = "on_*")]
+mod unlisted {
+    pub mod game {}
+}
+
+#[symbaker_modules(game::inner)]
+mod nested {
+    pub mod game {}
+}
+
+fn main() {}
@@ -0,0 +1,15 @@
+use symbaker::symbaker_module;
+
+#[symbaker_module(only = "init, on_frmae")]
+mod typo {
+    pub extern "C" fn init() {}
+
+    pub extern "C" fn on_frame() {}
+}
+
+#[symbaker_module(include_glob = "on_*", only = "init")]
+mod combined {
+    pub extern "C" fn init() {}
+}
+
+fn main() {}
@@ -0,0 +1,13 @@
+use symbaker::symbaker_module;
+
+#[symbaker_module(priority = "attr,cfg")]
+mod misspelled {
+    pub extern "C" fn init() {}
+}
+
+#[symbaker_module(priority = "attr,crate,attr")]
+mod repeated {
+    pub extern "C" fn init() {}
+}
+
+fn main() {}
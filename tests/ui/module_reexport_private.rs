@@ -0,0 +1,20 @@
+use symbaker::symbaker_module;
+
+#[symbaker_module]
+mod facade {
+    mod imp {
+        #[repr(C)]
+        struct Secret {
+            id: i32,
+        }
+
+        #[allow(private_interfaces)]
+        pub extern "C" fn reveal(secret: Secret) -> i32 {
+            secret.id
+        }
+    }
+
+    pub use imp::reveal;
+}
+
+fn main() {}